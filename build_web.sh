@@ -1,10 +1,16 @@
 #!/usr/bin/env bash
 set -euo pipefail
 
-CRATE="chess"
+# ./build_web.sh            — the Bevy app into web/wasm
+# ./build_web.sh chess_web  — the engine-only module into web/chess_web
+CRATE="${1:-chess}"
 PROFILE="wasm-release"
 TARGET="wasm32-unknown-unknown"
-OUT_DIR="web/wasm"
+if [[ "$CRATE" == "chess" ]]; then
+    OUT_DIR="web/wasm"
+else
+    OUT_DIR="web/${CRATE}"
+fi
 
 BINARY="target/${TARGET}/${PROFILE}/${CRATE}.wasm"
 
@@ -2,12 +2,10 @@ use bevy::prelude::*;
 use bevy::ecs::message::{MessageReader, MessageWriter};
 use bevy_async_task::TaskPool;
 use bevy_tweening::{lens::TransformPositionLens, Delay, *};
-use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
-#[cfg(target_arch = "wasm32")]
-use std::sync::Mutex;
+use std::sync::atomic::Ordering;
 use std::task::Poll;
 use chess_board::ChessBoard;
-use chess_evaluation::{evaluate_board, iterative_deepening_root, OpeningBook};
+use chess_evaluation::{evaluate_board, Engine, OpeningBook, SearchLimits};
 // evaluate_board is used in launch_multi_ponder for ranking ponder candidates
 use chess_foundation::ChessMove;
 use move_generator::{
@@ -124,108 +122,71 @@ pub type SearchTaskOutput = (i32, Option<ChessMove>, Option<ChessMove>, bool, u6
 // ── Main search task ──────────────────────────────────────────────────────────
 
 async fn alpha_beta_task(
-    chess_board: &mut ChessBoard,
-    conductor: &PieceConductor,
-    book: &OpeningBook,
-    max_depth: i32,
-    is_white: bool,
+    chess_board: ChessBoard,
+    conductor: PieceConductor,
+    book: OpeningBook,
+    elo: Option<i32>,
     deadline: Option<web_time::Instant>,
-    noise_cp: i32,
 ) -> SearchTaskOutput {
+    let mut engine = strength_engine(conductor, book, elo);
+    engine.set_position(chess_board);
+    // The deadline only stops new iterations, so the move is never half-searched.
+    let limits = SearchLimits { soft_deadline: deadline, ..SearchLimits::infinite() };
+
     #[cfg(not(target_arch = "wasm32"))]
     {
-        let result = iterative_deepening_root(
-            chess_board, conductor, Some(book), max_depth, is_white, deadline, None, noise_cp,
-        );
+        let result = engine.search(limits);
         return (result.score, result.best_move, result.ponder_move, false, 0);
     }
 
     #[cfg(target_arch = "wasm32")]
     {
-        // Opening book fast path (no worker needed).
-        if let Some((from, to)) = book.probe(chess_board) {
-            let mut legal = Vec::new();
-            get_all_legal_moves_for_color(chess_board, conductor, is_white, &mut legal, &mut Vec::new());
-            if let Some(bm) = legal
-                .into_iter()
-                .find(|m| m.start_square() == from && m.target_square() == to)
-            {
-                return (0, Some(bm), None, false, 0);
-            }
-        }
-
-        let stop = Arc::new(AtomicBool::new(false));
-        let result: Arc<Mutex<Option<(i32, Option<ChessMove>, Option<ChessMove>)>>> =
-            Arc::new(Mutex::new(None));
-        {
-            let stop_w = Arc::clone(&stop);
-            let res_w = Arc::clone(&result);
-            let mut board_w = chess_board.clone();
-            let conductor_w = conductor.clone();
-            let book_w = book.clone();
-            rayon::spawn(move || {
-                let r = iterative_deepening_root(
-                    &mut board_w, &conductor_w, Some(&book_w),
-                    max_depth, is_white, None, Some(stop_w), noise_cp,
-                );
-                *res_w.lock().unwrap() = Some((r.score, r.best_move, r.ponder_move));
-            });
-        }
+        // The main thread must not block: poll the engine between frames and
+        // stop it once the deadline has passed.
+        engine.go(limits, |_| {});
         loop {
             gloo_timers::future::TimeoutFuture::new(5).await;
             if let Some(dl) = deadline {
                 if web_time::Instant::now() >= dl {
-                    stop.store(true, Ordering::Relaxed);
+                    if let Some(stop) = engine.stop_handle() {
+                        stop.store(true, Ordering::Relaxed);
+                    }
                 }
             }
-            if let Some((score, bm, pm)) = result.lock().unwrap().take() {
-                return (score, bm, pm, false, 0);
+            if let Some(r) = engine.try_result() {
+                return (r.score, r.best_move, r.ponder_move, false, 0);
             }
         }
     }
 }
 
+/// An engine playing at `elo` (`None` = full strength).
+fn strength_engine(conductor: PieceConductor, book: OpeningBook, elo: Option<i32>) -> Engine {
+    let mut engine = Engine::from_parts(conductor, Some(book));
+    if let Some(elo) = elo {
+        engine.set_limit_strength(true);
+        engine.set_elo(elo);
+    }
+    engine
+}
+
 // ── Ponder search task ────────────────────────────────────────────────────────
 
-async fn ponder_search_task(
-    mut board: ChessBoard,
-    conductor: PieceConductor,
-    book: OpeningBook,
-    depth: i32,
-    is_white: bool,
-    stop: Arc<AtomicBool>,
-    board_hash: u64,
-) -> SearchTaskOutput {
+/// Wait for an already started ponder search.  It is stopped through the
+/// engine's stop handle, kept in `PonderState::stops`.
+async fn ponder_search_task(mut engine: Engine, board_hash: u64) -> SearchTaskOutput {
     #[cfg(not(target_arch = "wasm32"))]
     {
-        let result = iterative_deepening_root(
-            &mut board, &conductor, Some(&book),
-            depth, is_white, None, Some(stop), 0,
-        );
+        let result = engine.wait().expect("ponder search was started");
         return (result.score, result.best_move, result.ponder_move, true, board_hash);
     }
 
     #[cfg(target_arch = "wasm32")]
     {
-        let result: Arc<Mutex<Option<(i32, Option<ChessMove>, Option<ChessMove>)>>> =
-            Arc::new(Mutex::new(None));
-        {
-            let stop_w = Arc::clone(&stop);
-            let res_w = Arc::clone(&result);
-            let conductor_w = conductor.clone();
-            let book_w = book.clone();
-            rayon::spawn(move || {
-                let r = iterative_deepening_root(
-                    &mut board, &conductor_w, Some(&book_w),
-                    depth, is_white, None, Some(stop_w), 0,
-                );
-                *res_w.lock().unwrap() = Some((r.score, r.best_move, r.ponder_move));
-            });
-        }
         loop {
             gloo_timers::future::TimeoutFuture::new(5).await;
-            if let Some((score, bm, pm)) = result.lock().unwrap().take() {
-                return (score, bm, pm, true, board_hash);
+            if let Some(r) = engine.try_result() {
+                return (r.score, r.best_move, r.ponder_move, true, board_hash);
             }
         }
     }
@@ -239,7 +200,7 @@ fn launch_multi_ponder(
     board: &ChessBoard,
     conductor: &PieceConductor,
     book: &OpeningBook,
-    depth: i32,
+    elo: Option<i32>,
     ai_is_white: bool,
 ) {
     for s in &ponder_state.stops {
@@ -278,17 +239,12 @@ fn launch_multi_ponder(
             continue;
         }
         let board_hash = ponder_board.current_hash();
-        let stop = Arc::new(AtomicBool::new(false));
-        ponder_state.stops.push(Arc::clone(&stop));
-
-        let conductor_c = conductor.clone();
-        let book_c = book.clone();
-        task_pool.spawn(async move {
-            ponder_search_task(
-                ponder_board, conductor_c, book_c, depth, ai_is_white, stop, board_hash,
-            )
-            .await
-        });
+        let mut engine = strength_engine(conductor.clone(), book.clone(), elo);
+        engine.set_position(ponder_board);
+        engine.go(SearchLimits::infinite(), |_| {});
+        ponder_state.stops.extend(engine.stop_handle());
+
+        task_pool.spawn(async move { ponder_search_task(engine, board_hash).await });
         eprintln!("Ponder candidate hash={board_hash:#x}");
     }
 
@@ -381,11 +337,10 @@ pub fn handle_async_moves(
                         if moves.len() == 1 { Some(moves[0]) } else { None }
                     };
 
-                    let mut board_c = chess_board.chess_board.clone();
+                    let board_c = chess_board.chess_board.clone();
                     let conductor_c = move_generator.magic.clone();
                     let book_c = opening_book.book.clone();
-                    let depth = game_settings.strength.max_depth();
-                    let noise_cp = game_settings.strength.eval_noise_cp();
+                    let elo = game_settings.strength.elo();
                     let budget = game_clocks.move_budget(ai_is_white);
                     let deadline = Some(web_time::Instant::now() + budget);
 
@@ -393,11 +348,8 @@ pub fn handle_async_moves(
                         if let Some(m) = forced {
                             return (0, Some(m), None, false, 0);
                         }
-                        alpha_beta_task(
-                            &mut board_c, &conductor_c, &book_c,
-                            depth, ai_is_white, deadline, noise_cp,
-                        )
-                        .await
+                        alpha_beta_task(board_c, conductor_c, book_c, elo, deadline)
+                            .await
                     });
                     ponder_state.main_search_active = true;
                     is_ai_thinking.0 = true;
@@ -491,7 +443,7 @@ pub fn handle_async_moves(
                 &chess_board.chess_board,
                 &move_generator.magic,
                 &opening_book.book,
-                game_settings.strength.max_depth(),
+                game_settings.strength.elo(),
                 ai_is_white,
             );
         }
@@ -101,8 +101,8 @@ impl TimeControl {
 
 // ── Strength levels ───────────────────────────────────────────────────────────
 
-/// Five strength levels targeting approximate ELO bands.
-/// ELO values are rough estimates — calibrate against Stockfish/lichess.
+/// Five strength levels mapped to `UCI_Elo` targets of the engine's skill
+/// model (see `chess_evaluation::skill`, calibrated with `self_play`).
 #[derive(Resource, Clone, Copy, PartialEq, Default)]
 pub enum Strength {
     S1,
@@ -114,27 +114,14 @@ pub enum Strength {
 }
 
 impl Strength {
-    pub fn max_depth(self) -> i32 {
+    /// Target rating, or `None` for full strength.
+    pub fn elo(self) -> Option<i32> {
         match self {
-            // S1-S4 use noise, so the deep search result is discarded — keep depth low
-            // to avoid burning the time budget on a search that gets thrown away.
-            Strength::S1 => 1,
-            Strength::S2 => 1,
-            Strength::S3 => 2,   // slight lookahead before noise so it avoids immediate blunders
-            Strength::S4 => 4,   // same — noise is small so search quality starts to matter
-            Strength::S5 => 50,
-        }
-    }
-
-    /// Root-level eval noise in centipawns.  After searching to max_depth, each root
-    /// move's static score has a random ±noise sample added before selecting the best.
-    pub fn eval_noise_cp(self) -> i32 {
-        match self {
-            Strength::S1 => 100,  // blunders regularly, clear piece drops
-            Strength::S2 => 35,   // misses short tactics, drops pawns occasionally
-            Strength::S3 => 15,   // solid, rare lapses
-            Strength::S4 => 5,    // near-engine strength, very rare slip
-            Strength::S5 => 0,
+            Strength::S1 => Some(1200),  // blunders regularly, clear piece drops
+            Strength::S2 => Some(1500),  // misses short tactics, drops pawns occasionally
+            Strength::S3 => Some(1800),  // solid, rare lapses
+            Strength::S4 => Some(2100),  // near-engine strength, very rare slip
+            Strength::S5 => None,
         }
     }
 
@@ -21,9 +21,13 @@ fn xorshift64(s: &mut u64) -> u64 {
 }
 
 impl ZobristTable {
+    /// xorshift64 seed the keys are generated from.  Same golden-ratio seed
+    /// used in the opening book for consistency.  Anything persisted by hash
+    /// (e.g. a saved transposition table) must be keyed on this value.
+    pub const SEED: u64 = 0x9E37_79B9_7F4A_7C15;
+
     fn build() -> Self {
-        // Same golden-ratio seed used in the opening book for consistency.
-        let mut s = 0x9E37_79B9_7F4A_7C15u64;
+        let mut s = Self::SEED;
         let mut next = || xorshift64(&mut s);
 
         let mut pieces = [[0u64; 64]; 12];
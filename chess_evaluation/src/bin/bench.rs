@@ -15,11 +15,26 @@
 //!
 //! Shortcut for a full hash/thread grid (depth 12 recommended for TT benchmarks):
 //!   cargo run -p chess_evaluation --bin bench --release -- --hash-sweep
+//!
+//! Reproducible multi-threaded node counts (deterministic Lazy SMP):
+//!   cargo run -p chess_evaluation --bin bench --release -- --threads 4 --deterministic
+//!
+//! Pruning / cutoff statistics after each suite:
+//!   cargo run -p chess_evaluation --bin bench --release --features search-stats
+//!
+//! Signature (fixed single-thread iterative deepening, total node count like
+//! Stockfish's `bench`), saved as a baseline and compared later:
+//!   cargo run -p chess_evaluation --bin bench --release -- --signature
+//!   cargo run -p chess_evaluation --bin bench --release -- --save bench_signature.txt
+//!   cargo run -p chess_evaluation --bin bench --release -- --compare bench_signature.txt
+//! `--compare` exits with 1 when node counts changed (a functional change) and
+//! 2 when the baseline was made with another depth, hash, eval or position set.
+//! Same nodes at a different NPS is a speed change; `--nps-noise PCT` (default
+//! 3) sets how much NPS may move before it is reported as one.
 
 use chess_board::ChessBoard;
 use chess_evaluation::{
-    alpha_beta, iterative_deepening_root_with_tt,
-    SearchContext, TranspositionTable, TT_SIZE,
+    alpha_beta, Engine, SearchContext, SearchLimits, TranspositionTable, DEFAULT_HASH_MB, TT_SIZE,
 };
 
 /// Weights embedded at compile time — only included when a NN feature is active.
@@ -32,7 +47,11 @@ fn init_nn() {
         Ok(()) => {
             #[cfg(feature = "runtime-switch")]
             chess_evaluation::set_neural_eval_enabled(true);
-            eprintln!("Neural eval loaded ({} KB).", NNUE_WEIGHTS.len() / 1024);
+            eprintln!(
+                "Neural eval loaded ({} KB, {} kernels).",
+                NNUE_WEIGHTS.len() / 1024,
+                chess_evaluation::simd_level()
+            );
         }
         Err(e) => eprintln!("warn: neural eval not loaded: {e}"),
     }
@@ -99,12 +118,16 @@ fn bench_sequential(fen: &str, is_white: bool, depth: i32) -> (u64, u128, i32) {
     }
 
     let t0 = Instant::now();
+    // Same accumulator setup as search_root, so nn-incremental is measured
+    // on its incremental path rather than a full forward pass per node.
+    ctx.init_accumulators(&board);
 
     let mut best_score = if is_white { i32::MIN + 1 } else { i32::MAX };
     let alpha = i32::MIN + 1;
     let beta  = i32::MAX;
 
     for mut mv in moves {
+        ctx.acc_push(0, &mv);
         board.make_move(&mut mv);
         let (score, _) = alpha_beta(
             &mut board,
@@ -129,31 +152,22 @@ fn bench_sequential(fen: &str, is_white: bool, depth: i32) -> (u64, u128, i32) {
     (ctx.nodes, elapsed_ms, best_score)
 }
 
-/// Run a multi-threaded iterative-deepening search up to `depth`.
-/// Returns (total_nodes_across_all_threads, elapsed_ms, score).
-/// `tt_entries` overrides TT_SIZE when non-zero.
-fn bench_threaded(fen: &str, is_white: bool, depth: i32, num_threads: usize, tt_entries: usize) -> (u64, u128, i32) {
+/// Run a multi-threaded iterative-deepening search up to `depth` on a fresh
+/// engine (no book).  Returns (total_nodes_across_all_threads, elapsed_ms, score).
+/// `hash_mb` overrides the default table size when non-zero.
+fn bench_threaded(fen: &str, depth: i32, num_threads: usize, hash_mb: usize, deterministic: bool) -> (u64, u128, i32) {
     let mut board = ChessBoard::new();
     board.set_from_fen(fen);
-    let conductor = PieceConductor::new();
-    let entries = if tt_entries > 0 { tt_entries } else { TT_SIZE };
-    let tt = TranspositionTable::new(entries);
+    let mut engine = Engine::from_parts(PieceConductor::new(), None);
+    engine.set_hash_mb(if hash_mb > 0 { hash_mb } else { DEFAULT_HASH_MB });
+    engine.set_threads(num_threads);
+    engine.set_deterministic(deterministic);
+    engine.set_position(board);
 
     let t0 = Instant::now();
 
-    let result = iterative_deepening_root_with_tt(
-        &mut board,
-        &conductor,
-        None,
-        &tt,
-        depth,
-        is_white,
-        None, // no deadline — run to full depth
-        None, // no external stop
-        num_threads,
-        None,
-        0,
-    );
+    // No deadline or stop — run to full depth.
+    let result = engine.search(SearchLimits::depth(depth));
 
     let elapsed_ms = t0.elapsed().as_millis();
     (result.total_nodes, elapsed_ms, result.score)
@@ -174,15 +188,14 @@ struct BenchResult {
 ///             so all thread counts are directly comparable.
 /// `hash_mb`: transposition table size in MiB (0 = use TT_SIZE default).
 /// Returns a BenchResult for the whole suite.
-fn run_suite(depth: i32, num_threads: usize, force_id: bool, hash_mb: usize) -> BenchResult {
-    let entries_for_mb = |mb: usize| mb * 1024 * 1024 / 24;
-    let tt_entries = if hash_mb > 0 { entries_for_mb(hash_mb) } else { 0 };
-
+fn run_suite(depth: i32, num_threads: usize, force_id: bool, hash_mb: usize, deterministic: bool) -> BenchResult {
     let hash_str = if hash_mb > 0 { format!("{}MB", hash_mb) } else { "default".to_string() };
     let mode = if !force_id && num_threads <= 1 && hash_mb == 0 {
         "sequential fixed-depth (1 thread, deterministic)".to_string()
     } else if num_threads <= 1 {
         format!("iterative deepening (1 thread, hash={hash_str})")
+    } else if deterministic {
+        format!("deterministic Lazy SMP ({num_threads} threads, hash={hash_str})")
     } else {
         format!("Lazy SMP ({num_threads} threads, hash={hash_str})")
     };
@@ -193,11 +206,14 @@ fn run_suite(depth: i32, num_threads: usize, force_id: bool, hash_mb: usize) ->
     let mut total_nodes = 0u64;
     let mut total_ms    = 0u128;
 
+    #[cfg(feature = "search-stats")]
+    chess_evaluation::take_search_stats();
+
     for &(fen, is_white, label) in POSITIONS {
         let (nodes, ms, _score) = if !force_id && num_threads <= 1 && hash_mb == 0 {
             bench_sequential(fen, is_white, depth)
         } else {
-            bench_threaded(fen, is_white, depth, num_threads, tt_entries)
+            bench_threaded(fen, depth, num_threads, hash_mb, deterministic)
         };
         let nps = if ms > 0 { nodes as u128 * 1000 / ms } else { 0 };
         total_nodes += nodes;
@@ -214,6 +230,9 @@ fn run_suite(depth: i32, num_threads: usize, force_id: bool, hash_mb: usize) ->
               total_nodes={total_nodes}  avg_nps={avg_nps}  total_ms={total_ms}",
              POSITIONS.len());
 
+    #[cfg(feature = "search-stats")]
+    println!("\n{}", chess_evaluation::take_search_stats());
+
     BenchResult { hash_mb, threads: num_threads, depth, total_nodes, total_ms, avg_nps }
 }
 
@@ -255,15 +274,228 @@ fn print_top_results(results: &[BenchResult], n: usize) {
     println!();
 }
 
+// ── Signature mode ────────────────────────────────────────────────────────────
+//
+// The node count of a fixed search identifies the engine's behaviour: any
+// change to it is a functional change, while equal counts at a different
+// NPS are a speed change only.
+
+const SIGNATURE_DEPTH: i32 = 11;
+const SIGNATURE_HASH_MB: usize = 16;
+/// NPS differences within this many percent are reported as noise
+/// (`--nps-noise` overrides it).
+const NPS_NOISE_PCT: f64 = 3.0;
+const SIGNATURE_HEADER: &str = "bench-signature 1";
+
+/// A signature run: configuration plus (label, nodes, ms) per position.
+#[derive(Debug, PartialEq)]
+struct Signature {
+    eval: String,
+    depth: i32,
+    hash_mb: usize,
+    positions: Vec<(String, u64, u128)>,
+}
+
+impl Signature {
+    fn nodes(&self) -> u64 {
+        self.positions.iter().map(|p| p.1).sum()
+    }
+
+    fn ms(&self) -> u128 {
+        self.positions.iter().map(|p| p.2).sum()
+    }
+
+    fn nps(&self) -> u128 {
+        self.nodes() as u128 * 1000 / self.ms().max(1)
+    }
+
+    /// Tab-separated baseline file.
+    fn to_text(&self) -> String {
+        let mut out = format!(
+            "{SIGNATURE_HEADER}\neval\t{}\ndepth\t{}\nhash_mb\t{}\n",
+            self.eval, self.depth, self.hash_mb
+        );
+        for (label, nodes, ms) in &self.positions {
+            out.push_str(&format!("position\t{label}\t{nodes}\t{ms}\n"));
+        }
+        out.push_str(&format!("total\t{}\t{}\t{}\n", self.nodes(), self.ms(), self.nps()));
+        out
+    }
+
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines();
+        if lines.next() != Some(SIGNATURE_HEADER) {
+            return Err(format!("not a bench signature file (expected {SIGNATURE_HEADER:?} first)"));
+        }
+        let mut sig = Signature { eval: String::new(), depth: 0, hash_mb: 0, positions: Vec::new() };
+        for line in lines {
+            let fields: Vec<&str> = line.split('\t').collect();
+            let num = |i: usize| -> Result<u128, String> {
+                fields.get(i).and_then(|f| f.parse().ok()).ok_or_else(|| format!("bad line {line:?}"))
+            };
+            match fields[0] {
+                "eval" => sig.eval = fields.get(1).unwrap_or(&"").to_string(),
+                "depth" => sig.depth = num(1)? as i32,
+                "hash_mb" => sig.hash_mb = num(1)? as usize,
+                "position" => sig.positions.push((fields.get(1).unwrap_or(&"").to_string(), num(2)? as u64, num(3)?)),
+                "total" | "" => {}
+                other => return Err(format!("unknown key {other:?}")),
+            }
+        }
+        Ok(sig)
+    }
+}
+
+/// Evaluation backend compiled in, plus whether a network is loaded.
+fn eval_backend() -> String {
+    let backend = if cfg!(feature = "nn-incremental") {
+        "nn-incremental"
+    } else if cfg!(feature = "nn-full-forward") {
+        "nn-full-forward"
+    } else if cfg!(feature = "runtime-switch") {
+        "runtime-switch"
+    } else {
+        "classical-eval"
+    };
+    if chess_evaluation::is_neural_eval_initialized() {
+        format!("{backend}+net")
+    } else {
+        backend.to_string()
+    }
+}
+
+fn run_signature(depth: i32, hash_mb: usize) -> Signature {
+    println!("\n=== signature  depth={depth}  1 thread  hash={hash_mb}MB ===\n");
+    println!("{:<28} {:>12} {:>10} {:>12}", "Position", "Nodes", "ms", "NPS");
+    println!("{}", "-".repeat(66));
+    let mut positions = Vec::with_capacity(POSITIONS.len());
+    for &(fen, _, label) in POSITIONS {
+        let (nodes, ms, _) = bench_threaded(fen, depth, 1, hash_mb, false);
+        let nps = (nodes as u128 * 1000).checked_div(ms).unwrap_or(0);
+        println!("{:<28} {:>12} {:>10} {:>12}", label, nodes, ms, nps);
+        positions.push((label.to_string(), nodes, ms));
+    }
+    let sig = Signature { eval: eval_backend(), depth, hash_mb, positions };
+    println!("{}", "-".repeat(66));
+    println!("{:<28} {:>12} {:>10} {:>12}", "TOTAL / AVG NPS", sig.nodes(), sig.ms(), sig.nps());
+    println!();
+    println!("signature eval={} depth={depth} hash={hash_mb}MB nodes={} ms={} nps={}",
+             sig.eval, sig.nodes(), sig.ms(), sig.nps());
+    sig
+}
+
+/// How a run differs from its baseline.
+#[derive(Debug, PartialEq)]
+enum Verdict {
+    /// Baseline made with another configuration; nothing to compare.
+    Incompatible(String),
+    /// Node counts differ: the search behaves differently.
+    Functional,
+    /// Same nodes, NPS moved by more than the noise threshold (percent).
+    Speed(f64),
+    /// Same nodes, NPS within noise (percent).
+    Unchanged(f64),
+}
+
+fn compare(base: &Signature, now: &Signature, nps_noise: f64) -> Verdict {
+    if base.eval != now.eval || base.depth != now.depth || base.hash_mb != now.hash_mb {
+        return Verdict::Incompatible(format!(
+            "baseline is eval={} depth={} hash={}MB, this run is eval={} depth={} hash={}MB",
+            base.eval, base.depth, base.hash_mb, now.eval, now.depth, now.hash_mb
+        ));
+    }
+    let labels = |s: &Signature| s.positions.iter().map(|p| p.0.clone()).collect::<Vec<_>>();
+    if labels(base) != labels(now) {
+        return Verdict::Incompatible("the position list changed".to_string());
+    }
+    if base.positions.iter().zip(&now.positions).any(|(b, n)| b.1 != n.1) {
+        return Verdict::Functional;
+    }
+    let pct = (now.nps() as f64 / base.nps().max(1) as f64 - 1.0) * 100.0;
+    if pct.abs() > nps_noise {
+        Verdict::Speed(pct)
+    } else {
+        Verdict::Unchanged(pct)
+    }
+}
+
+/// Print the comparison and return the process exit code.
+fn report(base: &Signature, now: &Signature, nps_noise: f64) -> i32 {
+    let verdict = compare(base, now, nps_noise);
+    if let Verdict::Incompatible(why) = &verdict {
+        println!("\nINCOMPATIBLE BASELINE: {why}");
+        return 2;
+    }
+    println!("\n{:<28} {:>12} {:>12} {:>9}", "Position", "Base nodes", "Nodes", "Change");
+    println!("{}", "-".repeat(64));
+    for (b, n) in base.positions.iter().zip(&now.positions) {
+        let change = (n.1 as f64 / b.1.max(1) as f64 - 1.0) * 100.0;
+        let mark = if b.1 != n.1 { " *" } else { "" };
+        println!("{:<28} {:>12} {:>12} {:>+8.1}%{mark}", b.0, b.1, n.1, change);
+    }
+    println!("{}", "-".repeat(64));
+    let node_change = (now.nodes() as f64 / base.nodes().max(1) as f64 - 1.0) * 100.0;
+    let nps_change = (now.nps() as f64 / base.nps().max(1) as f64 - 1.0) * 100.0;
+    println!("{:<28} {:>12} {:>12} {:>+8.1}%", "TOTAL", base.nodes(), now.nodes(), node_change);
+    println!("{:<28} {:>12} {:>12} {:>+8.1}%", "NPS", base.nps(), now.nps(), nps_change);
+    match verdict {
+        Verdict::Functional => {
+            println!("\nFUNCTIONAL CHANGE: node counts differ (marked *)");
+            1
+        }
+        Verdict::Speed(pct) => {
+            println!("\nSPEED CHANGE: same nodes, NPS {pct:+.1}%");
+            0
+        }
+        Verdict::Unchanged(pct) => {
+            println!("\nNO CHANGE: same nodes, NPS {pct:+.1}% (within ±{nps_noise}%)");
+            0
+        }
+        Verdict::Incompatible(_) => unreachable!(),
+    }
+}
+
+fn signature_main(args: &[String]) {
+    let arg = |name: &str| args.windows(2).find(|w| w[0] == name).map(|w| w[1].clone());
+    let baseline = arg("--compare").map(|path| {
+        let text = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("cannot read {path}: {e}"));
+        Signature::parse(&text).unwrap_or_else(|e| panic!("{path}: {e}"))
+    });
+    let depth = arg("--depth")
+        .and_then(|d| d.parse().ok())
+        .or(baseline.as_ref().map(|b| b.depth))
+        .unwrap_or(SIGNATURE_DEPTH);
+    let hash_mb = arg("--hash")
+        .and_then(|h| h.parse().ok())
+        .or(baseline.as_ref().map(|b| b.hash_mb))
+        .unwrap_or(SIGNATURE_HASH_MB);
+
+    let sig = run_signature(depth, hash_mb);
+    if let Some(path) = arg("--save") {
+        std::fs::write(&path, sig.to_text()).unwrap_or_else(|e| panic!("cannot write {path}: {e}"));
+        println!("saved {path}");
+    }
+    if let Some(base) = baseline {
+        let nps_noise = arg("--nps-noise").and_then(|n| n.parse().ok()).unwrap_or(NPS_NOISE_PCT);
+        std::process::exit(report(&base, &sig, nps_noise));
+    }
+}
+
 fn main() {
     #[cfg(any(feature = "nn-full-forward", feature = "nn-incremental", feature = "runtime-switch"))]
     init_nn();
 
     let args: Vec<String> = std::env::args().collect();
 
+    if args.iter().any(|a| a == "--signature" || a == "--save" || a == "--compare") {
+        signature_main(&args);
+        return;
+    }
+
     // --hash-sweep: predefined grid of hash sizes and thread counts at depth 12.
     // The right depth to show meaningful TT hit rate differences.
     let hash_sweep = args.iter().any(|a| a == "--hash-sweep");
+    let deterministic = args.iter().any(|a| a == "--deterministic");
 
     let depth: i32 = args.windows(2)
         .find(|w| w[0] == "--depth")
@@ -316,7 +548,7 @@ fn main() {
 
     for &hash_mb in &hash_sizes {
         for &tc in &thread_counts {
-            let result = run_suite(depth, tc, force_id, hash_mb);
+            let result = run_suite(depth, tc, force_id, hash_mb, deterministic);
             all_results.push(result);
         }
     }
@@ -326,3 +558,37 @@ fn main() {
         print_top_results(&all_results, 5);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signature(nodes: &[u64], ms: u128) -> Signature {
+        Signature {
+            eval: "classical-eval".to_string(),
+            depth: 11,
+            hash_mb: 16,
+            positions: nodes.iter().enumerate().map(|(i, &n)| (format!("pos {i}"), n, ms)).collect(),
+        }
+    }
+
+    #[test]
+    fn signature_file_round_trips() {
+        let sig = signature(&[1000, 2500, 7], 20);
+        assert_eq!(Signature::parse(&sig.to_text()).unwrap(), sig);
+        assert!(Signature::parse("depth\t11\n").is_err());
+    }
+
+    #[test]
+    fn node_changes_are_functional_and_nps_changes_are_speed() {
+        let base = signature(&[1000, 2000], 100);
+        assert_eq!(compare(&base, &signature(&[1000, 2001], 100), NPS_NOISE_PCT), Verdict::Functional);
+        assert!(matches!(compare(&base, &signature(&[1000, 2000], 80), NPS_NOISE_PCT), Verdict::Speed(p) if p > 20.0));
+        assert!(matches!(compare(&base, &signature(&[1000, 2000], 101), NPS_NOISE_PCT), Verdict::Unchanged(_)));
+
+        let mut deeper = signature(&[1000, 2000], 100);
+        deeper.depth = 12;
+        assert!(matches!(compare(&base, &deeper, NPS_NOISE_PCT), Verdict::Incompatible(_)));
+        assert!(matches!(compare(&base, &signature(&[1000], 100), NPS_NOISE_PCT), Verdict::Incompatible(_)));
+    }
+}
@@ -0,0 +1,126 @@
+//! datagen — self-play training data for the NNUE.
+//!
+//! Plays engine-vs-engine games in parallel from random openings and writes
+//! the quiet positions as JSONL, one `{fen, cp, wdl, result}` record per
+//! line with everything from the side to move, ready for `nnue_preprocess`.
+//! The output depends on the options and `--seed` only, not on `--threads`.
+//!
+//! Usage:
+//!   cargo run -p chess_evaluation --bin datagen --release -- --games 10000 --nodes 5000 --out selfplay.jsonl
+//!   cargo run -p chess_evaluation --bin datagen --release -- --games 2000 --depth 8 --threads 8 --seed 7 --out d8.jsonl
+//!
+//! Limits: `--nodes N` (a hard per-move budget: the search stops after N
+//! nodes and plays its last completed iteration's move; default 5000 unless
+//! `--depth` is given) and `--depth N`.  `--random-plies N` random opening
+//! moves (8), redrawn while the first search scores them beyond
+//! `--opening-max-cp` (300).  Adjudication: `--win-cp` (1000) for
+//! `--win-plies` (6) plies, `--draw-cp` (10) for `--draw-plies` (12) plies
+//! after ply `--draw-after` (80); a threshold of 0 disables it.  Games end
+//! at `--max-plies` (400).  `--max-cp` (3000) drops positions scored beyond
+//! it.  `--hash MB` sizes each thread's table (16).
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::time::Instant;
+
+use chess_evaluation::datagen::{generate, DatagenConfig, GameResult};
+
+/// Weights embedded at compile time — only included when a NN feature is active.
+#[cfg(any(feature = "nn-full-forward", feature = "nn-incremental", feature = "runtime-switch"))]
+static NNUE_WEIGHTS: &[u8] = include_bytes!("../eval.npz");
+
+#[cfg(any(feature = "nn-full-forward", feature = "nn-incremental", feature = "runtime-switch"))]
+fn init_nn() {
+    match chess_evaluation::init_neural_eval_from_bytes(NNUE_WEIGHTS) {
+        Ok(()) => {
+            #[cfg(feature = "runtime-switch")]
+            chess_evaluation::set_neural_eval_enabled(true);
+            eprintln!("Neural eval loaded ({} KB).", NNUE_WEIGHTS.len() / 1024);
+        }
+        Err(e) => eprintln!("warn: neural eval not loaded: {e}"),
+    }
+}
+
+const MAX_DEPTH: i32 = 64;
+
+fn arg<T: std::str::FromStr>(args: &[String], name: &str) -> Option<T> {
+    args.windows(2).find(|w| w[0] == name).map(|w| {
+        w[1].parse().unwrap_or_else(|_| panic!("bad value for {name}: {}", w[1]))
+    })
+}
+
+fn main() {
+    #[cfg(any(feature = "nn-full-forward", feature = "nn-incremental", feature = "runtime-switch"))]
+    init_nn();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some(out) = arg::<String>(&args, "--out") else {
+        eprintln!(
+            "Usage: datagen --out FILE [--games N] [--nodes N] [--depth N] [--threads N] [--seed N] [--hash MB] \
+             [--random-plies N] [--opening-max-cp CP] [--max-plies N] [--win-cp CP] [--win-plies N] \
+             [--draw-cp CP] [--draw-plies N] [--draw-after N] [--max-cp CP]"
+        );
+        std::process::exit(2);
+    };
+
+    let defaults = DatagenConfig::default();
+    let depth: Option<i32> = arg(&args, "--depth");
+    let config = DatagenConfig {
+        games: arg(&args, "--games").unwrap_or(defaults.games),
+        depth: depth.unwrap_or(MAX_DEPTH).clamp(1, MAX_DEPTH),
+        nodes: arg(&args, "--nodes").or(if depth.is_some() { None } else { defaults.nodes }),
+        hash_mb: arg(&args, "--hash").unwrap_or(defaults.hash_mb),
+        random_plies: arg(&args, "--random-plies").unwrap_or(defaults.random_plies),
+        opening_max_cp: arg(&args, "--opening-max-cp").unwrap_or(defaults.opening_max_cp),
+        max_plies: arg(&args, "--max-plies").unwrap_or(defaults.max_plies),
+        win_cp: arg(&args, "--win-cp").unwrap_or(defaults.win_cp),
+        win_plies: arg(&args, "--win-plies").unwrap_or(defaults.win_plies),
+        draw_cp: arg(&args, "--draw-cp").unwrap_or(defaults.draw_cp),
+        draw_plies: arg(&args, "--draw-plies").unwrap_or(defaults.draw_plies),
+        draw_after: arg(&args, "--draw-after").unwrap_or(defaults.draw_after),
+        max_cp: arg(&args, "--max-cp").unwrap_or(defaults.max_cp),
+        seed: arg(&args, "--seed").unwrap_or(defaults.seed),
+    };
+    let threads = arg::<usize>(&args, "--threads").unwrap_or_else(chess_evaluation::available_threads).max(1);
+
+    let file = File::create(&out).unwrap_or_else(|e| panic!("cannot create {out}: {e}"));
+    let mut writer = BufWriter::new(file);
+    eprintln!(
+        "{} games, depth {}, nodes {}, {threads} threads, seed {} → {out}",
+        config.games,
+        config.depth,
+        config.nodes.map_or_else(|| "-".to_string(), |n| n.to_string()),
+        config.seed
+    );
+
+    let t0 = Instant::now();
+    let (mut positions, mut plies) = (0usize, 0usize);
+    let mut results = [0usize; 3];
+    generate(&config, threads, |i, game| {
+        for record in &game.records {
+            writeln!(writer, "{}", record.to_json()).unwrap_or_else(|e| panic!("cannot write {out}: {e}"));
+        }
+        positions += game.records.len();
+        plies += game.plies;
+        results[match game.result {
+            GameResult::WhiteWins => 0,
+            GameResult::Draw => 1,
+            GameResult::BlackWins => 2,
+        }] += 1;
+        let done = i + 1;
+        if done % 100 == 0 || done == config.games {
+            let secs = t0.elapsed().as_secs_f64();
+            eprintln!(
+                "games {done}/{}  +{} ={} -{}  positions {positions}  plies/game {:.0}  {:.1} games/s",
+                config.games,
+                results[0],
+                results[1],
+                results[2],
+                plies as f64 / done as f64,
+                done as f64 / secs
+            );
+        }
+    });
+    writer.flush().unwrap_or_else(|e| panic!("cannot write {out}: {e}"));
+    eprintln!("wrote {positions} positions to {out} in {:.1}s", t0.elapsed().as_secs_f64());
+}
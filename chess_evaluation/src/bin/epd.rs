@@ -0,0 +1,333 @@
+//! epd — run an EPD test suite (WAC, ECM, STS, ...) through the search.
+//!
+//! Every position is searched from a cleared hash table.  A position is
+//! solved when the final best move is a `bm` move (or avoids every `am`
+//! move); its time to solution is when the iteration that settled on a
+//! solving move for good completed.  STS suites are also scored by the
+//! points in their `c0` comments.
+//!
+//! Usage:
+//!   cargo run -p chess_evaluation --bin epd --release -- wac.epd
+//!   cargo run -p chess_evaluation --bin epd --release -- wac.epd --movetime 500
+//!   cargo run -p chess_evaluation --bin epd --release -- sts*.epd --depth 10 --threads 4
+//!   cargo run -p chess_evaluation --bin epd --release -- ecm.epd --nodes 2000000 --json ecm.json
+//!   cargo run -p chess_evaluation --bin epd --release -- wac.epd --depth 8 --json - | jq .solved
+//!
+//! Limits: `--movetime MS` (default 1000 unless `--depth` or `--nodes` is
+//! given), `--depth N`, `--nodes N` (no new iteration once reached).
+//! `--hash MB` and `--threads N` size the search.  `--json FILE` writes the
+//! per-position results for comparing builds; `-` writes them to stdout and
+//! moves the table and summary to stderr, so stdout is only the JSON.
+//! Scores in the output are from the side to move.
+
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chess_evaluation::epd::{parse_epd, to_san, EpdEntry, STS_MAX_POINTS};
+use chess_evaluation::{iterative_deepening_root_with_tt, DrawScores, TranspositionTable, DEFAULT_HASH_MB};
+use chess_foundation::ChessMove;
+use move_generator::{move_generator::get_all_legal_moves_for_color, piece_conductor::PieceConductor};
+
+/// Weights embedded at compile time — only included when a NN feature is active.
+#[cfg(any(feature = "nn-full-forward", feature = "nn-incremental", feature = "runtime-switch"))]
+static NNUE_WEIGHTS: &[u8] = include_bytes!("../eval.npz");
+
+#[cfg(any(feature = "nn-full-forward", feature = "nn-incremental", feature = "runtime-switch"))]
+fn init_nn() {
+    match chess_evaluation::init_neural_eval_from_bytes(NNUE_WEIGHTS) {
+        Ok(()) => {
+            #[cfg(feature = "runtime-switch")]
+            chess_evaluation::set_neural_eval_enabled(true);
+            eprintln!("Neural eval loaded ({} KB).", NNUE_WEIGHTS.len() / 1024);
+        }
+        Err(e) => eprintln!("warn: neural eval not loaded: {e}"),
+    }
+}
+
+const MAX_DEPTH: i32 = 64;
+const DEFAULT_MOVETIME_MS: u64 = 1000;
+
+struct Limits {
+    depth: i32,
+    movetime: Option<Duration>,
+    nodes: Option<u64>,
+    hash_mb: usize,
+    threads: usize,
+}
+
+/// Outcome of one position.
+struct PositionResult {
+    file: String,
+    id: String,
+    fen: String,
+    expected: Vec<String>,
+    avoid: Vec<String>,
+    played: String,
+    solved: bool,
+    /// Time, depth and nodes when the search settled on a solving move.
+    solved_at: Option<(u128, i32, u64)>,
+    points: Option<u32>,
+    score: i32,
+    depth: i32,
+    nodes: u64,
+    time_ms: u128,
+}
+
+fn run_position(entry: &EpdEntry, file: &str, index: usize, limits: &Limits, tt: &TranspositionTable, conductor: &PieceConductor) -> PositionResult {
+    let mut board = entry.board();
+    let is_white = board.is_white_active();
+    let mut legal = Vec::new();
+    get_all_legal_moves_for_color(&mut board, conductor, is_white, &mut legal, &mut Vec::new());
+    let solutions: Vec<ChessMove> =
+        legal.iter().copied().filter(|&m| entry.is_solution(&mut board, conductor, m)).collect();
+    let is_solution = |mv: &ChessMove| {
+        solutions.iter().any(|s| s.start_square() == mv.start_square() && s.target_square() == mv.target_square()
+            && s.promotion_piece_type() == mv.promotion_piece_type())
+    };
+
+    // (depth, elapsed_ms, nodes, solving) per completed iteration.
+    let iterations: Mutex<Vec<(i32, u128, u64, bool)>> = Mutex::new(Vec::new());
+    let stop = Arc::new(AtomicBool::new(false));
+    let on_depth = |depth: i32, _score: i32, nodes: u64, elapsed_ms: u128, best: Option<ChessMove>| {
+        iterations.lock().unwrap().push((depth, elapsed_ms, nodes, best.is_some_and(|m| is_solution(&m))));
+        if limits.nodes.is_some_and(|n| nodes >= n) {
+            stop.store(true, Ordering::Relaxed);
+        }
+    };
+
+    tt.clear();
+    let t0 = Instant::now();
+    let result = iterative_deepening_root_with_tt(
+        &mut board,
+        conductor,
+        None,
+        tt,
+        limits.depth,
+        is_white,
+        limits.movetime.map(|t| t0 + t),
+        Some(stop.clone()),
+        limits.threads,
+        Some(&on_depth),
+        0,
+        false,
+        DrawScores::default(),
+    );
+    let time_ms = t0.elapsed().as_millis();
+
+    let iterations = iterations.into_inner().unwrap();
+    let played = result.best_move;
+    let solved = played.is_some_and(|m| is_solution(&m));
+    let solved_at = solved.then(|| {
+        // First iteration of the final solving streak; a solution found
+        // only in an unfinished iteration counts at the end of the search.
+        let streak = iterations.iter().rev().take_while(|it| it.3).count();
+        match iterations.len().checked_sub(streak).and_then(|i| iterations.get(i)) {
+            Some(&(depth, ms, nodes, _)) => (ms, depth, nodes),
+            None => (time_ms, iterations.last().map_or(0, |it| it.0), result.total_nodes),
+        }
+    });
+    let depth = iterations.last().map_or(0, |it| it.0);
+
+    PositionResult {
+        file: file.to_string(),
+        id: entry.id.clone().unwrap_or_else(|| format!("{file}:{}", index + 1)),
+        fen: entry.fen.clone(),
+        expected: entry.best_moves.clone(),
+        avoid: entry.avoid_moves.clone(),
+        played: played.map_or_else(|| "-".to_string(), |m| to_san(&mut board, conductor, m)),
+        solved,
+        solved_at,
+        points: played.and_then(|m| entry.points_for(&mut board, conductor, m)),
+        score: if is_white { result.score } else { -result.score },
+        depth,
+        nodes: result.total_nodes,
+        time_ms,
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn json_list(items: &[String]) -> String {
+    format!("[{}]", items.iter().map(|s| json_string(s)).collect::<Vec<_>>().join(", "))
+}
+
+fn json_opt<T: std::fmt::Display>(v: Option<T>) -> String {
+    v.map_or_else(|| "null".to_string(), |v| v.to_string())
+}
+
+fn to_json(results: &[PositionResult], limits: &Limits, total_ms: u128) -> String {
+    let solved = results.iter().filter(|r| r.solved).count();
+    let sts: Vec<u32> = results.iter().filter_map(|r| r.points).collect();
+    let mut out = String::from("{\n");
+    let _ = writeln!(
+        out,
+        "  \"limits\": {{\"depth\": {}, \"movetime_ms\": {}, \"nodes\": {}, \"hash_mb\": {}, \"threads\": {}}},",
+        limits.depth,
+        json_opt(limits.movetime.map(|t| t.as_millis())),
+        json_opt(limits.nodes),
+        limits.hash_mb,
+        limits.threads
+    );
+    let _ = writeln!(out, "  \"positions\": {},", results.len());
+    let _ = writeln!(out, "  \"solved\": {solved},");
+    let _ = writeln!(out, "  \"sts_points\": {},", sts.iter().sum::<u32>());
+    let _ = writeln!(out, "  \"sts_max\": {},", sts.len() as u32 * STS_MAX_POINTS);
+    let _ = writeln!(out, "  \"total_ms\": {total_ms},");
+    out.push_str("  \"results\": [\n");
+    for (i, r) in results.iter().enumerate() {
+        let _ = write!(
+            out,
+            "    {{\"file\": {}, \"id\": {}, \"fen\": {}, \"bm\": {}, \"am\": {}, \"played\": {}, \"solved\": {}, \
+             \"solved_ms\": {}, \"solved_depth\": {}, \"solved_nodes\": {}, \"points\": {}, \"score\": {}, \
+             \"depth\": {}, \"nodes\": {}, \"time_ms\": {}}}",
+            json_string(&r.file),
+            json_string(&r.id),
+            json_string(&r.fen),
+            json_list(&r.expected),
+            json_list(&r.avoid),
+            json_string(&r.played),
+            r.solved,
+            json_opt(r.solved_at.map(|s| s.0)),
+            json_opt(r.solved_at.map(|s| s.1)),
+            json_opt(r.solved_at.map(|s| s.2)),
+            json_opt(r.points),
+            r.score,
+            r.depth,
+            r.nodes,
+            r.time_ms
+        );
+        out.push_str(if i + 1 < results.len() { ",\n" } else { "\n" });
+    }
+    out.push_str("  ]\n}\n");
+    out
+}
+
+fn arg<T: std::str::FromStr>(args: &[String], name: &str) -> Option<T> {
+    args.windows(2).find(|w| w[0] == name).map(|w| {
+        w[1].parse().unwrap_or_else(|_| panic!("bad value for {name}: {}", w[1]))
+    })
+}
+
+fn main() {
+    #[cfg(any(feature = "nn-full-forward", feature = "nn-incremental", feature = "runtime-switch"))]
+    init_nn();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut files = Vec::new();
+    let mut i = 0;
+    while i < args.len() {
+        if args[i].starts_with("--") {
+            i += 2;
+        } else {
+            files.push(args[i].clone());
+            i += 1;
+        }
+    }
+    if files.is_empty() {
+        eprintln!("Usage: epd <suite.epd>... [--depth N] [--movetime MS] [--nodes N] [--hash MB] [--threads N] [--json FILE]");
+        std::process::exit(2);
+    }
+
+    let depth: Option<i32> = arg(&args, "--depth");
+    let nodes: Option<u64> = arg(&args, "--nodes");
+    let movetime: Option<u64> = arg(&args, "--movetime");
+    let limits = Limits {
+        depth: depth.unwrap_or(MAX_DEPTH).clamp(1, MAX_DEPTH),
+        movetime: movetime
+            .or((depth.is_none() && nodes.is_none()).then_some(DEFAULT_MOVETIME_MS))
+            .map(Duration::from_millis),
+        nodes,
+        hash_mb: arg(&args, "--hash").unwrap_or(DEFAULT_HASH_MB),
+        threads: arg::<usize>(&args, "--threads").unwrap_or(1).max(1),
+    };
+    let json: Option<String> = arg(&args, "--json");
+    // With `--json -`, stdout carries the JSON alone.
+    let json_to_stdout = json.as_deref() == Some("-");
+    macro_rules! report {
+        ($($arg:tt)*) => {
+            if json_to_stdout { eprintln!($($arg)*) } else { println!($($arg)*) }
+        };
+    }
+
+    let conductor = PieceConductor::new();
+    let tt = TranspositionTable::with_mb(limits.hash_mb);
+    let mut results = Vec::new();
+    let t0 = Instant::now();
+
+    report!("{:<24} {:<12} {:<8} {:>6} {:>6} {:>8} {:>12}", "Id", "Expected", "Played", "Result", "Depth", "ms", "Nodes");
+    report!("{}", "-".repeat(82));
+    for file in &files {
+        let text = std::fs::read_to_string(file).unwrap_or_else(|e| panic!("cannot read {file}: {e}"));
+        let entries = parse_epd(&text).unwrap_or_else(|e| panic!("{file}: {e}"));
+        for (index, entry) in entries.iter().enumerate() {
+            let mut board = entry.board();
+            let unknown = entry.unknown_moves(&mut board, &conductor);
+            if !unknown.is_empty() {
+                eprintln!("warn: {file}:{}: moves not legal in the position: {}", index + 1, unknown.join(" "));
+            }
+            let r = run_position(entry, file, index, &limits, &tt, &conductor);
+            let expected = if r.expected.is_empty() {
+                format!("not {}", r.avoid.join(" "))
+            } else {
+                r.expected.join(" ")
+            };
+            let outcome = match (r.solved, r.points) {
+                (_, Some(p)) => format!("{p}/{STS_MAX_POINTS}"),
+                (true, None) => "ok".to_string(),
+                (false, None) => "FAIL".to_string(),
+            };
+            let (ms, depth, nodes) = r.solved_at.unwrap_or((r.time_ms, r.depth, r.nodes));
+            report!("{:<24} {:<12} {:<8} {:>6} {:>6} {:>8} {:>12}", r.id, expected, r.played, outcome, depth, ms, nodes);
+            results.push(r);
+        }
+    }
+    let total_ms = t0.elapsed().as_millis();
+
+    report!("{}", "-".repeat(82));
+    let solved: Vec<&PositionResult> = results.iter().filter(|r| r.solved).collect();
+    let pct = |n: usize, d: usize| if d > 0 { 100.0 * n as f64 / d as f64 } else { 0.0 };
+    report!("solved {}/{} ({:.1}%)", solved.len(), results.len(), pct(solved.len(), results.len()));
+    if !solved.is_empty() {
+        let mut times: Vec<u128> = solved.iter().filter_map(|r| r.solved_at.map(|s| s.0)).collect();
+        times.sort_unstable();
+        report!(
+            "time to solution: mean {} ms, median {} ms, max {} ms",
+            times.iter().sum::<u128>() / times.len() as u128,
+            times[times.len() / 2],
+            times[times.len() - 1]
+        );
+    }
+    let sts: Vec<u32> = results.iter().filter_map(|r| r.points).collect();
+    if !sts.is_empty() {
+        let max = sts.len() as u32 * STS_MAX_POINTS;
+        let points: u32 = sts.iter().sum();
+        report!("STS score {points}/{max} ({:.1}%)", pct(points as usize, max as usize));
+    }
+    report!("total time {total_ms} ms");
+
+    if let Some(path) = json {
+        let text = to_json(&results, &limits, total_ms);
+        if path == "-" {
+            print!("{text}");
+        } else {
+            std::fs::write(&path, text).unwrap_or_else(|e| panic!("cannot write {path}: {e}"));
+            eprintln!("wrote {path}");
+        }
+    }
+}
@@ -0,0 +1,117 @@
+//! net_convert — turn an exported `.npz` into a native net file, or show
+//! what a net file contains.
+//!
+//! Usage:
+//!   cargo run -p chess_evaluation --bin net_convert --release -- weights.npz weights.nnue
+//!   cargo run -p chess_evaluation --bin net_convert --release -- weights.npz weights.nnue \
+//!       --meta dataset=ccrl-2026 --meta epochs=40
+//!   cargo run -p chess_evaluation --bin net_convert --release -- weights.npz weights.nnue \
+//!       --features halfka --king-buckets 16
+//!   cargo run -p chess_evaluation --bin net_convert --release -- --info weights.nnue
+//!
+//! Conversion records `source=<input file name>` plus every `--meta KEY=VALUE`
+//! in the file's metadata.  Either format can be the input, so a native file
+//! can be relabelled too: its metadata, `source` included, is kept and
+//! `--meta` keys replace existing ones.  The content hash only covers
+//! architecture and weights, so it is the same for the `.npz` and the
+//! converted file.
+//!
+//! An `.npz` only gives the input size, which can fit several feature
+//! layouts (HalfKP with 8 or 16 king buckets have the same size); the first
+//! trained one is assumed.  `--features` and `--king-buckets` say which
+//! layout the net was actually trained on.
+
+use std::path::Path;
+
+use chess_evaluation::features::{FeatureLayout, FeatureSet, KingBuckets};
+use chess_evaluation::net_file::NetFile;
+
+fn usage() -> ! {
+    eprintln!("Usage: net_convert <in.npz|in.nnue> <out.nnue> [--meta KEY=VALUE]...");
+    eprintln!("                   [--features legacy-768|halfkp|halfka] [--king-buckets 8|16|32]");
+    eprintln!("       net_convert --info <file>");
+    std::process::exit(2);
+}
+
+fn load(path: &str) -> NetFile {
+    let bytes = std::fs::read(path).unwrap_or_else(|e| {
+        eprintln!("error: cannot read {path}: {e}");
+        std::process::exit(1);
+    });
+    NetFile::from_bytes(&bytes).unwrap_or_else(|e| {
+        eprintln!("error: {path}: {e}");
+        std::process::exit(1);
+    })
+}
+
+fn print_info(net: &NetFile) {
+    println!("architecture  {}", net.arch);
+    println!("hash          {}", net.hash_hex());
+    for (key, value) in &net.metadata {
+        println!("{:<13} {value}", key);
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("--info") {
+        let Some(path) = args.get(1) else { usage() };
+        print_info(&load(path));
+        return;
+    }
+
+    let mut files = Vec::new();
+    let mut meta = Vec::new();
+    let mut set = None;
+    let mut kings = None;
+    let mut i = 0;
+    while i < args.len() {
+        if args[i] == "--meta" {
+            let Some((key, value)) = args.get(i + 1).and_then(|kv| kv.split_once('=')) else { usage() };
+            meta.push((key.to_string(), value.to_string()));
+            i += 2;
+        } else if args[i] == "--features" {
+            let Some(Ok(s)) = args.get(i + 1).map(|s| s.parse::<FeatureSet>()) else { usage() };
+            set = Some(s);
+            i += 2;
+        } else if args[i] == "--king-buckets" {
+            let Some(k) = args.get(i + 1).and_then(|n| n.parse().ok()).and_then(KingBuckets::from_count) else {
+                usage()
+            };
+            kings = Some(k);
+            i += 2;
+        } else if args[i].starts_with("--") {
+            usage();
+        } else {
+            files.push(args[i].clone());
+            i += 1;
+        }
+    }
+    let [input, output] = files.as_slice() else { usage() };
+
+    let mut net = load(input);
+    if set.is_some() || kings.is_some() {
+        let current = net.arch.features;
+        let layout = FeatureLayout::new(set.unwrap_or(current.set), kings.unwrap_or(current.kings));
+        net = net.with_features(layout).unwrap_or_else(|e| {
+            eprintln!("error: {input}: {e}");
+            std::process::exit(1);
+        });
+    }
+    let source = Path::new(input).file_name().map_or(input.clone(), |n| n.to_string_lossy().into_owned());
+    if !net.metadata.iter().any(|(k, _)| k == "source") {
+        net.metadata.push(("source".to_string(), source));
+    }
+    for (key, value) in meta {
+        net.metadata.retain(|(k, _)| *k != key);
+        net.metadata.push((key, value));
+    }
+
+    let bytes = net.to_bytes();
+    std::fs::write(output, &bytes).unwrap_or_else(|e| {
+        eprintln!("error: cannot write {output}: {e}");
+        std::process::exit(1);
+    });
+    println!("wrote {output} ({} KB)", bytes.len() / 1024);
+    print_info(&net);
+}
@@ -0,0 +1,434 @@
+//! Self-play training data.
+//!
+//! Plays engine-vs-engine games from randomized openings and records the
+//! quiet positions along the way, scored by the search that chose the move.
+//! Each game is a pure function of the seed and its index: the opening
+//! moves come from a per-game RNG, every search is single-threaded on a
+//! hash table cleared at the start of the game, and node limits are exact
+//! counts that stop a search mid-iteration.  The `datagen` binary writes
+//! the records as JSONL for `nnue_preprocess`.
+
+use chess_board::{ChessBoard, FENParser};
+use chess_foundation::ChessMove;
+use move_generator::{move_generator::get_all_legal_moves_for_color, piece_conductor::PieceConductor};
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use rayon::prelude::*;
+
+use crate::alpha_beta::{iterative_deepening_root_with_context, DrawScores, SearchContext};
+use crate::see::see;
+use crate::transposition_table::TranspositionTable;
+
+/// 50-move rule: 100 half-moves without a pawn move or capture.
+const FIFTY_MOVE_PLIES: u32 = 100;
+
+/// Attempts at a random opening inside `opening_max_cp` before taking the
+/// last one anyway.
+const OPENING_ATTEMPTS: usize = 16;
+
+#[derive(Clone, Debug)]
+pub struct DatagenConfig {
+    pub games: usize,
+    /// Deepest iteration of every search.
+    pub depth: i32,
+    /// Nodes per search.  The search stops as soon as it has visited this
+    /// many and plays the best move of its last completed iteration.
+    pub nodes: Option<u64>,
+    pub hash_mb: usize,
+    /// Uniformly random moves played before the engine takes over.
+    pub random_plies: usize,
+    /// An opening the first search scores beyond this is drawn again.
+    pub opening_max_cp: i32,
+    /// Games still running after this many plies are drawn.
+    pub max_plies: usize,
+    /// A game is won once every search of `win_plies` consecutive plies
+    /// scores at least this for the same side; 0 disables.
+    pub win_cp: i32,
+    pub win_plies: usize,
+    /// A game is drawn once, past ply `draw_after`, every search of
+    /// `draw_plies` consecutive plies scores within this; 0 disables.
+    pub draw_cp: i32,
+    pub draw_plies: usize,
+    pub draw_after: usize,
+    /// Positions scored beyond this (mates, mostly) are not recorded.
+    pub max_cp: i32,
+    pub seed: u64,
+}
+
+impl Default for DatagenConfig {
+    fn default() -> Self {
+        DatagenConfig {
+            games: 1000,
+            depth: 64,
+            nodes: Some(5000),
+            hash_mb: 16,
+            random_plies: 8,
+            opening_max_cp: 300,
+            max_plies: 400,
+            win_cp: 1000,
+            win_plies: 6,
+            draw_cp: 10,
+            draw_plies: 12,
+            draw_after: 80,
+            max_cp: 3000,
+            seed: 1,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+}
+
+impl GameResult {
+    /// Score for the side to move: 1 for a win, 0.5 for a draw, 0 for a loss.
+    pub fn for_side(self, white: bool) -> f32 {
+        match (self, white) {
+            (GameResult::Draw, _) => 0.5,
+            (GameResult::WhiteWins, true) | (GameResult::BlackWins, false) => 1.0,
+            _ => 0.0,
+        }
+    }
+
+    pub fn pgn(self) -> &'static str {
+        match self {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            GameResult::Draw => "1/2-1/2",
+        }
+    }
+}
+
+/// One recorded position.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record {
+    pub fen: String,
+    /// Search score for the side to move.
+    pub cp: i32,
+    /// Game result for the side to move, as in `GameResult::for_side`.
+    pub result: f32,
+}
+
+impl Record {
+    /// The JSONL line: `nnue_preprocess` reads `fen` and `cp`; `wdl` is the
+    /// score's win/draw/loss per mille and `result` the game outcome, both
+    /// for the side to move.
+    pub fn to_json(&self) -> String {
+        let [w, d, l] = cp_to_wdl_permille(self.cp);
+        format!(
+            "{{\"fen\": \"{}\", \"cp\": {}, \"wdl\": [{w}, {d}, {l}], \"result\": {}}}",
+            self.fen, self.cp, self.result
+        )
+    }
+}
+
+/// Win/draw/loss per mille for a centipawn score, with the model the
+/// training targets use: a logistic win probability at 180 cp and a draw
+/// share that fades out linearly by 800 cp.  Sums to 1000.
+pub fn cp_to_wdl_permille(cp: i32) -> [u32; 3] {
+    let cp = cp as f64;
+    let p_win = 1.0 / (1.0 + (-cp / 180.0).exp());
+    let draw = (1.0 - cp.abs() / 800.0).max(0.0);
+    let win = (1000.0 * p_win * (1.0 - draw)).round() as u32;
+    let draw = (1000.0 * draw).round() as u32;
+    [win, draw, 1000 - win - draw]
+}
+
+#[derive(Clone, Debug)]
+pub struct Game {
+    pub records: Vec<Record>,
+    pub result: GameResult,
+    /// Why the game ended: "checkmate", "stalemate", "repetition",
+    /// "50-move rule", "adjudicated win", "adjudicated draw" or "max plies".
+    pub termination: &'static str,
+    pub plies: usize,
+}
+
+fn is_noisy(board: &ChessBoard, mv: &ChessMove) -> bool {
+    mv.capture.is_some()
+        || mv.is_promotion()
+        || mv.has_flag(ChessMove::EN_PASSANT_CAPTURE_FLAG)
+        || board.get_piece_at_square(mv.target_square()).is_some()
+}
+
+/// Positions whose static evaluation would misstate the search score: the
+/// side to move is in check, or has a capture that wins material.
+fn is_tactical(board: &ChessBoard, conductor: &PieceConductor, legal: &[ChessMove], white: bool) -> bool {
+    conductor.is_king_in_check(board, white)
+        || legal.iter().any(|m| {
+            is_noisy(board, m) && see(board, conductor, m.start_square() as usize, m.target_square() as usize, white) > 0
+        })
+}
+
+/// RNG of game `index`, independent of which games are played alongside.
+fn game_rng(seed: u64, index: usize) -> StdRng {
+    StdRng::seed_from_u64(seed ^ (index as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15))
+}
+
+fn legal_moves(board: &mut ChessBoard, conductor: &PieceConductor) -> Vec<ChessMove> {
+    let white = board.is_white_active();
+    let mut legal = Vec::new();
+    get_all_legal_moves_for_color(board, conductor, white, &mut legal, &mut Vec::new());
+    legal
+}
+
+/// `random_plies` random moves from the start position, or `None` when
+/// they run into a mate or stalemate.
+fn random_opening(rng: &mut StdRng, plies: usize, conductor: &PieceConductor) -> Option<ChessBoard> {
+    let mut board = ChessBoard::new();
+    for _ in 0..plies {
+        let mut mv = *legal_moves(&mut board, conductor).choose(rng)?;
+        board.make_move(&mut mv);
+    }
+    Some(board)
+}
+
+/// Searches `board` within the configured limits; returns the white-absolute
+/// score and the best move.
+fn search(board: &mut ChessBoard, config: &DatagenConfig, tt: &TranspositionTable, conductor: &PieceConductor) -> (i32, Option<ChessMove>) {
+    let mut ctx = SearchContext::new();
+    ctx.node_limit = config.nodes.unwrap_or(u64::MAX);
+    tt.new_search();
+    let white = board.is_white_active();
+    let result = iterative_deepening_root_with_context(
+        board,
+        conductor,
+        None,
+        tt,
+        config.depth,
+        white,
+        None,
+        None,
+        1,
+        None,
+        0,
+        false,
+        DrawScores::default(),
+        &mut ctx,
+    );
+    (result.score, result.best_move)
+}
+
+/// Score streaks for adjudication.  Both sides are the same search, so a
+/// streak of scores is a streak of agreement between the two engines.
+#[derive(Default)]
+struct Adjudicator {
+    win_streak: usize,
+    draw_streak: usize,
+    last_score: i32,
+}
+
+impl Adjudicator {
+    /// Takes the white-absolute score of ply `ply`; returns the result once
+    /// the game can be adjudicated.
+    fn update(&mut self, config: &DatagenConfig, ply: usize, score: i32) -> Option<(GameResult, &'static str)> {
+        let decisive = config.win_cp > 0 && score.abs() >= config.win_cp;
+        self.win_streak = match (decisive, (score > 0) == (self.last_score > 0)) {
+            (false, _) => 0,
+            (true, true) => self.win_streak + 1,
+            (true, false) => 1,
+        };
+        let level = config.draw_cp > 0 && ply >= config.draw_after && score.abs() <= config.draw_cp;
+        self.draw_streak = if level { self.draw_streak + 1 } else { 0 };
+        self.last_score = score;
+
+        if self.win_streak > 0 && self.win_streak >= config.win_plies {
+            Some((if score > 0 { GameResult::WhiteWins } else { GameResult::BlackWins }, "adjudicated win"))
+        } else if self.draw_streak > 0 && self.draw_streak >= config.draw_plies {
+            Some((GameResult::Draw, "adjudicated draw"))
+        } else {
+            None
+        }
+    }
+}
+
+/// Plays game `index` of `config` on `tt`.
+pub fn play_game(index: usize, config: &DatagenConfig, tt: &TranspositionTable, conductor: &PieceConductor) -> Game {
+    let mut rng = game_rng(config.seed, index);
+    tt.clear();
+
+    let mut board = ChessBoard::new();
+    for attempt in 0..OPENING_ATTEMPTS {
+        let Some(mut opening) = random_opening(&mut rng, config.random_plies, conductor) else { continue };
+        if legal_moves(&mut opening, conductor).is_empty() {
+            continue;
+        }
+        let (score, _) = search(&mut opening, config, tt, conductor);
+        board = opening;
+        if score.abs() <= config.opening_max_cp || attempt + 1 == OPENING_ATTEMPTS {
+            break;
+        }
+    }
+
+    let mut records = Vec::new();
+    let mut sides = Vec::new();
+    let mut adjudicator = Adjudicator::default();
+    let mut ply = 0;
+    let (result, termination) = loop {
+        let white = board.is_white_active();
+        let legal = legal_moves(&mut board, conductor);
+        if legal.is_empty() {
+            break if conductor.is_king_in_check(&board, white) {
+                (if white { GameResult::BlackWins } else { GameResult::WhiteWins }, "checkmate")
+            } else {
+                (GameResult::Draw, "stalemate")
+            };
+        }
+        if board.is_repetition(3) {
+            break (GameResult::Draw, "repetition");
+        }
+        if board.get_halfmove_clock() >= FIFTY_MOVE_PLIES {
+            break (GameResult::Draw, "50-move rule");
+        }
+        if ply >= config.max_plies {
+            break (GameResult::Draw, "max plies");
+        }
+
+        let (score, best) = search(&mut board, config, tt, conductor);
+        if let Some(end) = adjudicator.update(config, ply, score) {
+            break end;
+        }
+        let mut best = best.unwrap_or(legal[0]);
+
+        let cp = if white { score } else { -score };
+        if cp.abs() < config.max_cp && !is_noisy(&board, &best) && !is_tactical(&board, conductor, &legal, white) {
+            records.push(Record { fen: FENParser::board_to_fen(&board), cp, result: 0.0 });
+            sides.push(white);
+        }
+
+        board.make_move(&mut best);
+        ply += 1;
+    };
+
+    for (record, white) in records.iter_mut().zip(sides) {
+        record.result = result.for_side(white);
+    }
+    Game { records, result, termination, plies: ply }
+}
+
+/// Plays all games of `config` on `threads` threads, calling `on_game` with
+/// each game in index order.  The games do not depend on `threads`.
+pub fn generate(config: &DatagenConfig, threads: usize, mut on_game: impl FnMut(usize, Game)) {
+    let threads = threads.max(1);
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().expect("cannot start datagen threads");
+    // One table per worker; a worker plays one game at a time.
+    let tables: Vec<TranspositionTable> = (0..threads).map(|_| TranspositionTable::with_mb(config.hash_mb)).collect();
+    let conductor = PieceConductor::new();
+
+    let batch = threads * 4;
+    for start in (0..config.games).step_by(batch) {
+        let end = (start + batch).min(config.games);
+        let games: Vec<Game> = pool.install(|| {
+            (start..end)
+                .into_par_iter()
+                .map(|i| {
+                    let tt = &tables[rayon::current_thread_index().unwrap_or(0)];
+                    play_game(i, config, tt, &conductor)
+                })
+                .collect()
+        });
+        for (i, game) in (start..).zip(games) {
+            on_game(i, game);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn small_config() -> DatagenConfig {
+        DatagenConfig { games: 3, depth: 2, nodes: None, hash_mb: 1, max_plies: 60, draw_after: 20, ..DatagenConfig::default() }
+    }
+
+    fn play_all(config: &DatagenConfig, threads: usize) -> Vec<Game> {
+        let mut games = Vec::new();
+        generate(config, threads, |i, game| {
+            assert_eq!(i, games.len());
+            games.push(game);
+        });
+        games
+    }
+
+    #[test]
+    fn games_depend_only_on_the_seed() {
+        let config = small_config();
+        let one = play_all(&config, 1);
+        let two = play_all(&config, 2);
+        assert_eq!(one.len(), config.games);
+        for (a, b) in one.iter().zip(&two) {
+            assert_eq!((a.result, a.termination, a.plies), (b.result, b.termination, b.plies));
+            assert_eq!(a.records, b.records);
+        }
+        assert_ne!(one[0].records, one[1].records, "games share an opening");
+
+        let other = play_all(&DatagenConfig { seed: 2, ..config }, 1);
+        assert_ne!(one[0].records, other[0].records);
+    }
+
+    #[test]
+    fn records_are_quiet_and_carry_the_result() {
+        let config = small_config();
+        let conductor = PieceConductor::new();
+        let tt = TranspositionTable::with_mb(1);
+        let game = play_game(0, &config, &tt, &conductor);
+        assert!(!game.records.is_empty());
+        assert!(game.plies <= config.max_plies);
+
+        for r in &game.records {
+            let mut board = ChessBoard::new();
+            board.clear();
+            FENParser::set_board_from_fen(&mut board, &r.fen);
+            let white = board.is_white_active();
+            let legal = legal_moves(&mut board, &conductor);
+            assert!(!is_tactical(&board, &conductor, &legal, white), "{}", r.fen);
+            assert!(r.cp.abs() < config.max_cp);
+            assert_eq!(r.result, game.result.for_side(white));
+
+            let json = r.to_json();
+            assert!(json.starts_with(&format!("{{\"fen\": \"{}\", \"cp\": {}, \"wdl\": [", r.fen, r.cp)), "{json}");
+        }
+        // The random opening is not recorded.
+        let first = game.records[0].fen.split(' ').nth(5).unwrap().parse::<usize>().unwrap();
+        assert!(first > config.random_plies / 2);
+    }
+
+    #[test]
+    fn adjudication_needs_a_streak() {
+        let config = DatagenConfig { win_cp: 500, win_plies: 3, draw_cp: 10, draw_plies: 2, draw_after: 10, ..small_config() };
+        let mut adj = Adjudicator::default();
+        assert_eq!(adj.update(&config, 0, 600), None);
+        assert_eq!(adj.update(&config, 1, 900), None);
+        assert_eq!(adj.update(&config, 2, -700), None, "the sign changed");
+        assert_eq!(adj.update(&config, 3, -700), None);
+        assert_eq!(adj.update(&config, 4, -2000), Some((GameResult::BlackWins, "adjudicated win")));
+
+        let mut adj = Adjudicator::default();
+        for ply in 0..10 {
+            assert_eq!(adj.update(&config, ply, 0), None, "too early to draw");
+        }
+        assert_eq!(adj.update(&config, 10, 5), None);
+        assert_eq!(adj.update(&config, 11, -3), Some((GameResult::Draw, "adjudicated draw")));
+
+        let off = DatagenConfig { win_cp: 0, draw_cp: 0, ..config };
+        let mut adj = Adjudicator::default();
+        assert!((0..50).all(|ply| adj.update(&off, ply, if ply < 25 { 5000 } else { 0 }).is_none()));
+    }
+
+    #[test]
+    fn wdl_per_mille() {
+        assert_eq!(cp_to_wdl_permille(0), [0, 1000, 0]);
+        for cp in [-2000, -300, -40, 40, 300, 2000] {
+            let [w, d, l] = cp_to_wdl_permille(cp);
+            assert_eq!(w + d + l, 1000);
+            assert_eq!(w > l, cp > 0);
+            let [w2, _, l2] = cp_to_wdl_permille(-cp);
+            assert!(w.abs_diff(l2) <= 1 && l.abs_diff(w2) <= 1);
+        }
+        assert_eq!(cp_to_wdl_permille(800)[1], 0);
+        assert_eq!(GameResult::WhiteWins.for_side(false), 0.0);
+        assert_eq!(GameResult::Draw.for_side(true), 0.5);
+    }
+}
@@ -0,0 +1,1055 @@
+//! Stateful engine façade.
+//!
+//! `Engine` owns everything a search needs — move generator tables, opening
+//! book, a persistent transposition table and the option settings — and runs
+//! each search on its own thread.  Front ends (UCI, GUI, self-play, bench)
+//! only set a position, call `go` and react to `SearchEvent`s.
+
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chess_board::ChessBoard;
+use move_generator::piece_conductor::PieceConductor;
+use web_time::Instant;
+
+use chess_foundation::ChessMove;
+
+use crate::alpha_beta::{
+    extract_ponder_move, iterative_deepening_root_with_context, pv_from_tt, DrawScores,
+    SearchContext, SearchResult, TT_SIZE_DEFAULT,
+};
+use crate::mate_search::find_mate;
+use crate::opening_book::OpeningBook;
+use crate::skill::{score_root_moves, Skill, MAX_ELO, MAX_SKILL_LEVEL};
+use crate::transposition_table::TranspositionTable;
+
+/// Depth used when a search is limited only by time or an explicit stop.
+const MAX_SEARCH_DEPTH: i32 = 64;
+
+/// How often the deadline / ponderhit watcher polls its flags.
+const WATCH_INTERVAL: Duration = Duration::from_millis(2);
+
+/// Longest line reported in analysis mode.
+const MAX_PV_LEN: usize = 32;
+
+/// Default interval between analysis-mode PV refreshes.
+pub const DEFAULT_ANALYSIS_REFRESH: Duration = Duration::from_millis(250);
+
+/// Default `Hash` size in MiB — the same table size `iterative_deepening_root`
+/// allocates per call.
+pub const DEFAULT_HASH_MB: usize = TT_SIZE_DEFAULT / TranspositionTable::entries_for_mb(1);
+
+/// Limits for one `Engine::go` call.  The default searches until stopped.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SearchLimits {
+    /// Maximum iterative-deepening depth (`None` = unbounded).
+    pub depth: Option<i32>,
+    /// No new iteration is started once this many nodes have been searched.
+    pub nodes: Option<u64>,
+    /// No new iteration is started after this point.
+    pub soft_deadline: Option<Instant>,
+    /// The running iteration is aborted at this point.
+    pub hard_deadline: Option<Instant>,
+    /// Think on the opponent's time: both deadlines are ignored until
+    /// `Engine::ponderhit`, after which the same search carries on with the
+    /// soft and hard budgets (measured from `go`) counted from the ponderhit.
+    /// A ponder search never finishes on its own before the ponderhit or
+    /// `Engine::stop`.
+    pub ponder: bool,
+    /// Look for a forced mate in at most this many moves with the mate
+    /// solver first; the normal search runs only if none is found.
+    pub mate: Option<u32>,
+}
+
+impl SearchLimits {
+    /// Search to a fixed depth.
+    pub fn depth(depth: i32) -> Self {
+        Self { depth: Some(depth), ..Self::default() }
+    }
+
+    /// Search until an iteration ends past `nodes` nodes.
+    pub fn nodes(nodes: u64) -> Self {
+        Self { nodes: Some(nodes), ..Self::default() }
+    }
+
+    /// Solve for a mate in at most `moves` moves (`go mate`).
+    pub fn mate(moves: u32) -> Self {
+        Self { mate: Some(moves), ..Self::default() }
+    }
+
+    /// Search for a fixed amount of time.
+    pub fn movetime(time: Duration) -> Self {
+        let deadline = Instant::now() + time;
+        Self {
+            soft_deadline: Some(deadline),
+            hard_deadline: Some(deadline),
+            ..Self::default()
+        }
+    }
+
+    /// Search until `Engine::stop`.
+    pub fn infinite() -> Self {
+        Self::default()
+    }
+}
+
+/// Progress reported to the `go` callback, always from the search thread.
+pub enum SearchEvent {
+    /// An iteration completed.  `score` is white-relative centipawns.
+    Depth { depth: i32, score: i32, nodes: u64, elapsed_ms: u128 },
+    /// Analysis mode only: the current line, after every iteration and then
+    /// every refresh interval.  `depth`, `score` and `nodes` are those of
+    /// the last completed iteration; the line is read from the hash table.
+    Pv { depth: i32, score: i32, nodes: u64, elapsed_ms: u128, pv: Vec<ChessMove> },
+    /// The search finished or was stopped.  Always the last event.
+    Finished(SearchResult),
+}
+
+/// Handle to the search currently owned by an `Engine`.
+struct ActiveSearch {
+    stop: Arc<AtomicBool>,
+    ponderhit: Arc<PonderHit>,
+    worker: Worker,
+}
+
+/// The `go` callback, shared by the search thread and the analysis
+/// refresher.  Nothing is delivered after `Finished`.
+struct EventSink<F>(Mutex<(F, bool)>);
+
+impl<F: FnMut(SearchEvent)> EventSink<F> {
+    fn new(on_event: F) -> Self {
+        EventSink(Mutex::new((on_event, false)))
+    }
+
+    fn send(&self, event: SearchEvent) {
+        let mut sink = self.0.lock().unwrap();
+        if sink.1 {
+            return;
+        }
+        sink.1 = matches!(event, SearchEvent::Finished(_));
+        (sink.0)(event);
+    }
+
+    fn is_finished(&self) -> bool {
+        self.0.lock().unwrap().1
+    }
+}
+
+/// Depth, score, nodes and best move of the last completed iteration, for
+/// the analysis refresher.
+type Progress = Mutex<Option<(i32, i32, u64, Option<ChessMove>)>>;
+
+/// What an analysis search leaves for the next one.
+struct AnalysisCarry {
+    /// Hashes of the positions along the final PV.
+    line: Vec<u64>,
+    ctx: Box<SearchContext>,
+}
+
+impl AnalysisCarry {
+    fn new(board: &mut ChessBoard, pv: &[ChessMove], ctx: Box<SearchContext>) -> Self {
+        let mut line = Vec::with_capacity(pv.len());
+        for &mv in pv {
+            let mut mv = mv;
+            board.make_move(&mut mv);
+            line.push(board.current_hash());
+        }
+        for _ in pv {
+            board.undo_move();
+        }
+        Self { line, ctx }
+    }
+}
+
+/// When the opponent played the expected move, set by `Engine::ponderhit`.
+#[derive(Default)]
+struct PonderHit(Mutex<Option<Instant>>);
+
+impl PonderHit {
+    fn fire(&self) {
+        self.0.lock().unwrap().get_or_insert_with(Instant::now);
+    }
+
+    fn at(&self) -> Option<Instant> {
+        *self.0.lock().unwrap()
+    }
+
+    /// Block until the ponderhit or `stop`.
+    fn wait(&self, stop: &AtomicBool) {
+        while self.at().is_none() && !stop.load(Ordering::Acquire) {
+            std::thread::sleep(WATCH_INTERVAL);
+        }
+    }
+}
+
+/// The search thread.  Native builds use a dedicated OS thread; wasm has no
+/// `std::thread::spawn`, so the search runs on the rayon pool instead.
+#[cfg(not(target_arch = "wasm32"))]
+struct Worker(std::thread::JoinHandle<SearchResult>);
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Worker {
+    fn spawn(f: impl FnOnce() -> SearchResult + Send + 'static) -> Self {
+        Worker(std::thread::spawn(f))
+    }
+
+    fn is_finished(&self) -> bool {
+        self.0.is_finished()
+    }
+
+    fn join(self) -> SearchResult {
+        self.0.join().expect("search thread panicked")
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+struct Worker(std::sync::mpsc::Receiver<SearchResult>, Option<SearchResult>);
+
+#[cfg(target_arch = "wasm32")]
+impl Worker {
+    fn spawn(f: impl FnOnce() -> SearchResult + Send + 'static) -> Self {
+        let (tx, rx) = std::sync::mpsc::channel();
+        rayon::spawn(move || {
+            let _ = tx.send(f());
+        });
+        Worker(rx, None)
+    }
+
+    fn is_finished(&mut self) -> bool {
+        if self.1.is_none() {
+            self.1 = self.0.try_recv().ok();
+        }
+        self.1.is_some()
+    }
+
+    fn join(mut self) -> SearchResult {
+        match self.1.take() {
+            Some(r) => r,
+            None => self.0.recv().expect("search thread panicked"),
+        }
+    }
+}
+
+/// A chess engine with a persistent position, hash table and option set.
+///
+/// ```no_run
+/// use chess_evaluation::{Engine, SearchEvent, SearchLimits};
+///
+/// let mut engine = Engine::new();
+/// engine.go(SearchLimits::depth(8), |event| {
+///     if let SearchEvent::Finished(result) = event {
+///         println!("best move: {:?}", result.best_move);
+///     }
+/// });
+/// let result = engine.wait();
+/// ```
+pub struct Engine {
+    conductor: PieceConductor,
+    book: Option<Arc<OpeningBook>>,
+    tt: Arc<TranspositionTable>,
+    board: ChessBoard,
+    hash_mb: usize,
+    threads: usize,
+    deterministic: bool,
+    noise_cp: i32,
+    own_book: bool,
+    skill_level: i32,
+    limit_strength: bool,
+    elo: i32,
+    draw: DrawScores,
+    analyse_mode: bool,
+    analysis_refresh: Duration,
+    analysis: Arc<Mutex<Option<AnalysisCarry>>>,
+    /// `new_game` in analysis mode: clear the hash at the next `go` unless
+    /// that position continues the previous line.
+    pending_clear: bool,
+    active: Option<ActiveSearch>,
+}
+
+impl Engine {
+    /// Build an engine with fresh move-generator tables and the built-in
+    /// opening book.
+    pub fn new() -> Self {
+        let conductor = PieceConductor::new();
+        let book = OpeningBook::build(&conductor);
+        Self::from_parts(conductor, Some(book))
+    }
+
+    /// Build an engine around components the caller already has, avoiding a
+    /// second table/book build.  `book = None` disables the book.
+    pub fn from_parts(conductor: PieceConductor, book: Option<OpeningBook>) -> Self {
+        Self {
+            conductor,
+            own_book: book.is_some(),
+            book: book.map(Arc::new),
+            tt: Arc::new(TranspositionTable::with_mb(DEFAULT_HASH_MB)),
+            board: ChessBoard::new(),
+            hash_mb: DEFAULT_HASH_MB,
+            threads: 1,
+            deterministic: false,
+            noise_cp: 0,
+            skill_level: MAX_SKILL_LEVEL,
+            limit_strength: false,
+            elo: MAX_ELO,
+            draw: DrawScores::default(),
+            analyse_mode: false,
+            analysis_refresh: DEFAULT_ANALYSIS_REFRESH,
+            analysis: Arc::new(Mutex::new(None)),
+            pending_clear: false,
+            active: None,
+        }
+    }
+
+    // ── Position ─────────────────────────────────────────────────────────────
+
+    /// Set the position the next `go` searches.  A running search is not
+    /// affected — it works on its own copy.
+    pub fn set_position(&mut self, board: ChessBoard) {
+        self.board = board;
+    }
+
+    pub fn board(&self) -> &ChessBoard {
+        &self.board
+    }
+
+    pub fn conductor(&self) -> &PieceConductor {
+        &self.conductor
+    }
+
+    /// Reset for a new game: stop any search, return to the start position
+    /// and clear the hash table.  In analysis mode the clear waits for the
+    /// next `go` and is skipped if that position continues the last line, so
+    /// a board that resets between moves does not lose its analysis.
+    pub fn new_game(&mut self) {
+        self.stop();
+        self.board = ChessBoard::new();
+        if self.analyse_mode {
+            self.pending_clear = true;
+        } else {
+            self.tt.clear();
+            *self.analysis.lock().unwrap() = None;
+        }
+    }
+
+    // ── Options ──────────────────────────────────────────────────────────────
+
+    /// Lazy SMP thread count (at least 1).  Takes effect on the next `go`.
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /// Resize the hash table to `mb` MiB, discarding its contents.  Stops a
+    /// running search first.
+    pub fn set_hash_mb(&mut self, mb: usize) {
+        let mb = mb.max(1);
+        if mb == self.hash_mb {
+            return;
+        }
+        self.stop();
+        self.hash_mb = mb;
+        match Arc::get_mut(&mut self.tt) {
+            Some(tt) => tt.resize(TranspositionTable::entries_for_mb(mb)),
+            None => self.tt = Arc::new(TranspositionTable::with_mb(mb)),
+        }
+    }
+
+    pub fn hash_mb(&self) -> usize {
+        self.hash_mb
+    }
+
+    /// Reproducible multi-threaded search, see `iterative_deepening_root_with_tt`.
+    pub fn set_deterministic(&mut self, deterministic: bool) {
+        self.deterministic = deterministic;
+    }
+
+    /// Random root-score noise in centipawns (0 = full strength).
+    pub fn set_noise_cp(&mut self, noise_cp: i32) {
+        self.noise_cp = noise_cp.max(0);
+    }
+
+    /// Whether book moves are played.  Has no effect without a book.
+    pub fn set_own_book(&mut self, own_book: bool) {
+        self.own_book = own_book;
+    }
+
+    /// `Skill Level` 0..=20; 20 is full strength.  Ignored while
+    /// `UCI_LimitStrength` is on.
+    pub fn set_skill_level(&mut self, level: i32) {
+        self.skill_level = level.clamp(0, MAX_SKILL_LEVEL);
+    }
+
+    /// `UCI_LimitStrength`: play at `UCI_Elo` instead of `Skill Level`.
+    pub fn set_limit_strength(&mut self, limit_strength: bool) {
+        self.limit_strength = limit_strength;
+    }
+
+    /// `UCI_Elo` target used while `UCI_LimitStrength` is on.
+    pub fn set_elo(&mut self, elo: i32) {
+        self.elo = elo;
+    }
+
+    /// The strength setting the next `go` plays at.
+    pub fn skill(&self) -> Skill {
+        if self.limit_strength {
+            Skill::from_elo(self.elo)
+        } else {
+            Skill::from_level(self.skill_level)
+        }
+    }
+
+    /// Contempt in centipawns: how much the engine gives up to avoid a draw.
+    /// Counted for the side the engine plays, whoever is to move.
+    pub fn set_contempt(&mut self, contempt: i32) {
+        self.draw.contempt = contempt;
+    }
+
+    /// Contempt plus the individual stalemate / repetition / fifty-move
+    /// scores, see `DrawScores`.
+    pub fn set_draw_scores(&mut self, draw: DrawScores) {
+        self.draw = draw;
+    }
+
+    pub fn draw_scores(&self) -> DrawScores {
+        self.draw
+    }
+
+    /// `UCI_AnalyseMode`: no book, noise or strength limit, a `Pv` event
+    /// every refresh interval, and the hash and history tables carry over
+    /// when the next position lies on the previous line.
+    pub fn set_analyse_mode(&mut self, analyse_mode: bool) {
+        self.analyse_mode = analyse_mode;
+        if !analyse_mode {
+            if std::mem::take(&mut self.pending_clear) {
+                self.stop();
+                self.tt.clear();
+            }
+            *self.analysis.lock().unwrap() = None;
+        }
+    }
+
+    pub fn analyse_mode(&self) -> bool {
+        self.analyse_mode
+    }
+
+    /// How often analysis mode re-sends the current line.
+    pub fn set_analysis_refresh(&mut self, interval: Duration) {
+        self.analysis_refresh = interval.max(WATCH_INTERVAL);
+    }
+
+    /// Empty the hash table.  Stops a running search first.
+    pub fn clear_hash(&mut self) {
+        self.stop();
+        self.tt.clear();
+    }
+
+    /// The shared hash table, e.g. for `save_to`.
+    pub fn tt(&self) -> &TranspositionTable {
+        &self.tt
+    }
+
+    /// Load a hash table saved with `TranspositionTable::save_to`, first
+    /// resizing to the `Hash` size it was saved at.  Stops a running search
+    /// first.  Returns the number of entries loaded.
+    pub fn load_hash(&mut self, path: &Path) -> io::Result<usize> {
+        self.stop();
+        if let Some(mb) = TranspositionTable::saved_mb(path)? {
+            self.set_hash_mb(mb);
+        }
+        self.tt.load_from(path)
+    }
+
+    // ── Searching ────────────────────────────────────────────────────────────
+
+    /// Start searching the current position on a background thread.  Any
+    /// previous search is stopped first (its result is discarded).
+    ///
+    /// `on_event` runs on the search thread; it receives a `Depth` event per
+    /// completed iteration and exactly one final `Finished` event.
+    pub fn go<F>(&mut self, limits: SearchLimits, on_event: F)
+    where
+        F: FnMut(SearchEvent) + Send + 'static,
+    {
+        self.stop();
+        let analyse = self.analyse_mode;
+        let ctx = self.take_search_context();
+        self.tt.new_search();
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let ponderhit = Arc::new(PonderHit::default());
+        let started = Instant::now();
+        if limits.hard_deadline.is_some() || limits.ponder {
+            spawn_watcher(limits, started, Arc::clone(&stop), Arc::clone(&ponderhit));
+        }
+
+        let events = Arc::new(EventSink::new(on_event));
+        let progress = Arc::new(Mutex::new(None));
+        if analyse {
+            spawn_refresher(
+                self.board.clone(),
+                self.conductor.clone(),
+                Arc::clone(&self.tt),
+                self.analysis_refresh,
+                started,
+                Arc::clone(&progress),
+                Arc::clone(&events),
+            );
+        }
+
+        let mut board = self.board.clone();
+        let conductor = self.conductor.clone();
+        // Analysis wants the engine's own view of every position.
+        let book = self.book.clone().filter(|_| self.own_book && !analyse);
+        let noise_cp = if analyse { 0 } else { self.noise_cp };
+        let skill = if analyse { Skill::full() } else { self.skill() };
+        let tt = Arc::clone(&self.tt);
+        let carry = Arc::clone(&self.analysis);
+        let search_stop = Arc::clone(&stop);
+        let search_ponderhit = Arc::clone(&ponderhit);
+        let max_depth = limits.depth.unwrap_or(MAX_SEARCH_DEPTH).min(skill.depth_limit().unwrap_or(i32::MAX));
+        let node_limit = limits.nodes.unwrap_or(u64::MAX).min(skill.node_limit().unwrap_or(u64::MAX));
+        // While pondering the deadlines only start at the ponderhit: the
+        // watcher enforces the hard one and `on_depth` the soft one, both
+        // through the stop flag, so the search itself never restarts.
+        let soft_deadline = if limits.ponder { None } else { limits.soft_deadline };
+        let ponder_soft_budget =
+            limits.soft_deadline.filter(|_| limits.ponder).map(|d| d.saturating_duration_since(started));
+        let threads = self.threads;
+        let deterministic = self.deterministic;
+        let draw = self.draw;
+
+        let worker = Worker::spawn(move || {
+            let mut ctx = ctx;
+            let is_white = board.is_white_active();
+            if let Some(moves) = limits.mate {
+                if let Some(mate) = find_mate(&mut board, &conductor, moves, Some(&search_stop)) {
+                    if limits.ponder {
+                        search_ponderhit.wait(&search_stop);
+                    }
+                    let score = 1_000_000 - mate.plies() as i32;
+                    let result = SearchResult {
+                        score: if is_white { score } else { -score },
+                        best_move: mate.line.first().copied(),
+                        ponder_move: mate.line.get(1).copied(),
+                        total_nodes: mate.nodes,
+                        pv: mate.line,
+                        root_moves: Vec::new(),
+                    };
+                    search_stop.store(true, Ordering::Release);
+                    events.send(SearchEvent::Finished(result.clone()));
+                    return result;
+                }
+            }
+            let completed_depth = AtomicI32::new(0);
+            let out_of_nodes = AtomicBool::new(false);
+            let pv_board = Mutex::new(board.clone());
+            let mut result = iterative_deepening_root_with_context(
+                &mut board,
+                &conductor,
+                book.as_deref(),
+                &tt,
+                max_depth,
+                is_white,
+                soft_deadline,
+                Some(Arc::clone(&search_stop)),
+                threads,
+                Some(&|depth, score, nodes, elapsed_ms, best_move| {
+                    completed_depth.store(depth, Ordering::Relaxed);
+                    events.send(SearchEvent::Depth { depth, score, nodes, elapsed_ms });
+                    if analyse {
+                        if let Some(mv) = best_move {
+                            let pv = pv_from_tt(&mut pv_board.lock().unwrap(), &conductor, &tt, mv, MAX_PV_LEN);
+                            events.send(SearchEvent::Pv { depth, score, nodes, elapsed_ms, pv });
+                        }
+                        *progress.lock().unwrap() = Some((depth, score, nodes, best_move));
+                    }
+                    if nodes >= node_limit {
+                        out_of_nodes.store(true, Ordering::Relaxed);
+                        search_stop.store(true, Ordering::Release);
+                    }
+                    let hit = search_ponderhit.at();
+                    if let (Some(hit), Some(budget)) = (hit, ponder_soft_budget) {
+                        if hit.elapsed() >= budget {
+                            search_stop.store(true, Ordering::Release);
+                        }
+                    }
+                }),
+                noise_cp,
+                deterministic,
+                draw,
+                &mut ctx,
+            );
+            // Reaching the depth limit early must not end a ponder search:
+            // the GUI expects no `bestmove` before its ponderhit or stop.
+            if limits.ponder {
+                search_ponderhit.wait(&search_stop);
+            }
+            // Book moves (no nodes) are played as they are.
+            if skill.is_enabled() && result.total_nodes > 0 {
+                // A stop from the node limit must not cut the re-scoring short.
+                let stop = (!out_of_nodes.load(Ordering::Relaxed)).then_some(&*search_stop);
+                let depth = completed_depth.load(Ordering::Relaxed).max(1);
+                // Out of time: a one-ply re-scoring costs next to nothing and
+                // keeps the move weakened instead of playing the search's.
+                if let Some((candidates, nodes)) =
+                    score_root_moves(&mut board, &conductor, &tt, depth, is_white, result.best_move, stop, draw)
+                        .or_else(|| score_root_moves(&mut board, &conductor, &tt, 1, is_white, None, None, draw))
+                {
+                    result.total_nodes += nodes;
+                    let (mv, score) = candidates[skill.pick(&candidates, &mut rand::thread_rng())];
+                    if result.best_move != Some(mv) {
+                        result.best_move = Some(mv);
+                        result.ponder_move = extract_ponder_move(&mut board, &conductor, &tt, mv, is_white);
+                    }
+                    result.score = if is_white { score } else { -score };
+                }
+            }
+            if analyse {
+                if let Some(mv) = result.best_move {
+                    result.pv = pv_from_tt(&mut board, &conductor, &tt, mv, MAX_PV_LEN);
+                }
+                *carry.lock().unwrap() = Some(AnalysisCarry::new(&mut board, &result.pv, ctx));
+            }
+            // Releases the watcher.
+            search_stop.store(true, Ordering::Release);
+            events.send(SearchEvent::Finished(result.clone()));
+            result
+        });
+
+        self.active = Some(ActiveSearch { stop, ponderhit, worker });
+    }
+
+    /// The main-thread context for the next search: the one the previous
+    /// analysis left behind if this position lies on its line, else a fresh
+    /// one.  Also applies a hash clear deferred by `new_game`.
+    fn take_search_context(&mut self) -> Box<SearchContext> {
+        let carry = self.analysis.lock().unwrap().take();
+        let reused = carry
+            .filter(|c| self.analyse_mode && c.line.contains(&self.board.current_hash()))
+            .map(|c| {
+                let mut ctx = c.ctx;
+                ctx.reuse_for_new_search();
+                ctx
+            });
+        if reused.is_none() && self.pending_clear {
+            self.tt.clear();
+        }
+        self.pending_clear = false;
+        reused.unwrap_or_else(|| Box::new(SearchContext::new()))
+    }
+
+    /// Run a search to completion on the calling thread's behalf.
+    pub fn search(&mut self, limits: SearchLimits) -> SearchResult {
+        self.go(limits, |_| {});
+        self.wait().expect("search was just started")
+    }
+
+    /// Signal the running search to stop and wait for its result.  Returns
+    /// `None` when no search was running.
+    pub fn stop(&mut self) -> Option<SearchResult> {
+        if let Some(active) = &self.active {
+            active.stop.store(true, Ordering::Release);
+        }
+        self.wait()
+    }
+
+    /// Wait for the running search to finish on its own.  Returns `None`
+    /// when no search was running.
+    pub fn wait(&mut self) -> Option<SearchResult> {
+        self.active.take().map(|a| a.worker.join())
+    }
+
+    /// Non-blocking: the result once the search has finished, else `None`.
+    pub fn try_result(&mut self) -> Option<SearchResult> {
+        if self.active.as_mut()?.worker.is_finished() {
+            self.wait()
+        } else {
+            None
+        }
+    }
+
+    pub fn is_searching(&self) -> bool {
+        self.active.is_some()
+    }
+
+    /// The stop flag of the running search, for callers that need to stop it
+    /// from elsewhere (e.g. another thread) without access to the engine.
+    pub fn stop_handle(&self) -> Option<Arc<AtomicBool>> {
+        self.active.as_ref().map(|a| Arc::clone(&a.stop))
+    }
+
+    /// The opponent played the expected move: the ponder search carries on
+    /// as a normal timed search, keeping its iteration, hash and history.
+    pub fn ponderhit(&self) {
+        if let Some(active) = &self.active {
+            active.ponderhit.fire();
+        }
+    }
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        // The wasm main thread must not block; a stopped search there just
+        // finishes on its own and its result is dropped.
+        #[cfg(target_arch = "wasm32")]
+        if let Some(active) = &self.active {
+            active.stop.store(true, Ordering::Release);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        self.stop();
+    }
+}
+
+/// Fire `stop` at the hard deadline.  When pondering, the clock only starts
+/// at ponderhit, with the budget the deadline allowed at `go` time.  Exits as
+/// soon as `stop` is set by anyone (including the finished search).
+fn spawn_watcher(limits: SearchLimits, started: Instant, stop: Arc<AtomicBool>, ponderhit: Arc<PonderHit>) {
+    let budget = limits.hard_deadline.map(|d| d.saturating_duration_since(started));
+    let watch = move || {
+        let mut deadline = if limits.ponder { None } else { limits.hard_deadline };
+        let mut pondering = limits.ponder;
+        while !stop.load(Ordering::Acquire) {
+            if pondering {
+                if let Some(hit) = ponderhit.at() {
+                    pondering = false;
+                    deadline = budget.map(|b| hit + b);
+                }
+            }
+            if deadline.is_some_and(|d| Instant::now() >= d) {
+                stop.store(true, Ordering::Release);
+                return;
+            }
+            std::thread::sleep(WATCH_INTERVAL);
+        }
+    };
+    spawn_detached(watch);
+}
+
+/// Analysis mode: send the line of the last completed iteration every
+/// `interval` until the search has finished.
+fn spawn_refresher<F>(
+    mut board: ChessBoard,
+    conductor: PieceConductor,
+    tt: Arc<TranspositionTable>,
+    interval: Duration,
+    started: Instant,
+    progress: Arc<Progress>,
+    events: Arc<EventSink<F>>,
+) where
+    F: FnMut(SearchEvent) + Send + 'static,
+{
+    spawn_detached(move || {
+        let mut next = started + interval;
+        while !events.is_finished() {
+            if Instant::now() < next {
+                std::thread::sleep(WATCH_INTERVAL);
+                continue;
+            }
+            next += interval;
+            let Some((depth, score, nodes, Some(mv))) = *progress.lock().unwrap() else {
+                continue;
+            };
+            let pv = pv_from_tt(&mut board, &conductor, &tt, mv, MAX_PV_LEN);
+            let elapsed_ms = started.elapsed().as_millis();
+            events.send(SearchEvent::Pv { depth, score, nodes, elapsed_ms, pv });
+        }
+    });
+}
+
+/// Run `f` on a thread of its own; on wasm, on the rayon pool.
+fn spawn_detached(f: impl FnOnce() + Send + 'static) {
+    #[cfg(not(target_arch = "wasm32"))]
+    std::thread::spawn(f);
+    #[cfg(target_arch = "wasm32")]
+    rayon::spawn(f);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn engine() -> Engine {
+        Engine::from_parts(PieceConductor::new(), None)
+    }
+
+    #[test]
+    fn engine_can_move_between_threads() {
+        fn assert_send<T: Send>() {}
+        assert_send::<Engine>();
+    }
+
+    #[test]
+    fn fixed_depth_search_returns_legal_move() {
+        let mut e = engine();
+        let r = e.search(SearchLimits::depth(3));
+        assert!(r.best_move.is_some());
+        assert!(!e.is_searching());
+    }
+
+    #[test]
+    fn callback_reports_each_depth_then_finishes() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&events);
+        let mut e = engine();
+        e.go(SearchLimits::depth(4), move |ev| {
+            sink.lock().unwrap().push(match ev {
+                SearchEvent::Depth { depth, .. } => depth,
+                SearchEvent::Pv { .. } => 0,
+                SearchEvent::Finished(_) => -1,
+            });
+        });
+        e.wait().unwrap();
+        assert_eq!(*events.lock().unwrap(), vec![1, 2, 3, 4, -1]);
+    }
+
+    #[test]
+    fn stop_ends_infinite_search() {
+        let mut e = engine();
+        e.go(SearchLimits::infinite(), |_| {});
+        std::thread::sleep(Duration::from_millis(50));
+        let r = e.stop().expect("a search was running");
+        assert!(r.best_move.is_some());
+        assert!(e.stop().is_none());
+    }
+
+    #[test]
+    fn movetime_search_stops_on_its_own() {
+        let mut e = engine();
+        let t0 = Instant::now();
+        e.search(SearchLimits::movetime(Duration::from_millis(100)));
+        assert!(t0.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn ponder_waits_for_ponderhit() {
+        let mut e = engine();
+        let limits = SearchLimits { ponder: true, ..SearchLimits::movetime(Duration::from_millis(50)) };
+        e.go(limits, |_| {});
+        std::thread::sleep(Duration::from_millis(150));
+        assert!(e.try_result().is_none(), "deadline must not apply before ponderhit");
+        e.ponderhit();
+        let t0 = Instant::now();
+        let r = e.wait().unwrap();
+        assert!(r.best_move.is_some());
+        assert!(t0.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn ponderhit_continues_the_same_search() {
+        let depths = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&depths);
+        let mut e = engine();
+        let limits = SearchLimits { ponder: true, ..SearchLimits::movetime(Duration::from_millis(50)) };
+        e.go(limits, move |ev| {
+            if let SearchEvent::Depth { depth, .. } = ev {
+                sink.lock().unwrap().push(depth);
+            }
+        });
+        std::thread::sleep(Duration::from_millis(150));
+        let before = depths.lock().unwrap().len();
+        assert!(before > 0);
+        e.ponderhit();
+        e.wait().unwrap();
+        let depths = depths.lock().unwrap();
+        assert!(depths.windows(2).all(|w| w[1] == w[0] + 1), "iterative deepening restarted: {depths:?}");
+    }
+
+    #[test]
+    fn ponderhit_arms_the_soft_deadline() {
+        let mut e = engine();
+        let now = Instant::now();
+        let limits = SearchLimits {
+            soft_deadline: Some(now + Duration::from_millis(20)),
+            hard_deadline: Some(now + Duration::from_secs(60)),
+            ponder: true,
+            ..SearchLimits::default()
+        };
+        e.go(limits, |_| {});
+        std::thread::sleep(Duration::from_millis(50));
+        e.ponderhit();
+        let t0 = Instant::now();
+        assert!(e.wait().unwrap().best_move.is_some());
+        assert!(t0.elapsed() < Duration::from_secs(30), "only the hard deadline stopped the search");
+    }
+
+    #[test]
+    fn finished_ponder_search_holds_its_result() {
+        let finished = Arc::new(AtomicBool::new(false));
+        let seen = Arc::clone(&finished);
+        let mut e = engine();
+        e.go(SearchLimits { ponder: true, ..SearchLimits::depth(2) }, move |ev| {
+            if let SearchEvent::Finished(_) = ev {
+                seen.store(true, Ordering::Relaxed);
+            }
+        });
+        std::thread::sleep(Duration::from_millis(200));
+        assert!(!finished.load(Ordering::Relaxed), "no bestmove before ponderhit");
+        e.ponderhit();
+        assert!(e.wait().unwrap().best_move.is_some());
+        assert!(finished.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn new_game_resets_position() {
+        let mut e = engine();
+        let mut board = ChessBoard::new();
+        board.set_from_fen("8/8/4k3/8/8/4K3/4P3/8 w - - 0 1");
+        e.set_position(board);
+        e.search(SearchLimits::depth(2));
+        e.new_game();
+        assert_eq!(e.board().current_hash(), ChessBoard::new().current_hash());
+    }
+
+    #[test]
+    fn hash_resize_keeps_engine_usable() {
+        let mut e = engine();
+        e.set_hash_mb(2);
+        assert_eq!(e.hash_mb(), 2);
+        assert_eq!(e.tt().capacity(), TranspositionTable::entries_for_mb(2));
+        assert!(e.search(SearchLimits::depth(2)).best_move.is_some());
+    }
+
+    #[test]
+    fn load_hash_restores_the_saved_size() {
+        let path = std::env::temp_dir().join(format!("engine_hash_{}.bin", std::process::id()));
+        let mut e = engine();
+        e.set_hash_mb(2);
+        e.search(SearchLimits::depth(4));
+        e.tt().save_to(&path).unwrap();
+
+        let mut other = engine();
+        other.set_hash_mb(1);
+        let loaded = other.load_hash(&path).unwrap();
+        assert!(loaded > 0);
+        assert_eq!(loaded, e.tt().entries().count());
+        assert_eq!(other.hash_mb(), 2);
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn node_limit_stops_after_an_iteration() {
+        let depths = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&depths);
+        let mut e = engine();
+        e.go(SearchLimits::nodes(1), move |ev| {
+            if let SearchEvent::Depth { depth, .. } = ev {
+                sink.lock().unwrap().push(depth);
+            }
+        });
+        assert!(e.wait().unwrap().best_move.is_some());
+        assert_eq!(*depths.lock().unwrap(), vec![1]);
+    }
+
+    #[test]
+    fn go_mate_returns_the_mating_line() {
+        let mut e = engine();
+        let mut board = ChessBoard::new();
+        board.set_from_fen("7k/8/8/4K3/8/8/8/R7 w - - 0 1");
+        e.set_position(board);
+        let r = e.search(SearchLimits::mate(4));
+        assert_eq!(r.pv.len(), 5);
+        assert_eq!(r.best_move.map(|m| m.target_square()), Some(45));
+        assert_eq!(r.score, 1_000_000 - 5);
+
+        // No mate: falls back to a normal search.
+        e.new_game();
+        let r = e.search(SearchLimits { depth: Some(2), ..SearchLimits::mate(2) });
+        assert!(r.best_move.is_some() && r.pv.is_empty());
+    }
+
+    #[test]
+    fn analysis_streams_lines_until_stopped() {
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&lines);
+        let mut e = engine();
+        e.set_analyse_mode(true);
+        e.set_analysis_refresh(Duration::from_millis(20));
+        e.go(SearchLimits::infinite(), move |ev| match ev {
+            SearchEvent::Pv { pv, .. } => sink.lock().unwrap().push(pv),
+            SearchEvent::Finished(_) => sink.lock().unwrap().push(Vec::new()),
+            _ => {}
+        });
+        std::thread::sleep(Duration::from_millis(300));
+        let r = e.stop().unwrap();
+        assert!(!r.pv.is_empty() && r.pv[0] == r.best_move.unwrap());
+
+        let lines = lines.lock().unwrap();
+        let (finished, pvs) = lines.split_last().unwrap();
+        assert!(finished.is_empty(), "Finished is the last event");
+        assert!(pvs.len() >= 5, "only {} lines in 300 ms", pvs.len());
+        assert!(pvs.iter().all(|pv| !pv.is_empty()));
+    }
+
+    #[test]
+    fn analysis_keeps_state_along_the_line() {
+        let mut e = engine();
+        e.set_analyse_mode(true);
+        let r = e.search(SearchLimits::depth(5));
+        let mut next = ChessBoard::new();
+        let mut mv = r.pv[0];
+        next.make_move(&mut mv);
+        let next_hash = next.current_hash();
+        assert!(e.tt().probe(next_hash).is_some());
+
+        // A GUI that sends `ucinewgame` before every position.
+        e.new_game();
+        e.set_position(next.clone());
+        let kept = &*e.analysis.lock().unwrap().as_ref().unwrap().ctx as *const SearchContext;
+        let ctx = e.take_search_context();
+        assert_eq!(&*ctx as *const SearchContext, kept, "history must carry over");
+        assert!(e.tt().probe(next_hash).is_some(), "hash must survive");
+
+        // Off the line: fresh context, and the deferred clear applies.
+        e.search(SearchLimits::depth(3));
+        e.new_game();
+        let mut board = ChessBoard::new();
+        board.set_from_fen("8/8/4k3/8/8/4K3/4P3/8 w - - 0 1");
+        e.set_position(board);
+        e.take_search_context();
+        assert!(e.tt().probe(ChessBoard::new().current_hash()).is_none());
+    }
+
+    #[test]
+    fn analysis_ignores_the_book() {
+        let mut e = Engine::new();
+        e.set_analyse_mode(true);
+        assert!(e.search(SearchLimits::depth(2)).total_nodes > 0);
+        e.set_analyse_mode(false);
+        assert_eq!(e.search(SearchLimits::depth(2)).total_nodes, 0, "book move expected");
+    }
+
+    #[test]
+    fn skill_caps_depth_and_still_plays_legal_moves() {
+        let max_depth = Arc::new(AtomicI32::new(0));
+        let seen = Arc::clone(&max_depth);
+        let mut e = engine();
+        e.set_skill_level(0);
+        assert_eq!(e.skill().depth_limit(), Some(1));
+        for _ in 0..5 {
+            let seen = Arc::clone(&seen);
+            e.go(SearchLimits::depth(6), move |ev| {
+                if let SearchEvent::Depth { depth, .. } = ev {
+                    seen.fetch_max(depth, Ordering::Relaxed);
+                }
+            });
+            assert!(e.wait().unwrap().best_move.is_some());
+        }
+        assert_eq!(max_depth.load(Ordering::Relaxed), 1);
+
+        e.set_limit_strength(true);
+        e.set_elo(MAX_ELO);
+        assert!(!e.skill().is_enabled(), "UCI_LimitStrength overrides Skill Level");
+    }
+}
@@ -0,0 +1,510 @@
+//! EPD test suites (`bm` / `am` / `id` / `c0`).
+//!
+//! An EPD line is the first four FEN fields followed by `;`-terminated
+//! operations, e.g.
+//!
+//! ```text
+//! 2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id "WAC.001";
+//! ```
+//!
+//! `bm` lists the best moves and `am` moves to avoid, both in SAN.  STS
+//! suites put per-move points in the `c0` comment (`c0 "Qd5=10, Qe4=5"`);
+//! a position is worth at most 10 points.  The `epd` binary runs a suite
+//! through the search and reports the results.
+
+use chess_board::ChessBoard;
+use chess_foundation::{piece::PieceType, ChessMove};
+use move_generator::{
+    move_generator::get_all_legal_moves_for_color, piece_conductor::PieceConductor,
+    san_to_move::{san_to_square, square_to_san},
+};
+
+/// Points for the best move of an STS position.
+pub const STS_MAX_POINTS: u32 = 10;
+
+/// One position of a suite.
+#[derive(Clone, Debug, Default)]
+pub struct EpdEntry {
+    /// Full FEN: the EPD fields plus the `hmvc` / `fmvn` clocks, if given.
+    pub fen: String,
+    pub id: Option<String>,
+    /// `bm` moves in SAN, as written in the file.
+    pub best_moves: Vec<String>,
+    /// `am` moves in SAN, as written in the file.
+    pub avoid_moves: Vec<String>,
+    /// `c0` comment, verbatim.
+    pub comment: Option<String>,
+    /// STS points per move parsed from `c0`; empty for other suites.
+    pub points: Vec<(String, u32)>,
+}
+
+/// Parse one EPD line.  Returns `Ok(None)` for blank lines and `#` comments.
+pub fn parse_epd_line(line: &str) -> Result<Option<EpdEntry>, String> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+    let mut rest = line;
+    let mut fields = Vec::with_capacity(4);
+    for _ in 0..4 {
+        rest = rest.trim_start();
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        if end == 0 {
+            return Err(format!("expected four FEN fields: {line}"));
+        }
+        fields.push(&rest[..end]);
+        rest = &rest[end..];
+    }
+    validate_layout(fields[0])?;
+    if fields[1] != "w" && fields[1] != "b" {
+        return Err(format!("bad side to move {:?}: {line}", fields[1]));
+    }
+
+    let mut entry = EpdEntry::default();
+    let (mut halfmove, mut fullmove) = ("0".to_string(), "1".to_string());
+    for op in split_operations(rest) {
+        let Some((opcode, operands)) = op.split_first() else { continue };
+        match opcode.as_str() {
+            "bm" => entry.best_moves.extend(operands.iter().cloned()),
+            "am" => entry.avoid_moves.extend(operands.iter().cloned()),
+            "id" => entry.id = operands.first().cloned(),
+            "c0" => {
+                let comment = operands.join(" ");
+                entry.points = parse_points(&comment);
+                entry.comment = Some(comment);
+            }
+            "hmvc" => halfmove = operands.first().cloned().unwrap_or(halfmove),
+            "fmvn" => fullmove = operands.first().cloned().unwrap_or(fullmove),
+            _ => {}
+        }
+    }
+    entry.fen = format!("{} {halfmove} {fullmove}", fields.join(" "));
+    Ok(Some(entry))
+}
+
+/// Parse every position in an EPD file.  Errors carry the line number.
+pub fn parse_epd(text: &str) -> Result<Vec<EpdEntry>, String> {
+    let mut entries = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        if let Some(entry) = parse_epd_line(line).map_err(|e| format!("line {}: {e}", i + 1))? {
+            entries.push(entry);
+        }
+    }
+    Ok(entries)
+}
+
+/// Check the layout and side-to-move fields of a FEN, and that each side has
+/// one king, before it reaches `ChessBoard::set_from_fen`.  The clocks and
+/// the castling and en-passant fields are left to the board.
+pub fn validate_fen(fen: &str) -> Result<(), String> {
+    let mut fields = fen.split_whitespace();
+    let (Some(layout), Some(side)) = (fields.next(), fields.next()) else {
+        return Err(format!("expected a board and a side to move: {fen:?}"));
+    };
+    validate_layout(layout)?;
+    if side != "w" && side != "b" {
+        return Err(format!("bad side to move {side:?}: {fen}"));
+    }
+    for (king, colour) in [('K', "white"), ('k', "black")] {
+        if layout.matches(king).count() != 1 {
+            return Err(format!("expected one {colour} king: {fen}"));
+        }
+    }
+    Ok(())
+}
+
+/// `ChessBoard::set_from_fen` panics on a malformed layout, so check it first.
+fn validate_layout(layout: &str) -> Result<(), String> {
+    let ranks: Vec<&str> = layout.split('/').collect();
+    let ok = ranks.len() == 8
+        && ranks.iter().all(|rank| {
+            let mut files = 0;
+            for c in rank.chars() {
+                match c {
+                    '1'..='8' => files += c as u32 - '0' as u32,
+                    'p' | 'n' | 'b' | 'r' | 'q' | 'k' | 'P' | 'N' | 'B' | 'R' | 'Q' | 'K' => files += 1,
+                    _ => return false,
+                }
+            }
+            files == 8
+        });
+    if ok {
+        Ok(())
+    } else {
+        Err(format!("bad board layout {layout:?}"))
+    }
+}
+
+/// Split the operation part of an EPD line into `[opcode, operands..]`
+/// lists.  Quoted operands may contain spaces and semicolons.
+fn split_operations(s: &str) -> Vec<Vec<String>> {
+    let mut ops = Vec::new();
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quoted = false;
+    let mut was_quoted = false;
+    for c in s.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                was_quoted = true;
+            }
+            _ if quoted => word.push(c),
+            ';' | ' ' | '\t' => {
+                if !word.is_empty() || was_quoted {
+                    words.push(std::mem::take(&mut word));
+                }
+                was_quoted = false;
+                if c == ';' && !words.is_empty() {
+                    ops.push(std::mem::take(&mut words));
+                }
+            }
+            _ => word.push(c),
+        }
+    }
+    if !word.is_empty() || was_quoted {
+        words.push(word);
+    }
+    if !words.is_empty() {
+        ops.push(words);
+    }
+    ops
+}
+
+/// STS points from a `c0` comment such as `"Qd5=10, Qe4=5, f5=3"`.
+/// Anything that doesn't look like `move=points` is skipped.
+fn parse_points(comment: &str) -> Vec<(String, u32)> {
+    comment
+        .split(',')
+        .filter_map(|item| {
+            let (mv, pts) = item.trim().rsplit_once('=')?;
+            Some((mv.trim().to_string(), pts.trim().parse().ok()?))
+        })
+        .collect()
+}
+
+fn legal_moves(board: &mut ChessBoard, conductor: &PieceConductor) -> Vec<ChessMove> {
+    let mut legal = Vec::new();
+    let white = board.is_white_active();
+    get_all_legal_moves_for_color(board, conductor, white, &mut legal, &mut Vec::new());
+    legal
+}
+
+fn same_move(a: &ChessMove, b: &ChessMove) -> bool {
+    a.start_square() == b.start_square()
+        && a.target_square() == b.target_square()
+        && a.promotion_piece_type() == b.promotion_piece_type()
+}
+
+fn piece_letter(piece: PieceType) -> Option<char> {
+    match piece {
+        PieceType::Knight => Some('N'),
+        PieceType::Bishop => Some('B'),
+        PieceType::Rook => Some('R'),
+        PieceType::Queen => Some('Q'),
+        PieceType::King => Some('K'),
+        PieceType::Pawn | PieceType::None => None,
+    }
+}
+
+/// Find the legal move a SAN string names.  Over-specified SAN (`Ng1f3`),
+/// `0-0` for castling and coordinate notation (`g1f3`, `e7e8q`) are
+/// accepted; ambiguous or illegal moves give `None`.
+pub fn parse_san(board: &mut ChessBoard, conductor: &PieceConductor, san: &str) -> Option<ChessMove> {
+    let san = san.trim_end_matches(['+', '#', '!', '?']);
+    let legal = legal_moves(board, conductor);
+    let unique = |mut matches: Vec<ChessMove>| (matches.len() == 1).then(|| matches.remove(0));
+
+    let castle = match san.replace('0', "O").as_str() {
+        "O-O" => Some(6),
+        "O-O-O" => Some(2),
+        _ => None,
+    };
+    if let Some(file) = castle {
+        return unique(
+            legal
+                .iter()
+                .filter(|m| {
+                    m.has_flag(ChessMove::CASTLE_FLAG)
+                        || (board.get_piece_type(m.start_square()) == Some(PieceType::King)
+                            && m.start_square().abs_diff(m.target_square()) == 2)
+                })
+                .filter(|m| m.target_square() % 8 == file)
+                .copied()
+                .collect(),
+        );
+    }
+
+    // Coordinate notation.
+    if san.len() >= 4 && san.is_char_boundary(4) {
+        if let (Some(from), Some(to)) = (san_to_square(&san[..2]), san_to_square(&san[2..4])) {
+            let promotion = san[4..].trim_start_matches('=').chars().next();
+            let found = legal
+                .iter()
+                .filter(|m| m.start_square() == from && m.target_square() == to)
+                .filter(|m| {
+                    m.promotion_piece_type().and_then(piece_letter)
+                        == promotion.map(|c| c.to_ascii_uppercase())
+                })
+                .copied()
+                .collect();
+            if let Some(mv) = unique(found) {
+                return Some(mv);
+            }
+        }
+    }
+
+    let mut chars: Vec<char> = san.chars().collect();
+    let piece = match chars.first() {
+        Some('N') => PieceType::Knight,
+        Some('B') => PieceType::Bishop,
+        Some('R') => PieceType::Rook,
+        Some('Q') => PieceType::Queen,
+        Some('K') => PieceType::King,
+        _ => PieceType::Pawn,
+    };
+    if piece != PieceType::Pawn {
+        chars.remove(0);
+    }
+    let mut promotion = None;
+    if let Some(eq) = chars.iter().position(|&c| c == '=') {
+        promotion = chars.get(eq + 1).copied();
+        chars.truncate(eq);
+    } else if piece == PieceType::Pawn && chars.last().is_some_and(|c| "QRBN".contains(*c)) {
+        promotion = chars.pop();
+    }
+    chars.retain(|&c| c != 'x' && c != '-' && c != ':');
+    if chars.len() < 2 {
+        return None;
+    }
+    let target: String = chars[chars.len() - 2..].iter().collect();
+    let target = san_to_square(&target)?;
+    let hints = &chars[..chars.len() - 2];
+
+    unique(
+        legal
+            .iter()
+            .filter(|m| m.target_square() == target)
+            .filter(|m| board.get_piece_type(m.start_square()) == Some(piece))
+            .filter(|m| m.promotion_piece_type().and_then(piece_letter) == promotion)
+            .filter(|m| {
+                let from = square_to_san(m.start_square());
+                hints.iter().all(|h| from.contains(*h))
+            })
+            .copied()
+            .collect(),
+    )
+}
+
+/// SAN for a legal move, with the minimal disambiguation and a `+` / `#`
+/// suffix.
+pub fn to_san(board: &mut ChessBoard, conductor: &PieceConductor, mv: ChessMove) -> String {
+    let from = mv.start_square();
+    let to = mv.target_square();
+    let piece = board.get_piece_type(from).unwrap_or(PieceType::Pawn);
+    let legal = legal_moves(board, conductor);
+    let mv = legal.iter().copied().find(|m| same_move(m, &mv)).unwrap_or(mv);
+
+    let mut san = String::new();
+    if piece == PieceType::King && from.abs_diff(to) == 2 {
+        san.push_str(if to % 8 == 6 { "O-O" } else { "O-O-O" });
+    } else {
+        let capture = mv.capture.is_some() || mv.has_flag(ChessMove::EN_PASSANT_CAPTURE_FLAG);
+        let from_name = square_to_san(from);
+        match piece_letter(piece) {
+            Some(letter) => {
+                san.push(letter);
+                let rivals: Vec<&ChessMove> = legal
+                    .iter()
+                    .filter(|m| m.target_square() == to && m.start_square() != from)
+                    .filter(|m| board.get_piece_type(m.start_square()) == Some(piece))
+                    .collect();
+                if !rivals.is_empty() {
+                    if rivals.iter().all(|m| m.start_square() % 8 != from % 8) {
+                        san.push_str(&from_name[..1]);
+                    } else if rivals.iter().all(|m| m.start_square() / 8 != from / 8) {
+                        san.push_str(&from_name[1..]);
+                    } else {
+                        san.push_str(&from_name);
+                    }
+                }
+            }
+            None if capture => san.push_str(&from_name[..1]),
+            None => {}
+        }
+        if capture {
+            san.push('x');
+        }
+        san.push_str(&square_to_san(to));
+        if let Some(letter) = mv.promotion_piece_type().and_then(piece_letter) {
+            san.push('=');
+            san.push(letter);
+        }
+    }
+
+    let white = board.is_white_active();
+    let mut played = mv;
+    board.make_move(&mut played);
+    if conductor.is_king_in_check(board, !white) {
+        san.push(if legal_moves(board, conductor).is_empty() { '#' } else { '+' });
+    }
+    board.undo_move();
+    san
+}
+
+impl EpdEntry {
+    /// Board set up from `fen`.
+    pub fn board(&self) -> ChessBoard {
+        let mut board = ChessBoard::new();
+        board.set_from_fen(&self.fen);
+        board
+    }
+
+    /// Whether `mv` solves the position: it is one of the `bm` moves, or
+    /// none of the `am` moves when there is no `bm`.  Positions with neither
+    /// are never solved.
+    pub fn is_solution(&self, board: &mut ChessBoard, conductor: &PieceConductor, mv: ChessMove) -> bool {
+        let matches = |list: &[String], board: &mut ChessBoard| {
+            list.iter().any(|san| parse_san(board, conductor, san).is_some_and(|m| same_move(&m, &mv)))
+        };
+        if !self.best_moves.is_empty() {
+            matches(&self.best_moves, board)
+        } else if !self.avoid_moves.is_empty() {
+            !matches(&self.avoid_moves, board)
+        } else {
+            false
+        }
+    }
+
+    /// STS points for playing `mv`; `None` when the position has no points.
+    pub fn points_for(&self, board: &mut ChessBoard, conductor: &PieceConductor, mv: ChessMove) -> Option<u32> {
+        if self.points.is_empty() {
+            return None;
+        }
+        let points = self
+            .points
+            .iter()
+            .find(|(san, _)| parse_san(board, conductor, san).is_some_and(|m| same_move(&m, &mv)))
+            .map_or(0, |&(_, p)| p);
+        Some(points.min(STS_MAX_POINTS))
+    }
+
+    /// Moves named by `bm`, `am` or `c0` that are not legal here, if any.
+    pub fn unknown_moves(&self, board: &mut ChessBoard, conductor: &PieceConductor) -> Vec<String> {
+        self.best_moves
+            .iter()
+            .chain(&self.avoid_moves)
+            .chain(self.points.iter().map(|(san, _)| san))
+            .filter(|san| parse_san(board, conductor, san).is_none())
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WAC_1: &str =
+        r#"2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id "WAC.001";"#;
+    const STS_1: &str = r#"1kr5/3n4/q3p2p/p2n2p1/PppB1P2/5BP1/1P2Q2P/3R2K1 w - - bm f5; id "Undermine.001"; c0 "f5=10, Be5+=2, Bf2=3, Bg4=2";"#;
+
+    #[test]
+    fn parses_opcodes_and_quoted_operands() {
+        let entry = parse_epd_line(WAC_1).unwrap().unwrap();
+        assert_eq!(entry.fen, "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 0 1");
+        assert_eq!(entry.best_moves, ["Qg6"]);
+        assert_eq!(entry.id.as_deref(), Some("WAC.001"));
+        assert!(entry.points.is_empty());
+
+        let entry = parse_epd_line(STS_1).unwrap().unwrap();
+        assert_eq!(entry.id.as_deref(), Some("Undermine.001"));
+        assert_eq!(entry.points, [("f5".into(), 10), ("Be5+".into(), 2), ("Bf2".into(), 3), ("Bg4".into(), 2)]);
+
+        let entry = parse_epd_line(r#"8/8/8/8/8/8/8/K1k5 b - - am Kb1 Kd1; hmvc 12; fmvn 40; id "x;y";"#)
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.fen, "8/8/8/8/8/8/8/K1k5 b - - 12 40");
+        assert_eq!(entry.avoid_moves, ["Kb1", "Kd1"]);
+        assert_eq!(entry.id.as_deref(), Some("x;y"));
+
+        assert!(parse_epd_line("  # comment").unwrap().is_none());
+        assert!(parse_epd_line("8/8/8 w - - bm e4;").is_err());
+        assert!(parse_epd(&format!("{WAC_1}\n\n{STS_1}\nbad")).unwrap_err().starts_with("line 4"));
+
+        assert!(validate_fen("8/8/8/8/8/8/8/K1k5 b - - 0 1").is_ok());
+        assert!(validate_fen("8/8/8/8/8/8/8/K7 b - - 0 1").is_err());
+        assert!(validate_fen("8/8/8/8/8/8/8/K1k5 x").is_err());
+    }
+
+    #[test]
+    fn san_round_trips_for_every_legal_move() {
+        let conductor = PieceConductor::new();
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "4k3/8/8/8/N7/8/N7/R4RK1 w - - 0 1",
+            "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 0 1",
+        ] {
+            let mut board = ChessBoard::new();
+            board.set_from_fen(fen);
+            for mv in legal_moves(&mut board, &conductor) {
+                let san = to_san(&mut board, &conductor, mv);
+                let parsed = parse_san(&mut board, &conductor, &san).unwrap_or_else(|| panic!("{san} in {fen}"));
+                assert!(same_move(&parsed, &mv), "{san} in {fen}");
+            }
+        }
+    }
+
+    #[test]
+    fn san_forms() {
+        let conductor = PieceConductor::new();
+        let mut board = ChessBoard::new();
+        board.set_from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+        let san = |board: &mut ChessBoard, s: &str| {
+            parse_san(board, &conductor, s).map(|m| to_san(board, &conductor, m))
+        };
+        assert_eq!(san(&mut board, "0-0").as_deref(), Some("O-O"));
+        assert_eq!(san(&mut board, "e1c1").as_deref(), Some("O-O-O"));
+        assert_eq!(san(&mut board, "Qxf6").as_deref(), Some("Qxf6"));
+        assert_eq!(san(&mut board, "Qf3xf6").as_deref(), Some("Qxf6"));
+        assert_eq!(san(&mut board, "d5e6").as_deref(), Some("dxe6"));
+        assert_eq!(san(&mut board, "Nb5").as_deref(), Some("Nb5"));
+        assert_eq!(san(&mut board, "Qg5"), None, "not a legal move");
+
+        board.set_from_fen("4k3/8/8/8/N7/8/N7/R4RK1 w - - 0 1");
+        assert_eq!(san(&mut board, "Rd1"), None, "ambiguous");
+        assert_eq!(san(&mut board, "Rad1").as_deref(), Some("Rad1"));
+        assert_eq!(san(&mut board, "Nc3"), None, "ambiguous");
+        assert_eq!(san(&mut board, "Na2c3").as_deref(), Some("N2c3"));
+
+        board.set_from_fen("7k/P7/8/8/8/8/8/K7 w - - 0 1");
+        assert_eq!(san(&mut board, "a8=Q+").as_deref(), Some("a8=Q+"));
+        assert_eq!(san(&mut board, "a8N").as_deref(), Some("a8=N"));
+        assert_eq!(san(&mut board, "a7a8r").as_deref(), Some("a8=R+"));
+    }
+
+    #[test]
+    fn solutions_and_points() {
+        let conductor = PieceConductor::new();
+        let entry = parse_epd_line(STS_1).unwrap().unwrap();
+        let mut board = entry.board();
+        let f5 = parse_san(&mut board, &conductor, "f5").unwrap();
+        let bf2 = parse_san(&mut board, &conductor, "Bf2").unwrap();
+        let kh1 = parse_san(&mut board, &conductor, "Kh1").unwrap();
+        assert!(entry.is_solution(&mut board, &conductor, f5));
+        assert!(!entry.is_solution(&mut board, &conductor, bf2));
+        assert_eq!(entry.points_for(&mut board, &conductor, f5), Some(10));
+        assert_eq!(entry.points_for(&mut board, &conductor, bf2), Some(3));
+        assert_eq!(entry.points_for(&mut board, &conductor, kh1), Some(0));
+        assert!(entry.unknown_moves(&mut board, &conductor).is_empty());
+
+        let avoid = parse_epd_line("8/8/8/8/8/8/8/K1k5 w - - am Kb1;").unwrap().unwrap();
+        let mut board = avoid.board();
+        let ka2 = parse_san(&mut board, &conductor, "Ka2").unwrap();
+        assert!(avoid.is_solution(&mut board, &conductor, ka2));
+        assert_eq!(avoid.points_for(&mut board, &conductor, ka2), None);
+        assert_eq!(avoid.unknown_moves(&mut board, &conductor), ["Kb1"]);
+    }
+}
@@ -1,27 +1,46 @@
 pub mod alpha_beta;
 pub mod board_evaluation;
+pub mod datagen;
+pub mod engine;
+pub mod epd;
+pub mod mate_search;
+pub mod net_file;
 pub mod neural_eval;
+mod nnue_quant;
 pub mod opening_book;
 pub mod piece_tables;
 pub mod see;
+mod simd;
+pub mod skill;
 pub mod transposition_table;
 #[cfg(feature = "classical-eval")]
 pub mod classical_eval;
+#[cfg(feature = "search-stats")]
+pub mod search_stats;
+
+pub use nnue_features as features;
 
 pub use board_evaluation::evaluate_board;
 pub use neural_eval::{
     init_neural_eval, init_neural_eval_from_bytes,
-    set_neural_eval_enabled, is_neural_eval_enabled, is_neural_eval_initialized,
-    set_neural_confidence_threshold, get_neural_confidence_threshold,
+    set_neural_eval_enabled, is_neural_eval_enabled, is_neural_eval_initialized, neural_net_hash,
+    set_neural_confidence_threshold, get_neural_confidence_threshold, simd_level,
 };
 pub use alpha_beta::alpha_beta;
 pub use alpha_beta::alpha_beta_root;
 pub use alpha_beta::iterative_deepening_root;
 pub use alpha_beta::iterative_deepening_root_with_tt;
+pub use alpha_beta::iterative_deepening_root_with_context;
 pub use alpha_beta::search_root;
+pub use alpha_beta::{DrawKind, DrawScores, RootMove};
 pub use alpha_beta::{ASPIRATION_DELTA, TT_SIZE, TT_SIZE_DEFAULT, SearchContext, SearchResult, available_threads};
-pub use alpha_beta::extract_ponder_move;
+pub use alpha_beta::{extract_ponder_move, pv_from_tt};
+pub use engine::{Engine, SearchEvent, SearchLimits, DEFAULT_ANALYSIS_REFRESH, DEFAULT_HASH_MB};
+pub use mate_search::{find_mate, MateResult};
 pub use opening_book::OpeningBook;
+pub use skill::{Skill, MAX_ELO, MAX_SKILL_LEVEL, MIN_ELO};
+#[cfg(feature = "search-stats")]
+pub use search_stats::{take_search_stats, SearchStats};
 pub use transposition_table::TranspositionTable;
 pub use piece_tables::{
     evaluate_pawn_position, evaluate_knight_position,
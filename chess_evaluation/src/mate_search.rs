@@ -0,0 +1,282 @@
+//! Mate solver for composed problems (`go mate N`).
+//!
+//! A mate-only AND/OR search: at attacker nodes some move must force mate, at
+//! defender nodes every reply must still lose.  Attacker moves are tried
+//! checks first, then captures, then quiet moves, and on the final move only
+//! checks are generated since nothing else can mate.  The search is full
+//! width, so quiet key moves are found and a result is a proof, not a guess.
+//!
+//! Attacker positions are cached by Zobrist hash with the shortest mate
+//! proven and the longest horizon refuted, which lets `find_mate` deepen
+//! one move at a time without redoing earlier work.  Repetition and the
+//! fifty-move rule are ignored, as is usual for problems.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use chess_board::ChessBoard;
+use chess_foundation::ChessMove;
+use move_generator::{
+    move_generator::get_all_legal_moves_for_color, piece_conductor::PieceConductor,
+};
+
+/// A proven mate.
+#[derive(Clone, Debug)]
+pub struct MateResult {
+    /// Mate in this many attacker moves.
+    pub mate_in: u32,
+    /// Attacker and defender moves alternating, ending in checkmate.  The
+    /// defender always plays the reply that delays mate longest.
+    pub line: Vec<ChessMove>,
+    /// Positions visited.
+    pub nodes: u64,
+}
+
+impl MateResult {
+    /// Length of the line in plies.
+    pub fn plies(&self) -> u32 {
+        2 * self.mate_in - 1
+    }
+}
+
+/// What is known about an attacker-to-move position.
+#[derive(Clone, Copy, Default)]
+struct Proof {
+    /// No mate in this many moves or fewer.
+    refuted: u32,
+    /// Shortest mate found, with its first move.
+    mate: Option<(u32, ChessMove)>,
+}
+
+struct MateSearch<'a> {
+    conductor: &'a PieceConductor,
+    stop: Option<&'a AtomicBool>,
+    cache: HashMap<u64, Proof>,
+    nodes: u64,
+    aborted: bool,
+}
+
+impl MateSearch<'_> {
+    fn stopped(&mut self) -> bool {
+        if !self.aborted && self.stop.is_some_and(|s| s.load(Ordering::Relaxed)) {
+            self.aborted = true;
+        }
+        self.aborted
+    }
+
+    /// Attacker moves ordered checks, captures, quiet.  With `checks_only`
+    /// the others are dropped.
+    fn attacker_moves(&self, board: &mut ChessBoard, white: bool, checks_only: bool) -> Vec<ChessMove> {
+        let mut legal = Vec::new();
+        get_all_legal_moves_for_color(board, self.conductor, white, &mut legal, &mut Vec::new());
+        let mut keyed: Vec<(u8, ChessMove)> = Vec::with_capacity(legal.len());
+        for mut mv in legal {
+            let original = mv;
+            board.make_move(&mut mv);
+            let check = self.conductor.is_king_in_check(board, !white);
+            board.undo_move();
+            if check {
+                keyed.push((0, original));
+            } else if !checks_only {
+                keyed.push((if original.capture.is_some() { 1 } else { 2 }, original));
+            }
+        }
+        keyed.sort_by_key(|&(k, _)| k);
+        keyed.into_iter().map(|(_, m)| m).collect()
+    }
+
+    /// Shortest mate in at most `n` moves for `white` to move.
+    fn attack(&mut self, board: &mut ChessBoard, white: bool, n: u32) -> Option<u32> {
+        self.nodes += 1;
+        if n == 0 || self.stopped() {
+            return None;
+        }
+        let key = board.current_hash();
+        let mut proof = self.cache.get(&key).copied().unwrap_or_default();
+        if let Some((k, _)) = proof.mate {
+            if k <= n {
+                return Some(k);
+            }
+        }
+        if proof.refuted >= n {
+            return None;
+        }
+
+        for k in proof.refuted + 1..=n {
+            for mut mv in self.attacker_moves(board, white, k == 1) {
+                let original = mv;
+                board.make_move(&mut mv);
+                let mated = self.defend(board, !white, k - 1);
+                board.undo_move();
+                if self.aborted {
+                    return None;
+                }
+                if mated {
+                    proof.mate = Some((k, original));
+                    self.cache.insert(key, proof);
+                    return Some(k);
+                }
+            }
+            proof.refuted = k;
+        }
+        self.cache.insert(key, proof);
+        None
+    }
+
+    /// Whether `white`, to move, is mated now or after every reply within
+    /// `n` more attacker moves.
+    fn defend(&mut self, board: &mut ChessBoard, white: bool, n: u32) -> bool {
+        self.nodes += 1;
+        let mut replies = Vec::new();
+        get_all_legal_moves_for_color(board, self.conductor, white, &mut replies, &mut Vec::new());
+        if replies.is_empty() {
+            return self.conductor.is_king_in_check(board, white);
+        }
+        if n == 0 {
+            return false;
+        }
+        for mut mv in replies {
+            board.make_move(&mut mv);
+            let mated = self.attack(board, !white, n).is_some();
+            board.undo_move();
+            if !mated || self.aborted {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Line of a proven mate in `n` from an attacker position: the cached
+    /// key move, then the reply that delays mate longest.
+    fn line(&mut self, board: &mut ChessBoard, white: bool, n: u32) -> Vec<ChessMove> {
+        let mut line = Vec::new();
+        let Some(Proof { mate: Some((k, mut key_move)), .. }) = self.cache.get(&board.current_hash()).copied() else {
+            return line;
+        };
+        debug_assert!(k <= n);
+        let original = key_move;
+        board.make_move(&mut key_move);
+        line.push(original);
+
+        let mut replies = Vec::new();
+        get_all_legal_moves_for_color(board, self.conductor, !white, &mut replies, &mut Vec::new());
+        let mut longest: Option<(u32, ChessMove)> = None;
+        for mut mv in replies {
+            let reply = mv;
+            board.make_move(&mut mv);
+            let m = self.attack(board, white, k - 1).unwrap_or(0);
+            board.undo_move();
+            if longest.is_none_or(|(best, _)| m > best) {
+                longest = Some((m, reply));
+            }
+        }
+        if let Some((m, mut reply)) = longest {
+            line.push(reply);
+            board.make_move(&mut reply);
+            line.extend(self.line(board, white, m));
+            board.undo_move();
+        }
+        board.undo_move();
+        line
+    }
+}
+
+/// Look for a mate in at most `max_moves` moves for the side to move,
+/// shortest first.  Returns `None` when there is none or `stop` fired.
+pub fn find_mate(
+    chess_board: &mut ChessBoard,
+    conductor: &PieceConductor,
+    max_moves: u32,
+    stop: Option<&AtomicBool>,
+) -> Option<MateResult> {
+    let white = chess_board.is_white_active();
+    let mut search = MateSearch { conductor, stop, cache: HashMap::new(), nodes: 0, aborted: false };
+    let mate_in = search.attack(chess_board, white, max_moves)?;
+    let line = search.line(chess_board, white, mate_in);
+    Some(MateResult { mate_in, line, nodes: search.nodes })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solve(fen: &str, max_moves: u32) -> Option<MateResult> {
+        let mut board = ChessBoard::new();
+        board.set_from_fen(fen);
+        find_mate(&mut board, &PieceConductor::new(), max_moves, None)
+    }
+
+    /// Replay `line` and check it is legal and ends in checkmate.
+    fn assert_mating_line(fen: &str, result: &MateResult) {
+        let conductor = PieceConductor::new();
+        let mut board = ChessBoard::new();
+        board.set_from_fen(fen);
+        assert_eq!(result.line.len() as u32, result.plies());
+        for &mv in &result.line {
+            let white = board.is_white_active();
+            let mut legal = Vec::new();
+            get_all_legal_moves_for_color(&mut board, &conductor, white, &mut legal, &mut Vec::new());
+            let mut mv = *legal
+                .iter()
+                .find(|m| m.start_square() == mv.start_square() && m.target_square() == mv.target_square())
+                .expect("line move must be legal");
+            board.make_move(&mut mv);
+        }
+        let white = board.is_white_active();
+        let mut legal = Vec::new();
+        get_all_legal_moves_for_color(&mut board, &conductor, white, &mut legal, &mut Vec::new());
+        assert!(legal.is_empty() && conductor.is_king_in_check(&board, white), "line must end in mate");
+    }
+
+    /// Mate in exactly `n`: found at `n`, not at `n - 1`, and the line mates.
+    fn assert_mate_in(fen: &str, n: u32) -> MateResult {
+        let result = solve(fen, n + 1).unwrap_or_else(|| panic!("no mate found in {fen}"));
+        assert_eq!(result.mate_in, n, "{fen}");
+        assert!(solve(fen, n - 1).is_none(), "{fen} mates faster than {n}");
+        assert_mating_line(fen, &result);
+        result
+    }
+
+    #[test]
+    fn mate_in_one() {
+        assert_mate_in("6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1", 1);
+    }
+
+    #[test]
+    fn mate_in_three_rook_ending() {
+        // Kf6 Kh7 Ra8 Kh6 Rh8#: the key move is quiet.
+        let result = assert_mate_in("7k/8/8/4K3/8/8/8/R7 w - - 0 1", 3);
+        assert_eq!((result.line[0].start_square(), result.line[0].target_square()), (36, 45));
+    }
+
+    #[test]
+    fn mate_in_four_philidors_legacy() {
+        // Nf7+ Kg8 Nh6+ Kh8 Qg8+ Rxg8 Nf7#.
+        assert_mate_in("r6k/6pp/8/6N1/2Q5/8/8/6K1 w - - 0 1", 4);
+    }
+
+    #[test]
+    fn mate_in_four_for_black() {
+        assert_mate_in("r1b2k1r/ppp2ppp/8/2bBN3/3nn3/8/PPP2qPP/RNBQR2K b - - 0 1", 4);
+    }
+
+    #[test]
+    fn mate_in_five_rook_ending() {
+        assert_mate_in("7k/8/8/8/5K2/8/8/R7 w - - 0 1", 5);
+    }
+
+    #[test]
+    fn no_mate_with_a_lone_queen_far_away() {
+        assert!(solve("8/8/8/4k3/8/8/8/3QK3 w - - 0 1", 3).is_none());
+    }
+
+    #[test]
+    fn stop_flag_aborts() {
+        let stop = AtomicBool::new(true);
+        let mut board = ChessBoard::new();
+        board.set_from_fen("7k/8/8/8/5K2/8/8/R7 w - - 0 1");
+        let hash = board.current_hash();
+        assert!(find_mate(&mut board, &PieceConductor::new(), 5, Some(&stop)).is_none());
+        assert_eq!(board.current_hash(), hash);
+    }
+}
@@ -0,0 +1,735 @@
+//! Native network file format, and the `.npz` it replaces.
+//!
+//! One little-endian binary file:
+//!
+//! ```text
+//! magic          8 bytes   "XCNNUE\r\n"
+//! version        u32       FORMAT_VERSION
+//! architecture   24 bytes  feature set u8, perspectives u8, king buckets u8,
+//!                          reserved u8,
+//!                          input_dim u32, hidden1 u32, hidden2 u32,
+//!                          output buckets u32, scale f32
+//! metadata       u32 count, then per entry a key and a value, each
+//!                u32 byte length + UTF-8
+//! tensors        8 × (u32 element count + i16 data), in `TENSOR_NAMES` order,
+//!                row-major as the trainer stores them
+//! content hash   32 bytes  SHA-256 of the architecture and tensor sections
+//! ```
+//!
+//! Feature set codes: 1 = legacy 768, 2 = HalfKP, 3 = HalfKA (v2).  King
+//! buckets is the bucket count of the map, 0 for none or the original 8-bucket
+//! map, which keeps the hashes of older nets.  Version 1 files predate the
+//! byte and must have 0 there; they still load.
+//!
+//! The hash identifies the weights: metadata is left out, so relabelling a
+//! net does not change it, and a `.npz` hashes the same as its conversion.
+//! Loading checks magic, version, section sizes against the architecture and
+//! the hash, and `NeuralEvaluator` then checks the architecture against the
+//! one it was built for.  `.npz` files (the exporter's output, architecture
+//! inferred from tensor shapes) still load; the `net_convert` binary turns
+//! them into native files.
+
+use std::fmt;
+use std::io::{Read, Seek};
+
+use sha2::{Digest, Sha256};
+
+use crate::features::{FeatureLayout, FeatureSet, KingBuckets};
+
+pub const MAGIC: [u8; 8] = *b"XCNNUE\r\n";
+pub const FORMAT_VERSION: u32 = 2;
+
+/// Tensor order in the file, named as in the exporter's `.npz`.
+pub const TENSOR_NAMES: [&str; 8] = [
+    "backbone_0_weight",
+    "backbone_0_bias",
+    "backbone_3_weight",
+    "backbone_3_bias",
+    "cp_head_weight",
+    "cp_head_bias",
+    "wdl_head_weight",
+    "wdl_head_bias",
+];
+
+const ARCH_BYTES: usize = 24;
+const HASH_BYTES: usize = 32;
+
+/// Largest hidden layer and output bucket count a net may declare, far
+/// above anything trained; a header beyond them is damaged or not a net.
+const MAX_HIDDEN: usize = 1 << 16;
+const MAX_OUTPUT_BUCKETS: usize = 256;
+
+fn set_code(set: FeatureSet) -> u8 {
+    match set {
+        FeatureSet::Legacy768 => 1,
+        FeatureSet::HalfKp => 2,
+        FeatureSet::HalfKa => 3,
+    }
+}
+
+fn set_from_code(code: u8) -> Result<FeatureSet, String> {
+    match code {
+        1 => Ok(FeatureSet::Legacy768),
+        2 => Ok(FeatureSet::HalfKp),
+        3 => Ok(FeatureSet::HalfKa),
+        c => Err(format!("unknown feature set {c} (this build knows 1 = legacy 768, 2 = HalfKP, 3 = HalfKA)")),
+    }
+}
+
+fn kings_code(layout: FeatureLayout) -> u8 {
+    match (layout.set, layout.kings) {
+        (FeatureSet::Legacy768, _) | (_, KingBuckets::Mirrored8) => 0,
+        (_, kings) => kings.count() as u8,
+    }
+}
+
+fn kings_from_code(code: u8) -> Result<KingBuckets, String> {
+    match code {
+        0 => Ok(KingBuckets::Mirrored8),
+        c => KingBuckets::from_count(c as usize)
+            .ok_or_else(|| format!("unknown king bucket map with {c} buckets (expected 8, 16 or 32)")),
+    }
+}
+
+/// The first layout with `dim` inputs, for files that only give a size.
+/// Layouts sharing a size (HalfKP with 8 or 16 buckets) resolve to the one
+/// trained first; `NetFile::with_features` picks another.
+fn layout_from_input_dim(dim: usize) -> Result<FeatureLayout, String> {
+    let candidates = [
+        FeatureLayout::LEGACY,
+        FeatureLayout::HALFKP,
+        FeatureLayout::new(FeatureSet::HalfKa, KingBuckets::Mirrored8),
+        FeatureLayout::new(FeatureSet::HalfKp, KingBuckets::Mirrored32),
+        FeatureLayout::new(FeatureSet::HalfKa, KingBuckets::Mirrored32),
+    ];
+    candidates
+        .into_iter()
+        .find(|l| l.input_dim() == dim)
+        .ok_or_else(|| format!("no feature layout has {dim} inputs"))
+}
+
+/// Shape and quantization of a network.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Architecture {
+    pub features: FeatureLayout,
+    /// Both sides' accumulators feed layer 2 (dual) or only the mover's.
+    pub dual_perspective: bool,
+    pub hidden1: usize,
+    pub hidden2: usize,
+    pub output_buckets: usize,
+    /// Quantization scale: raw i16 / scale = float weight.
+    pub scale: f32,
+}
+
+impl Architecture {
+    fn perspectives(&self) -> usize {
+        if self.dual_perspective { 2 } else { 1 }
+    }
+
+    /// Element count of each tensor, in `TENSOR_NAMES` order.  Fails for
+    /// empty layers and sizes beyond `MAX_HIDDEN` / `MAX_OUTPUT_BUCKETS`.
+    pub fn tensor_lens(&self) -> Result<[usize; 8], String> {
+        let (h1, h2, b) = (self.hidden1, self.hidden2, self.output_buckets);
+        if h1 == 0 || h2 == 0 || b == 0 {
+            return Err(format!("empty layer in {self}"));
+        }
+        if h1 > MAX_HIDDEN || h2 > MAX_HIDDEN || b > MAX_OUTPUT_BUCKETS {
+            return Err(format!(
+                "implausible size {self} (at most {MAX_HIDDEN} per hidden layer and {MAX_OUTPUT_BUCKETS} buckets)"
+            ));
+        }
+        let mul = |factors: &[usize]| {
+            factors
+                .iter()
+                .try_fold(1usize, |n, &f| n.checked_mul(f))
+                .ok_or_else(|| format!("tensor size overflow in {self}"))
+        };
+        Ok([
+            mul(&[h1, self.features.input_dim()])?,
+            h1,
+            mul(&[h2, h1, self.perspectives()])?,
+            h2,
+            mul(&[b, h2])?,
+            b,
+            mul(&[b, 3, h2])?,
+            mul(&[b, 3])?,
+        ])
+    }
+
+    fn to_bytes(&self) -> [u8; ARCH_BYTES] {
+        let mut out = [0u8; ARCH_BYTES];
+        out[0] = set_code(self.features.set);
+        out[1] = self.perspectives() as u8;
+        out[2] = kings_code(self.features);
+        out[4..8].copy_from_slice(&(self.features.input_dim() as u32).to_le_bytes());
+        out[8..12].copy_from_slice(&(self.hidden1 as u32).to_le_bytes());
+        out[12..16].copy_from_slice(&(self.hidden2 as u32).to_le_bytes());
+        out[16..20].copy_from_slice(&(self.output_buckets as u32).to_le_bytes());
+        out[20..24].copy_from_slice(&self.scale.to_le_bytes());
+        out
+    }
+
+    /// Parses the descriptor of a file of format `version`.
+    fn from_bytes(b: &[u8; ARCH_BYTES], version: u32) -> Result<Self, String> {
+        let u32_at = |i: usize| u32::from_le_bytes([b[i], b[i + 1], b[i + 2], b[i + 3]]) as usize;
+        if version == 1 && b[2] != 0 {
+            return Err(format!("reserved byte is {} in a version 1 file", b[2]));
+        }
+        let features = FeatureLayout::new(set_from_code(b[0])?, kings_from_code(b[2])?);
+        let dual_perspective = match b[1] {
+            1 => false,
+            2 => true,
+            p => return Err(format!("{p} perspectives (expected 1 or 2)")),
+        };
+        if u32_at(4) != features.input_dim() {
+            return Err(format!("{features} has {} inputs, header says {}", features.input_dim(), u32_at(4)));
+        }
+        let arch = Architecture {
+            features,
+            dual_perspective,
+            hidden1: u32_at(8),
+            hidden2: u32_at(12),
+            output_buckets: u32_at(16),
+            scale: f32::from_le_bytes([b[20], b[21], b[22], b[23]]),
+        };
+        arch.tensor_lens()?;
+        if !(arch.scale.is_finite() && arch.scale > 0.0) {
+            return Err(format!("invalid scale {}", arch.scale));
+        }
+        Ok(arch)
+    }
+}
+
+impl fmt::Display for Architecture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {}→{}{}→{}→{} bucket{}, scale {}",
+            self.features,
+            self.features.input_dim(),
+            self.hidden1,
+            if self.dual_perspective { "x2" } else { "" },
+            self.hidden2,
+            self.output_buckets,
+            if self.output_buckets == 1 { "" } else { "s" },
+            self.scale
+        )
+    }
+}
+
+/// A network as stored: architecture, free-form metadata and raw tensors.
+#[derive(Clone, Debug)]
+pub struct NetFile {
+    pub arch: Architecture,
+    /// Training metadata (`dataset`, `epochs`, ...), in file order.
+    pub metadata: Vec<(String, String)>,
+    /// Raw i16 tensors in `TENSOR_NAMES` order.
+    pub tensors: [Vec<i16>; 8],
+}
+
+impl NetFile {
+    /// Parse either format, telling them apart by their first bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.starts_with(&MAGIC) {
+            Self::from_native(bytes)
+        } else if bytes.starts_with(b"PK") {
+            Self::from_npz(bytes)
+        } else {
+            Err("not a network file: expected the native format or an .npz".into())
+        }
+    }
+
+    /// Parse a native file, checking version, sizes and content hash.
+    pub fn from_native(bytes: &[u8]) -> Result<Self, String> {
+        let mut r = Reader { bytes, pos: 0 };
+        if r.take(MAGIC.len())? != MAGIC {
+            return Err("not a native network file (bad magic)".into());
+        }
+        let version = r.u32()?;
+        if !(1..=FORMAT_VERSION).contains(&version) {
+            return Err(format!(
+                "network file format version {version} is not supported (this build reads versions 1 to {FORMAT_VERSION})"
+            ));
+        }
+        let arch_start = r.pos;
+        let arch = Architecture::from_bytes(r.take(ARCH_BYTES)?.try_into().unwrap(), version)
+            .map_err(|e| format!("bad architecture descriptor: {e}"))?;
+
+        let n_meta = r.u32()? as usize;
+        let mut metadata = Vec::with_capacity(n_meta.min(64));
+        for _ in 0..n_meta {
+            metadata.push((r.string()?, r.string()?));
+        }
+
+        let tensors_start = r.pos;
+        let mut tensors: [Vec<i16>; 8] = Default::default();
+        for ((tensor, name), expected) in tensors.iter_mut().zip(TENSOR_NAMES).zip(arch.tensor_lens()?) {
+            let n = r.u32()? as usize;
+            if n != expected {
+                return Err(format!("{name} has {n} values, the architecture ({arch}) needs {expected}"));
+            }
+            *tensor = r
+                .take(n.checked_mul(2).ok_or("tensor size overflow")?)?
+                .chunks_exact(2)
+                .map(|c| i16::from_le_bytes([c[0], c[1]]))
+                .collect();
+        }
+        let tensors_end = r.pos;
+
+        let stored: [u8; HASH_BYTES] = r.take(HASH_BYTES)?.try_into().unwrap();
+        if r.pos != bytes.len() {
+            return Err(format!("{} unexpected bytes after the content hash", bytes.len() - r.pos));
+        }
+        let computed = content_hash(&bytes[arch_start..arch_start + ARCH_BYTES], &bytes[tensors_start..tensors_end]);
+        if computed != stored {
+            return Err(format!(
+                "content hash mismatch: file says {}, weights hash to {} (corrupt or modified file)",
+                hex(&stored),
+                hex(&computed)
+            ));
+        }
+        Ok(NetFile { arch, metadata, tensors })
+    }
+
+    /// Read the exporter's `.npz`, inferring the architecture from tensor
+    /// shapes.  Carries no metadata.
+    pub fn from_npz(bytes: &[u8]) -> Result<Self, String> {
+        let cursor = std::io::Cursor::new(bytes);
+        let mut zip = zip::ZipArchive::new(cursor)
+            .map_err(|e| format!("Not a valid NPZ/zip file: {e}"))?;
+
+        let scale = read_npy_f32_scalar(&mut zip, "scale.npy")?;
+        let mut tensors: [Vec<i16>; 8] = Default::default();
+        for (tensor, name) in tensors.iter_mut().zip(TENSOR_NAMES) {
+            *tensor = read_npy_i16(&mut zip, &format!("{name}.npy"))?;
+        }
+
+        let (hidden1, hidden2, output_buckets) = (tensors[1].len(), tensors[3].len(), tensors[5].len());
+        if hidden1 == 0 || hidden2 == 0 || output_buckets == 0 {
+            return Err("NPZ has an empty bias tensor".into());
+        }
+        let input_dim = tensors[0].len() / hidden1;
+        let perspectives = tensors[2].len() / hidden2.checked_mul(hidden1).ok_or("NPZ layer sizes overflow")?;
+        let arch = Architecture {
+            features: layout_from_input_dim(input_dim)?,
+            dual_perspective: match perspectives {
+                1 => false,
+                2 => true,
+                _ => {
+                    return Err(format!(
+                        "Unexpected backbone_3 size {} for {hidden1}→{hidden2} (expected 1 or 2 perspectives)",
+                        tensors[2].len()
+                    ))
+                }
+            },
+            hidden1,
+            hidden2,
+            output_buckets,
+            scale,
+        };
+        for ((tensor, name), expected) in tensors.iter().zip(TENSOR_NAMES).zip(arch.tensor_lens()?) {
+            if tensor.len() != expected {
+                return Err(format!("{name} has {} values, inferred architecture ({arch}) needs {expected}", tensor.len()));
+            }
+        }
+        Ok(NetFile { arch, metadata: Vec::new(), tensors })
+    }
+
+    /// The same weights read as `features`, for `.npz` files whose input
+    /// size fits more than one layout.  The sizes must agree.
+    pub fn with_features(mut self, features: FeatureLayout) -> Result<Self, String> {
+        if features.input_dim() != self.arch.features.input_dim() {
+            return Err(format!(
+                "{features} has {} inputs, the net has {}",
+                features.input_dim(),
+                self.arch.features.input_dim()
+            ));
+        }
+        self.arch.features = features;
+        Ok(self)
+    }
+
+    /// Serialize in the native format.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let tensors = self.tensor_bytes();
+        let arch = self.arch.to_bytes();
+        let mut out = Vec::with_capacity(64 + tensors.len());
+        out.extend_from_slice(&MAGIC);
+        out.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        out.extend_from_slice(&arch);
+        out.extend_from_slice(&(self.metadata.len() as u32).to_le_bytes());
+        for (key, value) in &self.metadata {
+            for s in [key, value] {
+                out.extend_from_slice(&(s.len() as u32).to_le_bytes());
+                out.extend_from_slice(s.as_bytes());
+            }
+        }
+        out.extend_from_slice(&tensors);
+        out.extend_from_slice(&content_hash(&arch, &tensors));
+        out
+    }
+
+    /// SHA-256 of architecture and weights, as lowercase hex.
+    pub fn hash_hex(&self) -> String {
+        hex(&content_hash(&self.arch.to_bytes(), &self.tensor_bytes()))
+    }
+
+    fn tensor_bytes(&self) -> Vec<u8> {
+        let total: usize = self.tensors.iter().map(|t| 4 + 2 * t.len()).sum();
+        let mut out = Vec::with_capacity(total);
+        for t in &self.tensors {
+            out.extend_from_slice(&(t.len() as u32).to_le_bytes());
+            for v in t {
+                out.extend_from_slice(&v.to_le_bytes());
+            }
+        }
+        out
+    }
+}
+
+/// The net hash of a file in either format without building an evaluator.
+/// Native files are fully verified.
+pub fn net_hash(bytes: &[u8]) -> Result<String, String> {
+    NetFile::from_bytes(bytes).map(|net| net.hash_hex())
+}
+
+fn content_hash(arch: &[u8], tensors: &[u8]) -> [u8; HASH_BYTES] {
+    let mut h = Sha256::new();
+    h.update(arch);
+    h.update(tensors);
+    h.finalize().into()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        let end = self.pos.checked_add(n).filter(|&e| e <= self.bytes.len()).ok_or_else(|| {
+            format!("network file truncated at byte {} ({} bytes)", self.pos, self.bytes.len())
+        })?;
+        let s = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(s)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let n = self.u32()? as usize;
+        String::from_utf8(self.take(n)?.to_vec()).map_err(|_| "metadata is not valid UTF-8".into())
+    }
+}
+
+// ── NPY parsing ───────────────────────────────────────────────────────────
+
+fn read_npy_i16<R: Read + Seek>(
+    zip: &mut zip::ZipArchive<R>,
+    name: &str,
+) -> Result<Vec<i16>, String> {
+    let mut buf = Vec::new();
+    zip.by_name(name)
+        .map_err(|_| format!("Missing array '{name}' in NPZ"))?
+        .read_to_end(&mut buf)
+        .map_err(|e| format!("Read error for '{name}': {e}"))?;
+    parse_npy_i16(&buf, name)
+}
+
+fn read_npy_f32_scalar<R: Read + Seek>(
+    zip: &mut zip::ZipArchive<R>,
+    name: &str,
+) -> Result<f32, String> {
+    let mut buf = Vec::new();
+    zip.by_name(name)
+        .map_err(|_| format!("Missing array '{name}' in NPZ"))?
+        .read_to_end(&mut buf)
+        .map_err(|e| format!("Read error for '{name}': {e}"))?;
+    parse_npy_f32(&buf, name)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| format!("'{name}' is empty"))
+}
+
+fn parse_npy_i16(buf: &[u8], name: &str) -> Result<Vec<i16>, String> {
+    let (offset, n) = parse_npy_header(buf, name, "<i2")?;
+    let data = &buf[offset..];
+    if data.len() < n * 2 {
+        return Err(format!("'{name}': data too short ({} bytes for {n} i16)", data.len()));
+    }
+    Ok(data[..n * 2]
+        .chunks_exact(2)
+        .map(|c| i16::from_le_bytes([c[0], c[1]]))
+        .collect())
+}
+
+fn parse_npy_f32(buf: &[u8], name: &str) -> Result<Vec<f32>, String> {
+    let (offset, n) = parse_npy_header(buf, name, "<f4")?;
+    let data = &buf[offset..];
+    if data.len() < n * 4 {
+        return Err(format!("'{name}': data too short ({} bytes for {n} f32)", data.len()));
+    }
+    Ok(data[..n * 4]
+        .chunks_exact(4)
+        .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+        .collect())
+}
+
+fn parse_npy_header(buf: &[u8], name: &str, expected_dtype: &str) -> Result<(usize, usize), String> {
+    if buf.len() < 10 || &buf[0..6] != b"\x93NUMPY" {
+        return Err(format!("'{name}' is not a valid .npy file"));
+    }
+    let major = buf[6];
+    let (header_len, header_start) = match major {
+        1 => (u16::from_le_bytes([buf[8], buf[9]]) as usize, 10usize),
+        2 => {
+            if buf.len() < 12 {
+                return Err(format!("'{name}': truncated v2 header"));
+            }
+            (u32::from_le_bytes([buf[8], buf[9], buf[10], buf[11]]) as usize, 12usize)
+        }
+        v => return Err(format!("'{name}': unsupported .npy version {v}")),
+    };
+
+    let data_offset = header_start + header_len;
+    if buf.len() < data_offset {
+        return Err(format!("'{name}': file truncated before data"));
+    }
+
+    let header = std::str::from_utf8(&buf[header_start..data_offset])
+        .map_err(|_| format!("'{name}': header is not valid UTF-8"))?;
+
+    if !header.contains(expected_dtype) {
+        return Err(format!(
+            "'{name}': expected dtype '{expected_dtype}', got header: {header}"
+        ));
+    }
+
+    let n = parse_shape_product(header, name)?;
+    Ok((data_offset, n))
+}
+
+fn parse_shape_product(header: &str, name: &str) -> Result<usize, String> {
+    let shape_start = header
+        .find("'shape'")
+        .or_else(|| header.find("\"shape\""))
+        .ok_or_else(|| format!("'{name}': no 'shape' key in header"))?;
+    let after = &header[shape_start..];
+    let open = after
+        .find('(')
+        .ok_or_else(|| format!("'{name}': malformed shape tuple"))?;
+    let close = after
+        .find(')')
+        .ok_or_else(|| format!("'{name}': malformed shape tuple"))?;
+    let inner = &after[open + 1..close];
+
+    let product: usize = inner
+        .split(',')
+        .filter_map(|s| s.trim().parse::<usize>().ok())
+        .product();
+
+    Ok(if product == 0 { 1 } else { product })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn small_net() -> NetFile {
+        let arch = Architecture {
+            features: FeatureLayout::LEGACY,
+            dual_perspective: true,
+            hidden1: 8,
+            hidden2: 4,
+            output_buckets: 2,
+            scale: 256.0,
+        };
+        let mut seed = 1u32;
+        let tensors = arch.tensor_lens().unwrap().map(|n| {
+            (0..n)
+                .map(|_| {
+                    seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                    (seed >> 16) as i16
+                })
+                .collect()
+        });
+        NetFile {
+            arch,
+            metadata: vec![("dataset".into(), "unit-test".into()), ("epochs".into(), "3".into())],
+            tensors,
+        }
+    }
+
+    fn npy_bytes(descr: &str, shape: &str, data: &[u8]) -> Vec<u8> {
+        let mut header = format!("{{'descr': '{descr}', 'fortran_order': False, 'shape': {shape}, }}");
+        while (10 + header.len() + 1) % 64 != 0 {
+            header.push(' ');
+        }
+        header.push('\n');
+        let mut out = b"\x93NUMPY\x01\x00".to_vec();
+        out.extend_from_slice(&(header.len() as u16).to_le_bytes());
+        out.extend_from_slice(header.as_bytes());
+        out.extend_from_slice(data);
+        out
+    }
+
+    /// The net as the Python exporter would write it.
+    fn npz_bytes(net: &NetFile) -> Vec<u8> {
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let opts = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Stored);
+        zip.start_file("scale.npy", opts).unwrap();
+        zip.write_all(&npy_bytes("<f4", "()", &net.arch.scale.to_le_bytes())).unwrap();
+        for (name, t) in TENSOR_NAMES.iter().zip(&net.tensors) {
+            let data: Vec<u8> = t.iter().flat_map(|v| v.to_le_bytes()).collect();
+            zip.start_file(format!("{name}.npy"), opts).unwrap();
+            zip.write_all(&npy_bytes("<i2", &format!("({},)", t.len()), &data)).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn native_round_trip() {
+        let net = small_net();
+        let bytes = net.to_bytes();
+        let back = NetFile::from_bytes(&bytes).unwrap();
+        assert_eq!(back.arch, net.arch);
+        assert_eq!(back.metadata, net.metadata);
+        assert_eq!(back.tensors, net.tensors);
+        assert_eq!(back.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn npz_converts_with_the_same_hash() {
+        let net = small_net();
+        let from_npz = NetFile::from_bytes(&npz_bytes(&net)).unwrap();
+        assert_eq!(from_npz.arch, net.arch);
+        assert_eq!(from_npz.tensors, net.tensors);
+        assert!(from_npz.metadata.is_empty());
+        // Metadata is not hashed: the npz and the labelled native file agree.
+        assert_eq!(net_hash(&npz_bytes(&net)).unwrap(), net_hash(&net.to_bytes()).unwrap());
+        assert_eq!(net.hash_hex().len(), 64);
+    }
+
+    #[test]
+    fn hash_changes_with_the_weights() {
+        let net = small_net();
+        let mut other = net.clone();
+        other.tensors[3][0] ^= 1;
+        assert_ne!(net.hash_hex(), other.hash_hex());
+    }
+
+    #[test]
+    fn rejects_bad_magic_and_versions() {
+        let mut bytes = small_net().to_bytes();
+        assert!(NetFile::from_bytes(&bytes[1..]).unwrap_err().contains("not a network file"));
+        for version in [0, FORMAT_VERSION + 1] {
+            bytes[8..12].copy_from_slice(&version.to_le_bytes());
+            let err = NetFile::from_bytes(&bytes).unwrap_err();
+            assert!(err.contains(&format!("version {version} is not supported")), "{err}");
+        }
+    }
+
+    #[test]
+    fn version_1_files_still_load() {
+        let mut net = small_net();
+        net.arch.features = FeatureLayout::HALFKP;
+        net.tensors = net.arch.tensor_lens().unwrap().map(|n| vec![1; n]);
+        let mut bytes = net.to_bytes();
+        bytes[8..12].copy_from_slice(&1u32.to_le_bytes());
+        let back = NetFile::from_bytes(&bytes).unwrap();
+        assert_eq!(back.arch, net.arch);
+        assert_eq!(back.hash_hex(), net.hash_hex());
+
+        // Version 1 had no king bucket byte: it must be 0.
+        bytes[14] = 16;
+        let err = NetFile::from_bytes(&bytes).unwrap_err();
+        assert!(err.contains("reserved byte is 16 in a version 1 file"), "{err}");
+    }
+
+    #[test]
+    fn rejects_truncated_and_trailing_bytes() {
+        let bytes = small_net().to_bytes();
+        for cut in [10, 30, bytes.len() / 2, bytes.len() - 1] {
+            let err = NetFile::from_bytes(&bytes[..cut]).unwrap_err();
+            assert!(err.contains("truncated"), "{cut}: {err}");
+        }
+        let mut long = bytes.clone();
+        long.push(0);
+        assert!(NetFile::from_bytes(&long).unwrap_err().contains("unexpected bytes"));
+    }
+
+    #[test]
+    fn rejects_corrupted_weights() {
+        let mut bytes = small_net().to_bytes();
+        let n = bytes.len();
+        bytes[n - HASH_BYTES - 1] ^= 0x40;
+        let err = NetFile::from_bytes(&bytes).unwrap_err();
+        assert!(err.contains("content hash mismatch"), "{err}");
+    }
+
+    #[test]
+    fn rejects_tensors_that_disagree_with_the_descriptor() {
+        let mut net = small_net();
+        net.arch.hidden2 = 5;
+        let err = NetFile::from_bytes(&net.to_bytes()).unwrap_err();
+        assert!(err.contains("backbone_3_weight has 64 values"), "{err}");
+
+        let mut bytes = small_net().to_bytes();
+        bytes[12] = 9;
+        let err = NetFile::from_bytes(&bytes).unwrap_err();
+        assert!(err.contains("unknown feature set 9"), "{err}");
+    }
+
+    #[test]
+    fn rejects_implausible_dimensions() {
+        let bytes = small_net().to_bytes();
+        // hidden1, hidden2 and output buckets, at 12 + 8, 12 + 12, 12 + 16.
+        for at in [20, 24, 28] {
+            for value in [0, u32::MAX] {
+                let mut forged = bytes.clone();
+                forged[at..at + 4].copy_from_slice(&value.to_le_bytes());
+                let err = NetFile::from_bytes(&forged).unwrap_err();
+                assert!(err.contains("empty layer") || err.contains("implausible size"), "{at} {value}: {err}");
+            }
+        }
+    }
+
+    #[test]
+    fn king_bucket_layouts_round_trip() {
+        let mut net = small_net();
+        net.arch.features = FeatureLayout::new(FeatureSet::HalfKa, KingBuckets::Mirrored32);
+        net.tensors = net.arch.tensor_lens().unwrap().map(|n| vec![1; n]);
+        let back = NetFile::from_bytes(&net.to_bytes()).unwrap();
+        assert_eq!(back.arch.features, net.arch.features);
+        assert_eq!(back.arch.to_string(), "halfka/32 22528→8x2→4→2 buckets, scale 256");
+
+        let mut bytes = net.to_bytes();
+        bytes[14] = 12;
+        let err = NetFile::from_bytes(&bytes).unwrap_err();
+        assert!(err.contains("king bucket map with 12 buckets"), "{err}");
+    }
+
+    #[test]
+    fn npz_layout_can_be_overridden() {
+        let mut net = small_net();
+        net.arch.features = FeatureLayout::HALFKP;
+        net.tensors = net.arch.tensor_lens().unwrap().map(|n| vec![1; n]);
+        let from_npz = NetFile::from_bytes(&npz_bytes(&net)).unwrap();
+        assert_eq!(from_npz.arch.features, FeatureLayout::HALFKP);
+        let sixteen = FeatureLayout::new(FeatureSet::HalfKp, KingBuckets::Mirrored16);
+        let relabelled = from_npz.clone().with_features(sixteen).unwrap();
+        assert_eq!(relabelled.arch.features, sixteen);
+        assert_ne!(relabelled.hash_hex(), from_npz.hash_hex());
+        assert!(from_npz.with_features(FeatureLayout::LEGACY).is_err());
+    }
+}
@@ -13,7 +13,8 @@
 //!   SCReLU activation: `clamp(x,0,1)²` at every activation site.
 //!   Output buckets: separate weights per game phase (2–32 pieces → bucket 0–7).
 //!
-//! Weights are loaded from an NPZ file exported by `scripts/export_weights.py`.
+//! Weights are loaded from a native net file (see `net_file`) or directly
+//! from the NPZ exported by `scripts/export_weights.py`.
 //!
 //! ```ignore
 //! init_neural_eval("path/to/nnue_like_weights.npz").unwrap();
@@ -23,24 +24,28 @@
 //! Score convention: returns centipawns from **white's perspective**
 //! (positive = good for white), matching the existing `evaluate_board`.
 
-use std::io::{Read, Seek};
 use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::OnceLock;
 
 use chess_board::ChessBoard;
-use chess_foundation::bitboard::Bitboard;
+use chess_foundation::piece::PieceType;
+
+use crate::features::FeatureLayout;
+use crate::net_file::NetFile;
+use crate::nnue_quant::{QuantizedNet, QA};
+use crate::simd;
 
 // ── Architecture constants ────────────────────────────────────────────────
 
 pub(crate) const HIDDEN1: usize = 1024;
-const HIDDEN2: usize = 32;
-const HIDDEN1_DUAL: usize = HIDDEN1 * 2; // 2048
+pub(crate) const HIDDEN2: usize = 32;
+pub(crate) const HIDDEN1_DUAL: usize = HIDDEN1 * 2; // 2048
 
 // ── SCReLU activation: clamp(x,0,1)² ──────────────────────────────────────
 
 /// SCReLU for the i16→f32 accumulator path: dequantize + clamp to [0,1] + square.
 #[inline(always)]
-fn screlu_i16(raw: i16, scale: f32) -> f32 {
+pub(crate) fn screlu_i16(raw: i16, scale: f32) -> f32 {
     let c = (raw.max(0) as f32).min(scale);
     let x = c / scale;
     x * x
@@ -53,8 +58,10 @@ fn screlu_f32(x: f32) -> f32 {
     c * c
 }
 
-const HALFKP_FEATURE_DIM: usize = 12 * 64 * 16; // 12,288
-const LEGACY_FEATURE_DIM: usize = 768;
+#[cfg(test)]
+const HALFKP_FEATURE_DIM: usize = FeatureLayout::HALFKP.input_dim(); // 12,288
+#[cfg(test)]
+const LEGACY_FEATURE_DIM: usize = FeatureLayout::LEGACY.input_dim();
 
 // ── Global state ──────────────────────────────────────────────────────────
 
@@ -65,15 +72,15 @@ static EVALUATOR: OnceLock<NeuralEvaluator> = OnceLock::new();
 /// Default 0.0 = always trust NN (no fallback).
 static CONFIDENCE_THRESHOLD: AtomicU32 = AtomicU32::new(0);
 
-/// Load weights from an NPZ file path.
+/// Load weights from a net file path (native format or `.npz`).
 pub fn init_neural_eval(path: &str) -> Result<(), String> {
     let bytes = std::fs::read(path).map_err(|e| format!("Failed to read {path}: {e}"))?;
     init_neural_eval_from_bytes(&bytes)
 }
 
-/// Load weights from an in-memory NPZ blob.
+/// Load weights from an in-memory net file (native format or `.npz`).
 pub fn init_neural_eval_from_bytes(bytes: &[u8]) -> Result<(), String> {
-    let evaluator = NeuralEvaluator::from_npz_bytes(bytes)?;
+    let evaluator = NeuralEvaluator::from_bytes(bytes)?;
     EVALUATOR
         .set(evaluator)
         .map_err(|_| "Neural evaluator already initialized".into())
@@ -84,6 +91,16 @@ pub fn is_neural_eval_initialized() -> bool {
     EVALUATOR.get().is_some()
 }
 
+/// Content hash of the loaded net, as lowercase hex.
+pub fn neural_net_hash() -> Option<&'static str> {
+    EVALUATOR.get().map(|e| e.hash.as_str())
+}
+
+/// Input feature layout of the loaded net, if any.
+pub fn feature_layout() -> Option<FeatureLayout> {
+    EVALUATOR.get().map(|e| e.layout)
+}
+
 /// Enable or disable neural network evaluation at runtime.
 pub fn set_neural_eval_enabled(enabled: bool) {
     NEURAL_ENABLED.store(enabled, Ordering::Relaxed);
@@ -142,7 +159,7 @@ pub fn try_neural_eval_accum(
             return None;
         }
         let bucket = piece_bucket(board, e.n_output_buckets);
-        let (score, confidence) = e.evaluate_from_accumulators(acc_white, acc_black, bucket);
+        let (score, confidence) = e.evaluate_from_accumulators_int(acc_white, acc_black, bucket);
         if confidence < threshold {
             return None;
         }
@@ -165,20 +182,26 @@ pub fn init_accumulators_for_board(
         Some(e) if e.dual_perspective => e,
         _ => return false,
     };
-    let ((w_idx, wc), (b_idx, bc)) = encode_dual_halfkp(board);
+    fill_accumulators(evaluator, board, acc_white, acc_black);
+    true
+}
 
-    acc_white.copy_from_slice(&evaluator.b1_i16);
+/// Both accumulators from scratch: bias plus one column per piece.
+fn fill_accumulators(
+    e: &NeuralEvaluator,
+    board: &ChessBoard,
+    acc_white: &mut [i16; HIDDEN1],
+    acc_black: &mut [i16; HIDDEN1],
+) {
+    let ((w_idx, wc), (b_idx, bc)) = e.layout.encode_dual(board);
+    acc_white.copy_from_slice(&e.b1_i16);
     for &i in &w_idx[..wc] {
-        let col = &evaluator.w1_t_i16[i * HIDDEN1..(i + 1) * HIDDEN1];
-        add_col(acc_white, col);
+        add_col(acc_white, &e.w1_t_i16[i * HIDDEN1..(i + 1) * HIDDEN1]);
     }
-
-    acc_black.copy_from_slice(&evaluator.b1_i16);
+    acc_black.copy_from_slice(&e.b1_i16);
     for &i in &b_idx[..bc] {
-        let col = &evaluator.w1_t_i16[i * HIDDEN1..(i + 1) * HIDDEN1];
-        add_col(acc_black, col);
+        add_col(acc_black, &e.w1_t_i16[i * HIDDEN1..(i + 1) * HIDDEN1]);
     }
-    true
 }
 
 // ── Compile-time-feature direct evaluation (no NEURAL_ENABLED check) ─────────
@@ -215,7 +238,10 @@ pub fn eval_accum_direct(
     let e = EVALUATOR.get()
         .expect("neural eval not loaded — call init_neural_eval_from_bytes at startup");
     let bucket = piece_bucket(board, e.n_output_buckets);
-    e.evaluate_from_accumulators(acc_white, acc_black, bucket).0
+    match &e.quantized {
+        Some(q) => q.cp(&q.forward(acc_white, acc_black), bucket),
+        None => e.evaluate_from_accumulators(acc_white, acc_black, bucket).0,
+    }
 }
 
 /// Initialize accumulators from a board position — no NEURAL_ENABLED check.
@@ -230,18 +256,115 @@ pub fn init_accumulators_direct(
         Some(e) if e.dual_perspective => e,
         _ => return false,
     };
-    let ((w_idx, wc), (b_idx, bc)) = encode_dual_halfkp(board);
-    acc_white.copy_from_slice(&evaluator.b1_i16);
-    for &i in &w_idx[..wc] {
-        let col = &evaluator.w1_t_i16[i * HIDDEN1..(i + 1) * HIDDEN1];
-        add_col(acc_white, col);
+    fill_accumulators(evaluator, board, acc_white, acc_black);
+    true
+}
+
+// ── Accumulator refresh cache ("Finny table") ────────────────────────────
+//
+// A king move changes the king bucket or mirror of its side's perspective, so
+// every feature of that perspective changes index and the accumulator can't
+// be updated incrementally.  Rebuilding it from the bias costs one column per
+// piece; instead each (perspective, king bucket, mirror) keeps the
+// accumulator it last produced and the pieces it was built from, and a
+// refresh only applies the pieces that differ.  The king tends to return to
+// squares it has used before, so that is usually a handful of columns.
+
+struct CacheEntry {
+    acc: [i16; HIDDEN1],
+    /// Pieces `acc` holds, `[colour * 6 + piece type]` (white first, pawn
+    /// to king), absolute squares.
+    pieces: [u64; 12],
+}
+
+/// Per-thread accumulator refresh cache, one entry per (perspective, king
+/// bucket, mirror) of the loaded net's feature layout.  Entries start as the empty board (bias only) the first
+/// time the cache is used with a loaded net.
+pub struct AccumulatorCache {
+    entries: Vec<CacheEntry>,
+}
+
+impl Default for AccumulatorCache {
+    fn default() -> Self {
+        Self::new()
     }
-    acc_black.copy_from_slice(&evaluator.b1_i16);
-    for &i in &b_idx[..bc] {
-        let col = &evaluator.w1_t_i16[i * HIDDEN1..(i + 1) * HIDDEN1];
-        add_col(acc_black, col);
+}
+
+impl AccumulatorCache {
+    pub fn new() -> Self {
+        Self { entries: Vec::new() }
+    }
+
+    /// Rebuild both accumulators for `board` through the cache.
+    fn refresh(
+        &mut self,
+        e: &NeuralEvaluator,
+        board: &ChessBoard,
+        acc_white: &mut [i16; HIDDEN1],
+        acc_black: &mut [i16; HIDDEN1],
+    ) {
+        if self.entries.is_empty() {
+            self.entries = (0..2 * e.layout.buckets() * 2)
+                .map(|_| CacheEntry { acc: e.b1_i16[..].try_into().unwrap(), pieces: [0; 12] })
+                .collect();
+        }
+        let by_colour = [board.get_white().0, board.get_black().0];
+        let by_type = [
+            board.get_pawns().0,
+            board.get_knights().0,
+            board.get_bishops().0,
+            board.get_rooks().0,
+            board.get_queens().0,
+            board.get_kings().0,
+        ];
+        let pieces: [u64; 12] = std::array::from_fn(|i| by_colour[i / 6] & by_type[i % 6]);
+
+        const TYPES: [PieceType; 6] =
+            [PieceType::Pawn, PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen, PieceType::King];
+
+        for (white_pov, acc) in [(true, acc_white), (false, acc_black)] {
+            let pov = e.layout.perspective(board, white_pov);
+            let slot = (usize::from(!white_pov) * e.layout.buckets() + pov.bucket()) * 2 + usize::from(pov.mirrored());
+            let entry = &mut self.entries[slot];
+            for (i, (&now, was)) in pieces.iter().zip(entry.pieces.iter_mut()).enumerate() {
+                let column = |sq: usize| {
+                    let f = pov.index(TYPES[i % 6], i < 6, sq);
+                    &e.w1_t_i16[f * HIDDEN1..(f + 1) * HIDDEN1]
+                };
+                let (mut removed, mut added) = (*was & !now, now & !*was);
+                while removed != 0 {
+                    sub_col(&mut entry.acc, column(removed.trailing_zeros() as usize));
+                    removed &= removed - 1;
+                }
+                while added != 0 {
+                    add_col(&mut entry.acc, column(added.trailing_zeros() as usize));
+                    added &= added - 1;
+                }
+                *was = now;
+            }
+            *acc = entry.acc;
+        }
+    }
+}
+
+/// Initialize both accumulators through a refresh cache: the same values as
+/// `init_accumulators_direct` (bit for bit while no sum saturates, which the
+/// incremental updates assume as well), at the cost of the pieces that
+/// changed since the cache entry was last used.  No NEURAL_ENABLED check.  Returns false
+/// only if no dual-perspective model is loaded.
+pub fn refresh_accumulators(
+    cache: &mut AccumulatorCache,
+    board: &ChessBoard,
+    acc_white: &mut [i16; HIDDEN1],
+    acc_black: &mut [i16; HIDDEN1],
+) -> bool {
+    match EVALUATOR.get() {
+        Some(e) if e.dual_perspective => {
+            cache.refresh(e, board, acc_white, acc_black);
+            true
+        }
+        _ => false,
     }
-    true
 }
 
 /// Add a feature column into an i16 accumulator (in-place, SIMD-dispatched).
@@ -264,217 +387,49 @@ pub fn acc_sub_feature(acc: &mut [i16; HIDDEN1], feature_idx: usize) {
     }
 }
 
-// ── SIMD column accumulator operations ───────────────────────────────────
+// ── SIMD kernels ──────────────────────────────────────────────────────────
 //
-// Three compile-time paths selected by cfg:
-//   x86_64 + avx2    → _mm256_adds_epi16  (16 i16/reg, 32 instr/col)
-//   wasm32 + simd128 → i16x8_add_sat       (8 i16/reg, 64 instr/col)
-//   fallback         → scalar saturating_add (1 i16/iter, 512 instr/col)
-//
-// `add_col` / `sub_col` are the public dispatch functions used everywhere.
-
-#[cfg(all(target_arch = "x86_64", target_feature = "avx2"))]
-#[target_feature(enable = "avx2")]
-unsafe fn add_col_avx2(acc: &mut [i16; HIDDEN1], col: &[i16]) {
-    use std::arch::x86_64::*;
-    for i in (0..HIDDEN1).step_by(16) {
-        let a = _mm256_loadu_si256(acc[i..].as_ptr() as *const __m256i);
-        let b = _mm256_loadu_si256(col[i..].as_ptr() as *const __m256i);
-        _mm256_storeu_si256(acc[i..].as_mut_ptr() as *mut __m256i, _mm256_adds_epi16(a, b));
-    }
-}
-
-#[cfg(all(target_arch = "x86_64", target_feature = "avx2"))]
-#[target_feature(enable = "avx2")]
-unsafe fn sub_col_avx2(acc: &mut [i16; HIDDEN1], col: &[i16]) {
-    use std::arch::x86_64::*;
-    for i in (0..HIDDEN1).step_by(16) {
-        let a = _mm256_loadu_si256(acc[i..].as_ptr() as *const __m256i);
-        let b = _mm256_loadu_si256(col[i..].as_ptr() as *const __m256i);
-        _mm256_storeu_si256(acc[i..].as_mut_ptr() as *mut __m256i, _mm256_subs_epi16(a, b));
-    }
-}
-
-#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
-unsafe fn add_col_wasm(acc: &mut [i16; HIDDEN1], col: &[i16]) {
-    use core::arch::wasm32::*;
-    for i in (0..HIDDEN1).step_by(8) {
-        let a = v128_load(acc[i..].as_ptr() as *const v128);
-        let b = v128_load(col[i..].as_ptr() as *const v128);
-        v128_store(acc[i..].as_mut_ptr() as *mut v128, i16x8_add_sat(a, b));
-    }
-}
-
-#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
-unsafe fn sub_col_wasm(acc: &mut [i16; HIDDEN1], col: &[i16]) {
-    use core::arch::wasm32::*;
-    for i in (0..HIDDEN1).step_by(8) {
-        let a = v128_load(acc[i..].as_ptr() as *const v128);
-        let b = v128_load(col[i..].as_ptr() as *const v128);
-        v128_store(acc[i..].as_mut_ptr() as *mut v128, i16x8_sub_sat(a, b));
-    }
-}
-
-#[allow(dead_code)]
-fn add_col_scalar(acc: &mut [i16; HIDDEN1], col: &[i16]) {
-    for (a, &b) in acc.iter_mut().zip(col) {
-        *a = a.saturating_add(b);
-    }
-}
-
-#[allow(dead_code)]
-fn sub_col_scalar(acc: &mut [i16; HIDDEN1], col: &[i16]) {
-    for (a, &b) in acc.iter_mut().zip(col) {
-        *a = a.saturating_sub(b);
-    }
-}
+// The kernels live in `simd`, one set per instruction set, picked at runtime
+// by CPU detection.  These wrappers are what the rest of this file calls.
 
-// Compile-time dispatch: each target gets exactly one definition.
-
-#[cfg(all(target_arch = "x86_64", target_feature = "avx2"))]
-#[inline(always)]
-fn add_col(acc: &mut [i16; HIDDEN1], col: &[i16]) {
-    unsafe { add_col_avx2(acc, col) }
-}
-
-#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
 #[inline(always)]
 fn add_col(acc: &mut [i16; HIDDEN1], col: &[i16]) {
-    unsafe { add_col_wasm(acc, col) }
+    debug_assert!(col.len() >= HIDDEN1);
+    unsafe { (simd::kernels().add_col)(acc, col) }
 }
 
-#[cfg(not(any(
-    all(target_arch = "x86_64", target_feature = "avx2"),
-    all(target_arch = "wasm32", target_feature = "simd128"),
-)))]
-#[inline(always)]
-fn add_col(acc: &mut [i16; HIDDEN1], col: &[i16]) {
-    add_col_scalar(acc, col)
-}
-
-#[cfg(all(target_arch = "x86_64", target_feature = "avx2"))]
 #[inline(always)]
 fn sub_col(acc: &mut [i16; HIDDEN1], col: &[i16]) {
-    unsafe { sub_col_avx2(acc, col) }
+    debug_assert!(col.len() >= HIDDEN1);
+    unsafe { (simd::kernels().sub_col)(acc, col) }
 }
 
-#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+/// SCReLU-dequantize an i16 accumulator: `clamp(x / scale, 0, 1)²`.
 #[inline(always)]
-fn sub_col(acc: &mut [i16; HIDDEN1], col: &[i16]) {
-    unsafe { sub_col_wasm(acc, col) }
-}
-
-#[cfg(not(any(
-    all(target_arch = "x86_64", target_feature = "avx2"),
-    all(target_arch = "wasm32", target_feature = "simd128"),
-)))]
-#[inline(always)]
-fn sub_col(acc: &mut [i16; HIDDEN1], col: &[i16]) {
-    sub_col_scalar(acc, col)
-}
-
-// ── SCReLU dequantization: i16 accumulator → f32 ─────────────────────────
-//
-// Converts raw i16 accumulator values to f32 with SCReLU applied:
-//   clamp(x / scale, 0, 1)²
-//
-// AVX2 path: processes 8 i16 per iteration via cvtepi16→cvtepi32→cvtepi32_ps.
-// Scalar fallback used on non-AVX2 targets.
-
-#[cfg(all(target_arch = "x86_64", target_feature = "avx2"))]
-#[target_feature(enable = "avx2")]
-unsafe fn screlu_deq_avx2(acc: &[i16], scale: f32, out: &mut [f32]) {
-    use std::arch::x86_64::*;
+fn screlu_deq(acc: &[i16], scale: f32, out: &mut [f32]) {
     debug_assert_eq!(acc.len(), out.len());
-    let zero     = _mm256_setzero_ps();
-    let vscale   = _mm256_set1_ps(scale);
-    let inv_sc   = _mm256_set1_ps(1.0 / scale);
-    let chunks   = acc.len() / 8;
-    for k in 0..chunks {
-        let vi16 = _mm_loadu_si128(acc.as_ptr().add(k * 8) as *const __m128i);
-        let vi32 = _mm256_cvtepi16_epi32(vi16);
-        let vf   = _mm256_cvtepi32_ps(vi32);
-        let clp  = _mm256_min_ps(_mm256_max_ps(vf, zero), vscale);
-        let norm = _mm256_mul_ps(clp, inv_sc);
-        _mm256_storeu_ps(out.as_mut_ptr().add(k * 8), _mm256_mul_ps(norm, norm));
-    }
-    for k in chunks * 8..acc.len() {
-        out[k] = screlu_i16(acc[k], scale);
-    }
-}
-
-#[cfg(not(all(target_arch = "x86_64", target_feature = "avx2")))]
-fn screlu_deq_avx2(acc: &[i16], scale: f32, out: &mut [f32]) {
-    for (o, &a) in out.iter_mut().zip(acc.iter()) {
-        *o = screlu_i16(a, scale);
-    }
+    unsafe { (simd::kernels().screlu_deq)(acc, scale, out) }
 }
 
+/// Column-major GEMV for fc2: `w[i * HIDDEN2 + j]` is the weight for output
+/// j, input i; `acc` starts as the bias.
 #[inline(always)]
-fn screlu_deq(acc: &[i16], scale: f32, out: &mut [f32]) {
-    #[cfg(all(target_arch = "x86_64", target_feature = "avx2"))]
-    unsafe { return screlu_deq_avx2(acc, scale, out); }
-    #[cfg(not(all(target_arch = "x86_64", target_feature = "avx2")))]
-    for (o, &a) in out.iter_mut().zip(acc.iter()) {
-        *o = screlu_i16(a, scale);
-    }
-}
-
-// ── Column-major GEMV for fc2 (input_dim × HIDDEN2 = 32) ─────────────────
-//
-// w is stored column-major: w[i * HIDDEN2 + j] = weight for output j, input i.
-// acc is pre-initialised with bias; x is the input vector.
-//
-// AVX2 + FMA path: holds all 32 outputs in 4 YMM registers, streams
-// through x once — each input element touches its 32-wide weight column
-// without evicting the accumulator registers.
-//
-// Note: hardcoded for HIDDEN2 == 32 (4 × 8-wide YMM registers).
-
-#[cfg(all(target_arch = "x86_64", target_feature = "avx2"))]
-#[target_feature(enable = "avx2", enable = "fma")]
-unsafe fn gemv_col32_avx2(w: &[f32], x: &[f32], acc: &mut [f32; HIDDEN2]) {
-    use std::arch::x86_64::*;
-    debug_assert_eq!(HIDDEN2, 32);
+fn gemv_col32(w: &[f32], x: &[f32], acc: &mut [f32; HIDDEN2]) {
     debug_assert_eq!(w.len(), x.len() * HIDDEN2);
-    let mut a0 = _mm256_loadu_ps(acc.as_ptr());
-    let mut a1 = _mm256_loadu_ps(acc.as_ptr().add(8));
-    let mut a2 = _mm256_loadu_ps(acc.as_ptr().add(16));
-    let mut a3 = _mm256_loadu_ps(acc.as_ptr().add(24));
-    for i in 0..x.len() {
-        let xi  = _mm256_set1_ps(*x.get_unchecked(i));
-        let col = w.as_ptr().add(i * HIDDEN2);
-        a0 = _mm256_fmadd_ps(_mm256_loadu_ps(col),        xi, a0);
-        a1 = _mm256_fmadd_ps(_mm256_loadu_ps(col.add(8)),  xi, a1);
-        a2 = _mm256_fmadd_ps(_mm256_loadu_ps(col.add(16)), xi, a2);
-        a3 = _mm256_fmadd_ps(_mm256_loadu_ps(col.add(24)), xi, a3);
-    }
-    _mm256_storeu_ps(acc.as_mut_ptr(),        a0);
-    _mm256_storeu_ps(acc.as_mut_ptr().add(8),  a1);
-    _mm256_storeu_ps(acc.as_mut_ptr().add(16), a2);
-    _mm256_storeu_ps(acc.as_mut_ptr().add(24), a3);
+    unsafe { (simd::kernels().gemv_col32)(w, x, acc) }
 }
 
-fn gemv_col32_scalar(w: &[f32], x: &[f32], acc: &mut [f32; HIDDEN2]) {
-    for i in 0..x.len() {
-        let xi  = x[i];
-        let col = &w[i * HIDDEN2..(i + 1) * HIDDEN2];
-        for j in 0..HIDDEN2 { acc[j] += col[j] * xi; }
-    }
-}
-
-#[inline(always)]
-fn gemv_col32(w: &[f32], x: &[f32], acc: &mut [f32; HIDDEN2]) {
-    #[cfg(all(target_arch = "x86_64", target_feature = "avx2"))]
-    unsafe { return gemv_col32_avx2(w, x, acc); }
-    gemv_col32_scalar(w, x, acc)
+/// Name of the SIMD kernel set in use ("avx2", "neon", ...), detected from
+/// the CPU on first use.
+pub fn simd_level() -> &'static str {
+    simd::kernels().name
 }
 
 // ── Evaluator ─────────────────────────────────────────────────────────────
 
 pub struct NeuralEvaluator {
-    /// Number of input features: 768 (legacy) or 12,288 (HalfKP).
-    feature_dim: usize,
+    /// Input features the first layer was trained on.
+    layout: FeatureLayout,
 
     /// True when `backbone_3_weight` is (32, 1024) instead of (32, 512).
     pub dual_perspective: bool,
@@ -509,25 +464,51 @@ pub struct NeuralEvaluator {
 
     /// Number of output buckets: 1 for old single-bucket models, 8 for new.
     pub n_output_buckets: usize,
+
+    /// Integer layer 2 + heads for the accumulator path.  Built at load time
+    /// for dual models exported at scale 256; others use the f32 path.
+    quantized: Option<QuantizedNet>,
+
+    /// Content hash of the loaded net file (see `net_file`).
+    hash: String,
 }
 
 impl NeuralEvaluator {
-    fn from_npz_bytes(data: &[u8]) -> Result<Self, String> {
-        let cursor = std::io::Cursor::new(data);
-        let mut zip = zip::ZipArchive::new(cursor)
-            .map_err(|e| format!("Not a valid NPZ/zip file: {e}"))?;
-
-        let scale = read_npy_f32_scalar(&mut zip, "scale.npy")?;
-
-        let w1_raw    = read_npy_i16(&mut zip, "backbone_0_weight.npy")?;
-        let b1_raw    = read_npy_i16(&mut zip, "backbone_0_bias.npy")?;
-        let w2_i16   = read_npy_i16(&mut zip, "backbone_3_weight.npy")?;
-        let b2_i16   = read_npy_i16(&mut zip, "backbone_3_bias.npy")?;
-        let w3_i16   = read_npy_i16(&mut zip, "cp_head_weight.npy")?;
-        let b3_i16   = read_npy_i16(&mut zip, "cp_head_bias.npy")?;
-        let w_wdl_i16 = read_npy_i16(&mut zip, "wdl_head_weight.npy")?;
-        let b_wdl_i16 = read_npy_i16(&mut zip, "wdl_head_bias.npy")?;
+    /// Load a native net file or an exporter `.npz`.
+    fn from_bytes(data: &[u8]) -> Result<Self, String> {
+        Self::from_net(NetFile::from_bytes(data)?)
+    }
 
+    /// Build from a parsed net, rejecting architectures this build can't run.
+    fn from_net(net: NetFile) -> Result<Self, String> {
+        let arch = &net.arch;
+        if arch.hidden1 != HIDDEN1 || arch.hidden2 != HIDDEN2 {
+            return Err(format!(
+                "Net is {arch}, but this engine is built for {HIDDEN1}→{HIDDEN2} hidden layers"
+            ));
+        }
+        let hash = net.hash_hex();
+        let [w1, b1, w2, b2, w3, b3, w_wdl, b_wdl] = net.tensors;
+        let mut eval = Self::from_arrays(net.arch.features, net.arch.scale, w1, b1, w2, b2, w3, b3, w_wdl, b_wdl)?;
+        eval.hash = hash;
+        Ok(eval)
+    }
+
+    /// Build from the exported arrays, all raw i16 at `scale` and row-major
+    /// as in the NPZ.
+    #[allow(clippy::too_many_arguments)]
+    fn from_arrays(
+        layout: FeatureLayout,
+        scale: f32,
+        w1_raw: Vec<i16>,
+        b1_raw: Vec<i16>,
+        w2_i16: Vec<i16>,
+        b2_i16: Vec<i16>,
+        w3_i16: Vec<i16>,
+        b3_i16: Vec<i16>,
+        w_wdl_i16: Vec<i16>,
+        b_wdl_i16: Vec<i16>,
+    ) -> Result<Self, String> {
         let dq = |v: &[i16]| -> Vec<f32> { v.iter().map(|&x| x as f32 / scale).collect() };
 
         // Detect dual from w2 size: HIDDEN2×HIDDEN1_DUAL vs HIDDEN2×HIDDEN1
@@ -540,11 +521,11 @@ impl NeuralEvaluator {
             ));
         }
 
-        // Detect feature_dim from w1 weight shape
-        let feature_dim = w1_raw.len() / HIDDEN1;
-        if feature_dim != LEGACY_FEATURE_DIM && feature_dim != HALFKP_FEATURE_DIM {
+        let feature_dim = layout.input_dim();
+        if w1_raw.len() != HIDDEN1 * feature_dim {
             return Err(format!(
-                "Unexpected feature_dim {feature_dim} (expected {LEGACY_FEATURE_DIM} or {HALFKP_FEATURE_DIM})"
+                "Unexpected backbone_0 size {} (expected {HIDDEN1} × {feature_dim} for {layout})",
+                w1_raw.len()
             ));
         }
 
@@ -585,8 +566,12 @@ impl NeuralEvaluator {
             }
         }
 
+        let quantized = (dual && scale == QA as f32).then(|| {
+            QuantizedNet::new(&w2_i16, &b2_i16, &w3_i16, &b3_i16, &w_wdl_i16, &b_wdl_i16, n_output_buckets)
+        });
+
         Ok(Self {
-            feature_dim,
+            layout,
             dual_perspective: dual,
             scale,
             w1_t,
@@ -600,6 +585,8 @@ impl NeuralEvaluator {
             w_wdl: dq(&w_wdl_i16),
             b_wdl: dq(&b_wdl_i16),
             n_output_buckets,
+            quantized,
+            hash: String::new(),
         })
     }
 
@@ -611,7 +598,7 @@ impl NeuralEvaluator {
     pub fn evaluate_with_confidence(&self, board: &ChessBoard) -> (i32, f32) {
         let bucket = piece_bucket(board, self.n_output_buckets);
         if self.dual_perspective {
-            let ((w_idx, wc), (b_idx, bc)) = encode_dual_halfkp(board);
+            let ((w_idx, wc), (b_idx, bc)) = self.layout.encode_dual(board);
 
             let mut h_w = [0.0f32; HIDDEN1];
             let mut h_b = [0.0f32; HIDDEN1];
@@ -634,11 +621,7 @@ impl NeuralEvaluator {
             for v in h_b.iter_mut() { *v = screlu_f32(*v); }
             self.forward_l2_heads_dual(&h_w, &h_b, bucket)
         } else {
-            let active = if self.feature_dim == HALFKP_FEATURE_DIM {
-                encode_active_features_halfkp(board)
-            } else {
-                encode_active_features_legacy(board)
-            };
+            let active = self.layout.encode_single(board);
 
             let mut h1 = [0.0f32; HIDDEN1];
             h1.copy_from_slice(&self.b1);
@@ -654,8 +637,28 @@ impl NeuralEvaluator {
         }
     }
 
+    /// Evaluate from pre-activation i16 accumulators with integer layer 2 and
+    /// heads (see `nnue_quant`), falling back to the f32 path when the net
+    /// has no integer form.  What the search uses.
+    /// Only valid for dual-perspective models.
+    pub fn evaluate_from_accumulators_int(
+        &self,
+        acc_white: &[i16; HIDDEN1],
+        acc_black: &[i16; HIDDEN1],
+        bucket: usize,
+    ) -> (i32, f32) {
+        match &self.quantized {
+            Some(q) => {
+                let h2 = q.forward(acc_white, acc_black);
+                (q.cp(&h2, bucket), q.confidence(&h2, bucket))
+            }
+            None => self.evaluate_from_accumulators(acc_white, acc_black, bucket),
+        }
+    }
+
     /// Evaluate from pre-activation i16 accumulators (Phase 4 incremental path).
     /// SCReLU-clamps and dequantizes each element, then runs the f32 L2 heads.
+    /// The float reference for `evaluate_from_accumulators_int`.
     /// Only valid for dual-perspective models.
     pub fn evaluate_from_accumulators(
         &self,
@@ -728,216 +731,20 @@ impl NeuralEvaluator {
 
 // ── Feature encoding ──────────────────────────────────────────────────────
 
-/// Map total piece count to output bucket index.
-/// Formula: clamp((total_pieces - 2) * n_buckets / 30, 0, n_buckets - 1).
-/// 2 pieces → 0, 32 pieces → n_buckets - 1.
-pub fn piece_bucket(board: &ChessBoard, n_buckets: usize) -> usize {
-    let total = board.get_all_pieces().count_ones() as usize;
-    ((total.saturating_sub(2)) * n_buckets / 30).min(n_buckets - 1)
-}
+/// Map total piece count to output bucket index (see `features`).
+pub use crate::features::piece_bucket;
 
-pub(crate) const KING_BUCKET: [usize; 64] = {
-    let mut t = [0usize; 64];
-    let mut sq = 0usize;
-    while sq < 64 {
-        let file = sq % 8;
-        let rank = sq / 8;
-        let file_bucket = if file <= 3 { file } else { 7 - file };
-        let rank_half = if rank <= 3 { 0 } else { 1 };
-        t[sq] = rank_half * 4 + file_bucket;
-        sq += 1;
-    }
-    t
-};
-
-/// Dual HalfKP encoding: returns ((white_indices, white_count), (black_indices, black_count)).
-///
-/// White perspective: absolute (white king = ours, white pieces = slots 0-5).
-/// Black perspective: rank-flipped (black king = ours, black pieces = slots 0-5).
-/// Both use the same feature formula: slot*64*16 + mapped_sq*16 + king_bucket.
-///
-/// Horizontal mirroring: when the king is on files 4-7 (e-h), piece square files
-/// are flipped (`sq ^ 7` flips bits 0-2, preserving rank bits 3-5).  This ensures
-/// that king-on-a1 and king-on-h1 see identical feature distributions.
-pub(crate) fn encode_dual_halfkp(
-    board: &ChessBoard,
-) -> (([usize; 32], usize), ([usize; 32], usize)) {
-    let white_bb = board.get_white();
-    let black_bb = board.get_black();
-
-    // White perspective: white king bucket (no rank flip)
-    let wk_sq = (white_bb & board.get_kings()).0.trailing_zeros() as usize;
-    let wk_sq = wk_sq.min(63);
-    let wk_bucket = KING_BUCKET[wk_sq];
-    let mirror_w = (wk_sq % 8) >= 4;
-
-    // Black perspective: black king rank-flipped
-    let bk_sq_raw = (black_bb & board.get_kings()).0.trailing_zeros() as usize;
-    let bk_sq_raw = bk_sq_raw.min(63);
-    let bk_flipped = bk_sq_raw ^ 56;
-    let bk_bucket = KING_BUCKET[bk_flipped];
-    let mirror_b = (bk_flipped % 8) >= 4;
-
-    let mut w_indices = [0usize; 32];
-    let mut b_indices = [0usize; 32];
-    let mut wc = 0usize;
-    let mut bc = 0usize;
-
-    macro_rules! push_white {
-        ($bb:expr, $slot:expr) => {
-            let mut bb: Bitboard = $bb;
-            while bb.0 != 0 {
-                let sq = bb.0.trailing_zeros() as usize;
-                bb.0 &= bb.0 - 1;
-                if wc < 32 {
-                    let mapped = if mirror_w { sq ^ 7 } else { sq };
-                    w_indices[wc] = $slot * 64 * 16 + mapped * 16 + wk_bucket;
-                    wc += 1;
-                }
-            }
-        };
-    }
-
-    macro_rules! push_black {
-        ($bb:expr, $slot:expr) => {
-            let mut bb: Bitboard = $bb;
-            while bb.0 != 0 {
-                let sq = bb.0.trailing_zeros() as usize;
-                bb.0 &= bb.0 - 1;
-                if bc < 32 {
-                    let rank_flipped = sq ^ 56;
-                    let mapped = if mirror_b { rank_flipped ^ 7 } else { rank_flipped };
-                    b_indices[bc] = $slot * 64 * 16 + mapped * 16 + bk_bucket;
-                    bc += 1;
-                }
-            }
-        };
-    }
-
-    // White perspective: white pieces = ours (0-5), black pieces = theirs (6-11)
-    push_white!(white_bb & board.get_pawns(),    0);
-    push_white!(white_bb & board.get_knights(),  1);
-    push_white!(white_bb & board.get_bishops(),  2);
-    push_white!(white_bb & board.get_rooks(),    3);
-    push_white!(white_bb & board.get_queens(),   4);
-    push_white!(white_bb & board.get_kings(),    5);
-    push_white!(black_bb & board.get_pawns(),    6);
-    push_white!(black_bb & board.get_knights(),  7);
-    push_white!(black_bb & board.get_bishops(),  8);
-    push_white!(black_bb & board.get_rooks(),    9);
-    push_white!(black_bb & board.get_queens(),  10);
-    push_white!(black_bb & board.get_kings(),   11);
-
-    // Black perspective: black pieces = ours (0-5), white pieces = theirs (6-11), squares rank-flipped
-    push_black!(black_bb & board.get_pawns(),    0);
-    push_black!(black_bb & board.get_knights(),  1);
-    push_black!(black_bb & board.get_bishops(),  2);
-    push_black!(black_bb & board.get_rooks(),    3);
-    push_black!(black_bb & board.get_queens(),   4);
-    push_black!(black_bb & board.get_kings(),    5);
-    push_black!(white_bb & board.get_pawns(),    6);
-    push_black!(white_bb & board.get_knights(),  7);
-    push_black!(white_bb & board.get_bishops(),  8);
-    push_black!(white_bb & board.get_rooks(),    9);
-    push_black!(white_bb & board.get_queens(),  10);
-    push_black!(white_bb & board.get_kings(),   11);
-
-    ((w_indices, wc), (b_indices, bc))
-}
-
-/// Legacy 768-dim encoder.
-fn encode_active_features_legacy(board: &ChessBoard) -> ([usize; 32], usize) {
-    let white_to_move = board.is_white_active();
-    let flip = !white_to_move;
-    let (ours, theirs) = if white_to_move {
-        (board.get_white(), board.get_black())
-    } else {
-        (board.get_black(), board.get_white())
-    };
-
-    let mut indices = [0usize; 32];
-    let mut count = 0usize;
-
-    macro_rules! push_bb {
-        ($bb:expr, $offset:expr) => {
-            let mut bb: Bitboard = $bb;
-            while bb.0 != 0 {
-                let sq = bb.0.trailing_zeros() as usize;
-                bb.0 &= bb.0 - 1;
-                let mapped = if flip { sq ^ 56 } else { sq };
-                indices[count] = $offset + mapped;
-                count += 1;
-            }
-        };
-    }
-
-    push_bb!(ours   & board.get_pawns(),    0);
-    push_bb!(ours   & board.get_knights(), 64);
-    push_bb!(ours   & board.get_bishops(), 128);
-    push_bb!(ours   & board.get_rooks(),   192);
-    push_bb!(ours   & board.get_queens(),  256);
-    push_bb!(ours   & board.get_kings(),   320);
-    push_bb!(theirs & board.get_pawns(),   384);
-    push_bb!(theirs & board.get_knights(), 448);
-    push_bb!(theirs & board.get_bishops(), 512);
-    push_bb!(theirs & board.get_rooks(),   576);
-    push_bb!(theirs & board.get_queens(),  640);
-    push_bb!(theirs & board.get_kings(),   704);
-
-    (indices, count)
-}
-
-/// HalfKP 12,288-dim king-bucketed encoder (single-perspective, side-to-move normalized).
-fn encode_active_features_halfkp(board: &ChessBoard) -> ([usize; 32], usize) {
-    let white_to_move = board.is_white_active();
-    let flip = !white_to_move;
-    let (ours, theirs) = if white_to_move {
-        (board.get_white(), board.get_black())
-    } else {
-        (board.get_black(), board.get_white())
-    };
-
-    let king_raw = (ours & board.get_kings()).0.trailing_zeros() as usize;
-    let king_sq = if flip { king_raw ^ 56 } else { king_raw };
-    let bucket = KING_BUCKET[king_sq.min(63)];
-
-    let mut indices = [0usize; 32];
-    let mut count = 0usize;
-
-    macro_rules! push_bb_halfkp {
-        ($bb:expr, $slot:expr) => {
-            let mut bb: Bitboard = $bb;
-            while bb.0 != 0 {
-                let sq = bb.0.trailing_zeros() as usize;
-                bb.0 &= bb.0 - 1;
-                let mapped = if flip { sq ^ 56 } else { sq };
-                indices[count] = $slot * 64 * 16 + mapped * 16 + bucket;
-                count += 1;
-            }
-        };
-    }
-
-    push_bb_halfkp!(ours   & board.get_pawns(),    0);
-    push_bb_halfkp!(ours   & board.get_knights(),  1);
-    push_bb_halfkp!(ours   & board.get_bishops(),  2);
-    push_bb_halfkp!(ours   & board.get_rooks(),    3);
-    push_bb_halfkp!(ours   & board.get_queens(),   4);
-    push_bb_halfkp!(ours   & board.get_kings(),    5);
-    push_bb_halfkp!(theirs & board.get_pawns(),    6);
-    push_bb_halfkp!(theirs & board.get_knights(),  7);
-    push_bb_halfkp!(theirs & board.get_bishops(),  8);
-    push_bb_halfkp!(theirs & board.get_rooks(),    9);
-    push_bb_halfkp!(theirs & board.get_queens(),  10);
-    push_bb_halfkp!(theirs & board.get_kings(),   11);
-
-    (indices, count)
+/// Dual HalfKP encoding of the first nets, for tests.
+#[cfg(test)]
+fn encode_dual_halfkp(board: &ChessBoard) -> (crate::features::ActiveFeatures, crate::features::ActiveFeatures) {
+    FeatureLayout::HALFKP.encode_dual(board)
 }
 
 /// Dense feature vector — only used by unit tests (legacy 768-dim).
 #[cfg(test)]
 fn encode_features_legacy(board: &ChessBoard) -> [f32; LEGACY_FEATURE_DIM] {
     let mut feat = [0.0f32; LEGACY_FEATURE_DIM];
-    let (indices, count) = encode_active_features_legacy(board);
+    let (indices, count) = FeatureLayout::LEGACY.encode_single(board);
     for i in indices[..count].iter().copied() {
         feat[i] = 1.0;
     }
@@ -948,122 +755,13 @@ fn encode_features_legacy(board: &ChessBoard) -> [f32; LEGACY_FEATURE_DIM] {
 #[cfg(test)]
 fn encode_features_halfkp(board: &ChessBoard) -> [f32; HALFKP_FEATURE_DIM] {
     let mut feat = [0.0f32; HALFKP_FEATURE_DIM];
-    let (indices, count) = encode_active_features_halfkp(board);
+    let (indices, count) = FeatureLayout::HALFKP.encode_single(board);
     for i in indices[..count].iter().copied() {
         feat[i] = 1.0;
     }
     feat
 }
 
-// ── NPY parsing ───────────────────────────────────────────────────────────
-
-fn read_npy_i16<R: Read + Seek>(
-    zip: &mut zip::ZipArchive<R>,
-    name: &str,
-) -> Result<Vec<i16>, String> {
-    let mut buf = Vec::new();
-    zip.by_name(name)
-        .map_err(|_| format!("Missing array '{name}' in NPZ"))?
-        .read_to_end(&mut buf)
-        .map_err(|e| format!("Read error for '{name}': {e}"))?;
-    parse_npy_i16(&buf, name)
-}
-
-fn read_npy_f32_scalar<R: Read + Seek>(
-    zip: &mut zip::ZipArchive<R>,
-    name: &str,
-) -> Result<f32, String> {
-    let mut buf = Vec::new();
-    zip.by_name(name)
-        .map_err(|_| format!("Missing array '{name}' in NPZ"))?
-        .read_to_end(&mut buf)
-        .map_err(|e| format!("Read error for '{name}': {e}"))?;
-    parse_npy_f32(&buf, name)?
-        .into_iter()
-        .next()
-        .ok_or_else(|| format!("'{name}' is empty"))
-}
-
-fn parse_npy_i16(buf: &[u8], name: &str) -> Result<Vec<i16>, String> {
-    let (offset, n) = parse_npy_header(buf, name, "<i2")?;
-    let data = &buf[offset..];
-    if data.len() < n * 2 {
-        return Err(format!("'{name}': data too short ({} bytes for {n} i16)", data.len()));
-    }
-    Ok(data[..n * 2]
-        .chunks_exact(2)
-        .map(|c| i16::from_le_bytes([c[0], c[1]]))
-        .collect())
-}
-
-fn parse_npy_f32(buf: &[u8], name: &str) -> Result<Vec<f32>, String> {
-    let (offset, n) = parse_npy_header(buf, name, "<f4")?;
-    let data = &buf[offset..];
-    if data.len() < n * 4 {
-        return Err(format!("'{name}': data too short ({} bytes for {n} f32)", data.len()));
-    }
-    Ok(data[..n * 4]
-        .chunks_exact(4)
-        .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
-        .collect())
-}
-
-fn parse_npy_header(buf: &[u8], name: &str, expected_dtype: &str) -> Result<(usize, usize), String> {
-    if buf.len() < 10 || &buf[0..6] != b"\x93NUMPY" {
-        return Err(format!("'{name}' is not a valid .npy file"));
-    }
-    let major = buf[6];
-    let (header_len, header_start) = match major {
-        1 => (u16::from_le_bytes([buf[8], buf[9]]) as usize, 10usize),
-        2 => {
-            if buf.len() < 12 {
-                return Err(format!("'{name}': truncated v2 header"));
-            }
-            (u32::from_le_bytes([buf[8], buf[9], buf[10], buf[11]]) as usize, 12usize)
-        }
-        v => return Err(format!("'{name}': unsupported .npy version {v}")),
-    };
-
-    let data_offset = header_start + header_len;
-    if buf.len() < data_offset {
-        return Err(format!("'{name}': file truncated before data"));
-    }
-
-    let header = std::str::from_utf8(&buf[header_start..data_offset])
-        .map_err(|_| format!("'{name}': header is not valid UTF-8"))?;
-
-    if !header.contains(expected_dtype) {
-        return Err(format!(
-            "'{name}': expected dtype '{expected_dtype}', got header: {header}"
-        ));
-    }
-
-    let n = parse_shape_product(header, name)?;
-    Ok((data_offset, n))
-}
-
-fn parse_shape_product(header: &str, name: &str) -> Result<usize, String> {
-    let shape_start = header
-        .find("'shape'")
-        .or_else(|| header.find("\"shape\""))
-        .ok_or_else(|| format!("'{name}': no 'shape' key in header"))?;
-    let after = &header[shape_start..];
-    let open = after
-        .find('(')
-        .ok_or_else(|| format!("'{name}': malformed shape tuple"))?;
-    let close = after
-        .find(')')
-        .ok_or_else(|| format!("'{name}': malformed shape tuple"))?;
-    let inner = &after[open + 1..close];
-
-    let product: usize = inner
-        .split(',')
-        .filter_map(|s| s.trim().parse::<usize>().ok())
-        .product();
-
-    Ok(if product == 0 { 1 } else { product })
-}
-
 // ── Tests ─────────────────────────────────────────────────────────────────
 
 #[cfg(test)]
@@ -1136,13 +834,6 @@ mod tests {
         }
     }
 
-    #[test]
-    fn test_halfkp_king_bucket_consistency() {
-        for sq in 0..64 {
-            assert!(KING_BUCKET[sq] < 16, "KING_BUCKET[{sq}] = {} out of range", KING_BUCKET[sq]);
-        }
-    }
-
     // ── Dual HalfKP tests ─────────────────────────────────────────────────
 
     #[test]
@@ -1315,7 +1006,7 @@ mod tests {
             Ok(b) => b,
             Err(_) => { println!("skipping: src/eval.npz not found"); return; }
         };
-        let eval = NeuralEvaluator::from_npz_bytes(&bytes).unwrap();
+        let eval = NeuralEvaluator::from_bytes(&bytes).unwrap();
         if !eval.dual_perspective {
             println!("skipping: model is single-perspective (backbone_3 is 32×512, need 32×1024)");
             return;
@@ -1444,7 +1135,7 @@ mod tests {
             Ok(b) => b,
             Err(_) => { println!("skipping: src/eval.npz not found"); return; }
         };
-        let eval = NeuralEvaluator::from_npz_bytes(&bytes).unwrap();
+        let eval = NeuralEvaluator::from_bytes(&bytes).unwrap();
         if !eval.dual_perspective {
             println!("skipping: model is single-perspective");
             return;
@@ -1489,4 +1180,184 @@ mod tests {
             );
         }
     }
+
+    /// A random dual net with the legacy 768 inputs (small enough to build in
+    /// a test) and weight magnitudes like a trained one.
+    fn random_dual_evaluator(seed: u64) -> NeuralEvaluator {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut v = |n: usize, r: i16| -> Vec<i16> { (0..n).map(|_| rng.gen_range(-r..=r)).collect() };
+        NeuralEvaluator::from_arrays(
+            FeatureLayout::LEGACY,
+            256.0,
+            v(HIDDEN1 * LEGACY_FEATURE_DIM, 20),
+            v(HIDDEN1, 100),
+            v(HIDDEN2 * HIDDEN1_DUAL, 24),
+            v(HIDDEN2, 30),
+            v(8 * HIDDEN2, 25_000),
+            v(8, 5_000),
+            v(8 * 3 * HIDDEN2, 200),
+            v(8 * 3, 50),
+        )
+        .unwrap()
+    }
+
+    /// A random dual net with king-bucketed inputs; weights small enough
+    /// that no accumulator sum saturates.
+    fn random_bucketed_evaluator(layout: FeatureLayout, seed: u64) -> NeuralEvaluator {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut v = |n: usize, r: i16| -> Vec<i16> { (0..n).map(|_| rng.gen_range(-r..=r)).collect() };
+        NeuralEvaluator::from_arrays(
+            layout,
+            256.0,
+            v(HIDDEN1 * layout.input_dim(), 100),
+            v(HIDDEN1, 100),
+            v(HIDDEN2 * HIDDEN1_DUAL, 24),
+            v(HIDDEN2, 30),
+            v(8 * HIDDEN2, 25_000),
+            v(8, 5_000),
+            v(8 * 3 * HIDDEN2, 200),
+            v(8 * 3, 50),
+        )
+        .unwrap()
+    }
+
+    /// Random games through one cache: every refresh, whatever entry it
+    /// starts from (fresh, same game, another game), gives exactly the
+    /// accumulators `init_accumulators_direct` builds.
+    #[test]
+    fn test_refresh_cache_matches_full_rebuild() {
+        check_refresh_cache(FeatureLayout::HALFKP);
+    }
+
+    #[test]
+    fn test_refresh_cache_matches_full_rebuild_halfka() {
+        use crate::features::{FeatureSet, KingBuckets};
+        check_refresh_cache(FeatureLayout::new(FeatureSet::HalfKa, KingBuckets::Mirrored16));
+    }
+
+    fn check_refresh_cache(layout: FeatureLayout) {
+        use move_generator::{move_generator::get_all_legal_moves_for_color, piece_conductor::PieceConductor};
+        use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+        let e = random_bucketed_evaluator(layout, 11);
+        let conductor = PieceConductor::new();
+        let mut rng = StdRng::seed_from_u64(12);
+        let mut cache = AccumulatorCache::new();
+        let (mut cached_w, mut cached_b) = ([0i16; HIDDEN1], [0i16; HIDDEN1]);
+        let (mut full_w, mut full_b) = ([0i16; HIDDEN1], [0i16; HIDDEN1]);
+        let mut king_moves = 0;
+
+        for _game in 0..4 {
+            let mut board = ChessBoard::new();
+            for _ply in 0..150 {
+                let mut legal = Vec::new();
+                let white = board.is_white_active();
+                get_all_legal_moves_for_color(&mut board, &conductor, white, &mut legal, &mut Vec::new());
+                let Some(&mv) = legal.choose(&mut rng) else { break };
+                let mut mv = mv;
+                if mv.chess_piece.is_some_and(|p| p.piece_type() == PieceType::King) {
+                    king_moves += 1;
+                }
+                board.make_move(&mut mv);
+
+                cache.refresh(&e, &board, &mut cached_w, &mut cached_b);
+                fill_accumulators(&e, &board, &mut full_w, &mut full_b);
+                assert!(cached_w == full_w && cached_b == full_b, "{layout}: mismatch after {mv:?}");
+            }
+        }
+        assert!(king_moves > 20, "only {king_moves} king moves exercised");
+    }
+
+    /// The integer path rounds the L1 activations to 1/256 and, for rows
+    /// with weights above 0.5, layer 2 weights to int8; over 2048 inputs
+    /// that stays within a few centipawns of the f32 reference.
+    #[test]
+    fn test_int_accum_path_close_to_f32() {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+        let eval = random_dual_evaluator(7);
+        assert!(eval.quantized.is_some());
+        let mut rng = StdRng::seed_from_u64(8);
+        let mut worst = 0;
+        for i in 0..200 {
+            let acc_w: [i16; HIDDEN1] = std::array::from_fn(|_| rng.gen_range(-200..=350));
+            let acc_b: [i16; HIDDEN1] = std::array::from_fn(|_| rng.gen_range(-200..=350));
+            let bucket = i % eval.n_output_buckets;
+            let (f32_score, f32_conf) = eval.evaluate_from_accumulators(&acc_w, &acc_b, bucket);
+            let (int_score, int_conf) = eval.evaluate_from_accumulators_int(&acc_w, &acc_b, bucket);
+            worst = worst.max((f32_score - int_score).abs());
+            assert!((f32_conf - int_conf).abs() < 0.01, "confidence {f32_conf} vs {int_conf}");
+        }
+        assert!(worst <= 4, "int path off by {worst}cp");
+    }
+
+    #[test]
+    fn test_no_integer_form_for_other_scales() {
+        let mut eval = random_dual_evaluator(9);
+        eval.quantized = None;
+        let acc = [128i16; HIDDEN1];
+        assert_eq!(
+            eval.evaluate_from_accumulators_int(&acc, &acc, 3),
+            eval.evaluate_from_accumulators(&acc, &acc, 3)
+        );
+    }
+
+    #[test]
+    fn test_rejects_nets_of_another_size() {
+        use crate::net_file::Architecture;
+        let arch = Architecture {
+            features: FeatureLayout::LEGACY,
+            dual_perspective: true,
+            hidden1: 512,
+            hidden2: HIDDEN2,
+            output_buckets: 8,
+            scale: 256.0,
+        };
+        let tensors = arch.tensor_lens().unwrap().map(|n| vec![0i16; n]);
+        let net = NetFile { arch, metadata: Vec::new(), tensors };
+        let err = NeuralEvaluator::from_bytes(&net.to_bytes()).err().unwrap();
+        assert!(err.contains("768→512x2→32→8") && err.contains("built for 1024→32"), "{err}");
+    }
+
+    /// Integer vs f32 accumulator evaluation on the real net.
+    #[test]
+    #[ignore = "requires src/eval.npz — run with --include-ignored"]
+    fn test_int_accum_matches_f32_on_real_net() {
+        let bytes = match std::fs::read("src/eval.npz") {
+            Ok(b) => b,
+            Err(_) => { println!("skipping: src/eval.npz not found"); return; }
+        };
+        let eval = NeuralEvaluator::from_bytes(&bytes).unwrap();
+        if !eval.dual_perspective {
+            println!("skipping: model is single-perspective");
+            return;
+        }
+        let positions = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+            "r3r1k1/pp3pbp/1qp3p1/2B5/2BP2b1/Q1n2N2/P4PPP/3RR1K1 w - - 0 1",
+            "8/8/4k3/8/8/4K3/8/8 w - - 0 1",
+        ];
+        for fen in positions {
+            let mut board = ChessBoard::new();
+            board.set_from_fen(fen);
+            let ((w_idx, wc), (b_idx, bc)) = encode_dual_halfkp(&board);
+            let mut acc_w = [0i16; HIDDEN1];
+            let mut acc_b = [0i16; HIDDEN1];
+            acc_w.copy_from_slice(&eval.b1_i16);
+            acc_b.copy_from_slice(&eval.b1_i16);
+            for &i in &w_idx[..wc] {
+                add_col(&mut acc_w, &eval.w1_t_i16[i * HIDDEN1..(i + 1) * HIDDEN1]);
+            }
+            for &i in &b_idx[..bc] {
+                add_col(&mut acc_b, &eval.w1_t_i16[i * HIDDEN1..(i + 1) * HIDDEN1]);
+            }
+            let bucket = piece_bucket(&board, eval.n_output_buckets);
+            let (f32_score, _) = eval.evaluate_from_accumulators(&acc_w, &acc_b, bucket);
+            let (int_score, _) = eval.evaluate_from_accumulators_int(&acc_w, &acc_b, bucket);
+            let diff = (f32_score - int_score).unsigned_abs();
+            assert!(diff <= 4, "FEN {fen}: f32={f32_score} int={int_score} diff={diff}cp (expected ≤4cp)");
+        }
+    }
 }
@@ -0,0 +1,252 @@
+//! All-integer inference after the accumulators (dual-perspective nets).
+//!
+//! The float path dequantizes the i16 accumulators through `screlu_deq` and
+//! runs layer 2 and the heads as f32 GEMVs.  Here everything stays integer,
+//! with weights requantized once at load time:
+//!
+//! * SCReLU: `a = (clamp(x, 0, QA)² + QA/2) >> 8`, an i16 in `0..=QA`, so
+//!   `a / QA` is the float activation rounded to 1/256.
+//! * Layer 2: int8 weights.  Each output neuron gets its own power-of-two
+//!   scale `2^shift`, the largest that keeps its weights within ±127, so
+//!   small weights lose nothing against the exported i16 (scale QA) and
+//!   large ones lose a bit or two.  Products are summed in i32 and the sum
+//!   shifted back to scale QA.  Inputs are taken in pairs, one `madd` per
+//!   pair and eight outputs, and pairs whose activations are both zero —
+//!   after SCReLU, usually most of them — are skipped.
+//! * Heads: SCReLU again, then the exported i16 head weights summed in i32;
+//!   the centipawn score is that sum rounded down from scale QA².
+//!
+//! The kernels are in `simd`, which checks every SIMD version against the
+//! scalar one bit for bit.
+
+use crate::neural_eval::{HIDDEN1, HIDDEN1_DUAL, HIDDEN2};
+use crate::simd;
+
+/// Accumulator scale: the exporter's quantization scale, `raw / QA` = float.
+pub(crate) const QA: i32 = 256;
+
+/// Largest int8 weight magnitude used.
+const Q8_MAX: i32 = 127;
+
+/// Integer layer 2 and heads, built from the exported i16 arrays.
+pub(crate) struct QuantizedNet {
+    /// Layer 2 weights, pair-interleaved: `w2[(p * HIDDEN2 + j) * 2 + k]` is
+    /// the weight from input `2p + k` to output `j`, at scale `2^shift[j]`.
+    w2: Vec<i8>,
+    /// Per-output weight scale exponent.
+    shift: [u32; HIDDEN2],
+    /// Layer 2 biases at scale `QA · 2^shift[j]`.
+    b2: [i32; HIDDEN2],
+    /// CP head, `[bucket][HIDDEN2]`, i16 at scale QA.
+    w3: Vec<i16>,
+    /// CP head biases at scale QA².
+    b3: Vec<i32>,
+    /// WDL head, `[bucket][3][HIDDEN2]`, i16 at scale QA.
+    w_wdl: Vec<i16>,
+    /// WDL head biases at scale QA².
+    b_wdl: Vec<i32>,
+    n_buckets: usize,
+}
+
+impl QuantizedNet {
+    /// Requantize the exported arrays.  `w2` is row-major
+    /// `[HIDDEN2 × HIDDEN1_DUAL]`, everything at scale QA.
+    pub(crate) fn new(
+        w2: &[i16],
+        b2: &[i16],
+        w3: &[i16],
+        b3: &[i16],
+        w_wdl: &[i16],
+        b_wdl: &[i16],
+        n_buckets: usize,
+    ) -> Self {
+        debug_assert_eq!(w2.len(), HIDDEN2 * HIDDEN1_DUAL);
+        let mut shift = [0u32; HIDDEN2];
+        let mut b2_q = [0i32; HIDDEN2];
+        let mut w2_q = vec![0i8; HIDDEN2 * HIDDEN1_DUAL];
+        for j in 0..HIDDEN2 {
+            let row = &w2[j * HIDDEN1_DUAL..(j + 1) * HIDDEN1_DUAL];
+            let max_abs = row.iter().map(|w| (*w as i32).abs()).max().unwrap_or(0);
+            shift[j] = weight_shift(max_abs);
+            b2_q[j] = (b2[j] as i32) << shift[j];
+            for (i, &w) in row.iter().enumerate() {
+                w2_q[((i / 2) * HIDDEN2 + j) * 2 + i % 2] = requantize(w, shift[j]);
+            }
+        }
+        let qa2 = |v: &[i16]| v.iter().map(|&b| b as i32 * QA).collect();
+        Self {
+            w2: w2_q,
+            shift,
+            b2: b2_q,
+            w3: w3.to_vec(),
+            b3: qa2(b3),
+            w_wdl: w_wdl.to_vec(),
+            b_wdl: qa2(b_wdl),
+            n_buckets,
+        }
+    }
+
+    /// Layer 2 output after SCReLU, at scale QA.
+    #[inline]
+    pub(crate) fn forward(&self, acc_white: &[i16; HIDDEN1], acc_black: &[i16; HIDDEN1]) -> [i32; HIDDEN2] {
+        let k = simd::kernels();
+        let mut act = [0i16; HIDDEN1_DUAL];
+        let (act_w, act_b) = act.split_at_mut(HIDDEN1);
+        let mut sum = self.b2;
+        unsafe {
+            (k.screlu_q)(acc_white, act_w);
+            (k.screlu_q)(acc_black, act_b);
+            (k.l2_pairs)(&self.w2, &act, &mut sum);
+        }
+        let mut h2 = [0i32; HIDDEN2];
+        for j in 0..HIDDEN2 {
+            h2[j] = screlu_int(sum[j] >> self.shift[j]) as i32;
+        }
+        h2
+    }
+
+    /// Centipawns, white-relative, from `forward`'s output.
+    #[inline]
+    pub(crate) fn cp(&self, h2: &[i32; HIDDEN2], bucket: usize) -> i32 {
+        let b = bucket.min(self.n_buckets - 1);
+        let sum = dot(&self.w3[b * HIDDEN2..(b + 1) * HIDDEN2], h2) + self.b3[b];
+        (sum + QA * QA / 2) >> 16
+    }
+
+    /// Largest WDL probability, the confidence of the score.
+    pub(crate) fn confidence(&self, h2: &[i32; HIDDEN2], bucket: usize) -> f32 {
+        let b = bucket.min(self.n_buckets - 1);
+        let mut logits = [0.0f32; 3];
+        for (k, logit) in logits.iter_mut().enumerate() {
+            let row = (b * 3 + k) * HIDDEN2;
+            let sum = dot(&self.w_wdl[row..row + HIDDEN2], h2) + self.b_wdl[b * 3 + k];
+            *logit = sum as f32 / (QA * QA) as f32;
+        }
+        let max_l = logits[0].max(logits[1]).max(logits[2]);
+        let exps = logits.map(|l| (l - max_l).exp());
+        exps[0].max(exps[1]).max(exps[2]) / (exps[0] + exps[1] + exps[2])
+    }
+}
+
+/// Largest scale exponent that keeps every weight of a row, `max_abs` at
+/// scale QA, within ±`Q8_MAX` after rounding.
+fn weight_shift(max_abs: i32) -> u32 {
+    (0..=15u32)
+        .rev()
+        .find(|&s| ((max_abs as i64) << s) < (2 * Q8_MAX as i64 + 1) * QA as i64 / 2)
+        .unwrap_or(0)
+}
+
+/// `w / QA · 2^shift` rounded half away from zero, saturated to int8.
+fn requantize(w: i16, shift: u32) -> i8 {
+    let scaled = (w as i32) << shift;
+    let q = scaled.signum() * ((scaled.abs() + QA / 2) / QA);
+    q.clamp(-Q8_MAX, Q8_MAX) as i8
+}
+
+#[inline(always)]
+fn dot(w: &[i16], x: &[i32; HIDDEN2]) -> i32 {
+    w.iter().zip(x).map(|(&w, &x)| w as i32 * x).sum()
+}
+
+/// `clamp(x, 0, QA)² / QA`, rounded: the activation the `screlu_q` kernels
+/// compute for each lane.
+#[inline(always)]
+pub(crate) fn screlu_int(x: i32) -> i16 {
+    let c = x.clamp(0, QA);
+    ((c * c + QA / 2) >> 8) as i16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn random_i16(rng: &mut StdRng, n: usize, range: std::ops::RangeInclusive<i16>) -> Vec<i16> {
+        (0..n).map(|_| rng.gen_range(range.clone())).collect()
+    }
+
+    /// Accumulators like a real position's: mostly in the SCReLU range,
+    /// a share clipped on either side.
+    fn random_acc(rng: &mut StdRng) -> [i16; HIDDEN1] {
+        std::array::from_fn(|_| rng.gen_range(-300..=400))
+    }
+
+    fn random_net(rng: &mut StdRng, w2_range: std::ops::RangeInclusive<i16>) -> QuantizedNet {
+        QuantizedNet::new(
+            &random_i16(rng, HIDDEN2 * HIDDEN1_DUAL, w2_range),
+            &random_i16(rng, HIDDEN2, -100..=100),
+            &random_i16(rng, 8 * HIDDEN2, -20000..=20000),
+            &random_i16(rng, 8, -5000..=5000),
+            &random_i16(rng, 8 * 3 * HIDDEN2, -300..=300),
+            &random_i16(rng, 8 * 3, -50..=50),
+            8,
+        )
+    }
+
+    /// The whole integer forward pass against a direct transcription of
+    /// the formulas in the module docs, on the un-interleaved weights.
+    #[test]
+    fn forward_is_bit_exact_against_a_naive_reference() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let w2 = random_i16(&mut rng, HIDDEN2 * HIDDEN1_DUAL, -600..=600);
+        let b2 = random_i16(&mut rng, HIDDEN2, -100..=100);
+        let w3 = random_i16(&mut rng, 8 * HIDDEN2, -20000..=20000);
+        let b3 = random_i16(&mut rng, 8, -5000..=5000);
+        let net = QuantizedNet::new(&w2, &b2, &w3, &b3, &[0; 8 * 3 * HIDDEN2], &[0; 8 * 3], 8);
+
+        for _ in 0..10 {
+            let (acc_w, acc_b) = (random_acc(&mut rng), random_acc(&mut rng));
+            let act: Vec<i64> = acc_w
+                .iter()
+                .chain(&acc_b)
+                .map(|&x| {
+                    let c = (x as i64).clamp(0, 256);
+                    (c * c + 128) / 256
+                })
+                .collect();
+            let mut h2 = [0i32; HIDDEN2];
+            for j in 0..HIDDEN2 {
+                let row = &w2[j * HIDDEN1_DUAL..(j + 1) * HIDDEN1_DUAL];
+                let max_abs = row.iter().map(|w| (*w as i64).abs()).max().unwrap();
+                let s = (0..=15).rev().find(|&s| (max_abs << s) * 2 < 255 * 256).unwrap();
+                let q = |w: i16| {
+                    let v = (w as f64) * (1 << s) as f64 / 256.0;
+                    (v.abs() + 0.5).floor().copysign(v).clamp(-127.0, 127.0) as i64
+                };
+                let sum: i64 = row.iter().zip(&act).map(|(&w, &a)| q(w) * a).sum::<i64>() + ((b2[j] as i64) << s);
+                let c = (sum >> s).clamp(0, 256);
+                h2[j] = ((c * c + 128) / 256) as i32;
+            }
+            assert_eq!(net.forward(&acc_w, &acc_b), h2);
+            for bucket in 0..8 {
+                let sum: i64 = (0..HIDDEN2).map(|j| w3[bucket * HIDDEN2 + j] as i64 * h2[j] as i64).sum::<i64>()
+                    + b3[bucket] as i64 * 256;
+                assert_eq!(net.cp(&h2, bucket) as i64, (sum + 32768).div_euclid(65536));
+            }
+        }
+    }
+
+    #[test]
+    fn small_weights_are_kept_exactly() {
+        // |w| ≤ 127 at scale QA fits int8 at shift 8 or more: no rounding.
+        assert_eq!(weight_shift(127), 8);
+        assert_eq!(weight_shift(63), 9);
+        assert_eq!(weight_shift(128), 7);
+        assert_eq!(weight_shift(32767), 0);
+        assert_eq!(requantize(-127, 8), -127);
+        assert_eq!(requantize(300, 6), 75);
+        assert_eq!(requantize(-302, 6), -76);
+        assert_eq!(requantize(32767, 0), 127);
+    }
+
+    #[test]
+    fn heavy_weights_still_use_the_int8_range() {
+        let mut rng = StdRng::seed_from_u64(4);
+        let net = random_net(&mut rng, -2000..=2000);
+        assert!(net.shift.iter().all(|&s| s == 4));
+        let max = net.w2.iter().map(|w| (*w as i32).abs()).max().unwrap();
+        assert!((120..=127).contains(&max), "{max}");
+    }
+}
@@ -0,0 +1,241 @@
+//! Search statistics, compiled only with the `search-stats` feature.
+//!
+//! Every `SearchContext` carries a `SearchStats` that the search bumps at each
+//! pruning / cutoff decision.  When a thread finishes searching, its counters
+//! are folded into a process-wide total that front ends read with
+//! `take_search_stats` (the `bench` binary and the `stats` command of
+//! `chess_uci`).  Counters are plain integers on the hot path; the global
+//! mutex is only touched once per thread per search.
+
+use std::fmt;
+use std::sync::Mutex;
+
+/// Remaining depths at or above this share the last bucket.
+pub const STATS_MAX_DEPTH: usize = 32;
+
+/// Iterations at or above this share the last aspiration bucket.
+pub const STATS_MAX_ITERATION: usize = 64;
+
+/// Cutoff counters for one remaining depth.
+#[derive(Clone, Copy, Default)]
+pub struct DepthStats {
+    /// Interior nodes that reached the move loop.
+    pub nodes: u64,
+    /// Nodes whose move loop ended in a beta cutoff.
+    pub cutoffs: u64,
+    /// Cutoffs produced by the first move searched.
+    pub first_move_cutoffs: u64,
+}
+
+/// Aspiration-window outcomes for one iterative-deepening depth.
+/// Fail-low / fail-high are white-relative: the score landed at or below /
+/// at or above the window.
+#[derive(Clone, Copy, Default)]
+pub struct AspirationStats {
+    pub searches: u64,
+    pub fail_lows: u64,
+    pub fail_highs: u64,
+}
+
+#[derive(Clone)]
+pub struct SearchStats {
+    /// `alpha_beta` calls that were not aborted.
+    pub nodes: u64,
+    /// `quiescence` calls.
+    pub qnodes: u64,
+    pub tt_probes: u64,
+    pub tt_hits: u64,
+    /// Hits whose entry holds an exact score.
+    pub tt_exact_hits: u64,
+    /// Hits deep enough to end the node without searching.
+    pub tt_cutoffs: u64,
+    pub rfp_prunes: u64,
+    pub nmp_tries: u64,
+    pub nmp_cutoffs: u64,
+    pub probcut_cutoffs: u64,
+    pub futility_prunes: u64,
+    pub lmp_prunes: u64,
+    pub lmr_reductions: u64,
+    /// Reduced searches that beat the bound and were repeated at full depth.
+    pub lmr_researches: u64,
+    /// Null-window PVS searches that had to be repeated with the full window.
+    pub pvs_researches: u64,
+    pub by_depth: [DepthStats; STATS_MAX_DEPTH],
+    pub aspiration: [AspirationStats; STATS_MAX_ITERATION],
+}
+
+impl Default for SearchStats {
+    fn default() -> Self {
+        Self {
+            nodes: 0,
+            qnodes: 0,
+            tt_probes: 0,
+            tt_hits: 0,
+            tt_exact_hits: 0,
+            tt_cutoffs: 0,
+            rfp_prunes: 0,
+            nmp_tries: 0,
+            nmp_cutoffs: 0,
+            probcut_cutoffs: 0,
+            futility_prunes: 0,
+            lmp_prunes: 0,
+            lmr_reductions: 0,
+            lmr_researches: 0,
+            pvs_researches: 0,
+            by_depth: [DepthStats::default(); STATS_MAX_DEPTH],
+            aspiration: [AspirationStats::default(); STATS_MAX_ITERATION],
+        }
+    }
+}
+
+impl SearchStats {
+    #[inline]
+    pub fn depth_mut(&mut self, depth: i32) -> &mut DepthStats {
+        &mut self.by_depth[(depth.max(0) as usize).min(STATS_MAX_DEPTH - 1)]
+    }
+
+    #[inline]
+    pub fn aspiration_mut(&mut self, iteration: i32) -> &mut AspirationStats {
+        &mut self.aspiration[(iteration.max(0) as usize).min(STATS_MAX_ITERATION - 1)]
+    }
+
+    /// Add `other`'s counters to this one.
+    pub fn merge(&mut self, other: &SearchStats) {
+        self.nodes += other.nodes;
+        self.qnodes += other.qnodes;
+        self.tt_probes += other.tt_probes;
+        self.tt_hits += other.tt_hits;
+        self.tt_exact_hits += other.tt_exact_hits;
+        self.tt_cutoffs += other.tt_cutoffs;
+        self.rfp_prunes += other.rfp_prunes;
+        self.nmp_tries += other.nmp_tries;
+        self.nmp_cutoffs += other.nmp_cutoffs;
+        self.probcut_cutoffs += other.probcut_cutoffs;
+        self.futility_prunes += other.futility_prunes;
+        self.lmp_prunes += other.lmp_prunes;
+        self.lmr_reductions += other.lmr_reductions;
+        self.lmr_researches += other.lmr_researches;
+        self.pvs_researches += other.pvs_researches;
+        for (a, b) in self.by_depth.iter_mut().zip(other.by_depth.iter()) {
+            a.nodes += b.nodes;
+            a.cutoffs += b.cutoffs;
+            a.first_move_cutoffs += b.first_move_cutoffs;
+        }
+        for (a, b) in self.aspiration.iter_mut().zip(other.aspiration.iter()) {
+            a.searches += b.searches;
+            a.fail_lows += b.fail_lows;
+            a.fail_highs += b.fail_highs;
+        }
+    }
+}
+
+fn pct(part: u64, whole: u64) -> f64 {
+    if whole == 0 { 0.0 } else { part as f64 * 100.0 / whole as f64 }
+}
+
+impl fmt::Display for SearchStats {
+    /// Multi-line human-readable report.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "nodes {}  qnodes {}", self.nodes, self.qnodes)?;
+        writeln!(
+            f,
+            "tt: probes {}  hit {:.1}%  exact {:.1}%  cutoff {:.1}%",
+            self.tt_probes,
+            pct(self.tt_hits, self.tt_probes),
+            pct(self.tt_exact_hits, self.tt_probes),
+            pct(self.tt_cutoffs, self.tt_probes),
+        )?;
+        writeln!(
+            f,
+            "pruning: rfp {}  nmp {}/{} ({:.1}%)  probcut {}  futility {}  lmp {}",
+            self.rfp_prunes,
+            self.nmp_cutoffs,
+            self.nmp_tries,
+            pct(self.nmp_cutoffs, self.nmp_tries),
+            self.probcut_cutoffs,
+            self.futility_prunes,
+            self.lmp_prunes,
+        )?;
+        writeln!(
+            f,
+            "re-search: lmr {}/{} ({:.1}%)  pvs {}",
+            self.lmr_researches,
+            self.lmr_reductions,
+            pct(self.lmr_researches, self.lmr_reductions),
+            self.pvs_researches,
+        )?;
+        writeln!(f, "cutoffs by remaining depth (nodes, cutoff %, first-move %):")?;
+        for (d, s) in self.by_depth.iter().enumerate().filter(|(_, s)| s.nodes > 0) {
+            let plus = if d == STATS_MAX_DEPTH - 1 { "+" } else { "" };
+            writeln!(
+                f,
+                "  d{d}{plus}: {}  {:.1}%  {:.1}%",
+                s.nodes,
+                pct(s.cutoffs, s.nodes),
+                pct(s.first_move_cutoffs, s.cutoffs),
+            )?;
+        }
+        write!(f, "aspiration by iteration (searches, fail-low, fail-high):")?;
+        for (d, s) in self.aspiration.iter().enumerate().filter(|(_, s)| s.searches > 0) {
+            write!(f, "\n  it{d}: {}  {}  {}", s.searches, s.fail_lows, s.fail_highs)?;
+        }
+        Ok(())
+    }
+}
+
+static TOTAL: Mutex<Option<SearchStats>> = Mutex::new(None);
+
+/// Fold one thread's counters into the process-wide total.
+pub fn record_search_stats(stats: &SearchStats) {
+    TOTAL.lock().unwrap().get_or_insert_with(SearchStats::default).merge(stats);
+}
+
+/// Return the counters accumulated since the last call and reset them.
+pub fn take_search_stats() -> SearchStats {
+    TOTAL.lock().unwrap().take().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_adds_every_counter() {
+        let mut a = SearchStats { nodes: 3, ..Default::default() };
+        a.depth_mut(2).cutoffs = 1;
+        a.aspiration_mut(5).fail_highs = 2;
+        let mut b = a.clone();
+        b.merge(&a);
+        assert_eq!(b.nodes, 6);
+        assert_eq!(b.by_depth[2].cutoffs, 2);
+        assert_eq!(b.aspiration[5].fail_highs, 4);
+    }
+
+    #[test]
+    fn search_records_counters() {
+        use crate::iterative_deepening_root;
+        use chess_board::ChessBoard;
+        use move_generator::piece_conductor::PieceConductor;
+
+        let mut board = ChessBoard::new();
+        board.set_from_fen("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3");
+        let conductor = PieceConductor::new();
+        iterative_deepening_root(&mut board, &conductor, None, 5, true, None, None, 0);
+
+        // Other tests may search concurrently, so only check lower bounds.
+        let stats = take_search_stats();
+        assert!(stats.nodes > 0 && stats.qnodes > 0);
+        assert!(stats.tt_probes >= stats.tt_hits);
+        assert!(stats.by_depth.iter().any(|d| d.cutoffs > 0));
+        assert!(stats.aspiration[3].searches > 0, "iteration 3 uses an aspiration window");
+    }
+
+    #[test]
+    fn deep_nodes_share_last_bucket() {
+        let mut s = SearchStats::default();
+        s.depth_mut(100).nodes += 1;
+        s.depth_mut(-1).nodes += 1;
+        assert_eq!(s.by_depth[STATS_MAX_DEPTH - 1].nodes, 1);
+        assert_eq!(s.by_depth[0].nodes, 1);
+    }
+}
@@ -0,0 +1,722 @@
+//! NNUE kernels with runtime CPU dispatch.
+//!
+//! Each kernel has a scalar reference and SIMD versions for SSE4.1, AVX2 and
+//! AVX-512 (x86_64), NEON (aarch64) and simd128 (wasm32).  `kernels()`
+//! detects the CPU once and returns the best complete set as a table of
+//! function pointers, so a generic release build runs AVX2 or AVX-512 code
+//! where the machine has it instead of what the compile target guaranteed.
+//! wasm has no runtime detection: simd128 is used when compiled in.
+//!
+//! Integer kernels match the scalar ones bit for bit on every path, and so
+//! does the float code on SSE4.1, which multiplies then adds like the scalar
+//! loop.  The AVX2, AVX-512 and NEON GEMVs use fused multiply-add and differ
+//! in the last bits.
+
+use std::sync::OnceLock;
+
+use crate::neural_eval::{screlu_i16, HIDDEN1, HIDDEN1_DUAL, HIDDEN2};
+use crate::nnue_quant::screlu_int;
+
+/// Layer 2 inputs are processed two at a time (see `nnue_quant`).
+const PAIRS: usize = HIDDEN1_DUAL / 2;
+
+/// One complete set of kernels for an instruction set.
+pub(crate) struct Kernels {
+    pub name: &'static str,
+    /// `acc += col`, saturating.
+    pub add_col: unsafe fn(&mut [i16; HIDDEN1], &[i16]),
+    /// `acc -= col`, saturating.
+    pub sub_col: unsafe fn(&mut [i16; HIDDEN1], &[i16]),
+    /// i16 accumulator → f32 SCReLU activation, `clamp(x / scale, 0, 1)²`.
+    pub screlu_deq: unsafe fn(&[i16], f32, &mut [f32]),
+    /// Column-major f32 GEMV into 32 outputs: `acc[j] += Σ w[i·32 + j]·x[i]`.
+    pub gemv_col32: unsafe fn(&[f32], &[f32], &mut [f32; HIDDEN2]),
+    /// i16 accumulator → i16 SCReLU activation at scale QA.
+    pub screlu_q: unsafe fn(&[i16], &mut [i16]),
+    /// Sparse int8 layer 2 over input pairs, into i32 sums.
+    pub l2_pairs: unsafe fn(&[i8], &[i16; HIDDEN1_DUAL], &mut [i32; HIDDEN2]),
+}
+
+static KERNELS: OnceLock<&'static Kernels> = OnceLock::new();
+
+/// The best kernel set this CPU supports, detected on first use.
+#[inline]
+pub(crate) fn kernels() -> &'static Kernels {
+    KERNELS.get_or_init(|| *available().last().unwrap())
+}
+
+/// Every kernel set this CPU can run, scalar first, best last.
+pub(crate) fn available() -> Vec<&'static Kernels> {
+    #[allow(unused_mut)]
+    let mut sets: Vec<&'static Kernels> = vec![&SCALAR];
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("sse4.1") {
+            sets.push(&x86::SSE41);
+        }
+        if is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma") {
+            sets.push(&x86::AVX2);
+        }
+        if is_x86_feature_detected!("avx512f") && is_x86_feature_detected!("avx512bw") {
+            sets.push(&x86::AVX512);
+        }
+    }
+    #[cfg(target_arch = "aarch64")]
+    if std::arch::is_aarch64_feature_detected!("neon") {
+        sets.push(&neon::NEON);
+    }
+    #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+    sets.push(&wasm::SIMD128);
+    sets
+}
+
+// ── Scalar reference ──────────────────────────────────────────────────────
+
+pub(crate) static SCALAR: Kernels = Kernels {
+    name: "scalar",
+    add_col: add_col_scalar,
+    sub_col: sub_col_scalar,
+    screlu_deq: screlu_deq_scalar,
+    gemv_col32: gemv_col32_scalar,
+    screlu_q: screlu_q_scalar,
+    l2_pairs: l2_pairs_scalar,
+};
+
+fn add_col_scalar(acc: &mut [i16; HIDDEN1], col: &[i16]) {
+    for (a, &b) in acc.iter_mut().zip(col) {
+        *a = a.saturating_add(b);
+    }
+}
+
+fn sub_col_scalar(acc: &mut [i16; HIDDEN1], col: &[i16]) {
+    for (a, &b) in acc.iter_mut().zip(col) {
+        *a = a.saturating_sub(b);
+    }
+}
+
+fn screlu_deq_scalar(acc: &[i16], scale: f32, out: &mut [f32]) {
+    for (o, &a) in out.iter_mut().zip(acc.iter()) {
+        *o = screlu_i16(a, scale);
+    }
+}
+
+fn gemv_col32_scalar(w: &[f32], x: &[f32], acc: &mut [f32; HIDDEN2]) {
+    for i in 0..x.len() {
+        let xi  = x[i];
+        let col = &w[i * HIDDEN2..(i + 1) * HIDDEN2];
+        for j in 0..HIDDEN2 { acc[j] += col[j] * xi; }
+    }
+}
+
+fn screlu_q_scalar(acc: &[i16], out: &mut [i16]) {
+    for (o, &a) in out.iter_mut().zip(acc) {
+        *o = screlu_int(a as i32);
+    }
+}
+
+fn l2_pairs_scalar(w2: &[i8], act: &[i16; HIDDEN1_DUAL], sum: &mut [i32; HIDDEN2]) {
+    for p in 0..PAIRS {
+        let (a0, a1) = (act[2 * p] as i32, act[2 * p + 1] as i32);
+        if a0 == 0 && a1 == 0 {
+            continue;
+        }
+        let col = &w2[p * HIDDEN2 * 2..(p + 1) * HIDDEN2 * 2];
+        for j in 0..HIDDEN2 {
+            sum[j] += a0 * col[2 * j] as i32 + a1 * col[2 * j + 1] as i32;
+        }
+    }
+}
+
+// ── x86_64: SSE4.1, AVX2 (+FMA), AVX-512 (F + BW) ────────────────────────
+//
+// Same shapes at three widths.  GEMV and layer 2 hold all 32 outputs in
+// registers (8 × 4, 4 × 8, 2 × 16 lanes) and stream through the input once.
+// SCReLU in integer is `mulhrs(c << 3, c << 4)` = `(c² + 128) >> 8`.
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use std::arch::x86_64::*;
+
+    use super::{Kernels, PAIRS};
+    use crate::neural_eval::{HIDDEN1, HIDDEN1_DUAL, HIDDEN2};
+    use crate::nnue_quant::QA;
+
+    pub(super) static SSE41: Kernels = Kernels {
+        name: "sse4.1",
+        add_col: add_col_sse41,
+        sub_col: sub_col_sse41,
+        screlu_deq: screlu_deq_sse41,
+        gemv_col32: gemv_col32_sse41,
+        screlu_q: screlu_q_sse41,
+        l2_pairs: l2_pairs_sse41,
+    };
+
+    pub(super) static AVX2: Kernels = Kernels {
+        name: "avx2",
+        add_col: add_col_avx2,
+        sub_col: sub_col_avx2,
+        screlu_deq: screlu_deq_avx2,
+        gemv_col32: gemv_col32_avx2,
+        screlu_q: screlu_q_avx2,
+        l2_pairs: l2_pairs_avx2,
+    };
+
+    pub(super) static AVX512: Kernels = Kernels {
+        name: "avx512",
+        add_col: add_col_avx512,
+        sub_col: sub_col_avx512,
+        screlu_deq: screlu_deq_avx512,
+        gemv_col32: gemv_col32_avx512,
+        screlu_q: screlu_q_avx512,
+        l2_pairs: l2_pairs_avx512,
+    };
+
+    // SSE4.1
+
+    #[target_feature(enable = "sse4.1")]
+    unsafe fn add_col_sse41(acc: &mut [i16; HIDDEN1], col: &[i16]) {
+        debug_assert!(col.len() >= HIDDEN1);
+        for i in (0..HIDDEN1).step_by(8) {
+            let a = _mm_loadu_si128(acc.as_ptr().add(i) as *const __m128i);
+            let b = _mm_loadu_si128(col.as_ptr().add(i) as *const __m128i);
+            _mm_storeu_si128(acc.as_mut_ptr().add(i) as *mut __m128i, _mm_adds_epi16(a, b));
+        }
+    }
+
+    #[target_feature(enable = "sse4.1")]
+    unsafe fn sub_col_sse41(acc: &mut [i16; HIDDEN1], col: &[i16]) {
+        debug_assert!(col.len() >= HIDDEN1);
+        for i in (0..HIDDEN1).step_by(8) {
+            let a = _mm_loadu_si128(acc.as_ptr().add(i) as *const __m128i);
+            let b = _mm_loadu_si128(col.as_ptr().add(i) as *const __m128i);
+            _mm_storeu_si128(acc.as_mut_ptr().add(i) as *mut __m128i, _mm_subs_epi16(a, b));
+        }
+    }
+
+    #[target_feature(enable = "sse4.1")]
+    unsafe fn screlu_deq_sse41(acc: &[i16], scale: f32, out: &mut [f32]) {
+        debug_assert!(acc.len() == out.len() && acc.len().is_multiple_of(4));
+        let zero   = _mm_setzero_ps();
+        let vscale = _mm_set1_ps(scale);
+        let inv_sc = _mm_set1_ps(1.0 / scale);
+        for i in (0..acc.len()).step_by(4) {
+            let vi = _mm_cvtepi16_epi32(_mm_loadl_epi64(acc.as_ptr().add(i) as *const __m128i));
+            let clp  = _mm_min_ps(_mm_max_ps(_mm_cvtepi32_ps(vi), zero), vscale);
+            let norm = _mm_mul_ps(clp, inv_sc);
+            _mm_storeu_ps(out.as_mut_ptr().add(i), _mm_mul_ps(norm, norm));
+        }
+    }
+
+    #[target_feature(enable = "sse4.1")]
+    unsafe fn gemv_col32_sse41(w: &[f32], x: &[f32], acc: &mut [f32; HIDDEN2]) {
+        debug_assert_eq!(w.len(), x.len() * HIDDEN2);
+        let mut a: [__m128; 8] = std::array::from_fn(|k| _mm_loadu_ps(acc.as_ptr().add(k * 4)));
+        for i in 0..x.len() {
+            let xi  = _mm_set1_ps(*x.get_unchecked(i));
+            let col = w.as_ptr().add(i * HIDDEN2);
+            for (k, ak) in a.iter_mut().enumerate() {
+                *ak = _mm_add_ps(*ak, _mm_mul_ps(_mm_loadu_ps(col.add(k * 4)), xi));
+            }
+        }
+        for (k, ak) in a.iter().enumerate() {
+            _mm_storeu_ps(acc.as_mut_ptr().add(k * 4), *ak);
+        }
+    }
+
+    #[target_feature(enable = "sse4.1")]
+    unsafe fn screlu_q_sse41(acc: &[i16], out: &mut [i16]) {
+        debug_assert!(acc.len() == out.len() && acc.len().is_multiple_of(8));
+        let zero = _mm_setzero_si128();
+        let qa = _mm_set1_epi16(QA as i16);
+        for i in (0..acc.len()).step_by(8) {
+            let x = _mm_loadu_si128(acc.as_ptr().add(i) as *const __m128i);
+            let c = _mm_min_epi16(_mm_max_epi16(x, zero), qa);
+            let a = _mm_mulhrs_epi16(_mm_slli_epi16(c, 3), _mm_slli_epi16(c, 4));
+            _mm_storeu_si128(out.as_mut_ptr().add(i) as *mut __m128i, a);
+        }
+    }
+
+    /// Eight bytes of weights widen to four outputs' pairs.
+    #[target_feature(enable = "sse4.1")]
+    unsafe fn l2_pairs_sse41(w2: &[i8], act: &[i16; HIDDEN1_DUAL], sum: &mut [i32; HIDDEN2]) {
+        debug_assert_eq!(w2.len(), PAIRS * HIDDEN2 * 2);
+        let mut s: [__m128i; 8] =
+            std::array::from_fn(|k| _mm_loadu_si128(sum.as_ptr().add(k * 4) as *const __m128i));
+        let pairs = act.as_ptr() as *const i32;
+        for p in 0..PAIRS {
+            let pair = pairs.add(p).read_unaligned();
+            if pair == 0 {
+                continue;
+            }
+            let x = _mm_set1_epi32(pair);
+            let col = w2.as_ptr().add(p * HIDDEN2 * 2);
+            for (k, sk) in s.iter_mut().enumerate() {
+                let w = _mm_cvtepi8_epi16(_mm_loadl_epi64(col.add(k * 8) as *const __m128i));
+                *sk = _mm_add_epi32(*sk, _mm_madd_epi16(w, x));
+            }
+        }
+        for (k, sk) in s.iter().enumerate() {
+            _mm_storeu_si128(sum.as_mut_ptr().add(k * 4) as *mut __m128i, *sk);
+        }
+    }
+
+    // AVX2
+
+    #[target_feature(enable = "avx2")]
+    unsafe fn add_col_avx2(acc: &mut [i16; HIDDEN1], col: &[i16]) {
+        debug_assert!(col.len() >= HIDDEN1);
+        for i in (0..HIDDEN1).step_by(16) {
+            let a = _mm256_loadu_si256(acc.as_ptr().add(i) as *const __m256i);
+            let b = _mm256_loadu_si256(col.as_ptr().add(i) as *const __m256i);
+            _mm256_storeu_si256(acc.as_mut_ptr().add(i) as *mut __m256i, _mm256_adds_epi16(a, b));
+        }
+    }
+
+    #[target_feature(enable = "avx2")]
+    unsafe fn sub_col_avx2(acc: &mut [i16; HIDDEN1], col: &[i16]) {
+        debug_assert!(col.len() >= HIDDEN1);
+        for i in (0..HIDDEN1).step_by(16) {
+            let a = _mm256_loadu_si256(acc.as_ptr().add(i) as *const __m256i);
+            let b = _mm256_loadu_si256(col.as_ptr().add(i) as *const __m256i);
+            _mm256_storeu_si256(acc.as_mut_ptr().add(i) as *mut __m256i, _mm256_subs_epi16(a, b));
+        }
+    }
+
+    #[target_feature(enable = "avx2")]
+    unsafe fn screlu_deq_avx2(acc: &[i16], scale: f32, out: &mut [f32]) {
+        debug_assert!(acc.len() == out.len() && acc.len().is_multiple_of(8));
+        let zero   = _mm256_setzero_ps();
+        let vscale = _mm256_set1_ps(scale);
+        let inv_sc = _mm256_set1_ps(1.0 / scale);
+        for i in (0..acc.len()).step_by(8) {
+            let vi = _mm256_cvtepi16_epi32(_mm_loadu_si128(acc.as_ptr().add(i) as *const __m128i));
+            let clp  = _mm256_min_ps(_mm256_max_ps(_mm256_cvtepi32_ps(vi), zero), vscale);
+            let norm = _mm256_mul_ps(clp, inv_sc);
+            _mm256_storeu_ps(out.as_mut_ptr().add(i), _mm256_mul_ps(norm, norm));
+        }
+    }
+
+    #[target_feature(enable = "avx2", enable = "fma")]
+    unsafe fn gemv_col32_avx2(w: &[f32], x: &[f32], acc: &mut [f32; HIDDEN2]) {
+        debug_assert_eq!(w.len(), x.len() * HIDDEN2);
+        let mut a0 = _mm256_loadu_ps(acc.as_ptr());
+        let mut a1 = _mm256_loadu_ps(acc.as_ptr().add(8));
+        let mut a2 = _mm256_loadu_ps(acc.as_ptr().add(16));
+        let mut a3 = _mm256_loadu_ps(acc.as_ptr().add(24));
+        for i in 0..x.len() {
+            let xi  = _mm256_set1_ps(*x.get_unchecked(i));
+            let col = w.as_ptr().add(i * HIDDEN2);
+            a0 = _mm256_fmadd_ps(_mm256_loadu_ps(col),         xi, a0);
+            a1 = _mm256_fmadd_ps(_mm256_loadu_ps(col.add(8)),  xi, a1);
+            a2 = _mm256_fmadd_ps(_mm256_loadu_ps(col.add(16)), xi, a2);
+            a3 = _mm256_fmadd_ps(_mm256_loadu_ps(col.add(24)), xi, a3);
+        }
+        _mm256_storeu_ps(acc.as_mut_ptr(),         a0);
+        _mm256_storeu_ps(acc.as_mut_ptr().add(8),  a1);
+        _mm256_storeu_ps(acc.as_mut_ptr().add(16), a2);
+        _mm256_storeu_ps(acc.as_mut_ptr().add(24), a3);
+    }
+
+    #[target_feature(enable = "avx2")]
+    unsafe fn screlu_q_avx2(acc: &[i16], out: &mut [i16]) {
+        debug_assert!(acc.len() == out.len() && acc.len().is_multiple_of(16));
+        let zero = _mm256_setzero_si256();
+        let qa = _mm256_set1_epi16(QA as i16);
+        for i in (0..acc.len()).step_by(16) {
+            let x = _mm256_loadu_si256(acc.as_ptr().add(i) as *const __m256i);
+            let c = _mm256_min_epi16(_mm256_max_epi16(x, zero), qa);
+            let a = _mm256_mulhrs_epi16(_mm256_slli_epi16(c, 3), _mm256_slli_epi16(c, 4));
+            _mm256_storeu_si256(out.as_mut_ptr().add(i) as *mut __m256i, a);
+        }
+    }
+
+    /// Each 16-byte quarter of the pair's 64 weight bytes widens to eight
+    /// outputs' pairs for one `madd` against the broadcast activations.
+    #[target_feature(enable = "avx2")]
+    unsafe fn l2_pairs_avx2(w2: &[i8], act: &[i16; HIDDEN1_DUAL], sum: &mut [i32; HIDDEN2]) {
+        debug_assert_eq!(w2.len(), PAIRS * HIDDEN2 * 2);
+        let mut s0 = _mm256_loadu_si256(sum.as_ptr() as *const __m256i);
+        let mut s1 = _mm256_loadu_si256(sum.as_ptr().add(8) as *const __m256i);
+        let mut s2 = _mm256_loadu_si256(sum.as_ptr().add(16) as *const __m256i);
+        let mut s3 = _mm256_loadu_si256(sum.as_ptr().add(24) as *const __m256i);
+        let pairs = act.as_ptr() as *const i32;
+        for p in 0..PAIRS {
+            let pair = pairs.add(p).read_unaligned();
+            if pair == 0 {
+                continue;
+            }
+            let x = _mm256_set1_epi32(pair);
+            let col = w2.as_ptr().add(p * HIDDEN2 * 2);
+            let w0 = _mm256_cvtepi8_epi16(_mm_loadu_si128(col as *const __m128i));
+            let w1 = _mm256_cvtepi8_epi16(_mm_loadu_si128(col.add(16) as *const __m128i));
+            let w2 = _mm256_cvtepi8_epi16(_mm_loadu_si128(col.add(32) as *const __m128i));
+            let w3 = _mm256_cvtepi8_epi16(_mm_loadu_si128(col.add(48) as *const __m128i));
+            s0 = _mm256_add_epi32(s0, _mm256_madd_epi16(w0, x));
+            s1 = _mm256_add_epi32(s1, _mm256_madd_epi16(w1, x));
+            s2 = _mm256_add_epi32(s2, _mm256_madd_epi16(w2, x));
+            s3 = _mm256_add_epi32(s3, _mm256_madd_epi16(w3, x));
+        }
+        _mm256_storeu_si256(sum.as_mut_ptr() as *mut __m256i, s0);
+        _mm256_storeu_si256(sum.as_mut_ptr().add(8) as *mut __m256i, s1);
+        _mm256_storeu_si256(sum.as_mut_ptr().add(16) as *mut __m256i, s2);
+        _mm256_storeu_si256(sum.as_mut_ptr().add(24) as *mut __m256i, s3);
+    }
+
+    // AVX-512
+
+    #[target_feature(enable = "avx512f", enable = "avx512bw")]
+    unsafe fn add_col_avx512(acc: &mut [i16; HIDDEN1], col: &[i16]) {
+        debug_assert!(col.len() >= HIDDEN1);
+        for i in (0..HIDDEN1).step_by(32) {
+            let a = _mm512_loadu_si512(acc.as_ptr().add(i) as *const __m512i);
+            let b = _mm512_loadu_si512(col.as_ptr().add(i) as *const __m512i);
+            _mm512_storeu_si512(acc.as_mut_ptr().add(i) as *mut __m512i, _mm512_adds_epi16(a, b));
+        }
+    }
+
+    #[target_feature(enable = "avx512f", enable = "avx512bw")]
+    unsafe fn sub_col_avx512(acc: &mut [i16; HIDDEN1], col: &[i16]) {
+        debug_assert!(col.len() >= HIDDEN1);
+        for i in (0..HIDDEN1).step_by(32) {
+            let a = _mm512_loadu_si512(acc.as_ptr().add(i) as *const __m512i);
+            let b = _mm512_loadu_si512(col.as_ptr().add(i) as *const __m512i);
+            _mm512_storeu_si512(acc.as_mut_ptr().add(i) as *mut __m512i, _mm512_subs_epi16(a, b));
+        }
+    }
+
+    #[target_feature(enable = "avx512f", enable = "avx512bw")]
+    unsafe fn screlu_deq_avx512(acc: &[i16], scale: f32, out: &mut [f32]) {
+        debug_assert!(acc.len() == out.len() && acc.len().is_multiple_of(16));
+        let zero   = _mm512_setzero_ps();
+        let vscale = _mm512_set1_ps(scale);
+        let inv_sc = _mm512_set1_ps(1.0 / scale);
+        for i in (0..acc.len()).step_by(16) {
+            let vi = _mm512_cvtepi16_epi32(_mm256_loadu_si256(acc.as_ptr().add(i) as *const __m256i));
+            let clp  = _mm512_min_ps(_mm512_max_ps(_mm512_cvtepi32_ps(vi), zero), vscale);
+            let norm = _mm512_mul_ps(clp, inv_sc);
+            _mm512_storeu_ps(out.as_mut_ptr().add(i), _mm512_mul_ps(norm, norm));
+        }
+    }
+
+    #[target_feature(enable = "avx512f")]
+    unsafe fn gemv_col32_avx512(w: &[f32], x: &[f32], acc: &mut [f32; HIDDEN2]) {
+        debug_assert_eq!(w.len(), x.len() * HIDDEN2);
+        let mut a0 = _mm512_loadu_ps(acc.as_ptr());
+        let mut a1 = _mm512_loadu_ps(acc.as_ptr().add(16));
+        for i in 0..x.len() {
+            let xi  = _mm512_set1_ps(*x.get_unchecked(i));
+            let col = w.as_ptr().add(i * HIDDEN2);
+            a0 = _mm512_fmadd_ps(_mm512_loadu_ps(col),         xi, a0);
+            a1 = _mm512_fmadd_ps(_mm512_loadu_ps(col.add(16)), xi, a1);
+        }
+        _mm512_storeu_ps(acc.as_mut_ptr(),         a0);
+        _mm512_storeu_ps(acc.as_mut_ptr().add(16), a1);
+    }
+
+    #[target_feature(enable = "avx512f", enable = "avx512bw")]
+    unsafe fn screlu_q_avx512(acc: &[i16], out: &mut [i16]) {
+        debug_assert!(acc.len() == out.len() && acc.len().is_multiple_of(32));
+        let zero = _mm512_setzero_si512();
+        let qa = _mm512_set1_epi16(QA as i16);
+        for i in (0..acc.len()).step_by(32) {
+            let x = _mm512_loadu_si512(acc.as_ptr().add(i) as *const __m512i);
+            let c = _mm512_min_epi16(_mm512_max_epi16(x, zero), qa);
+            let a = _mm512_mulhrs_epi16(_mm512_slli_epi16(c, 3), _mm512_slli_epi16(c, 4));
+            _mm512_storeu_si512(out.as_mut_ptr().add(i) as *mut __m512i, a);
+        }
+    }
+
+    /// Each 32-byte half of the pair's weights widens to sixteen outputs.
+    #[target_feature(enable = "avx512f", enable = "avx512bw")]
+    unsafe fn l2_pairs_avx512(w2: &[i8], act: &[i16; HIDDEN1_DUAL], sum: &mut [i32; HIDDEN2]) {
+        debug_assert_eq!(w2.len(), PAIRS * HIDDEN2 * 2);
+        let mut s0 = _mm512_loadu_si512(sum.as_ptr() as *const __m512i);
+        let mut s1 = _mm512_loadu_si512(sum.as_ptr().add(16) as *const __m512i);
+        let pairs = act.as_ptr() as *const i32;
+        for p in 0..PAIRS {
+            let pair = pairs.add(p).read_unaligned();
+            if pair == 0 {
+                continue;
+            }
+            let x = _mm512_set1_epi32(pair);
+            let col = w2.as_ptr().add(p * HIDDEN2 * 2);
+            let w0 = _mm512_cvtepi8_epi16(_mm256_loadu_si256(col as *const __m256i));
+            let w1 = _mm512_cvtepi8_epi16(_mm256_loadu_si256(col.add(32) as *const __m256i));
+            s0 = _mm512_add_epi32(s0, _mm512_madd_epi16(w0, x));
+            s1 = _mm512_add_epi32(s1, _mm512_madd_epi16(w1, x));
+        }
+        _mm512_storeu_si512(sum.as_mut_ptr() as *mut __m512i, s0);
+        _mm512_storeu_si512(sum.as_mut_ptr().add(16) as *mut __m512i, s1);
+    }
+}
+
+// ── aarch64: NEON ─────────────────────────────────────────────────────────
+//
+// Integer SCReLU uses `vqrdmulh(c << 3, c << 4)`, the same rounding as x86
+// `mulhrs`.  Layer 2 loads a pair's weights de-interleaved (`vld2q`) so the
+// two inputs' weights land in separate registers, then widens and
+// multiply-accumulates each against its scalar activation.
+
+#[cfg(target_arch = "aarch64")]
+mod neon {
+    use std::arch::aarch64::*;
+
+    use super::{Kernels, PAIRS};
+    use crate::neural_eval::{HIDDEN1, HIDDEN1_DUAL, HIDDEN2};
+    use crate::nnue_quant::QA;
+
+    pub(super) static NEON: Kernels = Kernels {
+        name: "neon",
+        add_col: add_col_neon,
+        sub_col: sub_col_neon,
+        screlu_deq: screlu_deq_neon,
+        gemv_col32: gemv_col32_neon,
+        screlu_q: screlu_q_neon,
+        l2_pairs: l2_pairs_neon,
+    };
+
+    #[target_feature(enable = "neon")]
+    unsafe fn add_col_neon(acc: &mut [i16; HIDDEN1], col: &[i16]) {
+        debug_assert!(col.len() >= HIDDEN1);
+        for i in (0..HIDDEN1).step_by(8) {
+            let a = vld1q_s16(acc.as_ptr().add(i));
+            let b = vld1q_s16(col.as_ptr().add(i));
+            vst1q_s16(acc.as_mut_ptr().add(i), vqaddq_s16(a, b));
+        }
+    }
+
+    #[target_feature(enable = "neon")]
+    unsafe fn sub_col_neon(acc: &mut [i16; HIDDEN1], col: &[i16]) {
+        debug_assert!(col.len() >= HIDDEN1);
+        for i in (0..HIDDEN1).step_by(8) {
+            let a = vld1q_s16(acc.as_ptr().add(i));
+            let b = vld1q_s16(col.as_ptr().add(i));
+            vst1q_s16(acc.as_mut_ptr().add(i), vqsubq_s16(a, b));
+        }
+    }
+
+    #[target_feature(enable = "neon")]
+    unsafe fn screlu_deq_neon(acc: &[i16], scale: f32, out: &mut [f32]) {
+        debug_assert!(acc.len() == out.len() && acc.len().is_multiple_of(8));
+        let zero   = vdupq_n_f32(0.0);
+        let vscale = vdupq_n_f32(scale);
+        let inv_sc = vdupq_n_f32(1.0 / scale);
+        let act = |v: int32x4_t| {
+            let norm = vmulq_f32(vminq_f32(vmaxq_f32(vcvtq_f32_s32(v), zero), vscale), inv_sc);
+            vmulq_f32(norm, norm)
+        };
+        for i in (0..acc.len()).step_by(8) {
+            let v = vld1q_s16(acc.as_ptr().add(i));
+            vst1q_f32(out.as_mut_ptr().add(i), act(vmovl_s16(vget_low_s16(v))));
+            vst1q_f32(out.as_mut_ptr().add(i + 4), act(vmovl_high_s16(v)));
+        }
+    }
+
+    #[target_feature(enable = "neon")]
+    unsafe fn gemv_col32_neon(w: &[f32], x: &[f32], acc: &mut [f32; HIDDEN2]) {
+        debug_assert_eq!(w.len(), x.len() * HIDDEN2);
+        let mut a: [float32x4_t; 8] = std::array::from_fn(|k| vld1q_f32(acc.as_ptr().add(k * 4)));
+        for i in 0..x.len() {
+            let xi  = *x.get_unchecked(i);
+            let col = w.as_ptr().add(i * HIDDEN2);
+            for (k, ak) in a.iter_mut().enumerate() {
+                *ak = vfmaq_n_f32(*ak, vld1q_f32(col.add(k * 4)), xi);
+            }
+        }
+        for (k, ak) in a.iter().enumerate() {
+            vst1q_f32(acc.as_mut_ptr().add(k * 4), *ak);
+        }
+    }
+
+    #[target_feature(enable = "neon")]
+    unsafe fn screlu_q_neon(acc: &[i16], out: &mut [i16]) {
+        debug_assert!(acc.len() == out.len() && acc.len().is_multiple_of(8));
+        let zero = vdupq_n_s16(0);
+        let qa = vdupq_n_s16(QA as i16);
+        for i in (0..acc.len()).step_by(8) {
+            let c = vminq_s16(vmaxq_s16(vld1q_s16(acc.as_ptr().add(i)), zero), qa);
+            let a = vqrdmulhq_s16(vshlq_n_s16::<3>(c), vshlq_n_s16::<4>(c));
+            vst1q_s16(out.as_mut_ptr().add(i), a);
+        }
+    }
+
+    #[target_feature(enable = "neon")]
+    unsafe fn l2_pairs_neon(w2: &[i8], act: &[i16; HIDDEN1_DUAL], sum: &mut [i32; HIDDEN2]) {
+        debug_assert_eq!(w2.len(), PAIRS * HIDDEN2 * 2);
+        let mut s: [int32x4_t; 8] = std::array::from_fn(|k| vld1q_s32(sum.as_ptr().add(k * 4)));
+        for p in 0..PAIRS {
+            let (a0, a1) = (act[2 * p], act[2 * p + 1]);
+            if a0 == 0 && a1 == 0 {
+                continue;
+            }
+            let col = w2.as_ptr().add(p * HIDDEN2 * 2);
+            // Two halves of sixteen outputs; `.0` holds input 2p's weights,
+            // `.1` input 2p+1's.
+            for h in 0..2 {
+                let w = vld2q_s8(col.add(h * 32));
+                for (wk, ak) in [(w.0, a0), (w.1, a1)] {
+                    let lo = vmovl_s8(vget_low_s8(wk));
+                    let hi = vmovl_high_s8(wk);
+                    let sk = &mut s[h * 4..h * 4 + 4];
+                    sk[0] = vmlal_n_s16(sk[0], vget_low_s16(lo), ak);
+                    sk[1] = vmlal_high_n_s16(sk[1], lo, ak);
+                    sk[2] = vmlal_n_s16(sk[2], vget_low_s16(hi), ak);
+                    sk[3] = vmlal_high_n_s16(sk[3], hi, ak);
+                }
+            }
+        }
+        for (k, sk) in s.iter().enumerate() {
+            vst1q_s32(sum.as_mut_ptr().add(k * 4), *sk);
+        }
+    }
+}
+
+// ── wasm32: simd128 (compile-time only) ───────────────────────────────────
+
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+mod wasm {
+    use core::arch::wasm32::*;
+
+    use super::Kernels;
+    use crate::neural_eval::HIDDEN1;
+
+    pub(super) static SIMD128: Kernels = Kernels {
+        name: "simd128",
+        add_col: add_col_wasm,
+        sub_col: sub_col_wasm,
+        ..super::SCALAR
+    };
+
+    unsafe fn add_col_wasm(acc: &mut [i16; HIDDEN1], col: &[i16]) {
+        for i in (0..HIDDEN1).step_by(8) {
+            let a = v128_load(acc[i..].as_ptr() as *const v128);
+            let b = v128_load(col[i..].as_ptr() as *const v128);
+            v128_store(acc[i..].as_mut_ptr() as *mut v128, i16x8_add_sat(a, b));
+        }
+    }
+
+    unsafe fn sub_col_wasm(acc: &mut [i16; HIDDEN1], col: &[i16]) {
+        for i in (0..HIDDEN1).step_by(8) {
+            let a = v128_load(acc[i..].as_ptr() as *const v128);
+            let b = v128_load(col[i..].as_ptr() as *const v128);
+            v128_store(acc[i..].as_mut_ptr() as *mut v128, i16x8_sub_sat(a, b));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn rng() -> StdRng {
+        StdRng::seed_from_u64(0x5EED)
+    }
+
+    fn random_i16(rng: &mut StdRng, n: usize) -> Vec<i16> {
+        // Mostly small values, some near the limits to exercise saturation.
+        (0..n)
+            .map(|_| if rng.gen_bool(0.1) { rng.gen_range(i16::MIN..=i16::MAX) } else { rng.gen_range(-400..=400) })
+            .collect()
+    }
+
+    #[test]
+    fn best_set_is_detected() {
+        let sets = available();
+        assert_eq!(sets[0].name, "scalar");
+        assert_eq!(kernels().name, sets.last().unwrap().name);
+    }
+
+    #[test]
+    fn add_sub_col_match_scalar() {
+        let mut rng = rng();
+        for k in available() {
+            for _ in 0..20 {
+                let acc: [i16; HIDDEN1] = random_i16(&mut rng, HIDDEN1).try_into().unwrap();
+                let col = random_i16(&mut rng, HIDDEN1);
+                let (mut fast, mut reference) = (acc, acc);
+                unsafe { (k.add_col)(&mut fast, &col) };
+                add_col_scalar(&mut reference, &col);
+                assert_eq!(fast, reference, "{} add_col", k.name);
+                unsafe { (k.sub_col)(&mut fast, &col) };
+                sub_col_scalar(&mut reference, &col);
+                assert_eq!(fast, reference, "{} sub_col", k.name);
+            }
+        }
+    }
+
+    /// Exact at the exporter's power-of-two scale, where multiplying by
+    /// `1 / scale` is the same as the scalar division.
+    #[test]
+    fn screlu_deq_matches_scalar() {
+        let mut rng = rng();
+        let acc = random_i16(&mut rng, HIDDEN1);
+        let mut reference = vec![0.0f32; HIDDEN1];
+        screlu_deq_scalar(&acc, 256.0, &mut reference);
+        for k in available() {
+            let mut fast = vec![0.0f32; HIDDEN1];
+            unsafe { (k.screlu_deq)(&acc, 256.0, &mut fast) };
+            assert_eq!(fast, reference, "{}", k.name);
+        }
+    }
+
+    /// Bit-exact without FMA; with it, within rounding of the terms.
+    #[test]
+    fn gemv_col32_matches_scalar() {
+        let mut rng = rng();
+        let x: Vec<f32> = (0..HIDDEN1).map(|_| rng.gen_range(0.0..1.0)).collect();
+        let w: Vec<f32> = (0..HIDDEN1 * HIDDEN2).map(|_| rng.gen_range(-0.5..0.5)).collect();
+        let bias: [f32; HIDDEN2] = std::array::from_fn(|_| rng.gen_range(-1.0..1.0));
+        let mut reference = bias;
+        gemv_col32_scalar(&w, &x, &mut reference);
+        let magnitude: f32 = x.iter().sum::<f32>() * 0.5;
+        for k in available() {
+            let mut fast = bias;
+            unsafe { (k.gemv_col32)(&w, &x, &mut fast) };
+            if matches!(k.name, "scalar" | "sse4.1" | "simd128") {
+                assert_eq!(fast, reference, "{}", k.name);
+            } else {
+                for j in 0..HIDDEN2 {
+                    let diff = (fast[j] - reference[j]).abs();
+                    assert!(diff <= magnitude * 1e-5, "{} output {j}: {} vs {}", k.name, fast[j], reference[j]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn screlu_q_matches_scalar() {
+        let mut rng = rng();
+        let mut acc = random_i16(&mut rng, HIDDEN1);
+        acc[..8].copy_from_slice(&[0, 1, 127, 128, 255, 256, 257, -1]);
+        let mut reference = vec![0i16; HIDDEN1];
+        screlu_q_scalar(&acc, &mut reference);
+        assert_eq!(reference[..8], [0, 0, 63, 64, 254, 256, 256, 0]);
+        for k in available() {
+            let mut fast = vec![0i16; HIDDEN1];
+            unsafe { (k.screlu_q)(&acc, &mut fast) };
+            assert_eq!(fast, reference, "{}", k.name);
+        }
+    }
+
+    #[test]
+    fn l2_pairs_match_scalar() {
+        let mut rng = rng();
+        for _ in 0..20 {
+            let w2: Vec<i8> = (0..PAIRS * HIDDEN2 * 2).map(|_| rng.gen_range(-127..=127)).collect();
+            // Half the activations zero, so some pairs are skipped and some
+            // half zero.
+            let act: [i16; HIDDEN1_DUAL] =
+                std::array::from_fn(|_| if rng.gen_bool(0.5) { 0 } else { rng.gen_range(0..=256) });
+            let bias: [i32; HIDDEN2] = std::array::from_fn(|_| rng.gen_range(-1 << 20..1 << 20));
+            let mut reference = bias;
+            l2_pairs_scalar(&w2, &act, &mut reference);
+            for k in available() {
+                let mut fast = bias;
+                unsafe { (k.l2_pairs)(&w2, &act, &mut fast) };
+                assert_eq!(fast, reference, "{}", k.name);
+            }
+        }
+    }
+}
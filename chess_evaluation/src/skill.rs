@@ -0,0 +1,334 @@
+//! Engine-side strength limiting (`Skill Level`, `UCI_LimitStrength`, `UCI_Elo`).
+//!
+//! A weakened search is capped in depth and nodes, then every root move is
+//! re-scored at the completed depth (a poor man's MultiPV).  The move played
+//! is picked among the best `SKILL_MULTIPV` candidates with a random push that
+//! grows as the level drops, and with a small per-move chance of a plain
+//! blunder: a random move that loses at most `BLUNDER_MAX_LOSS_CP`.
+//!
+//! Levels run from 0 to `MAX_SKILL_LEVEL`; the top level is full strength and
+//! disables all of the above.  `UCI_Elo` is mapped to a fractional level
+//! through `SKILL_ELO`, measured with `self_play` against the full-strength
+//! engine, or against a nearer level where full strength wins every game:
+//!
+//! ```text
+//! self_play chess_uci chess_uci --no-ponder --movetime 50 --games 40 \
+//!     --engine1-opt "Skill Level=3" --engine2-opt "Skill Level=7"
+//! ```
+//!
+//! and reading off the Elo difference it prints.  Absolute values assume the
+//! full-strength engine at `FULL_STRENGTH_ELO`.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use chess_board::ChessBoard;
+use chess_foundation::ChessMove;
+use move_generator::{
+    move_generator::get_all_legal_moves_for_color, piece_conductor::PieceConductor,
+};
+use rand::Rng;
+
+use crate::alpha_beta::{alpha_beta, DrawKind, DrawScores, SearchContext};
+use crate::transposition_table::TranspositionTable;
+
+/// Full strength.
+pub const MAX_SKILL_LEVEL: i32 = 20;
+
+/// Number of best root moves a weakened engine chooses between.
+pub const SKILL_MULTIPV: usize = 4;
+
+/// Assumed rating of the full-strength engine; anchors `SKILL_ELO`.
+pub const FULL_STRENGTH_ELO: i32 = 2400;
+
+/// `UCI_Elo` range advertised by `chess_uci`.
+pub const MIN_ELO: i32 = SKILL_ELO[0];
+pub const MAX_ELO: i32 = FULL_STRENGTH_ELO;
+
+/// Rating of each integer skill level (index = level).  Measured at 50 ms
+/// per move with 40-game matches (±100–170 Elo): 19 vs 20 = -35, 12 vs 20 =
+/// -147, 10 vs 20 = -301, 7 vs 10 lost every game, 3 vs 7 = -269, 0 vs 3 =
+/// -108; the levels in between are interpolated.  At that time control the
+/// depth cap barely bites above level 12, so the top of the table is flat.
+pub const SKILL_ELO: [i32; MAX_SKILL_LEVEL as usize + 1] = [
+    1170, 1200, 1240, 1280, 1350, 1420, 1490, 1560, 1740, 1920, //
+    2100, 2175, 2250, 2275, 2300, 2320, 2335, 2350, 2360, 2370, //
+    FULL_STRENGTH_ELO,
+];
+
+/// A blunder never gives away more than this (centipawns, side to move).
+pub const BLUNDER_MAX_LOSS_CP: i32 = 350;
+
+/// Cap on the random push between candidates, as in the MultiPV pick.
+const PAWN_CP: i64 = 100;
+
+/// A strength setting.  Fractional levels come from `from_elo`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Skill {
+    level: f64,
+}
+
+impl Skill {
+    /// Full strength.
+    pub fn full() -> Self {
+        Self { level: MAX_SKILL_LEVEL as f64 }
+    }
+
+    /// `Skill Level` 0..=20.
+    pub fn from_level(level: i32) -> Self {
+        Self { level: level.clamp(0, MAX_SKILL_LEVEL) as f64 }
+    }
+
+    /// `UCI_Elo`: interpolates `SKILL_ELO` linearly.
+    pub fn from_elo(elo: i32) -> Self {
+        let elo = elo.clamp(MIN_ELO, MAX_ELO);
+        let i = SKILL_ELO.windows(2).position(|w| elo <= w[1]).unwrap_or(0);
+        let (lo, hi) = (SKILL_ELO[i], SKILL_ELO[i + 1]);
+        let frac = if hi > lo { (elo - lo) as f64 / (hi - lo) as f64 } else { 0.0 };
+        Self { level: i as f64 + frac }
+    }
+
+    pub fn level(&self) -> f64 {
+        self.level
+    }
+
+    /// Estimated rating of this setting.
+    pub fn elo(&self) -> i32 {
+        let i = (self.level.floor() as usize).min(MAX_SKILL_LEVEL as usize - 1);
+        let frac = self.level - i as f64;
+        (SKILL_ELO[i] as f64 + frac * (SKILL_ELO[i + 1] - SKILL_ELO[i]) as f64).round() as i32
+    }
+
+    /// Whether the engine is weakened at all.
+    pub fn is_enabled(&self) -> bool {
+        self.level < MAX_SKILL_LEVEL as f64
+    }
+
+    /// Iterative-deepening depth cap: 1 at level 0, 10 at level 19.
+    pub fn depth_limit(&self) -> Option<i32> {
+        self.is_enabled().then(|| 1 + (self.level / 2.0) as i32)
+    }
+
+    /// No new iteration is started past this many nodes: 1k at level 0,
+    /// doubling every two levels.
+    pub fn node_limit(&self) -> Option<u64> {
+        self.is_enabled().then(|| (1000.0 * 2f64.powf(self.level / 2.0)) as u64)
+    }
+
+    /// Per-move chance of a blunder: 15% at level 0, falling quadratically.
+    pub fn blunder_chance(&self) -> f64 {
+        let weak = (MAX_SKILL_LEVEL as f64 - self.level) / MAX_SKILL_LEVEL as f64;
+        0.15 * weak * weak
+    }
+
+    /// Pick the move to play from `candidates`: root moves with side-to-move
+    /// scores, sorted best first.  Returns an index into `candidates`.
+    pub fn pick(&self, candidates: &[(ChessMove, i32)], rng: &mut impl Rng) -> usize {
+        if !self.is_enabled() || candidates.len() < 2 {
+            return 0;
+        }
+        let top = candidates[0].1 as i64;
+
+        if rng.gen_bool(self.blunder_chance()) {
+            let playable = candidates
+                .iter()
+                .take_while(|c| top - c.1 as i64 <= BLUNDER_MAX_LOSS_CP as i64)
+                .count();
+            if playable > 1 {
+                return rng.gen_range(1..playable);
+            }
+        }
+
+        // Stockfish-style: each candidate is pushed up by a share of its gap
+        // to the best move plus a random amount of up to a pawn.
+        let n = candidates.len().min(SKILL_MULTIPV);
+        let delta = (top - candidates[n - 1].1 as i64).min(PAWN_CP);
+        let weakness = 120 - (2.0 * self.level) as i64;
+        let mut best = (0, i64::MIN);
+        for (i, &(_, score)) in candidates[..n].iter().enumerate() {
+            let score = score as i64;
+            let push = (weakness * (top - score) + delta * rng.gen_range(0..weakness)) / 128;
+            if score + push > best.1 {
+                best = (i, score + push);
+            }
+        }
+        best.0
+    }
+}
+
+impl Default for Skill {
+    fn default() -> Self {
+        Self::full()
+    }
+}
+
+/// Score every legal root move with a search of `depth` plies and return
+/// them with side-to-move scores, best first, plus the nodes spent.  Only
+/// what `pick` reads is searched out, as in a MultiPV search: the best
+/// `SKILL_MULTIPV` moves get exact scores, and every other move only a
+/// null-window verdict on whether it is a playable blunder, scored as
+/// `top - BLUNDER_MAX_LOSS_CP` if so and below that if not.  `first`
+/// (the search's best move) is scored first so the windows are tight from
+/// the start.  Returns `None` if `stop` fired before every move was scored.
+#[allow(clippy::too_many_arguments)]
+pub fn score_root_moves(
+    chess_board: &mut ChessBoard,
+    conductor: &PieceConductor,
+    tt: &TranspositionTable,
+    depth: i32,
+    is_white: bool,
+    first: Option<ChessMove>,
+    stop: Option<&AtomicBool>,
+    draw: DrawScores,
+) -> Option<(Vec<(ChessMove, i32)>, u64)> {
+    let mut legal = Vec::new();
+    get_all_legal_moves_for_color(chess_board, conductor, is_white, &mut legal, &mut Vec::new());
+    if let Some(i) = legal.iter().position(|m| Some(*m) == first) {
+        legal.swap(0, i);
+    }
+
+    let mut ctx = SearchContext::new();
+    ctx.set_draw_scores(draw, is_white);
+    ctx.init_accumulators(chess_board);
+    // Exact scores, best first; then the moves that only got a verdict.
+    let mut exact: Vec<(ChessMove, i32)> = Vec::with_capacity(legal.len());
+    let mut bounded = Vec::new();
+    for mut mv in legal {
+        ctx.acc_push(0, &mv);
+        chess_board.make_move(&mut mv);
+        if chess_board.is_repetition(2) {
+            let score = ctx.draw_score(DrawKind::Repetition, chess_board);
+            exact.push((mv, if is_white { score } else { -score }));
+        } else {
+            // Side-to-move window (lo, hi) and score.  A stop leaves scores
+            // at the window's edge, so windows are clamped, not trusted.
+            let mut search = |lo: i32, hi: i32| {
+                let (lo, hi) = (lo.max(i32::MIN + 1), hi.max(i32::MIN + 1));
+                let (alpha, beta) = if is_white { (lo, hi) } else { (-hi, -lo) };
+                let score = alpha_beta(
+                    chess_board,
+                    conductor,
+                    tt,
+                    &mut ctx,
+                    depth - 1,
+                    1,
+                    alpha,
+                    beta,
+                    !is_white,
+                    true,
+                    stop,
+                )
+                .0;
+                if is_white { score } else { -score }
+            };
+            match exact.get(SKILL_MULTIPV - 1).map(|c| c.1) {
+                None => exact.push((mv, search(i32::MIN + 1, i32::MAX))),
+                Some(kth) if search(kth, kth.saturating_add(1)) > kth => exact.push((mv, search(kth, i32::MAX))),
+                Some(kth) => {
+                    let line = exact[0].1.saturating_sub(BLUNDER_MAX_LOSS_CP);
+                    let playable = kth >= line && search(line.saturating_sub(1), line) >= line;
+                    bounded.push((mv, if playable { line } else { line.saturating_sub(1).min(kth) }));
+                }
+            }
+            exact.sort_by_key(|c| std::cmp::Reverse(c.1));
+        }
+        chess_board.undo_move();
+        if stop.is_some_and(|s| s.load(Ordering::Relaxed)) {
+            return None;
+        }
+    }
+    // Stable: on a tie, an exact score stays ahead of a verdict.
+    exact.extend(bounded);
+    exact.sort_by_key(|c| std::cmp::Reverse(c.1));
+    Some((exact, ctx.nodes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn candidates(scores: &[i32]) -> Vec<(ChessMove, i32)> {
+        scores.iter().map(|&s| (ChessMove::new(0, 0), s)).collect()
+    }
+
+    #[test]
+    fn elo_maps_back_to_itself() {
+        for elo in [MIN_ELO, 1234, 1800, 2385, 2399, MAX_ELO] {
+            assert!((Skill::from_elo(elo).elo() - elo).abs() <= 1, "elo {elo}");
+        }
+        assert!(!Skill::from_elo(MAX_ELO).is_enabled());
+        assert!(Skill::from_elo(MAX_ELO - 1).is_enabled());
+        assert_eq!(Skill::from_elo(100).level(), 0.0);
+    }
+
+    #[test]
+    fn limits_grow_with_level() {
+        let weak = Skill::from_level(0);
+        let strong = Skill::from_level(19);
+        assert_eq!(weak.depth_limit(), Some(1));
+        assert_eq!(strong.depth_limit(), Some(10));
+        assert!(weak.node_limit() < strong.node_limit());
+        assert!(weak.blunder_chance() > strong.blunder_chance());
+        assert_eq!(Skill::full().depth_limit(), None);
+        assert_eq!(Skill::full().node_limit(), None);
+    }
+
+    #[test]
+    fn full_strength_always_plays_best() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let c = candidates(&[50, 49, 48, 47]);
+        assert!((0..100).all(|_| Skill::full().pick(&c, &mut rng) == 0));
+    }
+
+    #[test]
+    fn weak_levels_vary_between_close_moves() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let c = candidates(&[30, 20, 10, 0, -900]);
+        let picks: Vec<usize> = (0..400).map(|_| Skill::from_level(0).pick(&c, &mut rng)).collect();
+        assert!(picks.iter().any(|&i| i != 0), "level 0 should not always play the best move");
+        assert!(picks.iter().all(|&i| i < 4), "the -900 move is neither a candidate nor a playable blunder");
+    }
+
+    #[test]
+    fn mate_is_never_thrown_away_for_a_quiet_move() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let c = candidates(&[999_997, 40, 30, 20]);
+        assert!((0..400).all(|_| Skill::from_level(0).pick(&c, &mut rng) == 0));
+    }
+
+    #[test]
+    fn strong_levels_rarely_leave_the_best_move() {
+        let mut rng = StdRng::seed_from_u64(11);
+        let c = candidates(&[300, 0, -10, -20]);
+        let misses = (0..1000).filter(|_| Skill::from_level(19).pick(&c, &mut rng) != 0).count();
+        assert!(misses < 10, "{misses} misses");
+    }
+
+    #[test]
+    fn scored_root_moves_are_sorted_and_complete() {
+        let conductor = PieceConductor::new();
+        let mut board = ChessBoard::new();
+        let tt = TranspositionTable::new(1 << 12);
+        let (scored, nodes) = score_root_moves(&mut board, &conductor, &tt, 2, true, None, None, DrawScores::default()).unwrap();
+        assert_eq!(scored.len(), 20);
+        assert!(nodes > 0);
+        assert!(scored.windows(2).all(|w| w[0].1 >= w[1].1));
+        assert_eq!(board.current_hash(), ChessBoard::new().current_hash());
+    }
+
+    #[test]
+    fn moves_that_give_back_a_free_queen_are_not_playable() {
+        let conductor = PieceConductor::new();
+        let mut board = ChessBoard::new();
+        board.set_from_fen("rnb1kbnr/pppp1ppp/8/4p3/4P2q/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3");
+        let tt = TranspositionTable::new(1 << 12);
+        let (scored, _) = score_root_moves(&mut board, &conductor, &tt, 3, true, None, None, DrawScores::default()).unwrap();
+        let mut legal = Vec::new();
+        get_all_legal_moves_for_color(&mut board, &conductor, true, &mut legal, &mut Vec::new());
+        assert_eq!(scored.len(), legal.len());
+        assert_eq!((scored[0].0.start_square(), scored[0].0.target_square()), (21, 31));
+        assert!(scored.windows(2).all(|w| w[0].1 >= w[1].1));
+        assert!(scored[1..].iter().all(|c| scored[0].1 - c.1 > BLUNDER_MAX_LOSS_CP));
+    }
+}
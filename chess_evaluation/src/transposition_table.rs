@@ -1,7 +1,11 @@
-use std::cell::UnsafeCell;
-use std::sync::atomic::{AtomicU8, Ordering};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU32, AtomicU64, AtomicU8, Ordering};
 
+use chess_board::zobrist::ZobristTable;
 use chess_foundation::ChessMove;
+use rayon::prelude::*;
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum TtFlag {
@@ -14,8 +18,24 @@ pub enum TtFlag {
 /// A 3-gen-old depth-12 entry becomes depth-0, replaceable by any new entry.
 const AGE_COST: i32 = 4;
 
+/// Entries per bucket.  Each takes 12 bytes (data word + 32-bit check), so
+/// five fill a 64-byte cache line and a probe touches a single line no
+/// matter which slot holds the position.
+const BUCKET_ENTRIES: usize = 5;
 
-/// A transposition table entry — 16 bytes.
+/// Size of one bucket in bytes: one cache line.
+pub const TT_BUCKET_BYTES: usize = 64;
+
+/// Generations wrap at 64 — only 6 bits are stored per entry.
+const GENERATION_MASK: u8 = 0x3F;
+
+/// Magic bytes at the start of a saved table file.
+const TT_FILE_MAGIC: &[u8; 8] = b"XCHESSTT";
+
+/// Bumped whenever the file layout changes incompatibly.
+const TT_FILE_VERSION: u32 = 2;
+
+/// A decoded transposition table entry.
 ///
 /// The best move is stored as a raw `u16` (the `move_value` field of
 /// `ChessMove`) rather than `Option<ChessMove>`.  `Option<ChessMove>` costs
@@ -23,11 +43,13 @@ const AGE_COST: i32 = 4;
 /// the sentinel value 0 (a1→a1, never a legal move) represents "no move".
 #[derive(Clone, Copy)]
 pub struct TtEntry {
+    /// The probed hash.  The shared table stores only `verification_key`
+    /// of it, so entries read back without a probe carry just that key.
     pub hash:       u64,
     pub depth:      i32,
     pub score:      i32,
     pub flag:       TtFlag,
-    /// Search generation that wrote this entry.
+    /// Search generation that wrote this entry (0–63).
     pub generation: u8,
     /// Packed move: bits[5:0]=start, bits[11:6]=target, bits[15:12]=flag.
     /// 0 means no best move recorded.
@@ -53,6 +75,39 @@ impl TtEntry {
             ))
         }
     }
+
+    /// Pack everything except the key into one 64-bit word:
+    /// bits[15:0]=move, bits[47:16]=score, bits[55:48]=depth (i8),
+    /// bits[57:56]=flag, bits[63:58]=generation.
+    #[inline]
+    fn pack_data(&self) -> u64 {
+        let flag = match self.flag {
+            TtFlag::Exact => 0u64,
+            TtFlag::LowerBound => 1,
+            TtFlag::UpperBound => 2,
+        };
+        self.best_move_raw as u64
+            | (self.score as u32 as u64) << 16
+            | (self.depth.clamp(i8::MIN as i32, i8::MAX as i32) as i8 as u8 as u64) << 48
+            | flag << 56
+            | ((self.generation & GENERATION_MASK) as u64) << 58
+    }
+
+    #[inline]
+    fn unpack(hash: u64, data: u64) -> Self {
+        Self {
+            hash,
+            best_move_raw: data as u16,
+            score: (data >> 16) as u32 as i32,
+            depth: (data >> 48) as u8 as i8 as i32,
+            flag: match (data >> 56) & 0x3 {
+                1 => TtFlag::LowerBound,
+                2 => TtFlag::UpperBound,
+                _ => TtFlag::Exact,
+            },
+            generation: (data >> 58) as u8,
+        }
+    }
 }
 
 impl Default for TtEntry {
@@ -68,28 +123,114 @@ impl Default for TtEntry {
     }
 }
 
+/// The part of a hash a slot stores to recognise its position: the low 32
+/// bits.  The bucket index comes from the high bits, so the two are
+/// independent and a wrong position matches with odds of 2^-32 per slot.
+#[inline]
+fn verification_key(hash: u64) -> u32 {
+    hash as u32
+}
+
+/// Folds a data word into the 32-bit check.
+#[inline]
+fn fold(data: u64) -> u32 {
+    data as u32 ^ (data >> 32) as u32
+}
+
+/// A cache-line aligned group of slots sharing one index.
+///
+/// A slot is a data word and a check word: the key is never written on its
+/// own.  `check` holds `key ^ fold(data)`, so a reader that observes a torn
+/// write (the two words from different stores) recovers a key that does not
+/// match and rejects the slot.  An all-zero slot is empty.
+#[repr(C, align(64))]
+#[derive(Default)]
+struct Bucket {
+    data:  [AtomicU64; BUCKET_ENTRIES],
+    check: [AtomicU32; BUCKET_ENTRIES],
+}
+
+impl Bucket {
+    /// Returns slot `i` as `(key, data)` as seen by this reader.
+    #[inline]
+    fn load(&self, i: usize) -> (u32, u64) {
+        let data = self.data[i].load(Ordering::Relaxed);
+        (self.check[i].load(Ordering::Relaxed) ^ fold(data), data)
+    }
+
+    #[inline]
+    fn write(&self, i: usize, key: u32, data: u64) {
+        self.check[i].store(key ^ fold(data), Ordering::Relaxed);
+        self.data[i].store(data, Ordering::Relaxed);
+    }
+
+    #[inline]
+    fn is_empty(&self, i: usize) -> bool {
+        self.check[i].load(Ordering::Relaxed) == 0 && self.data[i].load(Ordering::Relaxed) == 0
+    }
+
+    #[inline]
+    fn clear(&self) {
+        for i in 0..BUCKET_ENTRIES {
+            self.check[i].store(0, Ordering::Relaxed);
+            self.data[i].store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Shared, lock-free transposition table.
+///
+/// Every slot is a pair of atomics, so concurrent probes and stores from
+/// Lazy SMP threads are data-race free; the XOR check word turns torn writes
+/// into misses instead of corrupt hits.
 pub struct TranspositionTable {
-    table: UnsafeCell<Vec<TtEntry>>,
-    size: usize,
+    buckets: Vec<Bucket>,
     /// Current search generation.  Incremented once per move via `new_search()`.
     generation: AtomicU8,
 }
 
-// Safety: concurrent probe/store calls accept benign data races (hash field
-// acts as a validity check).  new_search() is always called before parallel
-// access begins, so the generation counter is never raced.
-unsafe impl Send for TranspositionTable {}
-unsafe impl Sync for TranspositionTable {}
-
 impl TranspositionTable {
+    /// Create a table holding at least `size` entries (rounded up to whole buckets).
     pub fn new(size: usize) -> Self {
         Self {
-            table: UnsafeCell::new(vec![TtEntry::default(); size]),
-            size,
+            buckets: Self::alloc(size),
             generation: AtomicU8::new(0),
         }
     }
 
+    /// Create a table sized to `mb` MiB, as for the UCI `Hash` option.
+    pub fn with_mb(mb: usize) -> Self {
+        Self::new(Self::entries_for_mb(mb))
+    }
+
+    /// Number of entries that fit in `mb` MiB.
+    pub const fn entries_for_mb(mb: usize) -> usize {
+        mb * 1024 * 1024 / TT_BUCKET_BYTES * BUCKET_ENTRIES
+    }
+
+    fn alloc(size: usize) -> Vec<Bucket> {
+        let n = size.div_ceil(BUCKET_ENTRIES).max(1);
+        (0..n).into_par_iter().map(|_| Bucket::default()).collect()
+    }
+
+    /// Total number of entry slots.
+    pub fn capacity(&self) -> usize {
+        self.buckets.len() * BUCKET_ENTRIES
+    }
+
+    /// Reallocate to hold at least `size` entries.  All stored entries are
+    /// discarded; the generation counter is kept.
+    pub fn resize(&mut self, size: usize) {
+        self.buckets = Vec::new(); // free the old table before allocating the new one
+        self.buckets = Self::alloc(size);
+    }
+
+    /// Empty every slot.  Runs in parallel over the rayon pool, which matters
+    /// for multi-gigabyte tables.  Entries stored concurrently may survive.
+    pub fn clear(&self) {
+        self.buckets.par_iter().for_each(Bucket::clear);
+    }
+
     /// Advance the generation counter.  Call once before each new root search
     /// (i.e. each move).  Old entries remain in the table for move-ordering
     /// but are replaced more eagerly than fresh ones.
@@ -97,19 +238,33 @@ impl TranspositionTable {
         self.generation.fetch_add(1, Ordering::Relaxed);
     }
 
+    #[inline]
+    fn current_generation(&self) -> u8 {
+        self.generation.load(Ordering::Relaxed) & GENERATION_MASK
+    }
+
     /// How many generations old is this entry?
     #[inline]
     fn age_of(&self, entry: &TtEntry) -> i32 {
-        self.generation.load(Ordering::Relaxed).wrapping_sub(entry.generation) as i32
+        (self.current_generation().wrapping_sub(entry.generation) & GENERATION_MASK) as i32
     }
 
-    pub fn probe(&self, hash: u64) -> Option<TtEntry> {
-        let idx = (hash as usize) % self.size;
-        // Safety: benign data race — hash field validates the copy.
-        let entry = unsafe { (&(*self.table.get()))[idx] };
-        if entry.hash == hash { Some(entry) } else { None }
+    /// Map a hash to its bucket using the high bits (multiply-shift), so the
+    /// table size need not be a power of two.
+    #[inline]
+    fn bucket(&self, hash: u64) -> &Bucket {
+        let idx = ((hash as u128 * self.buckets.len() as u128) >> 64) as usize;
+        &self.buckets[idx]
     }
 
+    pub fn probe(&self, hash: u64) -> Option<TtEntry> {
+        let bucket = self.bucket(hash);
+        let key = verification_key(hash);
+        (0..BUCKET_ENTRIES).find_map(|i| {
+            let (k, data) = bucket.load(i);
+            (k == key && !bucket.is_empty(i)).then(|| TtEntry::unpack(hash, data))
+        })
+    }
 
     /// Store an entry using a generation-aware replacement policy.
     ///
@@ -119,40 +274,293 @@ impl TranspositionTable {
     /// This means old entries (from previous moves) are evicted even when
     /// they are deep, preventing stale analysis from poisoning new searches.
     ///
-    /// Store an entry using a generation-aware replacement policy.
+    /// Within a bucket: a slot already holding this position is updated in
+    /// place (if the new depth beats its effective depth); otherwise an empty
+    /// slot is used; otherwise the slot with the lowest effective depth is
+    /// evicted.
+    pub fn store(
+        &self,
+        hash: u64,
+        depth: i32,
+        score: i32,
+        flag: TtFlag,
+        best_move: Option<ChessMove>,
+    ) {
+        self.store_raw(hash, depth, score, flag, best_move.map_or(0, |m| m.value()));
+    }
+
+    fn store_raw(&self, hash: u64, depth: i32, score: i32, flag: TtFlag, best_move_raw: u16) {
+        let bucket = self.bucket(hash);
+        let key = verification_key(hash);
+        let mut victim = 0;
+        let mut victim_eff = i32::MAX;
+        let mut kept_move = best_move_raw;
+
+        for i in 0..BUCKET_ENTRIES {
+            if bucket.is_empty(i) {
+                if victim_eff > i32::MIN {
+                    victim = i;
+                    victim_eff = i32::MIN;
+                }
+                continue;
+            }
+            let (k, data) = bucket.load(i);
+            let existing = TtEntry::unpack(hash, data);
+            if k == key {
+                // Same position: only overwrite when the new search is at
+                // least as deep as the age-adjusted existing one.
+                if depth < existing.depth - self.age_of(&existing) * AGE_COST {
+                    return;
+                }
+                // Keep the old move if the new search did not produce one.
+                if kept_move == 0 {
+                    kept_move = existing.best_move_raw;
+                }
+                victim = i;
+                break;
+            }
+            let eff = existing.depth - self.age_of(&existing) * AGE_COST;
+            if eff < victim_eff {
+                victim = i;
+                victim_eff = eff;
+            }
+        }
+
+        let entry = TtEntry {
+            hash, depth, score, flag,
+            generation: self.current_generation(),
+            best_move_raw: kept_move,
+        };
+        bucket.write(victim, key, entry.pack_data());
+    }
+
+    /// Fingerprint of the packed entry encoding.  A reference entry with every
+    /// field distinct is packed and checked; any change to bit positions or
+    /// widths changes the result, so files written by an incompatible build
+    /// are rejected.
+    fn layout_fingerprint() -> u64 {
+        let data = TtEntry {
+            hash: 0,
+            depth: -7,
+            score: -123_456,
+            flag: TtFlag::UpperBound,
+            generation: 0x2A,
+            best_move_raw: 0xBEEF,
+        }
+        .pack_data();
+        data ^ u64::from(fold(data) ^ verification_key(0x0123_4567_89AB_CDEF))
+    }
+
+    /// Write the table to `path` so a later session can resume with its
+    /// accumulated analysis (see `load_from`).
     ///
-    /// Replacement priority (lower effective depth = replaced first):
-    ///   effective_depth = stored_depth - age * AGE_COST
+    /// Format (little-endian): magic, version, entry size, bucket size,
+    /// layout fingerprint, `ZobristTable::SEED`, generation, bucket count,
+    /// then every slot as its data word and check word.  Safe to call while
+    /// a search is running; entries stored concurrently may or may not be
+    /// captured.
+    pub fn save_to(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut w = BufWriter::new(File::create(path)?);
+        w.write_all(TT_FILE_MAGIC)?;
+        w.write_all(&TT_FILE_VERSION.to_le_bytes())?;
+        w.write_all(&(SAVED_SLOT_BYTES as u32).to_le_bytes())?;
+        w.write_all(&(BUCKET_ENTRIES as u32).to_le_bytes())?;
+        w.write_all(&Self::layout_fingerprint().to_le_bytes())?;
+        w.write_all(&ZobristTable::SEED.to_le_bytes())?;
+        w.write_all(&(self.current_generation() as u32).to_le_bytes())?;
+        w.write_all(&(self.buckets.len() as u64).to_le_bytes())?;
+        for b in &self.buckets {
+            for i in 0..BUCKET_ENTRIES {
+                w.write_all(&b.data[i].load(Ordering::Relaxed).to_le_bytes())?;
+                w.write_all(&b.check[i].load(Ordering::Relaxed).to_le_bytes())?;
+            }
+        }
+        w.flush()
+    }
+
+    /// Size in MiB of the table saved in `path`, if it is a whole number;
+    /// `load_from` needs a table of this size.
+    pub fn saved_mb(path: impl AsRef<Path>) -> io::Result<Option<usize>> {
+        let mut r = BufReader::new(File::open(path)?);
+        let bucket_count = read_header(&mut r)?.1;
+        let bytes = bucket_count.checked_mul(TT_BUCKET_BYTES as u64);
+        Ok(bytes.filter(|b| b % (1024 * 1024) == 0).map(|b| (b / (1024 * 1024)) as usize))
+    }
+
+    /// Load a table written by `save_to`, replacing the current contents.
+    /// Returns the number of entries loaded.
     ///
-    /// This means old entries (from previous moves) are evicted even when
-    /// they are deep, preventing stale analysis from poisoning new searches.
+    /// Slots only store part of each hash, so entries cannot move to another
+    /// bucket: the file must have been saved from a table of this size (see
+    /// `saved_mb`).  Files whose header does not match this build (version,
+    /// entry layout, Zobrist seed or size), or whose length does not match
+    /// their header, are rejected with `InvalidData`; the body is read in
+    /// full before any slot is written, so a failed load leaves the table
+    /// untouched.
+    pub fn load_from(&self, path: impl AsRef<Path>) -> io::Result<usize> {
+        let file = File::open(path)?;
+        let file_len = file.metadata()?.len();
+        let mut r = BufReader::new(file);
+        let (generation, bucket_count) = read_header(&mut r)?;
+        let body_len = bucket_count
+            .checked_mul((BUCKET_ENTRIES * SAVED_SLOT_BYTES) as u64)
+            .filter(|&n| n.checked_add(SAVED_HEADER_BYTES) == Some(file_len))
+            .ok_or_else(|| invalid("transposition table file is truncated or damaged"))?;
+        if bucket_count != self.buckets.len() as u64 {
+            return Err(invalid(&format!(
+                "transposition table was saved with {bucket_count} buckets, this one has {}",
+                self.buckets.len()
+            )));
+        }
+        // Read the whole body before touching the table, so a failed read
+        // leaves it as it was.
+        let mut body = vec![0u8; body_len as usize];
+        r.read_exact(&mut body)?;
+
+        self.clear();
+        self.generation.store(generation, Ordering::Relaxed);
+        let mut loaded = 0;
+        for (b, saved) in self.buckets.iter().zip(body.chunks_exact(BUCKET_ENTRIES * SAVED_SLOT_BYTES)) {
+            for (i, slot) in saved.chunks_exact(SAVED_SLOT_BYTES).enumerate() {
+                let data = u64::from_le_bytes(slot[..8].try_into().unwrap());
+                let check = u32::from_le_bytes(slot[8..].try_into().unwrap());
+                b.data[i].store(data, Ordering::Relaxed);
+                b.check[i].store(check, Ordering::Relaxed);
+                if !b.is_empty(i) {
+                    loaded += 1;
+                }
+            }
+        }
+        Ok(loaded)
+    }
+
+    /// Replay every entry of `overlay` into this table, in slot order,
+    /// through the normal replacement policy.
+    ///
+    /// Used by the deterministic Lazy SMP mode: each thread writes into a
+    /// private overlay table during an iteration, and the overlays are merged
+    /// into the shared table in a fixed thread order afterwards, so the final
+    /// table contents never depend on thread scheduling.
+    pub fn merge_from(&self, overlay: &TtOverlay) {
+        for &(hash, data) in overlay.slots.iter().filter(|s| s.0 != 0) {
+            let e = TtEntry::unpack(hash, data);
+            self.store_raw(e.hash, e.depth, e.score, e.flag, e.best_move_raw);
+        }
+    }
+
+    /// Every occupied slot, in slot order.  `hash` holds only the stored
+    /// verification key.
+    #[cfg(test)]
+    pub(crate) fn entries(&self) -> impl Iterator<Item = TtEntry> + '_ {
+        self.buckets.iter().flat_map(|b| {
+            (0..BUCKET_ENTRIES).filter(|&i| !b.is_empty(i)).map(|i| {
+                let (key, data) = b.load(i);
+                TtEntry::unpack(key as u64, data)
+            })
+        })
+    }
+}
+
+/// Private write table for deterministic Lazy SMP (see `merge_from`).
+///
+/// Unlike the shared table it keeps whole keys, so its entries can be
+/// replayed into a table of any size.  Direct-mapped: a store replaces the
+/// slot's entry unless that is a deeper one for the same position.
+pub struct TtOverlay {
+    /// `(hash, packed data)`; hash 0 marks an empty slot.
+    slots: Vec<(u64, u64)>,
+}
+
+impl TtOverlay {
+    pub fn new(size: usize) -> Self {
+        Self { slots: vec![(0, 0); size.max(1)] }
+    }
+
+    #[inline]
+    fn index(&self, hash: u64) -> usize {
+        ((hash as u128 * self.slots.len() as u128) >> 64) as usize
+    }
+
+    pub fn probe(&self, hash: u64) -> Option<TtEntry> {
+        let (key, data) = self.slots[self.index(hash)];
+        (key == hash && hash != 0).then(|| TtEntry::unpack(hash, data))
+    }
+
     pub fn store(
-        &self,
+        &mut self,
         hash: u64,
         depth: i32,
         score: i32,
         flag: TtFlag,
         best_move: Option<ChessMove>,
     ) {
-        let idx = (hash as usize) % self.size;
-        // Safety: concurrent reads/writes accepted (benign data race).
-        let table = unsafe { &mut *self.table.get() };
-        let existing = table[idx];
-        let age = self.age_of(&existing);
-        // Effective depth of the existing entry, penalised by age.
-        let existing_eff = existing.depth - age * AGE_COST;
-        // Replace if: slot is empty, hash collision (different position), or
-        // the new entry's depth beats the age-adjusted existing depth.
-        if existing.hash == 0 || existing.hash != hash || depth >= existing_eff {
-            table[idx] = TtEntry {
-                hash, depth, score, flag,
-                generation: self.generation.load(Ordering::Relaxed),
-                best_move_raw: best_move.map_or(0, |m| m.value()),
-            };
+        let i = self.index(hash);
+        let mut best_move_raw = best_move.map_or(0, |m| m.value());
+        let (key, data) = self.slots[i];
+        if key == hash {
+            let existing = TtEntry::unpack(key, data);
+            if depth < existing.depth {
+                return;
+            }
+            if best_move_raw == 0 {
+                best_move_raw = existing.best_move_raw;
+            }
         }
+        let entry = TtEntry { hash, depth, score, flag, generation: 0, best_move_raw };
+        self.slots[i] = (hash, entry.pack_data());
+    }
+
+    pub fn clear(&mut self) {
+        self.slots.fill((0, 0));
     }
 }
 
+/// Bytes per slot in a saved file: data word and check word.
+const SAVED_SLOT_BYTES: usize = 12;
+
+/// Bytes before the first slot in a saved file.
+const SAVED_HEADER_BYTES: u64 = 48;
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+/// Reads and checks a saved table's header, returning its generation and
+/// bucket count.
+fn read_header(r: &mut impl Read) -> io::Result<(u8, u64)> {
+    let mut magic = [0u8; 8];
+    r.read_exact(&mut magic)?;
+    if &magic != TT_FILE_MAGIC {
+        return Err(invalid("not a transposition table file"));
+    }
+    if read_u32(r)? != TT_FILE_VERSION {
+        return Err(invalid("unsupported transposition table file version"));
+    }
+    if read_u32(r)? != SAVED_SLOT_BYTES as u32
+        || read_u32(r)? != BUCKET_ENTRIES as u32
+        || read_u64(r)? != TranspositionTable::layout_fingerprint()
+    {
+        return Err(invalid("transposition table entry layout mismatch"));
+    }
+    if read_u64(r)? != ZobristTable::SEED {
+        return Err(invalid("transposition table was saved with different Zobrist keys"));
+    }
+    let generation = read_u32(r)? as u8 & GENERATION_MASK;
+    Ok((generation, read_u64(r)?))
+}
+
+fn read_u32(r: &mut impl Read) -> io::Result<u32> {
+    let mut buf = [0u8; 4];
+    r.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64(r: &mut impl Read) -> io::Result<u64> {
+    let mut buf = [0u8; 8];
+    r.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -194,7 +602,7 @@ mod tests {
 
     #[test]
     fn hash_collision_returns_none() {
-        // With size=8, hashes 0 and 8 map to the same slot but are different keys.
+        // With size=8, hashes 0 and 8 map to the same bucket but are different keys.
         let tt = TranspositionTable::new(8);
         tt.store(0, 3, 100, TtFlag::Exact, None);
         assert!(tt.probe(8).is_none(), "different hash in same slot must return None");
@@ -240,6 +648,170 @@ mod tests {
         assert!(tt.probe(1).is_some());
     }
 
+    #[test]
+    fn clear_empties_every_slot() {
+        let tt = TranspositionTable::new(1024);
+        tt.store(42, 3, 100, TtFlag::Exact, None);
+        tt.store(43, 4, 200, TtFlag::LowerBound, None);
+        tt.clear();
+        assert!(tt.probe(42).is_none());
+        assert!(tt.probe(43).is_none());
+    }
+
+    #[test]
+    fn merge_from_copies_entries_through_replacement_policy() {
+        let shared = TranspositionTable::new(1024);
+        shared.store(42, 8, 100, TtFlag::Exact, None);
+        let mut overlay = TtOverlay::new(1024);
+        overlay.store(42, 2, 999, TtFlag::Exact, None); // shallower — must not win
+        overlay.store(7, 5, 300, TtFlag::LowerBound, Some(ChessMove::new(12, 28)));
+        shared.merge_from(&overlay);
+
+        assert_eq!(shared.probe(42).unwrap().depth, 8, "deeper shared entry must survive");
+        let merged = shared.probe(7).expect("overlay entry must be merged");
+        assert_eq!(merged.score, 300);
+        assert_eq!(merged.flag, TtFlag::LowerBound);
+        assert_eq!(merged.best_move().unwrap().target_square(), 28);
+    }
+
+    // ── Buckets / lockless verification ─────────────────────────────────────
+
+    /// Keys that all land in bucket 0 of a small table (high bits zero).
+    const SAME_BUCKET: [u64; 6] = [11, 22, 33, 44, 55, 66];
+
+    #[test]
+    fn bucket_sizes() {
+        assert_eq!(std::mem::size_of::<Bucket>(), TT_BUCKET_BYTES, "bucket must be one cache line");
+        assert_eq!(std::mem::align_of::<Bucket>(), 64);
+        assert_eq!(TranspositionTable::with_mb(1).capacity(), 16_384 * BUCKET_ENTRIES);
+    }
+
+    #[test]
+    fn bucket_holds_five_colliding_positions() {
+        let tt = TranspositionTable::new(1024);
+        for (i, &h) in SAME_BUCKET[..5].iter().enumerate() {
+            tt.store(h, 3, i as i32, TtFlag::Exact, None);
+        }
+        for (i, &h) in SAME_BUCKET[..5].iter().enumerate() {
+            assert_eq!(tt.probe(h).expect("all five must coexist").score, i as i32);
+        }
+    }
+
+    #[test]
+    fn full_bucket_evicts_shallowest_entry() {
+        let tt = TranspositionTable::new(1024);
+        let depths = [6, 2, 9, 5, 7];
+        for (&h, &d) in SAME_BUCKET[..5].iter().zip(depths.iter()) {
+            tt.store(h, d, 0, TtFlag::Exact, None);
+        }
+        tt.store(SAME_BUCKET[5], 1, 0, TtFlag::Exact, None);
+        assert!(tt.probe(SAME_BUCKET[1]).is_none(), "depth-2 entry should be the victim");
+        for (i, &h) in SAME_BUCKET.iter().enumerate() {
+            assert_eq!(tt.probe(h).is_some(), i != 1);
+        }
+    }
+
+    #[test]
+    fn positions_differing_only_in_index_bits_are_told_apart() {
+        // Same low 32 bits, so the same verification key, but different buckets.
+        let tt = TranspositionTable::new(1 << 16);
+        let (a, b) = (0x0000_0001_0000_002A, 0x8000_0001_0000_002A);
+        tt.store(a, 4, 10, TtFlag::Exact, None);
+        tt.store(b, 4, 20, TtFlag::Exact, None);
+        assert_eq!(tt.probe(a).unwrap().score, 10);
+        assert_eq!(tt.probe(b).unwrap().score, 20);
+        // Same bucket, different key.
+        assert!(tt.probe(0x0000_0001_0000_002B).is_none());
+    }
+
+    #[test]
+    fn full_bucket_prefers_evicting_stale_entry() {
+        let tt = TranspositionTable::new(1024);
+        tt.store(SAME_BUCKET[0], 12, 0, TtFlag::Exact, None); // deep but will go stale
+        tt.new_search();
+        tt.new_search();
+        tt.new_search(); // effective depth 12 - 3*4 = 0
+        for &h in &SAME_BUCKET[1..5] {
+            tt.store(h, 3, 0, TtFlag::Exact, None);
+        }
+        tt.store(SAME_BUCKET[5], 3, 0, TtFlag::Exact, None);
+        assert!(tt.probe(SAME_BUCKET[0]).is_none(), "stale deep entry should be evicted first");
+    }
+
+    #[test]
+    fn torn_write_is_rejected() {
+        let tt = TranspositionTable::new(1024);
+        tt.store(42, 7, 123, TtFlag::Exact, None);
+        // Simulate a concurrent writer that replaced only the data word.
+        let bucket = tt.bucket(42);
+        let i = (0..BUCKET_ENTRIES).find(|&i| !bucket.is_empty(i)).unwrap();
+        let other = TtEntry { hash: 99, depth: 1, score: -5, ..TtEntry::default() };
+        bucket.data[i].store(other.pack_data(), Ordering::Relaxed);
+        assert!(tt.probe(42).is_none(), "mismatched check word must read as a miss");
+    }
+
+    #[test]
+    fn packed_fields_round_trip() {
+        let mv = ChessMove::new_with_flag(52, 60, ChessMove::PROMOTE_TO_QUEEN_FLAG);
+        let e = TtEntry {
+            hash: 0xABCD,
+            depth: -3,
+            score: -999_950,
+            flag: TtFlag::UpperBound,
+            generation: 37,
+            best_move_raw: mv.value(),
+        };
+        let back = TtEntry::unpack(e.hash, e.pack_data());
+        assert_eq!(back.depth, -3);
+        assert_eq!(back.score, -999_950);
+        assert_eq!(back.flag, TtFlag::UpperBound);
+        assert_eq!(back.generation, 37);
+        assert_eq!(back.best_move().unwrap().value(), mv.value());
+    }
+
+    #[test]
+    fn store_without_move_keeps_previous_move() {
+        let tt = TranspositionTable::new(1024);
+        tt.store(42, 3, 10, TtFlag::Exact, Some(ChessMove::new(8, 16)));
+        tt.store(42, 5, 20, TtFlag::LowerBound, None);
+        let e = tt.probe(42).unwrap();
+        assert_eq!(e.depth, 5);
+        assert_eq!(e.best_move().unwrap().target_square(), 16);
+    }
+
+    #[test]
+    fn resize_changes_capacity_and_discards_entries() {
+        let mut tt = TranspositionTable::with_mb(1);
+        assert_eq!(tt.capacity(), TranspositionTable::entries_for_mb(1));
+        tt.store(42, 3, 10, TtFlag::Exact, None);
+        tt.resize(TranspositionTable::entries_for_mb(2));
+        assert_eq!(tt.capacity(), TranspositionTable::entries_for_mb(2));
+        assert!(tt.probe(42).is_none());
+        tt.store(42, 3, 10, TtFlag::Exact, None);
+        assert!(tt.probe(42).is_some());
+    }
+
+    #[test]
+    fn concurrent_stores_never_yield_foreign_entries() {
+        // Many threads hammer a tiny table; every hit must carry the data its
+        // own key was stored with (score == low 20 bits of the key).
+        let tt = TranspositionTable::new(16);
+        std::thread::scope(|s| {
+            for t in 0..4u64 {
+                let tt = &tt;
+                s.spawn(move || {
+                    for i in 0..20_000u64 {
+                        let key = (i * 4 + t + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+                        tt.store(key, 1, (key & 0xF_FFFF) as i32, TtFlag::Exact, None);
+                        if let Some(e) = tt.probe(key) {
+                            assert_eq!(e.score, (key & 0xF_FFFF) as i32);
+                        }
+                    }
+                });
+            }
+        });
+    }
+
     // ── Generation / aging ───────────────────────────────────────────────────
 
     #[test]
@@ -336,4 +908,98 @@ mod tests {
         assert_eq!(entry.depth, 8, "deeper same-gen entry must not be overwritten by shallower");
         assert_eq!(entry.score, 100);
     }
+
+    // ── Persistence ──────────────────────────────────────────────────────────
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("tt_{}_{}.bin", name, std::process::id()))
+    }
+
+    #[test]
+    fn save_and_load_same_size_restores_entries() {
+        let path = temp_path("same_size");
+        let tt = TranspositionTable::new(1024);
+        tt.new_search();
+        tt.new_search();
+        tt.store(42, 5, -77, TtFlag::LowerBound, Some(ChessMove::new(12, 28)));
+        tt.store(0xFEED_FACE_0000_0001, 9, 300, TtFlag::Exact, None);
+        tt.save_to(&path).unwrap();
+
+        let restored = TranspositionTable::new(1024);
+        assert_eq!(restored.load_from(&path).unwrap(), 2);
+        let e = restored.probe(42).unwrap();
+        assert_eq!((e.depth, e.score, e.flag), (5, -77, TtFlag::LowerBound));
+        assert_eq!(e.best_move().unwrap().target_square(), 28);
+        assert_eq!(restored.current_generation(), tt.current_generation());
+        assert!(restored.probe(0xFEED_FACE_0000_0001).is_some());
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn load_needs_a_table_of_the_saved_size() {
+        let path = temp_path("resized");
+        let tt = TranspositionTable::with_mb(1);
+        for h in 1..=50u64 {
+            tt.store(h.wrapping_mul(0x9E37_79B9_7F4A_7C15), 4, h as i32, TtFlag::Exact, None);
+        }
+        tt.save_to(&path).unwrap();
+        assert_eq!(TranspositionTable::saved_mb(&path).unwrap(), Some(1));
+
+        let bigger = TranspositionTable::with_mb(2);
+        bigger.store(7, 3, 10, TtFlag::Exact, None);
+        assert_eq!(bigger.load_from(&path).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert!(bigger.probe(7).is_some(), "a rejected file must leave the table untouched");
+
+        let same = TranspositionTable::with_mb(1);
+        assert_eq!(same.load_from(&path).unwrap(), 50);
+        for h in 1..=50u64 {
+            let e = same.probe(h.wrapping_mul(0x9E37_79B9_7F4A_7C15)).unwrap();
+            assert_eq!(e.score, h as i32);
+        }
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn load_rejects_damaged_files_without_touching_the_table() {
+        let path = temp_path("damaged");
+        let tt = TranspositionTable::new(1024);
+        tt.store(42, 3, 10, TtFlag::Exact, None);
+        tt.save_to(&path).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+
+        let target = TranspositionTable::new(1024);
+        target.store(7, 3, 10, TtFlag::Exact, None);
+        let mut oversized = bytes.clone();
+        oversized[40..48].copy_from_slice(&u64::MAX.to_le_bytes());
+        for damaged in [&bytes[..bytes.len() - 5], &bytes[..60], &oversized[..]] {
+            std::fs::write(&path, damaged).unwrap();
+            assert_eq!(target.load_from(&path).unwrap_err().kind(), io::ErrorKind::InvalidData);
+            assert!(target.probe(7).is_some(), "a damaged file must leave the table as it was");
+            assert!(target.probe(42).is_none());
+        }
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn load_rejects_mismatched_header() {
+        let path = temp_path("bad_header");
+        let tt = TranspositionTable::new(64);
+        tt.store(42, 3, 10, TtFlag::Exact, None);
+        tt.save_to(&path).unwrap();
+
+        // Corrupt the Zobrist seed (bytes 28..36 of the header).
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[28] ^= 0xFF;
+        std::fs::write(&path, &bytes).unwrap();
+
+        let target = TranspositionTable::new(64);
+        target.store(7, 3, 10, TtFlag::Exact, None);
+        let err = target.load_from(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(target.probe(7).is_some(), "a rejected file must leave the table untouched");
+
+        std::fs::write(&path, b"garbage!").unwrap();
+        assert_eq!(target.load_from(&path).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        std::fs::remove_file(&path).ok();
+    }
 }
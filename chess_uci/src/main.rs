@@ -1,14 +1,11 @@
 use std::io::{self, BufRead, Write};
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
-use std::thread;
 use std::time::{Duration, Instant};
 
 use chess_board::ChessBoard;
 use chess_evaluation::{
-    init_neural_eval, is_neural_eval_enabled, is_neural_eval_initialized,
-    iterative_deepening_root_with_tt, set_neural_confidence_threshold,
-    set_neural_eval_enabled, OpeningBook, TranspositionTable,
+    init_neural_eval, is_neural_eval_enabled, is_neural_eval_initialized, neural_net_hash,
+    set_neural_confidence_threshold, set_neural_eval_enabled, Engine, SearchEvent,
+    SearchLimits, DEFAULT_ANALYSIS_REFRESH, MAX_ELO, MAX_SKILL_LEVEL, MIN_ELO,
 };
 
 /// Weights embedded at compile time for direct NN features (nn-full-forward / nn-incremental).
@@ -18,6 +15,19 @@ static NNUE_WEIGHTS: &[u8] = include_bytes!("../../chess_evaluation/src/eval.npz
 use chess_foundation::{piece::PieceType, ChessMove};
 use move_generator::{move_generator::get_all_legal_moves_for_color, piece_conductor::PieceConductor};
 
+/// Hash of the net the engine evaluates with: the one loaded through
+/// EvalFile, else the embedded one that `isready` will load.
+fn net_hash() -> Option<String> {
+    if let Some(hash) = neural_net_hash() {
+        return Some(hash.to_string());
+    }
+    #[cfg(any(feature = "nn-full-forward", feature = "nn-incremental"))]
+    if !NNUE_WEIGHTS.is_empty() {
+        return chess_evaluation::net_file::net_hash(NNUE_WEIGHTS).ok();
+    }
+    None
+}
+
 const NAME: &str = "XavChess";
 const AUTHOR: &str = "XavChess";
 
@@ -138,20 +148,9 @@ fn apply_position(board: &mut ChessBoard, conductor: &PieceConductor, tokens: &[
 
 // ── Go command parsing ───────────────────────────────────────────────────────
 
-struct GoParams {
-    max_depth: i32,
-    /// Soft deadline: iterative deepening checks this to decide whether to
-    /// start another iteration.
-    soft_deadline: Option<Instant>,
-    /// Hard deadline: a background timer fires the stop flag at this point.
-    hard_deadline: Option<Instant>,
-    /// Whether this is a ponder search (think on opponent's time).
-    _is_ponder: bool,
-    /// Raw movetime in ms — used by ponder to set deadline on ponderhit.
-    movetime_ms: Option<u64>,
-}
-
-fn parse_go(tokens: &[&str], is_white: bool, move_number: usize) -> GoParams {
+/// Translate `go` arguments into search limits, allocating time from the
+/// clock when no explicit movetime is given.
+fn parse_go(tokens: &[&str], is_white: bool, move_number: usize) -> SearchLimits {
     let mut max_depth: i32 = 64;
     let mut movetime_ms: Option<u64> = None;
     let mut wtime: Option<u64> = None;
@@ -160,6 +159,8 @@ fn parse_go(tokens: &[&str], is_white: bool, move_number: usize) -> GoParams {
     let mut binc: u64 = 0;
     let mut movestogo: Option<u64> = None;
     let mut is_ponder = false;
+    let mut nodes: Option<u64> = None;
+    let mut mate: Option<u32> = None;
 
     let mut i = 0;
     while i < tokens.len() {
@@ -171,6 +172,8 @@ fn parse_go(tokens: &[&str], is_white: bool, move_number: usize) -> GoParams {
             "winc"      => { winc  = tokens.get(i+1).and_then(|s| s.parse().ok()).unwrap_or(0); i += 2; }
             "binc"      => { binc  = tokens.get(i+1).and_then(|s| s.parse().ok()).unwrap_or(0); i += 2; }
             "movestogo" => { movestogo = tokens.get(i+1).and_then(|s| s.parse().ok()); i += 2; }
+            "nodes"     => { nodes = tokens.get(i+1).and_then(|s| s.parse().ok()); i += 2; }
+            "mate"      => { mate  = tokens.get(i+1).and_then(|s| s.parse().ok()); i += 2; }
             "ponder"    => { is_ponder = true; i += 1; }
             "infinite"  => { i += 1; } // max_depth=64, no deadline — stop signal controls it
             _           => { i += 1; }
@@ -179,13 +182,12 @@ fn parse_go(tokens: &[&str], is_white: bool, move_number: usize) -> GoParams {
 
     // Explicit movetime: use it directly (self-play, analysis, etc.)
     if let Some(ms) = movetime_ms {
-        let now = Instant::now();
-        return GoParams {
-            max_depth,
-            soft_deadline: Some(now + Duration::from_millis(ms)),
-            hard_deadline: Some(now + Duration::from_millis(ms)),
-            _is_ponder: is_ponder,
-            movetime_ms,
+        return SearchLimits {
+            depth: Some(max_depth),
+            nodes,
+            ponder: is_ponder,
+            mate,
+            ..SearchLimits::movetime(Duration::from_millis(ms))
         };
     }
 
@@ -230,83 +232,39 @@ fn parse_go(tokens: &[&str], is_white: bool, move_number: usize) -> GoParams {
             .min(rem * cap_pct * 2 / 100)
             .max(soft_ms);
 
+        // For ponder the engine re-arms hard_ms from the ponderhit.
         let now = Instant::now();
-        GoParams {
-            max_depth,
+        SearchLimits {
+            depth: Some(max_depth),
+            nodes,
             soft_deadline: Some(now + Duration::from_millis(soft_ms)),
             hard_deadline: Some(now + Duration::from_millis(hard_ms)),
-            _is_ponder: is_ponder,
-            // For ponder: use hard_ms as the duration after ponderhit
-            movetime_ms: Some(hard_ms),
+            ponder: is_ponder,
+            mate,
         }
     } else {
-        GoParams { max_depth, soft_deadline: None, hard_deadline: None, _is_ponder: is_ponder, movetime_ms: None }
+        SearchLimits { depth: Some(max_depth), nodes, ponder: is_ponder, mate, ..SearchLimits::infinite() }
     }
 }
 
-// ── Search thread ────────────────────────────────────────────────────────────
-
-/// Create an isolated stop flag for a search, with a hard-deadline timer
-/// and a propagator that bridges an external stop signal.
-fn make_search_stop(
-    ext_stop: &Arc<AtomicBool>,
-    hard_deadline: Option<Instant>,
-) -> Arc<AtomicBool> {
-    let search_stop = Arc::new(AtomicBool::new(false));
-
-    // Hard-deadline timer
-    if let Some(hard) = hard_deadline {
-        let stop_c = Arc::clone(&search_stop);
-        thread::spawn(move || {
-            let remaining = hard.saturating_duration_since(Instant::now());
-            if !remaining.is_zero() {
-                thread::sleep(remaining);
-            }
-            stop_c.store(true, Ordering::Relaxed);
-        });
-    }
+// ── Search output ────────────────────────────────────────────────────────────
 
-    // Propagate external stop into search_stop
-    let ext = Arc::clone(ext_stop);
-    let prop = Arc::clone(&search_stop);
-    thread::spawn(move || {
-        while !prop.load(Ordering::Relaxed) {
-            if ext.load(Ordering::Relaxed) {
-                prop.store(true, Ordering::Relaxed);
-                break;
-            }
-            thread::sleep(Duration::from_millis(5));
+/// Print engine search events as UCI `info` / `bestmove` lines.  In analysis
+/// mode every line comes from `Pv` events, which carry the moves.
+fn print_search_event(event: SearchEvent, is_white: bool, analyse: bool) {
+    match event {
+        SearchEvent::Depth { .. } if analyse => {}
+        SearchEvent::Pv { depth, score, nodes, elapsed_ms: ms, pv } => {
+            let engine_score = if is_white { score } else { -score };
+            let nps = if ms > 0 { nodes * 1000 / ms as u64 } else { nodes };
+            let pv: Vec<String> = pv.into_iter().map(mv_to_uci).collect();
+            println!(
+                "info depth {depth} score {} nodes {nodes} nps {nps} time {ms} pv {}",
+                format_score(engine_score),
+                pv.join(" ")
+            );
         }
-    });
-
-    search_stop
-}
-
-fn search_and_respond(
-    mut board: ChessBoard,
-    conductor: PieceConductor,
-    book: OpeningBook,
-    params: GoParams,
-    stop: Arc<AtomicBool>,
-    is_white: bool,
-    tt: Arc<TranspositionTable>,
-    num_threads: usize,
-) {
-    let search_stop = make_search_stop(&stop, params.hard_deadline);
-
-    let t0 = Instant::now();
-    tt.new_search();
-    let result = iterative_deepening_root_with_tt(
-        &mut board,
-        &conductor,
-        Some(&book),
-        &tt,
-        params.max_depth,
-        is_white,
-        params.soft_deadline,
-        Some(search_stop),
-        num_threads,
-        Some(&|depth, score, nodes, ms| {
+        SearchEvent::Depth { depth, score, nodes, elapsed_ms: ms } => {
             // score is from white's perspective; UCI expects engine's (side-to-move) perspective.
             let engine_score = if is_white { score } else { -score };
             let nps = if ms > 0 { nodes * 1000 / ms as u64 } else { nodes };
@@ -315,119 +273,27 @@ fn search_and_respond(
             if engine_score >= MATE_SCORE_THRESHOLD {
                 println!("info string {}", borg_taunt(depth));
             }
-            let _ = io::stdout().flush();
-        }),
-        0,
-    );
-    let _ms = t0.elapsed().as_millis();
-    let mv_str = result.best_move.map(mv_to_uci).unwrap_or_else(|| "0000".to_string());
-    let ponder_str = result.ponder_move.map(mv_to_uci);
-    if let Some(ref p) = ponder_str {
-        println!("bestmove {mv_str} ponder {p}");
-    } else {
-        println!("bestmove {mv_str}");
-    }
-    let _ = io::stdout().flush();
-}
-
-/// Ponder search: think indefinitely until ponderhit or stop.
-///
-/// On `ponderhit`: the search CONTINUES for movetime_ms more, preserving
-/// all TT state built up during pondering. This effectively extends
-/// thinking time by however long we pondered.
-/// On `stop`: stop flag fires → we output whatever we have.
-fn ponder_and_respond(
-    mut board: ChessBoard,
-    conductor: PieceConductor,
-    book: OpeningBook,
-    params: GoParams,
-    stop: Arc<AtomicBool>,
-    ponderhit: Arc<AtomicBool>,
-    is_white: bool,
-    tt: Arc<TranspositionTable>,
-    num_threads: usize,
-) {
-    let search_stop = Arc::new(AtomicBool::new(false));
-
-    // Watcher thread: monitors external stop and ponderhit.
-    // Phase 1 (ponder): search runs freely, stopped only by external stop.
-    // Phase 2 (ponderhit): start a hard-deadline timer, then stop.
-    {
-        let ext = Arc::clone(&stop);
-        let hit = Arc::clone(&ponderhit);
-        let ss = Arc::clone(&search_stop);
-        let movetime = params.movetime_ms;
-        thread::spawn(move || {
-            // Phase 1: wait for ponderhit or external stop
-            loop {
-                if ss.load(Ordering::Acquire) { return; } // search finished naturally
-                if ext.load(Ordering::Acquire) {
-                    ss.store(true, Ordering::Release);
-                    return;
-                }
-                if hit.load(Ordering::Acquire) { break; }
-                thread::sleep(Duration::from_millis(2));
-            }
-
-            // Phase 2: ponderhit received — give the search movetime more ms
-            if let Some(ms) = movetime {
-                let deadline = Instant::now() + Duration::from_millis(ms);
-                loop {
-                    if ext.load(Ordering::Acquire) || ss.load(Ordering::Acquire) {
-                        ss.store(true, Ordering::Release);
-                        return;
-                    }
-                    if Instant::now() >= deadline {
-                        ss.store(true, Ordering::Release);
-                        return;
-                    }
-                    thread::sleep(Duration::from_millis(2));
-                }
-            }
-            // No movetime — continue until external stop
-            loop {
-                if ext.load(Ordering::Acquire) || ss.load(Ordering::Acquire) {
-                    ss.store(true, Ordering::Release);
-                    return;
-                }
-                thread::sleep(Duration::from_millis(2));
+        }
+        SearchEvent::Finished(result) => {
+            // A solved `go mate` carries the whole mating line.
+            if !result.pv.is_empty() && !analyse {
+                let score = if is_white { result.score } else { -result.score };
+                let pv: Vec<String> = result.pv.iter().map(|&m| mv_to_uci(m)).collect();
+                println!(
+                    "info depth {} score {} nodes {} pv {}",
+                    result.pv.len(),
+                    format_score(score),
+                    result.total_nodes,
+                    pv.join(" ")
+                );
             }
-        });
-    }
-
-    let t0 = Instant::now();
-    tt.new_search();
-    let result = iterative_deepening_root_with_tt(
-        &mut board,
-        &conductor,
-        Some(&book),
-        &tt,
-        params.max_depth,
-        is_white,
-        None, // no soft deadline — stop flag controls everything
-        Some(search_stop),
-        num_threads,
-        Some(&|depth, score, nodes, ms| {
-            // score is from white's perspective; UCI expects engine's (side-to-move) perspective.
-            let engine_score = if is_white { score } else { -score };
-            let nps = if ms > 0 { nodes * 1000 / ms as u64 } else { nodes };
-            let score_str = format_score(engine_score);
-            println!("info depth {depth} score {score_str} nodes {nodes} nps {nps} time {ms}");
-            if engine_score >= MATE_SCORE_THRESHOLD {
-                println!("info string {}", borg_taunt(depth));
+            let mv_str = result.best_move.map(mv_to_uci).unwrap_or_else(|| "0000".to_string());
+            if let Some(p) = result.ponder_move.map(mv_to_uci) {
+                println!("bestmove {mv_str} ponder {p}");
+            } else {
+                println!("bestmove {mv_str}");
             }
-            let _ = io::stdout().flush();
-        }),
-        0,
-    );
-
-    let _ms = t0.elapsed().as_millis();
-    let mv_str = result.best_move.map(mv_to_uci).unwrap_or_else(|| "0000".to_string());
-    let ponder_str = result.ponder_move.map(mv_to_uci);
-    if let Some(ref p) = ponder_str {
-        println!("bestmove {mv_str} ponder {p}");
-    } else {
-        println!("bestmove {mv_str}");
+        }
     }
     let _ = io::stdout().flush();
 }
@@ -438,19 +304,14 @@ fn main() {
     // Weights are loaded lazily: EvalFile setoption takes priority.
     // Fallback to embedded bytes happens in the isready handler below.
 
-    let conductor = PieceConductor::new();
-    let book = OpeningBook::build(&conductor);
+    // The engine owns the persistent TT: it survives across moves so prior
+    // analysis is reused, and is cleared on `ucinewgame` / `Clear Hash`.
+    let mut engine = Engine::new();
     let mut board = ChessBoard::new();
     let mut move_number: usize = 1;
 
-    // TT size: default 96 MB (4M entries × 24 B).  Configurable via UCI Hash.
-    let mut hash_mb: usize = 96;
-    let entries_for_mb = |mb: usize| mb * 1024 * 1024 / 24;
-
-    // Persistent TT: survives across moves so the engine reuses prior search
-    // analysis.  `new_search()` is called before each search to age old entries.
-    // Cleared on `ucinewgame` or when Hash size changes.
-    let mut tt: Arc<TranspositionTable> = Arc::new(TranspositionTable::new(entries_for_mb(hash_mb)));
+    // TT size: default 96 MB (7.9M entries).  Configurable via UCI Hash.
+    engine.set_hash_mb(96);
 
     // Lazy SMP thread count.  Default = min(6, available logical CPUs).
     // 6 threads is the empirical sweet spot on the benchmark suite (depth 7).
@@ -458,11 +319,7 @@ fn main() {
     // the number of logical CPUs to avoid over-subscription.
     let max_threads = chess_evaluation::available_threads();
     let default_threads = max_threads.min(6);
-    let mut num_threads: usize = default_threads;
-
-    let stop_flag = Arc::new(AtomicBool::new(false));
-    let ponderhit_flag = Arc::new(AtomicBool::new(false));
-    let mut search_handle: Option<thread::JoinHandle<()>> = None;
+    engine.set_threads(default_threads);
 
     let stdin = io::stdin();
     for line in stdin.lock().lines() {
@@ -477,39 +334,80 @@ fn main() {
                 println!("id author {AUTHOR}");
                 println!("option name Threads type spin default {default_threads} min 1 max {max_threads}");
                 println!("option name Hash type spin default 96 min 1 max 65536");
+                println!("option name Clear Hash type button");
                 println!("option name Ponder type check default true");
+                println!("option name Deterministic type check default false");
+                println!("option name Contempt type spin default 0 min -200 max 200");
+                println!("option name UCI_AnalyseMode type check default false");
+                println!(
+                    "option name AnalysisRefresh type spin default {} min 10 max 10000",
+                    DEFAULT_ANALYSIS_REFRESH.as_millis()
+                );
+                println!("option name Skill Level type spin default {MAX_SKILL_LEVEL} min 0 max {MAX_SKILL_LEVEL}");
+                println!("option name UCI_LimitStrength type check default false");
+                println!("option name UCI_Elo type spin default {MAX_ELO} min {MIN_ELO} max {MAX_ELO}");
                 println!("option name EvalFile type string default <empty>");
                 println!("option name NeuralEval type check default false");
                 println!("option name NeuralConfidence type string default 0.0");
+                if let Some(hash) = net_hash() {
+                    println!("info string NNUE net {hash}");
+                }
                 println!("uciok");
             }
             "setoption" => {
-                // setoption name <name> value <value>
-                if let (Some(name_pos), Some(val_pos)) = (
-                    tokens.iter().position(|&t| t == "name"),
-                    tokens.iter().position(|&t| t == "value"),
-                ) {
-                    let name: String = tokens[name_pos + 1..val_pos].join(" ");
-                    let value = tokens.get(val_pos + 1).unwrap_or(&"");
+                // setoption name <name> [value <value>]  (buttons carry no value)
+                if let Some(name_pos) = tokens.iter().position(|&t| t == "name") {
+                    let val_pos = tokens.iter().position(|&t| t == "value");
+                    let name: String = tokens[name_pos + 1..val_pos.unwrap_or(tokens.len())].join(" ");
+                    let value = val_pos.and_then(|p| tokens.get(p + 1)).unwrap_or(&"");
                     match name.to_lowercase().as_str() {
                         "threads" => {
                             if let Ok(n) = value.parse::<usize>() {
-                                num_threads = n.max(1).min(max_threads);
+                                engine.set_threads(n.min(max_threads));
                             }
                         }
                         "hash" => {
                             if let Ok(mb) = value.parse::<usize>() {
-                                let mb = mb.max(1).min(65536);
-                                if mb != hash_mb {
-                                    hash_mb = mb;
-                                    tt = Arc::new(TranspositionTable::new(entries_for_mb(hash_mb)));
-                                }
+                                engine.set_hash_mb(mb.min(65536));
+                            }
+                        }
+                        "clear hash" => engine.clear_hash(),
+                        "deterministic" => {
+                            // Reproducible multi-threaded search for regression bisects.  Only
+                            // exact for depth-limited searches: a clock-based stop is racy.
+                            engine.set_deterministic(value.eq_ignore_ascii_case("true"));
+                        }
+                        "contempt" => {
+                            // Centipawns, for the side the engine plays.
+                            if let Ok(cp) = value.parse::<i32>() {
+                                engine.set_contempt(cp.clamp(-200, 200));
+                            }
+                        }
+                        "uci_analysemode" => engine.set_analyse_mode(value.eq_ignore_ascii_case("true")),
+                        "analysisrefresh" => {
+                            // Non-standard: milliseconds between analysis-mode PV lines.
+                            if let Ok(ms) = value.parse::<u64>() {
+                                engine.set_analysis_refresh(Duration::from_millis(ms.clamp(10, 10_000)));
+                            }
+                        }
+                        "skill level" => {
+                            if let Ok(level) = value.parse::<i32>() {
+                                engine.set_skill_level(level);
+                            }
+                        }
+                        "uci_limitstrength" => engine.set_limit_strength(value.eq_ignore_ascii_case("true")),
+                        "uci_elo" => {
+                            if let Ok(elo) = value.parse::<i32>() {
+                                engine.set_elo(elo.clamp(MIN_ELO, MAX_ELO));
                             }
                         }
                         "evalfile" => {
                             if !value.is_empty() && *value != "<empty>" {
                                 match init_neural_eval(value) {
-                                    Ok(()) => eprintln!("info string Loaded neural weights from {value}"),
+                                    Ok(()) => eprintln!(
+                                        "info string Loaded neural weights from {value} (net {})",
+                                        neural_net_hash().unwrap_or_default()
+                                    ),
                                     Err(e) => eprintln!("info string Failed to load neural weights: {e}"),
                                 }
                             }
@@ -547,58 +445,67 @@ fn main() {
                 println!("readyok");
             }
             "ucinewgame" => {
-                // Wait for any ongoing search to finish first
-                stop_flag.store(true, Ordering::Release);
-                if let Some(h) = search_handle.take() { let _ = h.join(); }
-                stop_flag.store(false, Ordering::Release);
+                // Stops any ongoing search and clears the TT: new game → old
+                // analysis is irrelevant.  In analysis mode the clear is
+                // skipped if the next position continues the last line.
+                engine.new_game();
                 board = ChessBoard::new();
                 move_number = 1;
-                // Clear TT: new game → old analysis is irrelevant.
-                tt = Arc::new(TranspositionTable::new(entries_for_mb(hash_mb)));
             }
             "position" => {
-                move_number = apply_position(&mut board, &conductor, &tokens[1..]);
+                move_number = apply_position(&mut board, engine.conductor(), &tokens[1..]);
+                engine.set_position(board.clone());
             }
             "go" => {
-                // Stop any previous search
-                if let Some(h) = search_handle.take() {
-                    stop_flag.store(true, Ordering::Release);
-                    let _ = h.join();
-                }
-                stop_flag.store(false, Ordering::Release);
-
-                let is_ponder = tokens.contains(&"ponder");
+                // Starting a search stops any previous one.
                 let is_white = board.is_white_active();
-                let params = parse_go(&tokens[1..], is_white, move_number);
-
-                let board_c     = board.clone();
-                let conductor_c = conductor.clone();
-                let book_c      = book.clone();
-                let stop_c      = Arc::clone(&stop_flag);
-                let tt_c        = Arc::clone(&tt);
-
-                let threads = num_threads;
-                if is_ponder {
-                    ponderhit_flag.store(false, Ordering::Release);
-                    let ponder_c = Arc::clone(&ponderhit_flag);
-                    search_handle = Some(thread::spawn(move || {
-                        ponder_and_respond(board_c, conductor_c, book_c, params, stop_c, ponder_c, is_white, tt_c, threads);
-                    }));
-                } else {
-                    search_handle = Some(thread::spawn(move || {
-                        search_and_respond(board_c, conductor_c, book_c, params, stop_c, is_white, tt_c, threads);
-                    }));
-                }
+                let limits = parse_go(&tokens[1..], is_white, move_number);
+                let analyse = engine.analyse_mode();
+                engine.go(limits, move |event| print_search_event(event, is_white, analyse));
             }
             "ponderhit" => {
-                // Opponent played the predicted move — signal the ponder
-                // search to transition to a real timed search.
-                ponderhit_flag.store(true, Ordering::Release);
+                // Opponent played the predicted move — the ponder search
+                // continues as a real timed search.
+                engine.ponderhit();
             }
             "stop" => {
-                stop_flag.store(true, Ordering::Release);
-                if let Some(h) = search_handle.take() { let _ = h.join(); }
-                stop_flag.store(false, Ordering::Release);
+                // The search prints its own bestmove when it finishes.
+                engine.stop();
+            }
+            "savehash" => {
+                // Non-standard: savehash <file>.  Persists the TT so a long
+                // analysis can be resumed later with `loadhash`.
+                let path = tokens[1..].join(" ");
+                if path.is_empty() {
+                    println!("info string usage: savehash <file>");
+                } else {
+                    match engine.tt().save_to(&path) {
+                        Ok(()) => println!("info string Hash saved to {path}"),
+                        Err(e) => println!("info string Failed to save hash to {path}: {e}"),
+                    }
+                }
+            }
+            "loadhash" => {
+                // Non-standard: loadhash <file>.  Hash is set to the size
+                // the file was saved at.
+                let path = tokens[1..].join(" ");
+                if path.is_empty() {
+                    println!("info string usage: loadhash <file>");
+                } else {
+                    match engine.load_hash(path.as_ref()) {
+                        Ok(n) => println!("info string Hash loaded from {path} ({n} entries)"),
+                        Err(e) => println!("info string Failed to load hash from {path}: {e}"),
+                    }
+                }
+            }
+            "stats" => {
+                // Non-standard: counters accumulated since the previous `stats`.
+                #[cfg(feature = "search-stats")]
+                for line in chess_evaluation::take_search_stats().to_string().lines() {
+                    println!("info string {line}");
+                }
+                #[cfg(not(feature = "search-stats"))]
+                println!("info string search statistics not compiled in (build with --features search-stats)");
             }
             "quit" => break,
             _ => {}
@@ -608,6 +515,5 @@ fn main() {
     }
 
     // Clean shutdown
-    stop_flag.store(true, Ordering::Relaxed);
-    if let Some(h) = search_handle.take() { let _ = h.join(); }
+    engine.stop();
 }
@@ -0,0 +1,178 @@
+//! JavaScript bindings for `WebEngine`.
+//!
+//! ```js
+//! import init, { initThreadPool, ChessEngine } from './chess_web.js';
+//!
+//! await init();
+//! await initThreadPool(navigator.hardwareConcurrency);
+//! const engine = new ChessEngine();
+//! engine.set_position('startpos', ['e2e4']);
+//! engine.go({ movetime: 1000 }, (info) => {
+//!   if (info.type === 'bestmove') postMessage(info.bestmove);
+//! });
+//! ```
+//!
+//! Infos are plain objects: `{ type: 'info', depth, cp | mate, nodes, nps,
+//! time, pv }` per iteration, then one `{ type: 'bestmove', bestmove,
+//! ponder, cp | mate, nodes, pv }`; `uci` holds the same thing as a UCI line.
+//! The search threads cannot call into JavaScript, so infos are queued and
+//! handed to the callback from the calling worker every `POLL_INTERVAL_MS`.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use gloo_timers::future::TimeoutFuture;
+use js_sys::{Array, Function, Object, Reflect};
+use wasm_bindgen::prelude::*;
+
+use crate::{GoLimits, Info, Score, WebEngine};
+
+/// How often queued infos are delivered to the `go` callback.
+const POLL_INTERVAL_MS: u32 = 10;
+
+#[wasm_bindgen]
+pub struct ChessEngine {
+    inner: WebEngine,
+}
+
+#[wasm_bindgen]
+impl ChessEngine {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> ChessEngine {
+        ChessEngine { inner: WebEngine::new() }
+    }
+
+    /// NNUE weights as the bytes of a net file (native format or `.npz`).
+    pub fn load_network(&mut self, bytes: &[u8]) -> Result<(), JsError> {
+        self.inner.load_network(bytes).map_err(|e| JsError::new(&e))
+    }
+
+    /// `'startpos'` or a FEN, then optional UCI moves.
+    pub fn set_position(&mut self, fen: &str, moves: Option<Vec<String>>) -> Result<(), JsError> {
+        let moves = moves.unwrap_or_default();
+        let moves: Vec<&str> = moves.iter().map(String::as_str).collect();
+        self.inner.set_position(fen, &moves).map_err(|e| JsError::new(&e))
+    }
+
+    pub fn legal_moves(&self) -> Vec<String> {
+        self.inner.legal_moves()
+    }
+
+    pub fn new_game(&mut self) {
+        self.inner.new_game();
+    }
+
+    pub fn set_hash_mb(&mut self, mb: usize) {
+        self.inner.set_hash_mb(mb);
+    }
+
+    pub fn set_threads(&mut self, threads: usize) {
+        self.inner.set_threads(threads);
+    }
+
+    pub fn set_own_book(&mut self, own_book: bool) {
+        self.inner.set_own_book(own_book);
+    }
+
+    pub fn set_skill_level(&mut self, level: i32) {
+        self.inner.set_skill_level(level);
+    }
+
+    pub fn set_elo(&mut self, elo: Option<i32>) {
+        self.inner.set_elo(elo);
+    }
+
+    pub fn set_analyse_mode(&mut self, analyse_mode: bool) {
+        self.inner.set_analyse_mode(analyse_mode);
+    }
+
+    /// Start a search.  `limits` may set `depth`, `nodes`, `movetime` (ms)
+    /// and `mate`; without any the search runs until `stop`.
+    pub fn go(&mut self, limits: JsValue, on_info: Function) -> Result<(), JsError> {
+        let queue: Arc<Mutex<VecDeque<Info>>> = Arc::default();
+        let sink = Arc::clone(&queue);
+        self.inner
+            .go(limits_from_js(&limits), move |info| sink.lock().unwrap().push_back(info))
+            .map_err(|e| JsError::new(&e))?;
+
+        wasm_bindgen_futures::spawn_local(async move {
+            loop {
+                let batch: Vec<Info> = queue.lock().unwrap().drain(..).collect();
+                let mut finished = false;
+                for info in batch {
+                    finished |= matches!(info, Info::BestMove { .. });
+                    let _ = on_info.call1(&JsValue::NULL, &info_to_js(&info));
+                }
+                if finished {
+                    break;
+                }
+                TimeoutFuture::new(POLL_INTERVAL_MS).await;
+            }
+        });
+        Ok(())
+    }
+
+    /// Ask the search to stop; the `bestmove` info follows.
+    pub fn stop(&self) {
+        self.inner.stop();
+    }
+
+    pub fn is_searching(&mut self) -> bool {
+        self.inner.is_searching()
+    }
+}
+
+impl Default for ChessEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn limits_from_js(limits: &JsValue) -> GoLimits {
+    let field = |name: &str| {
+        if limits.is_object() {
+            Reflect::get(limits, &JsValue::from_str(name)).ok().and_then(|v| v.as_f64()).filter(|v| *v >= 0.0)
+        } else {
+            None
+        }
+    };
+    GoLimits {
+        depth: field("depth").map(|v| v as i32),
+        nodes: field("nodes").map(|v| v as u64),
+        movetime_ms: field("movetime").map(|v| v as u64),
+        mate: field("mate").map(|v| v as u32),
+    }
+}
+
+fn info_to_js(info: &Info) -> JsValue {
+    let obj = Object::new();
+    let set = |key: &str, value: JsValue| {
+        let _ = Reflect::set(&obj, &JsValue::from_str(key), &value);
+    };
+    let set_score = |score: &Score| match score {
+        Score::Cp(cp) => set("cp", (*cp).into()),
+        Score::Mate(n) => set("mate", (*n).into()),
+    };
+    let pv_array = |pv: &[String]| pv.iter().map(|m| JsValue::from_str(m)).collect::<Array>();
+    match info {
+        Info::Depth { depth, score, nodes, nps, time_ms, pv } => {
+            set("type", "info".into());
+            set("depth", (*depth).into());
+            set_score(score);
+            set("nodes", (*nodes as f64).into());
+            set("nps", (*nps as f64).into());
+            set("time", (*time_ms as f64).into());
+            set("pv", pv_array(pv).into());
+        }
+        Info::BestMove { best_move, ponder, score, nodes, pv } => {
+            set("type", "bestmove".into());
+            set("bestmove", best_move.as_deref().map_or(JsValue::NULL, JsValue::from_str));
+            set("ponder", ponder.as_deref().map_or(JsValue::NULL, JsValue::from_str));
+            set_score(score);
+            set("nodes", (*nodes as f64).into());
+            set("pv", pv_array(pv).into());
+        }
+    }
+    set("uci", info.to_uci().into());
+    obj.into()
+}
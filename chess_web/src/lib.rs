@@ -0,0 +1,459 @@
+//! The engine for web pages and other embedders, without the Bevy app.
+//!
+//! `WebEngine` is a thin string-in, string-out layer over
+//! `chess_evaluation::Engine`: positions are a FEN plus UCI moves, limits a
+//! plain struct, and progress arrives as `Info` values carrying UCI move
+//! strings and side-to-move scores, much like a UCI front end would see.
+//! On wasm32 the `bindings` module exposes it to JavaScript as `ChessEngine`;
+//! natively the same API is what the tests drive.
+//!
+//! Build for the browser with `./build_web.sh chess_web`.  The module is
+//! meant to run inside a web worker: the page awaits `initThreadPool(n)`
+//! first, which starts the rayon workers the search runs on (as in the
+//! `chess` app), and a new `go` waits for the previous search to wind down,
+//! which the browser's main thread is not allowed to do.
+
+use std::time::Duration;
+
+use chess_board::ChessBoard;
+use chess_evaluation::epd::validate_fen;
+use chess_evaluation::{
+    init_neural_eval_from_bytes, is_neural_eval_initialized, set_neural_eval_enabled, Engine,
+    SearchEvent, SearchLimits, SearchResult,
+};
+use chess_foundation::{piece::PieceType, ChessMove};
+use move_generator::move_generator::get_all_legal_moves_for_color;
+
+#[cfg(target_arch = "wasm32")]
+mod bindings;
+
+#[cfg(target_arch = "wasm32")]
+pub use wasm_bindgen_rayon::init_thread_pool;
+
+const MATE_SCORE_THRESHOLD: i32 = 999_000;
+
+/// What `go` may spend.  With nothing set the search runs until `stop`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GoLimits {
+    pub depth: Option<i32>,
+    /// No new iteration is started past this many nodes.
+    pub nodes: Option<u64>,
+    pub movetime_ms: Option<u64>,
+    /// Look for a mate in at most this many moves first.
+    pub mate: Option<u32>,
+}
+
+impl GoLimits {
+    fn to_search_limits(self) -> SearchLimits {
+        let base = match self.movetime_ms {
+            Some(ms) => SearchLimits::movetime(Duration::from_millis(ms)),
+            None => SearchLimits::infinite(),
+        };
+        SearchLimits { depth: self.depth, nodes: self.nodes, mate: self.mate, ..base }
+    }
+}
+
+/// A score from the side to move's point of view, as in UCI.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Score {
+    Cp(i32),
+    /// Mate in this many moves; negative when the side to move is mated.
+    Mate(i32),
+}
+
+impl Score {
+    /// Convert a white-relative engine score.
+    fn from_engine(score: i32, is_white: bool) -> Self {
+        let score = if is_white { score } else { -score };
+        if score.abs() >= MATE_SCORE_THRESHOLD {
+            let moves = (1_000_000 - score.abs() + 1) / 2;
+            Score::Mate(if score > 0 { moves } else { -moves })
+        } else {
+            Score::Cp(score)
+        }
+    }
+}
+
+/// Progress of a search, delivered to the `go` callback.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Info {
+    /// An iteration completed.  `pv` is empty outside analysis mode; in
+    /// analysis mode the line is also re-sent every refresh interval.
+    Depth { depth: i32, score: Score, nodes: u64, nps: u64, time_ms: u64, pv: Vec<String> },
+    /// The search is over.  Always the last info of a `go`; `best_move` is
+    /// `None` only when the side to move has no legal move.
+    BestMove { best_move: Option<String>, ponder: Option<String>, score: Score, nodes: u64, pv: Vec<String> },
+}
+
+impl Info {
+    fn from_result(result: SearchResult, is_white: bool) -> Self {
+        Info::BestMove {
+            best_move: result.best_move.map(mv_to_uci),
+            ponder: result.ponder_move.map(mv_to_uci),
+            score: Score::from_engine(result.score, is_white),
+            nodes: result.total_nodes,
+            pv: result.pv.into_iter().map(mv_to_uci).collect(),
+        }
+    }
+
+    /// The equivalent UCI output line, `info ...` or `bestmove ...`.
+    pub fn to_uci(&self) -> String {
+        let score = |s: &Score| match s {
+            Score::Cp(cp) => format!("cp {cp}"),
+            Score::Mate(n) => format!("mate {n}"),
+        };
+        match self {
+            Info::Depth { depth, score: s, nodes, nps, time_ms, pv } => {
+                let mut line = format!("info depth {depth} score {} nodes {nodes} nps {nps} time {time_ms}", score(s));
+                if !pv.is_empty() {
+                    line += &format!(" pv {}", pv.join(" "));
+                }
+                line
+            }
+            Info::BestMove { best_move, ponder, .. } => {
+                let mut line = format!("bestmove {}", best_move.as_deref().unwrap_or("0000"));
+                if let Some(p) = ponder {
+                    line += &format!(" ponder {p}");
+                }
+                line
+            }
+        }
+    }
+}
+
+// ── Move helpers (mirrors chess_uci) ─────────────────────────────────────────
+
+fn sq_to_uci(sq: u16) -> String {
+    let file = (b'a' + (sq % 8) as u8) as char;
+    let rank = (b'1' + (sq / 8) as u8) as char;
+    format!("{}{}", file, rank)
+}
+
+fn mv_to_uci(mv: ChessMove) -> String {
+    let promo = mv
+        .promotion_piece_type()
+        .map(|pt| match pt {
+            PieceType::Queen => "q",
+            PieceType::Rook => "r",
+            PieceType::Bishop => "b",
+            PieceType::Knight => "n",
+            _ => "q",
+        })
+        .unwrap_or("");
+    format!("{}{}{}", sq_to_uci(mv.start_square()), sq_to_uci(mv.target_square()), promo)
+}
+
+fn parse_uci_move(uci: &str, legal: &[ChessMove]) -> Option<ChessMove> {
+    let b = uci.as_bytes();
+    if b.len() < 4 || b.len() > 5 {
+        return None;
+    }
+    let ff = b[0].wrapping_sub(b'a') as u16;
+    let fr = b[1].wrapping_sub(b'1') as u16;
+    let tf = b[2].wrapping_sub(b'a') as u16;
+    let tr = b[3].wrapping_sub(b'1') as u16;
+    if ff > 7 || fr > 7 || tf > 7 || tr > 7 {
+        return None;
+    }
+    let from = fr * 8 + ff;
+    let to = tr * 8 + tf;
+    let promo = b.get(4).copied().map(|c| c as char);
+
+    legal
+        .iter()
+        .find(|m| {
+            m.start_square() == from
+                && m.target_square() == to
+                && match promo {
+                    None => !m.is_promotion(),
+                    Some('q') => m.has_flag(ChessMove::PROMOTE_TO_QUEEN_FLAG),
+                    Some('r') => m.has_flag(ChessMove::PROMOTE_TO_ROOK_FLAG),
+                    Some('b') => m.has_flag(ChessMove::PROMOTE_TO_BISHOP_FLAG),
+                    Some('n') => m.has_flag(ChessMove::PROMOTE_TO_KNIGHT_FLAG),
+                    _ => false,
+                }
+        })
+        .copied()
+}
+
+// ── Engine ───────────────────────────────────────────────────────────────────
+
+/// An engine driven with FENs and UCI move strings.
+///
+/// ```no_run
+/// use chess_web::{GoLimits, Info, WebEngine};
+///
+/// let mut engine = WebEngine::new();
+/// engine.set_position("startpos", &["e2e4", "e7e5"]).unwrap();
+/// engine
+///     .go(GoLimits { depth: Some(8), ..GoLimits::default() }, |info| println!("{}", info.to_uci()))
+///     .unwrap();
+/// engine.wait();
+/// ```
+pub struct WebEngine {
+    engine: Engine,
+    /// Side to move of the search in flight, for side-to-move scores.
+    searching_white: bool,
+}
+
+impl WebEngine {
+    /// An engine with the built-in opening book.
+    pub fn new() -> Self {
+        Self::from_engine(Engine::new())
+    }
+
+    /// Wrap an engine that is already set up.
+    pub fn from_engine(engine: Engine) -> Self {
+        Self { engine, searching_white: true }
+    }
+
+    /// Load NNUE weights (net file or `.npz` bytes) and switch the evaluation to them.
+    /// Weights can only be loaded once per process.
+    pub fn load_network(&mut self, bytes: &[u8]) -> Result<(), String> {
+        init_neural_eval_from_bytes(bytes)?;
+        set_neural_eval_enabled(true);
+        Ok(())
+    }
+
+    /// Set the position for the next `go`: `"startpos"` or a FEN, then
+    /// `moves` in UCI notation.  On error the position is left unchanged.
+    pub fn set_position(&mut self, fen: &str, moves: &[&str]) -> Result<(), String> {
+        let mut board = ChessBoard::new();
+        if fen.trim() != "startpos" {
+            validate_fen(fen)?;
+            board.set_from_fen(fen.trim());
+        }
+        let conductor = self.engine.conductor();
+        for uci in moves {
+            let is_white = board.is_white_active();
+            let mut legal = Vec::new();
+            get_all_legal_moves_for_color(&mut board, conductor, is_white, &mut legal, &mut Vec::new());
+            let mut mv = parse_uci_move(uci, &legal).ok_or_else(|| format!("illegal move {uci:?}"))?;
+            board.make_move(&mut mv);
+        }
+        self.engine.set_position(board);
+        Ok(())
+    }
+
+    /// Legal moves in the current position, in UCI notation.
+    pub fn legal_moves(&self) -> Vec<String> {
+        let mut board = self.engine.board().clone();
+        let is_white = board.is_white_active();
+        let mut legal = Vec::new();
+        get_all_legal_moves_for_color(&mut board, self.engine.conductor(), is_white, &mut legal, &mut Vec::new());
+        legal.into_iter().map(mv_to_uci).collect()
+    }
+
+    /// Forget the game: start position, empty hash table.
+    pub fn new_game(&mut self) {
+        self.engine.new_game();
+    }
+
+    pub fn set_hash_mb(&mut self, mb: usize) {
+        self.engine.set_hash_mb(mb);
+    }
+
+    /// Lazy SMP threads, bounded in practice by the thread pool size.
+    pub fn set_threads(&mut self, threads: usize) {
+        self.engine.set_threads(threads);
+    }
+
+    pub fn set_own_book(&mut self, own_book: bool) {
+        self.engine.set_own_book(own_book);
+    }
+
+    /// `Skill Level` 0..=20; 20 is full strength.
+    pub fn set_skill_level(&mut self, level: i32) {
+        self.engine.set_skill_level(level);
+    }
+
+    /// Play at roughly this rating, or at `Skill Level` again with `None`.
+    pub fn set_elo(&mut self, elo: Option<i32>) {
+        self.engine.set_limit_strength(elo.is_some());
+        if let Some(elo) = elo {
+            self.engine.set_elo(elo);
+        }
+    }
+
+    /// Analysis mode: no book or strength limit, and `Depth` infos carry
+    /// the principal variation.
+    pub fn set_analyse_mode(&mut self, analyse_mode: bool) {
+        self.engine.set_analyse_mode(analyse_mode);
+    }
+
+    /// Start searching the current position.  `on_info` runs on a search
+    /// thread and receives `Depth` infos, then exactly one `BestMove`.
+    /// Fails only when the build needs a network and none is loaded.
+    pub fn go<F>(&mut self, limits: GoLimits, mut on_info: F) -> Result<(), String>
+    where
+        F: FnMut(Info) + Send + 'static,
+    {
+        if cfg!(feature = "nn-incremental") && !is_neural_eval_initialized() {
+            return Err("this build evaluates with a network only: call load_network first".into());
+        }
+        let is_white = self.engine.board().is_white_active();
+        let analyse = self.engine.analyse_mode();
+        self.searching_white = is_white;
+        self.engine.go(limits.to_search_limits(), move |event| {
+            let info = match event {
+                // In analysis mode every iteration is followed by its line.
+                SearchEvent::Depth { .. } if analyse => return,
+                SearchEvent::Depth { depth, score, nodes, elapsed_ms } => Info::Depth {
+                    depth,
+                    score: Score::from_engine(score, is_white),
+                    nodes,
+                    nps: nps(nodes, elapsed_ms),
+                    time_ms: elapsed_ms as u64,
+                    pv: Vec::new(),
+                },
+                SearchEvent::Pv { depth, score, nodes, elapsed_ms, pv } => Info::Depth {
+                    depth,
+                    score: Score::from_engine(score, is_white),
+                    nodes,
+                    nps: nps(nodes, elapsed_ms),
+                    time_ms: elapsed_ms as u64,
+                    pv: pv.into_iter().map(mv_to_uci).collect(),
+                },
+                SearchEvent::Finished(result) => Info::from_result(result, is_white),
+            };
+            on_info(info);
+        });
+        Ok(())
+    }
+
+    /// Ask the running search to stop.  Does not wait: the `BestMove` info
+    /// follows shortly on the search thread.
+    pub fn stop(&self) {
+        if let Some(stop) = self.engine.stop_handle() {
+            stop.store(true, std::sync::atomic::Ordering::Release);
+        }
+    }
+
+    /// Whether a search is still running.
+    pub fn is_searching(&mut self) -> bool {
+        self.engine.try_result().is_none() && self.engine.is_searching()
+    }
+
+    /// Block until the running search finishes and return its `BestMove`.
+    /// Not for the browser's main thread.
+    pub fn wait(&mut self) -> Option<Info> {
+        let is_white = self.searching_white;
+        self.engine.wait().map(|result| Info::from_result(result, is_white))
+    }
+}
+
+impl Default for WebEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn nps(nodes: u64, elapsed_ms: u128) -> u64 {
+    if elapsed_ms > 0 { nodes * 1000 / elapsed_ms as u64 } else { nodes }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use move_generator::piece_conductor::PieceConductor;
+    use std::sync::mpsc;
+
+    fn engine() -> WebEngine {
+        WebEngine::from_engine(Engine::from_parts(PieceConductor::new(), None))
+    }
+
+    fn depth(d: i32) -> GoLimits {
+        GoLimits { depth: Some(d), ..GoLimits::default() }
+    }
+
+    /// Run a search to the end and collect every info it sent.
+    fn run(engine: &mut WebEngine, limits: GoLimits) -> Vec<Info> {
+        let (tx, rx) = mpsc::channel();
+        engine.go(limits, move |info| tx.send(info).unwrap()).unwrap();
+        engine.wait();
+        rx.iter().collect()
+    }
+
+    #[test]
+    fn set_position_checks_fen_and_moves() {
+        let mut e = engine();
+        e.set_position("startpos", &["e2e4", "e7e5", "g1f3"]).unwrap();
+        assert!(!e.engine.board().is_white_active());
+        assert_eq!(e.legal_moves().len(), 29);
+
+        let hash = e.engine.board().current_hash();
+        assert!(e.set_position("startpos", &["e2e4", "e2e4"]).is_err());
+        assert!(e.set_position("8/8/8 w - - 0 1", &[]).is_err());
+        assert!(e.set_position("8/8/8/8/8/8/8/8 w - - 0 1", &[]).is_err());
+        assert_eq!(e.engine.board().current_hash(), hash, "a rejected position changes nothing");
+
+        e.set_position("4k3/P7/8/8/8/8/8/4K3 w - - 0 1", &["a7a8n"]).unwrap();
+        assert!(e.legal_moves().contains(&"e8d7".to_string()));
+    }
+
+    #[test]
+    fn go_streams_depth_infos_then_one_best_move() {
+        let mut e = engine();
+        e.set_position("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3", &[]).unwrap();
+        let infos = run(&mut e, depth(4));
+        let (last, iterations) = infos.split_last().unwrap();
+        let depths: Vec<i32> = iterations
+            .iter()
+            .map(|i| match i {
+                Info::Depth { depth, .. } => *depth,
+                other => panic!("unexpected {other:?}"),
+            })
+            .collect();
+        assert_eq!(depths, [1, 2, 3, 4]);
+        let Info::BestMove { best_move: Some(mv), .. } = last else { panic!("no best move: {last:?}") };
+        assert!(e.legal_moves().contains(mv));
+        assert!(last.to_uci().starts_with(&format!("bestmove {mv}")));
+    }
+
+    #[test]
+    fn scores_are_from_the_side_to_move() {
+        let mut e = engine();
+        // Black to move is a queen down.
+        e.set_position("4k3/8/8/8/8/8/8/3QK3 b - - 0 1", &[]).unwrap();
+        let infos = run(&mut e, depth(3));
+        let Some(Info::BestMove { score: Score::Cp(cp), .. }) = infos.last() else { panic!("{infos:?}") };
+        assert!(*cp < -500, "{cp}");
+
+        e.set_position("6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1", &[]).unwrap();
+        let infos = run(&mut e, depth(3));
+        let Some(Info::BestMove { score, best_move, .. }) = infos.last() else { panic!("{infos:?}") };
+        assert_eq!((*score, best_move.as_deref()), (Score::Mate(1), Some("d1d8")));
+    }
+
+    #[test]
+    fn analysis_infos_carry_the_line() {
+        let mut e = engine();
+        e.set_analyse_mode(true);
+        let infos = run(&mut e, depth(4));
+        let Some(Info::Depth { depth: 4, pv, .. }) = infos.iter().rev().nth(1) else { panic!("{infos:?}") };
+        assert!(!pv.is_empty());
+        assert!(infos[..infos.len() - 1].iter().all(|i| matches!(i, Info::Depth { pv, .. } if !pv.is_empty())));
+    }
+
+    #[test]
+    fn stop_ends_an_infinite_search() {
+        let mut e = engine();
+        let (tx, rx) = mpsc::channel();
+        e.go(GoLimits::default(), move |info| tx.send(info).unwrap()).unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(e.is_searching());
+        e.stop();
+        let best = rx.iter().find(|i| matches!(i, Info::BestMove { .. })).unwrap();
+        assert!(matches!(best, Info::BestMove { best_move: Some(_), .. }));
+        e.wait();
+        assert!(!e.is_searching());
+    }
+
+    #[test]
+    fn mate_scores_convert_like_uci() {
+        assert_eq!(Score::from_engine(1_000_000 - 3, true), Score::Mate(2));
+        assert_eq!(Score::from_engine(1_000_000 - 3, false), Score::Mate(-2));
+        assert_eq!(Score::from_engine(-(1_000_000 - 4), true), Score::Mate(-2));
+        assert_eq!(Score::from_engine(-35, false), Score::Cp(35));
+    }
+}
@@ -0,0 +1,264 @@
+//! Byte-level pieces of the format: varints and the compact position.
+//!
+//! A position is stored as
+//!
+//! ```text
+//! occupancy   u64 LE, bit a1 + 8·rank + file
+//! pieces      one nibble per occupied square, ascending, low nibble first:
+//!             0–5 PNBRQK, 6–11 pnbrqk; padded to a whole byte
+//! flags       u8: bit 0 black to move, bits 1–4 castling KQkq,
+//!             bit 5 en passant, bits 6–7 unused
+//! ep file     u8, only with the en passant bit
+//! halfmove    varint
+//! fullmove    varint
+//! ```
+//!
+//! 25–36 bytes for most positions, against 60–90 for the FEN.  Decoding
+//! gives back the FEN with the castling field in `KQkq` order.
+
+const PIECES: &[u8; 12] = b"PNBRQKpnbrqk";
+const CASTLING: &[u8; 4] = b"KQkq";
+
+const BLACK_TO_MOVE: u8 = 1;
+const EN_PASSANT: u8 = 1 << 5;
+
+pub fn put_varint(out: &mut Vec<u8>, mut v: u64) {
+    while v >= 0x80 {
+        out.push(v as u8 | 0x80);
+        v >>= 7;
+    }
+    out.push(v as u8);
+}
+
+pub fn zigzag(v: i64) -> u64 {
+    ((v << 1) ^ (v >> 63)) as u64
+}
+
+pub fn unzigzag(v: u64) -> i64 {
+    (v >> 1) as i64 ^ -((v & 1) as i64)
+}
+
+/// Reads from a decoded block.
+pub struct Bytes<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Bytes<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Bytes { buf, pos: 0 }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos == self.buf.len()
+    }
+
+    pub fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        let bytes = self.buf.get(self.pos..self.pos + n).ok_or("truncated block")?;
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn varint(&mut self) -> Result<u64, String> {
+        let mut v = 0u64;
+        for shift in (0..64).step_by(7) {
+            let b = self.u8()?;
+            v |= u64::from(b & 0x7f) << shift;
+            if b < 0x80 {
+                return Ok(v);
+            }
+        }
+        Err("varint too long".to_string())
+    }
+}
+
+/// Appends the compact form of `fen`.  Missing trailing fields default as
+/// in `FENParser`: white to move, no castling, no en passant, clocks 0 1.
+pub fn encode_position(out: &mut Vec<u8>, fen: &str) -> Result<(), String> {
+    let bad = |what: &str| format!("{what} in FEN \"{fen}\"");
+    let mut fields = fen.split_whitespace();
+    let placement = fields.next().ok_or_else(|| bad("no placement"))?;
+
+    let mut squares = [None; 64];
+    let ranks: Vec<&str> = placement.split('/').collect();
+    if ranks.len() != 8 {
+        return Err(bad("not 8 ranks"));
+    }
+    for (i, rank) in ranks.iter().enumerate() {
+        let mut file = 0;
+        for c in rank.bytes() {
+            match c {
+                b'1'..=b'8' => file += (c - b'0') as usize,
+                _ => {
+                    let piece = PIECES.iter().position(|&p| p == c).ok_or_else(|| bad("bad piece"))?;
+                    if file < 8 {
+                        squares[(7 - i) * 8 + file] = Some(piece as u8);
+                    }
+                    file += 1;
+                }
+            }
+        }
+        if file != 8 {
+            return Err(bad("rank not 8 squares"));
+        }
+    }
+
+    let black = match fields.next().unwrap_or("w") {
+        "w" => false,
+        "b" => true,
+        _ => return Err(bad("bad side to move")),
+    };
+    let mut flags = if black { BLACK_TO_MOVE } else { 0 };
+    match fields.next().unwrap_or("-") {
+        "-" => {}
+        castling => {
+            for c in castling.bytes() {
+                let right = CASTLING.iter().position(|&r| r == c).ok_or_else(|| bad("bad castling"))?;
+                flags |= 2 << right;
+            }
+        }
+    }
+    let ep = match fields.next().unwrap_or("-").as_bytes() {
+        b"-" => None,
+        &[file @ b'a'..=b'h', rank] if rank == if black { b'3' } else { b'6' } => Some(file - b'a'),
+        _ => return Err(bad("bad en passant square")),
+    };
+    let mut clock = |default: u64| fields.next().map_or(Ok(default), |f| f.parse().map_err(|_| bad("bad clock")));
+    let (halfmove, fullmove) = (clock(0)?, clock(1)?);
+
+    let occupancy = squares.iter().enumerate().filter(|(_, p)| p.is_some()).fold(0u64, |o, (sq, _)| o | 1 << sq);
+    out.extend_from_slice(&occupancy.to_le_bytes());
+    for pair in squares.iter().flatten().collect::<Vec<_>>().chunks(2) {
+        out.push(pair[0] | pair.get(1).map_or(0, |&&p| p << 4));
+    }
+    match ep {
+        Some(file) => out.extend_from_slice(&[flags | EN_PASSANT, file]),
+        None => out.push(flags),
+    }
+    put_varint(out, halfmove);
+    put_varint(out, fullmove);
+    Ok(())
+}
+
+/// Reads a position written by `encode_position` back as a FEN.
+pub fn decode_position(bytes: &mut Bytes) -> Result<String, String> {
+    let occupancy = u64::from_le_bytes(bytes.take(8)?.try_into().unwrap());
+    let count = occupancy.count_ones() as usize;
+    let nibbles = bytes.take(count.div_ceil(2))?;
+    let piece = |i: usize| PIECES.get(usize::from(nibbles[i / 2] >> (4 * (i % 2)) & 0xf)).copied();
+
+    let mut fen = String::with_capacity(90);
+    for rank in (0..8).rev() {
+        let mut empty = 0;
+        for file in 0..8 {
+            let sq = rank * 8 + file;
+            if occupancy & 1 << sq == 0 {
+                empty += 1;
+                continue;
+            }
+            if empty > 0 {
+                fen.push((b'0' + empty) as char);
+                empty = 0;
+            }
+            // Nibbles are in square order, a1 first.
+            let below = (occupancy & ((1 << sq) - 1)).count_ones() as usize;
+            fen.push(piece(below).ok_or("bad piece code")? as char);
+        }
+        if empty > 0 {
+            fen.push((b'0' + empty) as char);
+        }
+        if rank > 0 {
+            fen.push('/');
+        }
+    }
+
+    let flags = bytes.u8()?;
+    let black = flags & BLACK_TO_MOVE != 0;
+    fen.push_str(if black { " b " } else { " w " });
+    let castling: String = (0..4).filter(|i| flags & 2 << i != 0).map(|i| CASTLING[i] as char).collect();
+    fen.push_str(if castling.is_empty() { "-" } else { &castling });
+    if flags & EN_PASSANT != 0 {
+        let file = bytes.u8()?;
+        if file >= 8 {
+            return Err("bad en passant file".to_string());
+        }
+        fen.push(' ');
+        fen.push((b'a' + file) as char);
+        fen.push(if black { '3' } else { '6' });
+    } else {
+        fen.push_str(" -");
+    }
+    let (halfmove, fullmove) = (bytes.varint()?, bytes.varint()?);
+    fen.push_str(&format!(" {halfmove} {fullmove}"));
+    Ok(fen)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(fen: &str) -> String {
+        let mut out = Vec::new();
+        encode_position(&mut out, fen).unwrap();
+        let mut bytes = Bytes::new(&out);
+        let back = decode_position(&mut bytes).unwrap();
+        assert!(bytes.is_empty());
+        back
+    }
+
+    #[test]
+    fn positions_round_trip() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+            "r3k2r/8/8/8/8/8/8/R3K2R b Kq - 17 140",
+            "8/8/8/8/8/8/8/K1k5 w - - 0 1",
+            "8/2P5/8/8/8/8/5p2/K1k5 b - - 99 1000000",
+        ] {
+            assert_eq!(round_trip(fen), fen);
+        }
+        assert_eq!(round_trip("r3k2r/8/8/8/8/8/8/R3K2R w qkQ -"), "r3k2r/8/8/8/8/8/8/R3K2R w Qkq - 0 1");
+
+        let mut out = Vec::new();
+        encode_position(&mut out, "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        assert_eq!(out.len(), 8 + 16 + 1 + 2);
+    }
+
+    #[test]
+    fn rejects_malformed_fens() {
+        for fen in [
+            "",
+            "8/8/8/8/8/8/8 w - - 0 1",
+            "8/8/8/8/8/8/8/K1k6 w - - 0 1",
+            "8/8/8/8/8/8/8/K1x5 w - - 0 1",
+            "8/8/8/8/8/8/8/K1k5 x - - 0 1",
+            "8/8/8/8/8/8/8/K1k5 w A - 0 1",
+            "8/8/8/8/8/8/8/K1k5 w - e3 0 1",
+            "8/8/8/8/8/8/8/K1k5 w - - x 1",
+        ] {
+            assert!(encode_position(&mut Vec::new(), fen).is_err(), "{fen}");
+        }
+        assert!(decode_position(&mut Bytes::new(&[0xff; 8])).is_err());
+    }
+
+    #[test]
+    fn varints() {
+        let mut out = Vec::new();
+        let values = [0i64, 1, -1, 63, -64, 64, 1000, -1000, i32::MAX as i64, i32::MIN as i64];
+        for v in values {
+            put_varint(&mut out, zigzag(v));
+        }
+        assert_eq!(out[..3], [0, 2, 1]);
+        let mut bytes = Bytes::new(&out);
+        for v in values {
+            assert_eq!(unzigzag(bytes.varint().unwrap()), v);
+        }
+        assert!(bytes.is_empty());
+        assert!(Bytes::new(&[0x80]).varint().is_err());
+    }
+}
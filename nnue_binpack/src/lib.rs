@@ -0,0 +1,287 @@
+//! Compact storage for scored training positions.
+//!
+//! A binpack holds the positions of `{fen, cp, result}` JSONL at a few
+//! bytes each, by storing games rather than positions: a chain starts with
+//! a full position and continues with one byte per move, each position
+//! being the one the previous move leads to.  Scores along a chain are
+//! delta-coded, and chains are grouped into independently deflated blocks
+//! so readers can stream and shuffle without decoding a whole file.
+//!
+//! ```text
+//! file    "XCBPACK\n", version u32 LE, block*
+//! block   compressed length u32 LE, positions u32 LE, deflate(chain*)
+//! chain   start position (see `codec`), outcome u8, positions varint,
+//!         first score zigzag varint,
+//!         then per further position: move u8, zigzag(score + previous score) varint
+//! ```
+//!
+//! A move is its index among the legal moves sorted by their encoding.
+//! Scores are whole centipawns for the side to move; the outcome is the
+//! game's, stored for white, or unknown.  Unrelated positions deflate to
+//! about 15 bytes each; a game written in order takes about 2 per position
+//! once chained.  `datagen` skips tactical positions, which splits its
+//! games into several chains: about 8 bytes per position, against 110 for
+//! its JSONL.
+
+use chess_board::ChessBoard;
+use chess_foundation::ChessMove;
+use move_generator::{move_generator::get_all_legal_moves_for_color, piece_conductor::PieceConductor};
+
+pub mod codec;
+mod reader;
+mod writer;
+
+pub use codec::{decode_position, encode_position};
+pub use reader::{count_positions, Reader, ShuffledReader};
+pub use writer::Writer;
+
+const MAGIC: [u8; 8] = *b"XCBPACK\n";
+const VERSION: u32 = 1;
+
+/// Uncompressed chain bytes per block.  Blocks are the unit of shuffling,
+/// so they stay small: about 25,000 chained positions.
+const BLOCK_BYTES: usize = 64 * 1024;
+
+/// Largest block readers accept, compressed or decoded.  A block ends with
+/// the chain that takes it past `BLOCK_BYTES`, so this leaves room for long
+/// games while keeping a forged header from allocating gigabytes.
+const MAX_BLOCK_BYTES: usize = 16 * BLOCK_BYTES;
+
+/// One training position.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub fen: String,
+    /// Centipawns for the side to move.
+    pub cp: i32,
+    /// Game result for the side to move: 1 win, 0.5 draw, 0 loss.
+    pub result: Option<f32>,
+}
+
+impl Entry {
+    /// Parses a JSONL record: `fen` and `cp` (rounded to whole centipawns),
+    /// `result` if present.  Other fields, such as `wdl`, are dropped.
+    pub fn from_json(line: &str) -> Result<Self, String> {
+        let v: serde_json::Value = serde_json::from_str(line).map_err(|e| format!("JSON error: {e}"))?;
+        let fen = v["fen"].as_str().ok_or("missing fen")?.to_string();
+        let cp = v["cp"].as_f64().ok_or("missing cp")?.round() as i32;
+        let result = match &v["result"] {
+            serde_json::Value::Null => None,
+            r => Some(r.as_f64().ok_or("result is not a number")? as f32),
+        };
+        Ok(Entry { fen, cp, result })
+    }
+
+    pub fn to_json(&self) -> String {
+        let result = self.result.map_or_else(String::new, |r| format!(", \"result\": {r}"));
+        format!("{{\"fen\": {}, \"cp\": {}{result}}}", serde_json::Value::from(self.fen.as_str()), self.cp)
+    }
+}
+
+/// Game result as stored: for white.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+enum Outcome {
+    Unknown = 0,
+    WhiteWins = 1,
+    Draw = 2,
+    BlackWins = 3,
+}
+
+impl Outcome {
+    fn from_entry(result: Option<f32>, white: bool) -> Result<Self, String> {
+        let Some(r) = result else { return Ok(Outcome::Unknown) };
+        if r == 0.5 {
+            Ok(Outcome::Draw)
+        } else if r == 1.0 || r == 0.0 {
+            Ok(if (r == 1.0) == white { Outcome::WhiteWins } else { Outcome::BlackWins })
+        } else {
+            Err(format!("result {r} is not 1, 0.5 or 0"))
+        }
+    }
+
+    fn from_code(code: u8) -> Result<Self, String> {
+        [Outcome::Unknown, Outcome::WhiteWins, Outcome::Draw, Outcome::BlackWins]
+            .get(code as usize)
+            .copied()
+            .ok_or_else(|| format!("bad outcome {code}"))
+    }
+
+    fn for_side(self, white: bool) -> Option<f32> {
+        match self {
+            Outcome::Unknown => None,
+            Outcome::Draw => Some(0.5),
+            Outcome::WhiteWins => Some(if white { 1.0 } else { 0.0 }),
+            Outcome::BlackWins => Some(if white { 0.0 } else { 1.0 }),
+        }
+    }
+}
+
+/// Legal moves in the order move indices refer to.
+fn sorted_legal_moves(board: &mut ChessBoard, conductor: &PieceConductor) -> Vec<ChessMove> {
+    let white = board.is_white_active();
+    let mut legal = Vec::new();
+    get_all_legal_moves_for_color(board, conductor, white, &mut legal, &mut Vec::new());
+    legal.sort_unstable_by_key(ChessMove::value);
+    legal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    use chess_board::FENParser;
+    use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+    /// Random games with random scores and results, one entry per position.
+    fn random_games(games: usize, seed: u64) -> Vec<Vec<Entry>> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let conductor = PieceConductor::new();
+        (0..games)
+            .map(|_| {
+                let white_result = [Some(1.0), Some(0.5), Some(0.0), None][rng.gen_range(0..4)];
+                let mut board = ChessBoard::new();
+                let mut game = Vec::new();
+                for _ in 0..rng.gen_range(1..150) {
+                    let white = board.is_white_active();
+                    game.push(Entry {
+                        fen: FENParser::board_to_fen(&board),
+                        cp: rng.gen_range(-3000..3000),
+                        result: white_result.map(|r: f32| if white { r } else { 1.0 - r }),
+                    });
+                    let mut legal = Vec::new();
+                    get_all_legal_moves_for_color(&mut board, &conductor, white, &mut legal, &mut Vec::new());
+                    let Some(&mv) = legal.choose(&mut rng) else { break };
+                    board.make_move(&mut mv.clone());
+                }
+                game
+            })
+            .collect()
+    }
+
+    fn pack(entries: &[Entry]) -> (Vec<u8>, u64) {
+        let mut writer = Writer::new(Vec::new()).unwrap();
+        writer.block_bytes = 4096;
+        for entry in entries {
+            writer.push(entry).unwrap();
+        }
+        let (_, chains) = writer.stats();
+        (writer.finish().unwrap(), chains)
+    }
+
+    fn temp_file(name: &str, bytes: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("nnue_binpack_{name}_{}", std::process::id()));
+        std::fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    #[test]
+    fn games_chain_and_read_back() {
+        let games = random_games(40, 1);
+        let entries: Vec<Entry> = games.concat();
+        let (bytes, chains) = pack(&entries);
+        assert_eq!(chains, games.len() as u64, "every game is one chain");
+        assert!(
+            bytes.len() < 4 * entries.len(),
+            "{} bytes for {} positions",
+            bytes.len(),
+            entries.len()
+        );
+
+        let read: Vec<Entry> = Reader::new(bytes.as_slice()).unwrap().map(Result::unwrap).collect();
+        assert_eq!(read, entries);
+
+        // Unrelated positions are stored one per chain.
+        let mut shuffled = entries.clone();
+        shuffled.shuffle(&mut StdRng::seed_from_u64(2));
+        let (bytes, chains) = pack(&shuffled);
+        assert!(chains > shuffled.len() as u64 * 9 / 10);
+        let read: Vec<Entry> = Reader::new(bytes.as_slice()).unwrap().map(Result::unwrap).collect();
+        assert_eq!(read, shuffled);
+    }
+
+    #[test]
+    fn many_blocks_shuffle_deterministically() {
+        let entries: Vec<Entry> = random_games(80, 3).concat();
+        let (bytes, _) = pack(&entries);
+        let (first, second) = entries.split_at(entries.len() / 3);
+        let paths = [temp_file("all", &bytes), temp_file("first", &pack(first).0), temp_file("second", &pack(second).0)];
+        assert!(reader::scan_blocks(&paths[0], 0).unwrap().len() > 1, "test data fits in one block");
+        assert_eq!(count_positions(&paths[0]).unwrap(), entries.len() as u64);
+
+        let shuffle = |paths: &[PathBuf], seed| -> Vec<Entry> {
+            let reader = ShuffledReader::open(paths, 500, seed).unwrap();
+            assert_eq!(reader.len(), entries.len() as u64);
+            reader.map(Result::unwrap).collect()
+        };
+        let a = shuffle(&paths[..1], 7);
+        assert_ne!(a, entries);
+        assert_eq!(a, shuffle(&paths[..1], 7));
+        assert_ne!(a, shuffle(&paths[..1], 8));
+
+        let key = |e: &Entry| (e.fen.clone(), e.cp);
+        let mut sorted: Vec<_> = entries.iter().map(key).collect();
+        sorted.sort();
+        for got in [a, shuffle(&paths[1..], 7)] {
+            let mut got: Vec<_> = got.iter().map(key).collect();
+            got.sort();
+            assert_eq!(got, sorted);
+        }
+        for path in paths {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn json_and_damage() {
+        let entry = Entry::from_json(r#"{"fen": "8/8/8/8/8/8/8/K1k5 b - - 0 1", "cp": -12.6, "wdl": [1, 2, 997], "result": 0}"#)
+            .unwrap();
+        assert_eq!(entry, Entry { fen: "8/8/8/8/8/8/8/K1k5 b - - 0 1".into(), cp: -13, result: Some(0.0) });
+        assert_eq!(entry.to_json(), r#"{"fen": "8/8/8/8/8/8/8/K1k5 b - - 0 1", "cp": -13, "result": 0}"#);
+        assert_eq!(Entry::from_json(&Entry { result: None, ..entry.clone() }.to_json()).unwrap().result, None);
+        assert!(Entry::from_json(r#"{"fen": "8/8/8/8/8/8/8/K1k5 b - - 0 1"}"#).is_err());
+
+        let mut writer = Writer::new(Vec::new()).unwrap();
+        assert!(writer.push(&Entry { result: Some(0.25), ..entry.clone() }).is_err());
+        assert!(writer.push(&Entry { fen: "8/8 w - - 0 1".into(), ..entry.clone() }).is_err());
+
+        let (bytes, _) = pack(&random_games(3, 4).concat());
+        assert!(Reader::new(&bytes[..5]).is_err());
+        assert!(Reader::new(&b"XCBPACK\n\x02\0\0\0"[..]).is_err());
+        let truncated: Vec<_> = Reader::new(&bytes[..bytes.len() - 3]).unwrap().collect();
+        assert!(truncated.last().unwrap().is_err());
+        let mut corrupt = bytes.clone();
+        let last = corrupt.len() - 1;
+        corrupt[20..last].iter_mut().for_each(|b| *b ^= 0x5a);
+        assert!(Reader::new(corrupt.as_slice()).unwrap().any(|e| e.is_err()));
+    }
+
+    #[test]
+    fn forged_block_headers_are_rejected() {
+        let (bytes, _) = pack(&random_games(1, 5).concat());
+        let forge = |at: usize, value: u32| {
+            let mut forged = bytes.clone();
+            forged[at..at + 4].copy_from_slice(&value.to_le_bytes());
+            forged
+        };
+        let payload_len = u32::from_le_bytes(bytes[12..16].try_into().unwrap());
+        for (name, forged) in [
+            ("huge_len", forge(12, u32::MAX)),
+            ("long_len", forge(12, payload_len + 1)),
+            ("huge_count", forge(16, u32::MAX)),
+        ] {
+            let read: Vec<_> = Reader::new(forged.as_slice()).unwrap().collect();
+            assert!(matches!(read.as_slice(), [Err(_)]), "{name}");
+
+            let path = temp_file(name, &forged);
+            let shuffled = ShuffledReader::open(std::slice::from_ref(&path), 100, 0);
+            if name == "huge_count" {
+                assert!(shuffled.unwrap().any(|e| e.is_err()));
+            } else {
+                assert!(shuffled.is_err(), "{name}");
+                assert!(count_positions(&path).is_err(), "{name}");
+            }
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+}
@@ -0,0 +1,127 @@
+//! nnue_binpack — convert training positions between JSONL and binpacks.
+//!
+//! Usage:
+//!   cargo run -p nnue_binpack --release -- pack --input selfplay.jsonl --output selfplay.binpack
+//!   cargo run -p nnue_binpack --release -- unpack --input a.binpack --input b.binpack --output all.jsonl --shuffle
+//!   cargo run -p nnue_binpack --release -- info selfplay.binpack
+//!
+//! `pack` keeps `fen`, `cp` (rounded) and `result` of each record; consecutive
+//! records of one game are chained, so write games in order.  `unpack`
+//! writes the records back, in file order or with `--shuffle` through a
+//! buffer of `--buffer` positions.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use clap::{Parser, Subcommand};
+use nnue_binpack::{count_positions, Entry, Reader, ShuffledReader, Writer};
+
+#[derive(Parser)]
+#[command(about = "Convert NNUE training positions between JSONL and binpacks")]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// JSONL → binpack
+    Pack {
+        /// Input JSONL (repeatable, concatenated in order)
+        #[arg(long, required = true)]
+        input: Vec<PathBuf>,
+        #[arg(long)]
+        output: PathBuf,
+    },
+    /// Binpack → JSONL
+    Unpack {
+        /// Input binpack (repeatable)
+        #[arg(long, required = true)]
+        input: Vec<PathBuf>,
+        #[arg(long)]
+        output: PathBuf,
+        /// Write the positions in a random order
+        #[arg(long)]
+        shuffle: bool,
+        /// Positions held for shuffling
+        #[arg(long, default_value_t = 1_000_000)]
+        buffer: usize,
+        #[arg(long, default_value_t = 1)]
+        seed: u64,
+    },
+    /// Positions and size of binpacks
+    Info { files: Vec<PathBuf> },
+}
+
+fn fail(msg: impl std::fmt::Display) -> ! {
+    eprintln!("error: {msg}");
+    std::process::exit(1);
+}
+
+fn create(path: &Path) -> BufWriter<File> {
+    BufWriter::new(File::create(path).unwrap_or_else(|e| fail(format!("cannot create {}: {e}", path.display()))))
+}
+
+fn pack(inputs: &[PathBuf], output: &Path) {
+    let mut writer = Writer::new(create(output)).unwrap_or_else(|e| fail(e));
+    let mut read = 0u64;
+    for input in inputs {
+        let file = File::open(input).unwrap_or_else(|e| fail(format!("cannot open {}: {e}", input.display())));
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.unwrap_or_else(|e| fail(format!("{}: read error: {e}", input.display())));
+            if line.trim().is_empty() {
+                continue;
+            }
+            read += line.len() as u64 + 1;
+            let entry = Entry::from_json(&line).unwrap_or_else(|e| fail(format!("{}:{}: {e}", input.display(), i + 1)));
+            writer.push(&entry).unwrap_or_else(|e| fail(format!("{}:{}: {e}", input.display(), i + 1)));
+        }
+    }
+    let (positions, chains) = writer.stats();
+    writer.finish().unwrap_or_else(|e| fail(e));
+    let written = std::fs::metadata(output).map_or(0, |m| m.len());
+    eprintln!(
+        "{positions} positions in {chains} chains, {read} → {written} bytes ({:.2} bytes/position)",
+        written as f64 / positions.max(1) as f64
+    );
+}
+
+fn unpack(entries: impl Iterator<Item = Result<Entry, String>>, output: &Path) {
+    let mut out = create(output);
+    let mut n = 0u64;
+    for entry in entries {
+        let entry = entry.unwrap_or_else(|e| fail(e));
+        writeln!(out, "{}", entry.to_json()).unwrap_or_else(|e| fail(format!("cannot write {}: {e}", output.display())));
+        n += 1;
+    }
+    out.flush().unwrap_or_else(|e| fail(format!("cannot write {}: {e}", output.display())));
+    eprintln!("wrote {n} positions to {}", output.display());
+}
+
+fn main() {
+    let t0 = Instant::now();
+    match Args::parse().command {
+        Command::Pack { input, output } => pack(&input, &output),
+        Command::Unpack { input, output, shuffle: true, buffer, seed } => {
+            unpack(ShuffledReader::open(&input, buffer, seed).unwrap_or_else(|e| fail(e)), &output)
+        }
+        Command::Unpack { input, output, .. } => {
+            let readers = input.iter().map(|p| Reader::open(p).unwrap_or_else(|e| fail(e)));
+            unpack(readers.flatten(), &output)
+        }
+        Command::Info { files } => {
+            for path in files {
+                let positions = count_positions(&path).unwrap_or_else(|e| fail(e));
+                let bytes = std::fs::metadata(&path).map_or(0, |m| m.len());
+                println!(
+                    "{}: {positions} positions, {bytes} bytes ({:.2} bytes/position)",
+                    path.display(),
+                    bytes as f64 / positions.max(1) as f64
+                );
+            }
+        }
+    }
+    eprintln!("done in {:.1}s", t0.elapsed().as_secs_f64());
+}
@@ -0,0 +1,269 @@
+//! Reading binpacks: in file order, or shuffled through a buffer.
+
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use chess_board::{ChessBoard, FENParser};
+use flate2::read::DeflateDecoder;
+use move_generator::piece_conductor::PieceConductor;
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+use crate::codec::{unzigzag, Bytes};
+use crate::{decode_position, sorted_legal_moves, Entry, Outcome, MAGIC, MAX_BLOCK_BYTES, VERSION};
+
+/// Checks the file header.
+fn read_header(input: &mut impl Read) -> Result<(), String> {
+    let mut header = [0u8; 12];
+    input.read_exact(&mut header).map_err(|_| "not a binpack: no header".to_string())?;
+    if header[..8] != MAGIC {
+        return Err("not a binpack: bad magic".to_string());
+    }
+    let version = u32::from_le_bytes(header[8..].try_into().unwrap());
+    if version != VERSION {
+        return Err(format!("binpack version {version}, expected {VERSION}"));
+    }
+    Ok(())
+}
+
+/// Reads the next block header: compressed length and position count, or
+/// `None` at the end of the file.  Lengths over `MAX_BLOCK_BYTES` are
+/// rejected here, before anything is allocated for them.
+fn read_block_header(input: &mut impl Read) -> Result<Option<(usize, u32)>, String> {
+    let mut header = [0u8; 8];
+    let mut filled = 0;
+    while filled < header.len() {
+        match input.read(&mut header[filled..]) {
+            Ok(0) if filled == 0 => return Ok(None),
+            Ok(0) => return Err("truncated block header".to_string()),
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(format!("read error: {e}")),
+        }
+    }
+    let len = u32::from_le_bytes(header[..4].try_into().unwrap()) as usize;
+    if len > MAX_BLOCK_BYTES {
+        return Err(format!("block of {len} bytes, at most {MAX_BLOCK_BYTES} expected"));
+    }
+    Ok(Some((len, u32::from_le_bytes(header[4..].try_into().unwrap()))))
+}
+
+/// Decodes the `count` positions of one compressed block.
+fn decode_block(payload: &[u8], count: u32, board: &mut ChessBoard, conductor: &PieceConductor) -> Result<Vec<Entry>, String> {
+    let mut data = Vec::new();
+    DeflateDecoder::new(payload)
+        .take(MAX_BLOCK_BYTES as u64 + 1)
+        .read_to_end(&mut data)
+        .map_err(|e| format!("corrupt block: {e}"))?;
+    if data.len() > MAX_BLOCK_BYTES {
+        return Err(format!("block decodes to over {MAX_BLOCK_BYTES} bytes"));
+    }
+    // Every position after a chain's first takes at least two bytes.
+    if count as usize > data.len() / 2 {
+        return Err(format!("block of {} bytes cannot hold the {count} positions its header says", data.len()));
+    }
+    let mut bytes = Bytes::new(&data);
+    let mut entries = Vec::with_capacity(count as usize);
+    while !bytes.is_empty() {
+        let fen = decode_position(&mut bytes)?;
+        let outcome = Outcome::from_code(bytes.u8()?)?;
+        let len = bytes.varint()?;
+        let mut cp = unzigzag(bytes.varint()?) as i32;
+        board.set_from_fen(&fen);
+        entries.push(Entry { fen, cp, result: outcome.for_side(board.is_white_active()) });
+        for _ in 1..len {
+            let index = bytes.u8()? as usize;
+            cp = (unzigzag(bytes.varint()?) - i64::from(cp)) as i32;
+            let mut mv = *sorted_legal_moves(board, conductor).get(index).ok_or("bad move index")?;
+            board.make_move(&mut mv);
+            let fen = FENParser::board_to_fen(board);
+            entries.push(Entry { fen, cp, result: outcome.for_side(board.is_white_active()) });
+        }
+    }
+    if entries.len() != count as usize {
+        return Err(format!("block holds {} positions, header says {count}", entries.len()));
+    }
+    Ok(entries)
+}
+
+/// Reads the entries of a binpack in the order they were written.
+pub struct Reader<R: Read> {
+    input: R,
+    entries: std::vec::IntoIter<Entry>,
+    board: ChessBoard,
+    conductor: PieceConductor,
+    done: bool,
+}
+
+impl Reader<BufReader<File>> {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("cannot open {}: {e}", path.display()))?;
+        Reader::new(BufReader::new(file)).map_err(|e| format!("{}: {e}", path.display()))
+    }
+}
+
+impl<R: Read> Reader<R> {
+    pub fn new(mut input: R) -> Result<Self, String> {
+        read_header(&mut input)?;
+        Ok(Reader {
+            input,
+            entries: Vec::new().into_iter(),
+            board: ChessBoard::new(),
+            conductor: PieceConductor::new(),
+            done: false,
+        })
+    }
+
+    fn next_block(&mut self) -> Result<bool, String> {
+        let Some((len, count)) = read_block_header(&mut self.input)? else { return Ok(false) };
+        // Read what is there rather than allocating what the header claims.
+        let mut payload = Vec::new();
+        (&mut self.input).take(len as u64).read_to_end(&mut payload).map_err(|e| format!("read error: {e}"))?;
+        if payload.len() < len {
+            return Err("truncated block".to_string());
+        }
+        self.entries = decode_block(&payload, count, &mut self.board, &self.conductor)?.into_iter();
+        Ok(true)
+    }
+}
+
+impl<R: Read> Iterator for Reader<R> {
+    type Item = Result<Entry, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(entry) = self.entries.next() {
+                return Some(Ok(entry));
+            }
+            if self.done {
+                return None;
+            }
+            match self.next_block() {
+                Ok(true) => {}
+                Ok(false) => self.done = true,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+/// A block of a binpack file: where it starts and how many positions it holds.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct BlockRef {
+    file: usize,
+    offset: u64,
+    len: usize,
+    count: u32,
+}
+
+/// Block headers of `path`, without decoding the blocks.  Fails if a block
+/// runs past the end of the file.
+pub(crate) fn scan_blocks(path: &Path, file: usize) -> Result<Vec<BlockRef>, String> {
+    let ctx = |e: String| format!("{}: {e}", path.display());
+    let file_len = std::fs::metadata(path).map_err(|e| format!("cannot open {}: {e}", path.display()))?.len();
+    let mut input = BufReader::new(File::open(path).map_err(|e| format!("cannot open {}: {e}", path.display()))?);
+    read_header(&mut input).map_err(ctx)?;
+    let mut offset = 12u64;
+    let mut blocks = Vec::new();
+    while let Some((len, count)) = read_block_header(&mut input).map_err(ctx)? {
+        if offset + 8 + len as u64 > file_len {
+            return Err(ctx("truncated block".to_string()));
+        }
+        blocks.push(BlockRef { file, offset: offset + 8, len, count });
+        input.seek_relative(len as i64).map_err(|e| ctx(format!("read error: {e}")))?;
+        offset += 8 + len as u64;
+    }
+    Ok(blocks)
+}
+
+/// Positions in a binpack, from its block headers.
+pub fn count_positions(path: &Path) -> Result<u64, String> {
+    Ok(scan_blocks(path, 0)?.iter().map(|b| u64::from(b.count)).sum())
+}
+
+/// Streams the entries of several binpacks in a random order: blocks are
+/// visited in a seeded random order and their positions drawn at random
+/// from a buffer of about `buffer` positions.  Positions of one game stay
+/// within a few buffer lengths of each other; a buffer of a few hundred
+/// thousand breaks games up well, in memory proportional to it.
+pub struct ShuffledReader {
+    paths: Vec<PathBuf>,
+    files: Vec<File>,
+    blocks: Vec<BlockRef>,
+    next_block: usize,
+    buffer: Vec<Entry>,
+    capacity: usize,
+    rng: StdRng,
+    board: ChessBoard,
+    conductor: PieceConductor,
+}
+
+impl ShuffledReader {
+    pub fn open(paths: &[PathBuf], buffer: usize, seed: u64) -> Result<Self, String> {
+        let mut blocks = Vec::new();
+        let mut files = Vec::new();
+        for (i, path) in paths.iter().enumerate() {
+            blocks.extend(scan_blocks(path, i)?);
+            files.push(File::open(path).map_err(|e| format!("cannot open {}: {e}", path.display()))?);
+        }
+        let mut rng = StdRng::seed_from_u64(seed);
+        blocks.shuffle(&mut rng);
+        Ok(ShuffledReader {
+            paths: paths.to_vec(),
+            files,
+            blocks,
+            next_block: 0,
+            buffer: Vec::new(),
+            capacity: buffer.max(1),
+            rng,
+            board: ChessBoard::new(),
+            conductor: PieceConductor::new(),
+        })
+    }
+
+    /// Positions in all the files.
+    pub fn len(&self) -> u64 {
+        self.blocks.iter().map(|b| u64::from(b.count)).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn load(&mut self, block: BlockRef) -> Result<(), String> {
+        let path = &self.paths[block.file];
+        let file = &mut self.files[block.file];
+        let mut payload = vec![0u8; block.len];
+        file.seek(SeekFrom::Start(block.offset))
+            .and_then(|_| file.read_exact(&mut payload))
+            .map_err(|e| format!("{}: read error: {e}", path.display()))?;
+        let entries = decode_block(&payload, block.count, &mut self.board, &self.conductor)
+            .map_err(|e| format!("{}: {e}", path.display()))?;
+        self.buffer.extend(entries);
+        Ok(())
+    }
+}
+
+impl Iterator for ShuffledReader {
+    type Item = Result<Entry, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.buffer.len() < self.capacity && self.next_block < self.blocks.len() {
+            let block = self.blocks[self.next_block];
+            self.next_block += 1;
+            if let Err(e) = self.load(block) {
+                self.next_block = self.blocks.len();
+                self.buffer.clear();
+                return Some(Err(e));
+            }
+        }
+        if self.buffer.is_empty() {
+            return None;
+        }
+        let i = self.rng.gen_range(0..self.buffer.len());
+        Some(Ok(self.buffer.swap_remove(i)))
+    }
+}
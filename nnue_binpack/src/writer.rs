@@ -0,0 +1,135 @@
+//! Packing positions into chains and blocks.
+
+use std::io::Write;
+
+use chess_board::{ChessBoard, FENParser};
+use flate2::{write::DeflateEncoder, Compression};
+use move_generator::piece_conductor::PieceConductor;
+
+use crate::codec::{encode_position, put_varint, zigzag, Bytes};
+use crate::{decode_position, sorted_legal_moves, Entry, Outcome, BLOCK_BYTES, MAGIC, VERSION};
+
+/// The game being packed.
+struct Chain {
+    start: Vec<u8>,
+    outcome: Outcome,
+    scores: Vec<i32>,
+    moves: Vec<u8>,
+    /// Position after the last entry.
+    board: ChessBoard,
+}
+
+/// Writes entries to a binpack.  Each entry one legal move on from the
+/// previous one, with the same game result, extends the current chain;
+/// anything else starts a new one.  Call `finish` to write the last block.
+pub struct Writer<W: Write> {
+    out: W,
+    block: Vec<u8>,
+    block_positions: u32,
+    /// Block size, `BLOCK_BYTES` but for tests.
+    pub(crate) block_bytes: usize,
+    chain: Option<Chain>,
+    next: ChessBoard,
+    conductor: PieceConductor,
+    positions: u64,
+    chains: u64,
+}
+
+impl<W: Write> Writer<W> {
+    pub fn new(mut out: W) -> Result<Self, String> {
+        out.write_all(&MAGIC).map_err(|e| format!("cannot write header: {e}"))?;
+        out.write_all(&VERSION.to_le_bytes()).map_err(|e| format!("cannot write header: {e}"))?;
+        Ok(Writer {
+            out,
+            block: Vec::new(),
+            block_positions: 0,
+            block_bytes: BLOCK_BYTES,
+            chain: None,
+            next: ChessBoard::new(),
+            conductor: PieceConductor::new(),
+            positions: 0,
+            chains: 0,
+        })
+    }
+
+    /// Positions and chains pushed so far.
+    pub fn stats(&self) -> (u64, u64) {
+        (self.positions, self.chains + u64::from(self.chain.is_some()))
+    }
+
+    pub fn push(&mut self, entry: &Entry) -> Result<(), String> {
+        let mut start = Vec::new();
+        encode_position(&mut start, &entry.fen)?;
+        // Compare and replay the FEN as it will be read back.
+        let fen = decode_position(&mut Bytes::new(&start))?;
+        self.next.set_from_fen(&fen);
+        let outcome = Outcome::from_entry(entry.result, self.next.is_white_active())?;
+        self.positions += 1;
+
+        if let Some(chain) = self.chain.as_mut().filter(|c| c.outcome == outcome) {
+            let target = self.next.current_hash();
+            for (i, mut mv) in sorted_legal_moves(&mut chain.board, &self.conductor).into_iter().enumerate() {
+                chain.board.make_move(&mut mv);
+                if chain.board.current_hash() == target && FENParser::board_to_fen(&chain.board) == fen {
+                    chain.moves.push(i as u8);
+                    chain.scores.push(entry.cp);
+                    return Ok(());
+                }
+                chain.board.undo_move();
+            }
+        }
+
+        self.end_chain()?;
+        let mut board = ChessBoard::new();
+        board.set_from_fen(&fen);
+        self.chain = Some(Chain { start, outcome, scores: vec![entry.cp], moves: Vec::new(), board });
+        Ok(())
+    }
+
+    /// Appends the current chain to the block, writing the block once full.
+    fn end_chain(&mut self) -> Result<(), String> {
+        let Some(chain) = self.chain.take() else { return Ok(()) };
+        let block = &mut self.block;
+        block.extend_from_slice(&chain.start);
+        block.push(chain.outcome as u8);
+        put_varint(block, chain.scores.len() as u64);
+        put_varint(block, zigzag(chain.scores[0].into()));
+        for (pair, &mv) in chain.scores.windows(2).zip(&chain.moves) {
+            // Scores are for the side to move, so consecutive ones roughly
+            // cancel: store their sum.
+            block.push(mv);
+            put_varint(block, zigzag(i64::from(pair[1]) + i64::from(pair[0])));
+        }
+        self.block_positions += chain.scores.len() as u32;
+        self.chains += 1;
+        if self.block.len() >= self.block_bytes {
+            self.write_block()?;
+        }
+        Ok(())
+    }
+
+    fn write_block(&mut self) -> Result<(), String> {
+        if self.block_positions == 0 {
+            return Ok(());
+        }
+        let mut deflate = DeflateEncoder::new(Vec::new(), Compression::default());
+        let payload = deflate.write_all(&self.block).and_then(|_| deflate.finish());
+        let payload = payload.map_err(|e| format!("cannot compress block: {e}"))?;
+        let header = [(payload.len() as u32).to_le_bytes(), self.block_positions.to_le_bytes()].concat();
+        self.out
+            .write_all(&header)
+            .and_then(|_| self.out.write_all(&payload))
+            .map_err(|e| format!("cannot write block: {e}"))?;
+        self.block.clear();
+        self.block_positions = 0;
+        Ok(())
+    }
+
+    /// Writes what is left and returns the output.
+    pub fn finish(mut self) -> Result<W, String> {
+        self.end_chain()?;
+        self.write_block()?;
+        self.out.flush().map_err(|e| format!("cannot write block: {e}"))?;
+        Ok(self.out)
+    }
+}
@@ -0,0 +1,470 @@
+//! NNUE input features: which (piece, square) inputs a position activates,
+//! and which output bucket evaluates it.
+//!
+//! A network's first layer is trained on one `FeatureLayout`: a feature set
+//! and a king bucket map.  The net file records it, and everything that
+//! turns positions into feature indices — the engine's evaluator and its
+//! incremental accumulator updates (`chess_evaluation::features`),
+//! `nnue_preprocess` and the trainers — goes through this crate, so a new
+//! layout is a new variant here rather than a fork.  It depends on nothing
+//! but the board, so tools get the encoding without the search.
+//!
+//! Feature sets:
+//!
+//! * `Legacy768` — 12 piece slots × 64 squares, no king.
+//! * `HalfKp` — 12 piece slots × 64 squares × king bucket.  Slots 0-5 are
+//!   the perspective's own pawn..king, 6-11 the opponent's.
+//! * `HalfKa` — HalfKA v2: 10 non-king slots (own pawn..queen 0-4, the
+//!   opponent's 5-9) plus one plane for both kings (10), × 64 × king bucket.
+//!
+//! Each perspective sees the board from its own side: black's squares are
+//! rank-flipped.  In the king-bucketed sets, when the perspective's king is
+//! on files e-h, every square is also mirrored to put the king on a-d, and
+//! the bucket is taken from the king's square after that.  Single-perspective
+//! nets (the side to move's view only) predate mirroring and don't use it.
+//!
+//! Index: `slot * 64 * stride + square * stride + bucket`, where `stride` is
+//! the bucket count of the map except for `Mirrored8`, whose index space
+//! keeps room for 16 buckets as the first HalfKP nets were trained with.
+
+use std::fmt;
+
+use chess_board::ChessBoard;
+use chess_foundation::piece::PieceType;
+
+/// Output bucket of a position with `pieces` pieces (kings included) among
+/// `n_buckets`: `(pieces - 2) * n_buckets / 30`, so 2 pieces → 0 and 32 →
+/// `n_buckets - 1`.
+pub fn bucket_for_piece_count(pieces: usize, n_buckets: usize) -> usize {
+    (pieces.saturating_sub(2) * n_buckets / 30).min(n_buckets - 1)
+}
+
+/// Output bucket of `board` among `n_buckets`, by piece count.
+pub fn piece_bucket(board: &ChessBoard, n_buckets: usize) -> usize {
+    bucket_for_piece_count(board.get_all_pieces().count_ones() as usize, n_buckets)
+}
+
+/// Most features one perspective can have active: one per piece.
+pub const MAX_ACTIVE: usize = 32;
+
+/// Active feature indices of one perspective and how many there are.
+pub type ActiveFeatures = ([usize; MAX_ACTIVE], usize);
+
+/// Input encoding of the first layer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FeatureSet {
+    Legacy768,
+    HalfKp,
+    HalfKa,
+}
+
+impl FeatureSet {
+    pub const ALL: [FeatureSet; 3] = [FeatureSet::Legacy768, FeatureSet::HalfKp, FeatureSet::HalfKa];
+
+    /// Number of piece planes.
+    pub const fn slots(self) -> usize {
+        match self {
+            FeatureSet::Legacy768 | FeatureSet::HalfKp => 12,
+            FeatureSet::HalfKa => 11,
+        }
+    }
+
+    /// Plane of a piece as seen by a perspective it belongs (`ours`) or
+    /// doesn't belong to.
+    #[inline(always)]
+    pub fn slot(self, pt: PieceType, ours: bool) -> usize {
+        let base = match pt {
+            PieceType::Pawn => 0,
+            PieceType::Knight => 1,
+            PieceType::Bishop => 2,
+            PieceType::Rook => 3,
+            PieceType::Queen => 4,
+            PieceType::King => 5,
+            PieceType::None => 0,
+        };
+        match self {
+            FeatureSet::Legacy768 | FeatureSet::HalfKp => if ours { base } else { base + 6 },
+            FeatureSet::HalfKa if base == 5 => 10,
+            FeatureSet::HalfKa => if ours { base } else { base + 5 },
+        }
+    }
+
+    /// Name as used on command lines and in `Display`.
+    pub fn name(self) -> &'static str {
+        match self {
+            FeatureSet::Legacy768 => "legacy-768",
+            FeatureSet::HalfKp => "halfkp",
+            FeatureSet::HalfKa => "halfka",
+        }
+    }
+}
+
+impl std::str::FromStr for FeatureSet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        FeatureSet::ALL
+            .into_iter()
+            .find(|f| f.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown feature set '{s}' (expected legacy-768, halfkp or halfka)"))
+    }
+}
+
+/// King bucket map: which bucket each king square (perspective-relative,
+/// mirrored to files a-d) selects.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum KingBuckets {
+    /// 4 files × 2 rank halves.
+    Mirrored8,
+    /// 4 files × 4 rank pairs.
+    Mirrored16,
+    /// 4 files × 8 ranks: every king square its own bucket.
+    Mirrored32,
+}
+
+impl KingBuckets {
+    pub const ALL: [KingBuckets; 3] = [KingBuckets::Mirrored8, KingBuckets::Mirrored16, KingBuckets::Mirrored32];
+
+    pub const fn count(self) -> usize {
+        match self {
+            KingBuckets::Mirrored8 => 8,
+            KingBuckets::Mirrored16 => 16,
+            KingBuckets::Mirrored32 => 32,
+        }
+    }
+
+    /// The map with `n` buckets.
+    pub fn from_count(n: usize) -> Option<Self> {
+        KingBuckets::ALL.into_iter().find(|k| k.count() == n)
+    }
+
+    /// Bucket slots per square in the index space.
+    pub const fn stride(self) -> usize {
+        if self.count() < 16 { 16 } else { self.count() }
+    }
+
+    /// Bucket of a king on `sq`, any file: e-h map like their a-d mirror.
+    #[inline(always)]
+    pub fn bucket(self, sq: usize) -> usize {
+        let (file, rank) = (sq % 8, sq / 8);
+        let file = if file <= 3 { file } else { 7 - file };
+        match self {
+            KingBuckets::Mirrored8 => (rank / 4) * 4 + file,
+            KingBuckets::Mirrored16 => (rank / 2) * 4 + file,
+            KingBuckets::Mirrored32 => rank * 4 + file,
+        }
+    }
+}
+
+/// A feature set with its king bucket map (ignored by `Legacy768`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FeatureLayout {
+    pub set: FeatureSet,
+    pub kings: KingBuckets,
+}
+
+impl FeatureLayout {
+    /// 768 inputs, the first nets.
+    pub const LEGACY: Self = Self { set: FeatureSet::Legacy768, kings: KingBuckets::Mirrored8 };
+    /// HalfKP with 8 buckets in a 16-bucket index space: 12,288 inputs,
+    /// the layout of every net exported as `.npz`.
+    pub const HALFKP: Self = Self { set: FeatureSet::HalfKp, kings: KingBuckets::Mirrored8 };
+
+    pub fn new(set: FeatureSet, kings: KingBuckets) -> Self {
+        match set {
+            // No king buckets: keep one canonical value so layouts compare equal.
+            FeatureSet::Legacy768 => Self::LEGACY,
+            _ => Self { set, kings },
+        }
+    }
+
+    /// Index stride of the king bucket: 1 without buckets.
+    #[inline(always)]
+    const fn stride(self) -> usize {
+        match self.set {
+            FeatureSet::Legacy768 => 1,
+            _ => self.kings.stride(),
+        }
+    }
+
+    /// Number of distinct buckets a perspective can be in.
+    pub fn buckets(self) -> usize {
+        match self.set {
+            FeatureSet::Legacy768 => 1,
+            _ => self.kings.count(),
+        }
+    }
+
+    pub const fn input_dim(self) -> usize {
+        self.set.slots() * 64 * self.stride()
+    }
+
+    /// The view of `white`'s side (or black's) of `board`, mirrored.
+    #[inline]
+    pub fn perspective(self, board: &ChessBoard, white: bool) -> Perspective {
+        self.view(board, white, true)
+    }
+
+    fn view(self, board: &ChessBoard, white: bool, mirror: bool) -> Perspective {
+        let own = if white { board.get_white() } else { board.get_black() };
+        let flip = if white { 0 } else { 56 };
+        let king = ((own & board.get_kings()).0.trailing_zeros() as usize).min(63) ^ flip;
+        let (bucket, mirrored) = match self.set {
+            FeatureSet::Legacy768 => (0, false),
+            _ => (self.kings.bucket(king), mirror && king % 8 >= 4),
+        };
+        let stride = self.stride();
+        // [piece is white][piece type as u8]; PieceType::None maps like a pawn.
+        let base = std::array::from_fn(|colour| {
+            std::array::from_fn(|pt| {
+                let pt = PIECE_TYPES[pt.saturating_sub(1).min(5)];
+                self.set.slot(pt, (colour == 1) == white) * 64 * stride + bucket
+            })
+        });
+        Perspective { white, stride, bucket, mirrored, xor: flip ^ if mirrored { 7 } else { 0 }, base }
+    }
+
+    /// Active features of both perspectives, white's first.
+    pub fn encode_dual(self, board: &ChessBoard) -> (ActiveFeatures, ActiveFeatures) {
+        (
+            self.perspective(board, true).encode(board),
+            self.perspective(board, false).encode(board),
+        )
+    }
+
+    /// Active features of the side to move, for single-perspective nets
+    /// (not mirrored).
+    pub fn encode_single(self, board: &ChessBoard) -> ActiveFeatures {
+        self.view(board, board.is_white_active(), false).encode(board)
+    }
+}
+
+impl Default for FeatureLayout {
+    fn default() -> Self {
+        Self::HALFKP
+    }
+}
+
+impl fmt::Display for FeatureLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.set {
+            FeatureSet::Legacy768 => f.write_str(self.set.name()),
+            set => write!(f, "{}/{}", set.name(), self.kings.count()),
+        }
+    }
+}
+
+const PIECE_TYPES: [PieceType; 6] =
+    [PieceType::Pawn, PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen, PieceType::King];
+
+/// One side's view of a position: king bucket and square mapping.  Stays
+/// valid while that side's king doesn't move.
+#[derive(Clone, Copy, Debug)]
+pub struct Perspective {
+    white: bool,
+    stride: usize,
+    bucket: usize,
+    mirrored: bool,
+    /// Square mapping: rank flip for black, file mirror when `mirrored`.
+    xor: usize,
+    /// Index of each piece on square 0, by colour and `PieceType` value.
+    base: [[usize; 7]; 2],
+}
+
+impl Perspective {
+    pub fn bucket(&self) -> usize {
+        self.bucket
+    }
+
+    pub fn mirrored(&self) -> bool {
+        self.mirrored
+    }
+
+    /// Feature of a `piece_white` piece of type `pt` on `sq`.
+    #[inline(always)]
+    pub fn index(&self, pt: PieceType, piece_white: bool, sq: usize) -> usize {
+        self.base[usize::from(piece_white)][pt as usize] + (sq ^ self.xor) * self.stride
+    }
+
+    /// All active features of `board`: own pieces pawn to king, then the
+    /// opponent's.
+    pub fn encode(&self, board: &ChessBoard) -> ActiveFeatures {
+        let (own, theirs) = if self.white {
+            (board.get_white(), board.get_black())
+        } else {
+            (board.get_black(), board.get_white())
+        };
+        let types = [
+            board.get_pawns(),
+            board.get_knights(),
+            board.get_bishops(),
+            board.get_rooks(),
+            board.get_queens(),
+            board.get_kings(),
+        ];
+        let mut indices = [0usize; MAX_ACTIVE];
+        let mut count = 0;
+        for (colour, is_own) in [(own, true), (theirs, false)] {
+            for (pt, pieces) in PIECE_TYPES.into_iter().zip(types) {
+                let mut bb = (colour & pieces).0;
+                while bb != 0 && count < MAX_ACTIVE {
+                    indices[count] = self.index(pt, self.white == is_own, bb.trailing_zeros() as usize);
+                    count += 1;
+                    bb &= bb - 1;
+                }
+            }
+        }
+        (indices, count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chess_board::FENParser;
+
+    fn board(fen: &str) -> ChessBoard {
+        let mut b = ChessBoard::new();
+        b.clear();
+        FENParser::set_board_from_fen(&mut b, fen);
+        b
+    }
+
+    fn all_layouts() -> Vec<FeatureLayout> {
+        let mut v = vec![FeatureLayout::LEGACY];
+        for set in [FeatureSet::HalfKp, FeatureSet::HalfKa] {
+            v.extend(KingBuckets::ALL.map(|k| FeatureLayout::new(set, k)));
+        }
+        v
+    }
+
+    // The slot mapping is load-bearing: wrong slots corrupt both the
+    // accumulator updates and the contract with the Python trainer.
+    #[test]
+    fn piece_slots() {
+        let types = [
+            PieceType::Pawn,
+            PieceType::Knight,
+            PieceType::Bishop,
+            PieceType::Rook,
+            PieceType::Queen,
+            PieceType::King,
+        ];
+        let kp = FeatureSet::HalfKp;
+        assert_eq!(types.map(|pt| kp.slot(pt, true)), [0, 1, 2, 3, 4, 5]);
+        assert_eq!(types.map(|pt| kp.slot(pt, false)), [6, 7, 8, 9, 10, 11]);
+        assert_eq!(types.map(|pt| FeatureSet::Legacy768.slot(pt, false)), [6, 7, 8, 9, 10, 11]);
+        let ka = FeatureSet::HalfKa;
+        assert_eq!(types.map(|pt| ka.slot(pt, true)), [0, 1, 2, 3, 4, 10]);
+        assert_eq!(types.map(|pt| ka.slot(pt, false)), [5, 6, 7, 8, 9, 10]);
+    }
+
+    #[test]
+    fn input_dims() {
+        assert_eq!(FeatureLayout::LEGACY.input_dim(), 768);
+        assert_eq!(FeatureLayout::HALFKP.input_dim(), 12_288);
+        assert_eq!(FeatureLayout::new(FeatureSet::HalfKp, KingBuckets::Mirrored16).input_dim(), 12_288);
+        assert_eq!(FeatureLayout::new(FeatureSet::HalfKp, KingBuckets::Mirrored32).input_dim(), 24_576);
+        assert_eq!(FeatureLayout::new(FeatureSet::HalfKa, KingBuckets::Mirrored8).input_dim(), 11_264);
+        assert_eq!(FeatureLayout::new(FeatureSet::HalfKa, KingBuckets::Mirrored32).input_dim(), 22_528);
+        assert_eq!(FeatureLayout::new(FeatureSet::Legacy768, KingBuckets::Mirrored32), FeatureLayout::LEGACY);
+    }
+
+    #[test]
+    fn bucket_maps_cover_every_bucket_and_mirror() {
+        for kings in KingBuckets::ALL {
+            let mut seen = vec![0; kings.count()];
+            for sq in 0..64 {
+                assert_eq!(kings.bucket(sq), kings.bucket(sq ^ 7));
+                seen[kings.bucket(sq)] += 1;
+            }
+            assert!(seen.iter().all(|&n| n == 64 / kings.count()), "{kings:?}: {seen:?}");
+        }
+    }
+
+    #[test]
+    fn first_halfkp_layout_is_unchanged() {
+        // Black king on e8: rank-flipped to e1, mirrored to d1 (sq 3), bucket 3.
+        let b = ChessBoard::new();
+        let p = FeatureLayout::HALFKP.perspective(&b, false);
+        assert_eq!((p.bucket(), p.mirrored()), (3, true));
+        assert_eq!(p.index(PieceType::King, false, 60), 5 * 64 * 16 + 3 * 16 + 3);
+        // a7 pawn → a2 → h2 (mirrored) = 15.
+        assert_eq!(p.index(PieceType::Pawn, false, 48), 15 * 16 + 3);
+    }
+
+    #[test]
+    fn features_are_unique_and_in_range() {
+        let positions = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 b - - 0 1",
+            "6k1/8/8/8/8/8/8/1K6 w - - 0 1",
+        ];
+        for layout in all_layouts() {
+            for fen in positions {
+                let b = board(fen);
+                let pieces = b.get_all_pieces().0.count_ones() as usize;
+                let ((w, wc), (bl, bc)) = layout.encode_dual(&b);
+                let (s, sc) = layout.encode_single(&b);
+                for (idx, n) in [(&w, wc), (&bl, bc), (&s, sc)] {
+                    assert_eq!(n, pieces, "{layout} {fen}");
+                    let mut v = idx[..n].to_vec();
+                    assert!(v.iter().all(|&i| i < layout.input_dim()), "{layout} {fen}");
+                    v.sort_unstable();
+                    v.dedup();
+                    assert_eq!(v.len(), n, "{layout} {fen}: duplicate feature");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn colour_flip_swaps_perspectives() {
+        // The same position with colours reversed: white's view of one is
+        // black's view of the other.
+        let a = board("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+        let b = board("r3k2r/pppbbppp/2n2q1P/1P2p3/3pn3/BN2PNP1/P1PPQPB1/R3K2R b KQkq - 0 1");
+        for layout in all_layouts() {
+            let sorted = |(idx, n): ActiveFeatures| {
+                let mut v = idx[..n].to_vec();
+                v.sort_unstable();
+                v
+            };
+            let (aw, ab) = layout.encode_dual(&a);
+            let (bw, bb) = layout.encode_dual(&b);
+            assert_eq!(sorted(aw), sorted(bb), "{layout}");
+            assert_eq!(sorted(ab), sorted(bw), "{layout}");
+        }
+    }
+
+    #[test]
+    fn halfka_merges_the_kings() {
+        let layout = FeatureLayout::new(FeatureSet::HalfKa, KingBuckets::Mirrored32);
+        let p = layout.perspective(&ChessBoard::new(), true);
+        let own = p.index(PieceType::King, true, 4);
+        let theirs = p.index(PieceType::King, false, 4);
+        assert_eq!(own, theirs);
+        assert_eq!(own / (64 * 32), 10);
+    }
+
+    #[test]
+    fn output_buckets_by_piece_count() {
+        assert_eq!(bucket_for_piece_count(2, 8), 0);
+        assert_eq!(bucket_for_piece_count(32, 8), 7);
+        assert_eq!(bucket_for_piece_count(17, 8), 4);
+        assert_eq!(bucket_for_piece_count(1, 8), 0);
+        assert_eq!(bucket_for_piece_count(32, 1), 0);
+        assert_eq!(piece_bucket(&ChessBoard::new(), 8), 7);
+        assert_eq!(piece_bucket(&board("6k1/8/8/8/8/8/8/1K6 w - - 0 1"), 8), 0);
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for set in FeatureSet::ALL {
+            assert_eq!(set.name().parse::<FeatureSet>(), Ok(set));
+        }
+        assert!("halfkx".parse::<FeatureSet>().is_err());
+        assert_eq!(FeatureLayout::new(FeatureSet::HalfKa, KingBuckets::Mirrored16).to_string(), "halfka/16");
+    }
+}
@@ -0,0 +1,399 @@
+//! Fast JSONL → binary numpy encoder for NNUE training.
+//!
+//! Reads {fen, cp} JSONL, or a `.binpack` from `nnue_binpack`, and writes
+//! numpy-compatible .npy files:
+//!
+//! Dual mode (--dual):
+//!   {out}.white_indices.npy  (N, 32) uint16
+//!   {out}.black_indices.npy  (N, 32) uint16
+//!   {out}.counts.npy         (N,)    uint8
+//!   {out}.cp.npy             (N,)    float32   (white-absolute)
+//!   {out}.piece_count.npy    (N,)    uint8
+//!
+//! Single mode (default):
+//!   {out}.indices.npy        (N, 32) uint16
+//!   {out}.counts.npy         (N,)    uint8
+//!   {out}.cp.npy             (N,)    float32   (side-to-move)
+//!   {out}.piece_count.npy    (N,)    uint8
+//!
+//! Indices follow the feature layout chosen with --features and
+//! --king-buckets (default HalfKP with 8 king buckets, 12,288 inputs), encoded
+//! by the `nnue_features` crate exactly as the engine does.  Unused slots
+//! hold the layout's input dimension as a sentinel.
+//!
+//! Uses streaming BufWriter — no mmap, no large RAM allocation.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use chess_board::{ChessBoard, FENParser};
+
+use nnue_features::{ActiveFeatures, FeatureLayout, MAX_ACTIVE};
+
+const WRITE_BUF: usize = 8 * 1024 * 1024; // 8 MB per output file
+const PROGRESS_EVERY: usize = 1_000_000;
+
+// ── NPY v1.0 header writer ────────────────────────────────────────────────
+
+/// Write an NPY v1.0 header for a C-order array of `dtype` and `shape`.
+pub fn write_npy_header(w: &mut impl Write, dtype: &str, shape: &[usize]) -> io::Result<()> {
+    let shape_str = if shape.len() == 1 {
+        format!("({},)", shape[0])
+    } else {
+        format!(
+            "({})",
+            shape.iter().map(|d| d.to_string()).collect::<Vec<_>>().join(", ")
+        )
+    };
+    let dict = format!(
+        "{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}\n",
+        dtype, shape_str
+    );
+    // Total = 10-byte prefix + HEADER_LEN must be a multiple of 64.
+    let prefix = 10usize;
+    let total = prefix + dict.len();
+    let padded = (total + 63) / 64 * 64;
+    let padding = padded - total;
+    let header_len = (dict.len() + padding) as u16;
+
+    w.write_all(b"\x93NUMPY")?;
+    w.write_all(&[1u8, 0u8])?;
+    w.write_all(&header_len.to_le_bytes())?;
+    w.write_all(&dict.as_bytes()[..dict.len() - 1])?; // without trailing \n
+    for _ in 0..padding {
+        w.write_all(b" ")?;
+    }
+    w.write_all(b"\n")?;
+    Ok(())
+}
+
+// ── Feature encoding ──────────────────────────────────────────────────────
+
+/// Indices as u16, padded with the sentinel.
+fn to_u16((indices, count): ActiveFeatures, sentinel: u16) -> [u16; MAX_ACTIVE] {
+    let mut out = [sentinel; MAX_ACTIVE];
+    for (o, &i) in out.iter_mut().zip(&indices[..count]) {
+        *o = i as u16;
+    }
+    out
+}
+
+/// Both perspectives of `board`, padded with the sentinel, and the piece count.
+pub fn encode_dual(layout: FeatureLayout, board: &ChessBoard) -> ([u16; MAX_ACTIVE], [u16; MAX_ACTIVE], u8) {
+    let sentinel = layout.input_dim() as u16;
+    let (white, black) = layout.encode_dual(board);
+    (to_u16(white, sentinel), to_u16(black, sentinel), white.1 as u8)
+}
+
+/// Side-to-move features of `board`, padded with the sentinel.
+pub fn encode_single(layout: FeatureLayout, board: &ChessBoard) -> ([u16; MAX_ACTIVE], u8) {
+    let active = layout.encode_single(board);
+    (to_u16(active, layout.input_dim() as u16), active.1 as u8)
+}
+
+// ── Input ─────────────────────────────────────────────────────────────────
+
+fn count_lines(path: &Path) -> usize {
+    let f = File::open(path).expect("cannot open input for counting");
+    BufReader::new(f).lines().count()
+}
+
+/// Number of positions in `input` and the positions as (FEN, side-to-move
+/// cp): a `.binpack` by extension, JSONL otherwise.
+fn positions(input: &Path) -> (usize, Box<dyn Iterator<Item = (String, f64)>>) {
+    if input.extension().is_some_and(|e| e == "binpack") {
+        let n = nnue_binpack::count_positions(input).unwrap_or_else(|e| panic!("{e}"));
+        let reader = nnue_binpack::Reader::open(input).unwrap_or_else(|e| panic!("{e}"));
+        let path = input.display().to_string();
+        let entries = reader.map(move |e| e.unwrap_or_else(|e| panic!("{path}: {e}")));
+        return (n as usize, Box::new(entries.map(|e| (e.fen, e.cp as f64))));
+    }
+
+    let n = count_lines(input);
+    let reader = BufReader::with_capacity(WRITE_BUF, File::open(input).expect("cannot open input"));
+    let lines = reader.lines().map(|line| line.expect("read error")).filter(|line| !line.trim().is_empty());
+    let records = lines.enumerate().map(|(i, line)| {
+        let v: serde_json::Value = serde_json::from_str(&line)
+            .unwrap_or_else(|e| panic!("JSON error on line {i}: {e}\n  {line}"));
+        let fen = v["fen"].as_str().expect("missing fen").to_string();
+        (fen, v["cp"].as_f64().expect("missing cp"))
+    });
+    (n, Box::new(records))
+}
+
+// ── Output path helper ────────────────────────────────────────────────────
+
+fn out_path(prefix: &Path, ext: &str) -> PathBuf {
+    let mut s = prefix.to_string_lossy().into_owned();
+    s.push_str(ext);
+    PathBuf::from(s)
+}
+
+fn open_out(prefix: &Path, ext: &str) -> BufWriter<File> {
+    let p = out_path(prefix, ext);
+    BufWriter::with_capacity(
+        WRITE_BUF,
+        File::create(&p).unwrap_or_else(|e| panic!("cannot create {}: {e}", p.display())),
+    )
+}
+
+// ── Encode loop ───────────────────────────────────────────────────────────
+
+/// Encode the JSONL or binpack file `input` into `.npy` files next to
+/// `out_prefix`.
+pub fn run(input: &Path, out_prefix: &Path, layout: FeatureLayout, dual: bool, max_cp: f64) {
+    eprint!("Counting positions in {} … ", input.display());
+    let (n, positions) = positions(input);
+    eprintln!("{n}");
+    eprintln!("Features: {layout} ({} inputs)", layout.input_dim());
+
+    let mut board = ChessBoard::new();
+
+    if dual {
+        let mut w_f  = open_out(out_prefix, ".white_indices.npy");
+        let mut b_f  = open_out(out_prefix, ".black_indices.npy");
+        let mut c_f  = open_out(out_prefix, ".counts.npy");
+        let mut cp_f = open_out(out_prefix, ".cp.npy");
+        let mut pc_f = open_out(out_prefix, ".piece_count.npy");
+
+        write_npy_header(&mut w_f,  "<u2", &[n, MAX_ACTIVE]).unwrap();
+        write_npy_header(&mut b_f,  "<u2", &[n, MAX_ACTIVE]).unwrap();
+        write_npy_header(&mut c_f,  "|u1", &[n]).unwrap();
+        write_npy_header(&mut cp_f, "<f4", &[n]).unwrap();
+        write_npy_header(&mut pc_f, "|u1", &[n]).unwrap();
+
+        let t0 = Instant::now();
+        let mut i = 0usize;
+        for (fen, cp) in positions {
+            let mut cp = cp.clamp(-max_cp, max_cp);
+
+            board.clear();
+            FENParser::set_board_from_fen(&mut board, &fen);
+
+            if !board.is_white_active() { cp = -cp; }
+
+            let (w_idx, b_idx, count) = encode_dual(layout, &board);
+            let piece_count = board.get_all_pieces().count_ones() as u8;
+
+            for v in &w_idx  { w_f.write_all(&v.to_le_bytes()).unwrap(); }
+            for v in &b_idx  { b_f.write_all(&v.to_le_bytes()).unwrap(); }
+            c_f.write_all(&[count]).unwrap();
+            cp_f.write_all(&(cp as f32).to_le_bytes()).unwrap();
+            pc_f.write_all(&[piece_count]).unwrap();
+
+            i += 1;
+            if i % PROGRESS_EVERY == 0 {
+                let elapsed = t0.elapsed().as_secs_f64();
+                let rate = i as f64 / elapsed;
+                eprintln!("  {i}/{n}  ({:.0}k pos/s)", rate / 1000.0);
+            }
+        }
+        // Ensure all buffers flushed
+        for f in [&mut w_f, &mut b_f, &mut c_f, &mut cp_f, &mut pc_f] {
+            f.flush().unwrap();
+        }
+        eprintln!("Wrote dual files to {}.*", out_prefix.display());
+    } else {
+        let mut idx_f = open_out(out_prefix, ".indices.npy");
+        let mut c_f   = open_out(out_prefix, ".counts.npy");
+        let mut cp_f  = open_out(out_prefix, ".cp.npy");
+        let mut pc_f  = open_out(out_prefix, ".piece_count.npy");
+
+        write_npy_header(&mut idx_f, "<u2", &[n, MAX_ACTIVE]).unwrap();
+        write_npy_header(&mut c_f,   "|u1", &[n]).unwrap();
+        write_npy_header(&mut cp_f,  "<f4", &[n]).unwrap();
+        write_npy_header(&mut pc_f,  "|u1", &[n]).unwrap();
+
+        let t0 = Instant::now();
+        let mut i = 0usize;
+        for (fen, cp) in positions {
+            let cp = cp.clamp(-max_cp, max_cp);
+
+            board.clear();
+            FENParser::set_board_from_fen(&mut board, &fen);
+
+            let (indices, count) = encode_single(layout, &board);
+            let piece_count = board.get_all_pieces().count_ones() as u8;
+
+            for v in &indices { idx_f.write_all(&v.to_le_bytes()).unwrap(); }
+            c_f.write_all(&[count]).unwrap();
+            cp_f.write_all(&(cp as f32).to_le_bytes()).unwrap();
+            pc_f.write_all(&[piece_count]).unwrap();
+
+            i += 1;
+            if i % PROGRESS_EVERY == 0 {
+                let elapsed = t0.elapsed().as_secs_f64();
+                let rate = i as f64 / elapsed;
+                eprintln!("  {i}/{n}  ({:.0}k pos/s)", rate / 1000.0);
+            }
+        }
+        for f in [&mut idx_f, &mut c_f, &mut cp_f, &mut pc_f] {
+            f.flush().unwrap();
+        }
+        eprintln!("Wrote single files to {}.*", out_prefix.display());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chess_evaluation::net_file::{Architecture, NetFile};
+    use chess_evaluation::SearchContext;
+    use nnue_features::{FeatureSet, KingBuckets};
+    use move_generator::{move_generator::get_all_legal_moves_for_color, piece_conductor::PieceConductor};
+    use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+    /// Accumulator width of the engine.
+    const HIDDEN1: usize = 1024;
+
+    /// Along random games, the features written for each position, summed
+    /// over a random first layer, equal the accumulators the engine keeps
+    /// incrementally in search: same indices, same perspectives, for every
+    /// feature set and king bucket map.
+    #[test]
+    fn features_match_the_engine_accumulators() {
+        let mut layouts: Vec<FeatureLayout> = FeatureSet::ALL
+            .into_iter()
+            .flat_map(|set| KingBuckets::ALL.map(|kings| FeatureLayout::new(set, kings)))
+            .collect();
+        layouts.dedup();
+        assert_eq!(layouts.len(), 7);
+        // The engine loads one net per process, so each layout is checked
+        // by a run of this test binary of its own, all started at once.
+        if let Ok(i) = std::env::var(LAYOUT_INDEX_VAR) {
+            check_accumulators(layouts[i.parse::<usize>().unwrap()]);
+            return;
+        }
+        let runs: Vec<_> = (0..layouts.len())
+            .map(|i| {
+                std::process::Command::new(std::env::current_exe().unwrap())
+                    .args(["--exact", "tests::features_match_the_engine_accumulators", "--test-threads=1"])
+                    .env(LAYOUT_INDEX_VAR, i.to_string())
+                    .stdout(std::process::Stdio::piped())
+                    .stderr(std::process::Stdio::piped())
+                    .spawn()
+                    .unwrap()
+            })
+            .collect();
+        for (layout, run) in layouts.iter().zip(runs) {
+            let out = run.wait_with_output().unwrap();
+            assert!(out.status.success(), "{layout}:\n{}", String::from_utf8_lossy(&out.stdout));
+        }
+    }
+
+    const LAYOUT_INDEX_VAR: &str = "NNUE_PREPROCESS_TEST_LAYOUT";
+
+    fn check_accumulators(layout: FeatureLayout) {
+        let mut rng = StdRng::seed_from_u64(47);
+        let arch = Architecture {
+            features: layout,
+            dual_perspective: true,
+            hidden1: HIDDEN1,
+            hidden2: 32,
+            output_buckets: 8,
+            scale: 256.0,
+        };
+        // Multiplicative hashing rather than `rng`: the feature transformer
+        // of the 32-bucket layouts has 23 million weights.
+        let seed = rng.gen::<u32>() | 1;
+        let tensors = arch.tensor_lens().unwrap().map(|n| {
+            (0..n as u32).map(|i| (i.wrapping_add(seed).wrapping_mul(0x9e37_79b1) >> 24) as i16 - 128).collect::<Vec<i16>>()
+        });
+        let net = NetFile { arch, metadata: Vec::new(), tensors };
+        chess_evaluation::init_neural_eval_from_bytes(&net.to_bytes()).unwrap();
+        chess_evaluation::set_neural_eval_enabled(true);
+
+        let (w1, b1, dim) = (&net.tensors[0], &net.tensors[1], layout.input_dim());
+        // Bias plus the column of every written index (w1 is [HIDDEN1 × dim]).
+        let accumulate = |indices: &[u16; MAX_ACTIVE]| -> Vec<i16> {
+            (0..HIDDEN1)
+                .map(|j| {
+                    let active = indices.iter().map(|&i| i as usize).filter(|&i| i < dim);
+                    active.fold(b1[j], |acc, i| acc + w1[j * dim + i])
+                })
+                .collect()
+        };
+
+        let conductor = PieceConductor::new();
+        let mut checked = 0;
+        for _game in 0..2 {
+            let mut board = ChessBoard::new();
+            let mut ctx = SearchContext::new();
+            let mut ply = 0;
+            for _move in 0..240 {
+                // Long games move the kings around; restart the stack from
+                // the current position before it runs out.
+                if ply == 0 || ply == 60 {
+                    ctx.init_accumulators(&board);
+                    ply = 0;
+                }
+                let mut legal = Vec::new();
+                let white = board.is_white_active();
+                get_all_legal_moves_for_color(&mut board, &conductor, white, &mut legal, &mut Vec::new());
+                let Some(&mv) = legal.choose(&mut rng) else { break };
+                let mut mv = mv;
+                ctx.acc_push(ply, &mv);
+                board.make_move(&mut mv);
+                ply += 1;
+                // Skip some plies so updates also chain over several moves.
+                if rng.gen_bool(0.4) {
+                    continue;
+                }
+                ctx.acc_materialize(ply, &board);
+                let (acc_w, acc_b) = ctx.accumulators(ply).expect("accumulators not computed");
+
+                let (w_idx, b_idx, count) = encode_dual(layout, &board);
+                assert_eq!(count as u32, board.get_all_pieces().count_ones());
+                assert!(acc_w[..] == accumulate(&w_idx)[..], "{layout}: white perspective differs after {mv:?}");
+                assert!(acc_b[..] == accumulate(&b_idx)[..], "{layout}: black perspective differs after {mv:?}");
+                checked += 1;
+            }
+        }
+        assert!(checked > 150, "{layout}: only {checked} positions checked");
+    }
+
+    /// A binpack of some games encodes to the same arrays as their JSONL.
+    #[test]
+    fn binpack_input_matches_jsonl() {
+        let dir = std::env::temp_dir().join(format!("nnue_preprocess_binpack_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut rng = StdRng::seed_from_u64(5);
+        let conductor = PieceConductor::new();
+        let mut jsonl = String::new();
+        let mut writer = nnue_binpack::Writer::new(File::create(dir.join("games.binpack")).unwrap()).unwrap();
+        for _game in 0..6 {
+            let mut board = ChessBoard::new();
+            for _move in 0..80 {
+                let fen = FENParser::board_to_fen(&board);
+                let cp = rng.gen_range(-2000..2000);
+                jsonl.push_str(&format!("{{\"fen\": \"{fen}\", \"cp\": {cp}}}\n"));
+                writer.push(&nnue_binpack::Entry { fen, cp, result: None }).unwrap();
+
+                let mut legal = Vec::new();
+                let white = board.is_white_active();
+                get_all_legal_moves_for_color(&mut board, &conductor, white, &mut legal, &mut Vec::new());
+                let Some(&mv) = legal.choose(&mut rng) else { break };
+                board.make_move(&mut mv.clone());
+            }
+        }
+        writer.finish().unwrap();
+        std::fs::write(dir.join("games.jsonl"), jsonl).unwrap();
+
+        for dual in [true, false] {
+            run(&dir.join("games.jsonl"), &dir.join("a"), FeatureLayout::HALFKP, dual, 1500.0);
+            run(&dir.join("games.binpack"), &dir.join("b"), FeatureLayout::HALFKP, dual, 1500.0);
+            let exts: &[&str] = if dual {
+                &[".white_indices.npy", ".black_indices.npy", ".counts.npy", ".cp.npy", ".piece_count.npy"]
+            } else {
+                &[".indices.npy", ".counts.npy", ".cp.npy", ".piece_count.npy"]
+            };
+            for ext in exts {
+                let a = std::fs::read(out_path(&dir.join("a"), ext)).unwrap();
+                assert!(a == std::fs::read(out_path(&dir.join("b"), ext)).unwrap(), "{ext} differs");
+            }
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,266 @@
+//! Training positions from the `.npy` shards of `nnue_preprocess --dual`.
+//!
+//! A shard is the five files `{prefix}.white_indices.npy`,
+//! `.black_indices.npy`, `.counts.npy`, `.cp.npy` and `.piece_count.npy`.
+//! All shards are loaded into memory, about 140 bytes per position.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use nnue_features::{FeatureLayout, MAX_ACTIVE};
+
+// ── NPY reader ────────────────────────────────────────────────────────────
+
+/// A C-order array read from an `.npy` file, data still little-endian bytes.
+struct Npy {
+    descr: String,
+    shape: Vec<usize>,
+    data: Vec<u8>,
+}
+
+/// The value of `key` in an NPY header dict: a tuple, or up to the next `,`.
+fn header_field<'a>(header: &'a str, key: &str) -> Option<&'a str> {
+    let start = header.find(&format!("'{key}':"))? + key.len() + 3;
+    let rest = header[start..].trim_start();
+    let end = if rest.starts_with('(') { rest.find(')')? + 1 } else { rest.find([',', '}'])? };
+    Some(rest[..end].trim())
+}
+
+fn read_npy(path: &Path) -> Result<Npy, String> {
+    let bytes = fs::read(path).map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+    let bad = |what: &str| format!("{}: {what}", path.display());
+    if !bytes.starts_with(b"\x93NUMPY") || bytes.len() < 10 {
+        return Err(bad("not an .npy file"));
+    }
+    let (header_start, header_len) = match bytes[6] {
+        1 => (10, u16::from_le_bytes([bytes[8], bytes[9]]) as usize),
+        2 | 3 if bytes.len() >= 12 => (12, u32::from_le_bytes(bytes[8..12].try_into().unwrap()) as usize),
+        v => return Err(bad(&format!("unsupported .npy version {v}"))),
+    };
+    let data_start = header_start + header_len;
+    let header = bytes.get(header_start..data_start).ok_or_else(|| bad("truncated header"))?;
+    let header = std::str::from_utf8(header).map_err(|_| bad("header is not text"))?;
+
+    let descr = header_field(header, "descr").ok_or_else(|| bad("no descr"))?.trim_matches('\'');
+    if header_field(header, "fortran_order") != Some("False") {
+        return Err(bad("only C-order arrays are supported"));
+    }
+    let shape = header_field(header, "shape").ok_or_else(|| bad("no shape"))?;
+    let shape = shape
+        .trim_matches(['(', ')'])
+        .split(',')
+        .map(str::trim)
+        .filter(|d| !d.is_empty())
+        .map(|d| d.parse().map_err(|_| bad(&format!("bad shape {shape}"))))
+        .collect::<Result<Vec<usize>, _>>()?;
+
+    Ok(Npy { descr: descr.to_string(), shape, data: bytes[data_start..].to_vec() })
+}
+
+impl Npy {
+    /// Check the dtype and shape (`None` matches any length) and the data size.
+    fn expect(&self, path: &Path, descr: &str, shape: &[Option<usize>], item: usize) -> Result<usize, String> {
+        let dims_match = self.shape.len() == shape.len()
+            && self.shape.iter().zip(shape).all(|(&d, s)| s.is_none_or(|s| s == d));
+        if self.descr != descr || !dims_match {
+            return Err(format!(
+                "{}: expected {descr} of shape {shape:?}, found {} of shape {:?}",
+                path.display(),
+                self.descr,
+                self.shape
+            ));
+        }
+        let n: usize = self.shape.iter().product();
+        if self.data.len() != n * item {
+            return Err(format!("{}: {} data bytes for {n} items", path.display(), self.data.len()));
+        }
+        Ok(self.shape[0])
+    }
+
+    fn u16s(&self) -> Vec<u16> {
+        self.data.chunks_exact(2).map(|b| u16::from_le_bytes([b[0], b[1]])).collect()
+    }
+
+    fn f32s(&self) -> Vec<f32> {
+        self.data.chunks_exact(4).map(|b| f32::from_le_bytes(b.try_into().unwrap())).collect()
+    }
+}
+
+// ── Targets ───────────────────────────────────────────────────────────────
+
+/// Soft `[win, draw, loss]` target for a centipawn score, as in the Python
+/// pipeline: a logistic win probability at 180 cp, and a draw share that
+/// fades out linearly by 800 cp.
+pub fn cp_to_wdl(cp: f32) -> [f32; 3] {
+    let p_win = 1.0 / (1.0 + (-cp / 180.0).exp());
+    let draw = (1.0 - cp.abs() / 800.0).max(0.0);
+    let (win, loss) = (p_win * (1.0 - draw), (1.0 - p_win) * (1.0 - draw));
+    let sum = win + draw + loss;
+    [win / sum, draw / sum, loss / sum]
+}
+
+// ── Dataset ───────────────────────────────────────────────────────────────
+
+/// Dual-perspective positions with white-absolute scores.
+#[derive(Clone, Debug, Default)]
+pub struct Dataset {
+    /// Feature indices per position, `MAX_ACTIVE` each, padded.
+    white: Vec<u16>,
+    black: Vec<u16>,
+    counts: Vec<u8>,
+    /// Centipawns as written, for white.
+    cp: Vec<f32>,
+    piece_count: Vec<u8>,
+}
+
+/// Shard file `{prefix}{ext}`; a trailing `.jsonl` on the prefix is dropped,
+/// so the preprocessor's input name can be passed as well.
+fn shard_path(prefix: &Path, ext: &str) -> PathBuf {
+    let prefix = prefix.to_string_lossy();
+    let prefix = prefix.strip_suffix(".jsonl").unwrap_or(&prefix);
+    PathBuf::from(format!("{prefix}{ext}"))
+}
+
+impl Dataset {
+    /// Load one shard, checking it was encoded for `layout`: every active
+    /// index below its input size and every padding slot equal to it.
+    pub fn load(prefix: &Path, layout: FeatureLayout) -> Result<Self, String> {
+        let read = |ext: &str| {
+            let path = shard_path(prefix, ext);
+            read_npy(&path).map(|npy| (path, npy))
+        };
+        let (wp, white) = read(".white_indices.npy")?;
+        let (bp, black) = read(".black_indices.npy")?;
+        let (cnp, counts) = read(".counts.npy")?;
+        let (cpp, cp) = read(".cp.npy")?;
+        let (pcp, piece_count) = read(".piece_count.npy")?;
+
+        let n = white.expect(&wp, "<u2", &[None, Some(MAX_ACTIVE)], 2)?;
+        black.expect(&bp, "<u2", &[Some(n), Some(MAX_ACTIVE)], 2)?;
+        counts.expect(&cnp, "|u1", &[Some(n)], 1)?;
+        cp.expect(&cpp, "<f4", &[Some(n)], 4)?;
+        piece_count.expect(&pcp, "|u1", &[Some(n)], 1)?;
+
+        let data = Dataset {
+            white: white.u16s(),
+            black: black.u16s(),
+            counts: counts.data,
+            cp: cp.f32s(),
+            piece_count: piece_count.data,
+        };
+
+        let dim = layout.input_dim();
+        for i in 0..n {
+            let count = data.counts[i] as usize;
+            let rows = [&data.white[i * MAX_ACTIVE..][..MAX_ACTIVE], &data.black[i * MAX_ACTIVE..][..MAX_ACTIVE]];
+            let valid = count <= MAX_ACTIVE
+                && rows.iter().all(|row| {
+                    row[..count].iter().all(|&f| (f as usize) < dim) && row[count..].iter().all(|&f| f as usize == dim)
+                });
+            if !valid {
+                return Err(format!(
+                    "{}: position {i} is not encoded as {layout} ({dim} inputs); \
+                     was it preprocessed with other --features/--king-buckets?",
+                    prefix.display()
+                ));
+            }
+        }
+        Ok(data)
+    }
+
+    /// Load and concatenate several shards.
+    pub fn load_all(prefixes: &[PathBuf], layout: FeatureLayout) -> Result<Self, String> {
+        let mut all = Dataset::default();
+        for prefix in prefixes {
+            let shard = Self::load(prefix, layout)?;
+            all.white.extend(shard.white);
+            all.black.extend(shard.black);
+            all.counts.extend(shard.counts);
+            all.cp.extend(shard.cp);
+            all.piece_count.extend(shard.piece_count);
+        }
+        Ok(all)
+    }
+
+    pub fn len(&self) -> usize {
+        self.cp.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cp.is_empty()
+    }
+
+    /// Active white- and black-perspective features of position `i`.
+    pub fn features(&self, i: usize) -> (&[u16], &[u16]) {
+        let count = self.counts[i] as usize;
+        (&self.white[i * MAX_ACTIVE..][..count], &self.black[i * MAX_ACTIVE..][..count])
+    }
+
+    /// White-absolute score of position `i`, unclipped.
+    pub fn cp(&self, i: usize) -> f32 {
+        self.cp[i]
+    }
+
+    /// Number of pieces on the board in position `i`, kings included.
+    pub fn piece_count(&self, i: usize) -> usize {
+        self.piece_count[i] as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wdl_targets_follow_the_score() {
+        let even = cp_to_wdl(0.0);
+        assert!((even[1] - 1.0).abs() < 1e-6, "a level position is a sure draw: {even:?}");
+
+        for cp in [-1500.0, -300.0, -50.0, 50.0, 300.0, 1500.0] {
+            let [win, draw, loss] = cp_to_wdl(cp);
+            assert!((win + draw + loss - 1.0).abs() < 1e-6);
+            assert_eq!(win > loss, cp > 0.0);
+            let [w2, d2, l2] = cp_to_wdl(-cp);
+            assert!((win - l2).abs() < 1e-6 && (draw - d2).abs() < 1e-6 && (loss - w2).abs() < 1e-6);
+        }
+        assert_eq!(cp_to_wdl(800.0)[1], 0.0);
+        assert!(cp_to_wdl(1500.0)[0] > 0.99);
+    }
+
+    #[test]
+    fn reads_preprocessor_headers() {
+        let dir = std::env::temp_dir().join(format!("nnue_trainer_npy_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("a.npy");
+
+        let mut bytes = Vec::new();
+        nnue_preprocess::write_npy_header(&mut bytes, "<u2", &[3, MAX_ACTIVE]).unwrap();
+        assert_eq!(bytes.len() % 64, 0);
+        for v in 0..3 * MAX_ACTIVE as u16 {
+            bytes.extend_from_slice(&v.to_le_bytes());
+        }
+        fs::write(&path, &bytes).unwrap();
+
+        let npy = read_npy(&path).unwrap();
+        assert_eq!(npy.descr, "<u2");
+        assert_eq!(npy.shape, [3, MAX_ACTIVE]);
+        assert_eq!(npy.expect(&path, "<u2", &[None, Some(MAX_ACTIVE)], 2), Ok(3));
+        assert!(npy.expect(&path, "<f4", &[None, Some(MAX_ACTIVE)], 4).is_err());
+        assert_eq!(npy.u16s()[MAX_ACTIVE + 1], MAX_ACTIVE as u16 + 1);
+
+        let mut bytes = Vec::new();
+        nnue_preprocess::write_npy_header(&mut bytes, "|u1", &[5]).unwrap();
+        bytes.extend_from_slice(&[1, 2, 3, 4, 5]);
+        fs::write(&path, &bytes).unwrap();
+        let npy = read_npy(&path).unwrap();
+        assert_eq!((npy.descr.as_str(), npy.shape.as_slice()), ("|u1", &[5][..]));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn shard_prefix_may_name_the_jsonl() {
+        assert_eq!(shard_path(Path::new("d/train.jsonl"), ".cp.npy"), Path::new("d/train.cp.npy"));
+        assert_eq!(shard_path(Path::new("d/train"), ".cp.npy"), Path::new("d/train.cp.npy"));
+    }
+}
@@ -0,0 +1,153 @@
+//! CPU trainer for the dual-perspective NNUE.
+//!
+//! Reads the `.npy` shards of `nnue_preprocess --dual`, trains the network
+//! the Python pipeline trains (`EvalNetDual`: shared feature transformer,
+//! SCReLU, 2·H1 → H2 → bucketed cp and WDL heads) with the same loss,
+//! AdamW and learning-rate schedule, and exports native net files that
+//! `init_neural_eval` loads.  No GPU, no Python: meant for small nets,
+//! fine-tuning and tests rather than the big runs.
+
+pub mod data;
+pub mod network;
+pub mod trainer;
+
+pub use data::Dataset;
+pub use network::Network;
+pub use trainer::{Metrics, TrainConfig, Trainer};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fmt::Write as _;
+    use std::path::{Path, PathBuf};
+
+    use chess_board::{ChessBoard, FENParser};
+    use move_generator::{move_generator::get_all_legal_moves_for_color, piece_conductor::PieceConductor};
+    use nnue_features::FeatureLayout;
+    use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+    /// Material balance for white of a FEN's placement field, at a tenth of
+    /// the usual values so a few hundred optimizer steps can fit it.
+    fn material(fen: &str) -> i32 {
+        let value = |c: char| match c.to_ascii_lowercase() {
+            'p' => 10,
+            'n' | 'b' => 30,
+            'r' => 50,
+            'q' => 90,
+            _ => 0,
+        };
+        let placement = fen.split(' ').next().unwrap();
+        placement.chars().map(|c| if c.is_ascii_uppercase() { value(c) } else { -value(c) }).sum()
+    }
+
+    /// Positions of random games scored by material, written as JSONL and
+    /// encoded with `nnue_preprocess`.  Returns the shard prefix and FENs.
+    fn random_shard(dir: &Path, name: &str, games: usize, seed: u64) -> (PathBuf, Vec<String>) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let conductor = PieceConductor::new();
+        let mut jsonl = String::new();
+        let mut fens = Vec::new();
+        for _ in 0..games {
+            let mut board = ChessBoard::new();
+            for _ in 0..160 {
+                let mut legal = Vec::new();
+                let white = board.is_white_active();
+                get_all_legal_moves_for_color(&mut board, &conductor, white, &mut legal, &mut Vec::new());
+                let Some(&mv) = legal.choose(&mut rng) else { break };
+                let mut mv = mv;
+                board.make_move(&mut mv);
+
+                let fen = FENParser::board_to_fen(&board);
+                // The JSONL scores are for the side to move.
+                let cp = material(&fen) * if board.is_white_active() { 1 } else { -1 };
+                writeln!(jsonl, "{{\"fen\": \"{fen}\", \"cp\": {cp}}}").unwrap();
+                fens.push(fen);
+            }
+        }
+        let input = dir.join(format!("{name}.jsonl"));
+        std::fs::write(&input, jsonl).unwrap();
+        let prefix = dir.join(name);
+        nnue_preprocess::run(&input, &prefix, FeatureLayout::HALFKP, true, 1500.0);
+        (prefix, fens)
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("nnue_trainer_{name}_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Trains an engine-sized net on a few thousand positions, then checks
+    /// the engine loads the export and evaluates as the trainer does.
+    #[test]
+    fn trained_net_loads_in_the_engine() {
+        let dir = temp_dir("engine");
+        let (prefix, fens) = random_shard(&dir, "train", 24, 1);
+        let layout = FeatureLayout::HALFKP;
+        let data = Dataset::load(&prefix, layout).unwrap();
+        assert_eq!(data.len(), fens.len());
+
+        // Far too few games to generalize over 12,288 features: this checks
+        // the trainer fits what it is given.
+        let config = TrainConfig { epochs: 6, batch_size: 64, lr: 1e-2, warmup_epochs: 0, ..TrainConfig::default() };
+        let mut trainer = Trainer::new(Network::new(layout, 1024, 32, 3, 5).unwrap(), config);
+        let before = trainer.evaluate(&data);
+        while trainer.epoch < trainer.config.epochs {
+            trainer.train_epoch(&data);
+        }
+        let after = trainer.evaluate(&data);
+        assert!(after.loss < 0.6 * before.loss, "loss {:.1} → {:.1}", before.loss, after.loss);
+        assert!(after.cp_mae < 0.75 * before.cp_mae, "cp MAE {:.1} → {:.1}", before.cp_mae, after.cp_mae);
+
+        let net = trainer.net.to_net_file(vec![("dataset".into(), "random games".into())]).unwrap();
+        chess_evaluation::init_neural_eval_from_bytes(&net.to_bytes()).unwrap();
+        chess_evaluation::set_neural_eval_enabled(true);
+        assert_eq!(chess_evaluation::neural_net_hash(), Some(net.hash_hex().as_str()));
+
+        let mut s = trainer.net.scratch();
+        let mut board = ChessBoard::new();
+        for fen in fens.iter().step_by(37) {
+            board.clear();
+            FENParser::set_board_from_fen(&mut board, fen);
+            let (w, b, count) = nnue_preprocess::encode_dual(layout, &board);
+            let bucket = trainer.net.bucket(board.get_all_pieces().count_ones() as usize);
+            let (cp, _) = trainer.net.forward(&w[..count as usize], &b[..count as usize], bucket, &mut s);
+            let engine = chess_evaluation::neural_eval::try_neural_eval(&board).expect("neural eval unavailable");
+            assert!((engine as f32 - cp).abs() <= 1.0 + 0.01 * cp.abs(), "{fen}: engine {engine}, trainer {cp:.1}");
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Stopping after an epoch and resuming from the checkpoint gives the
+    /// same network as training straight through.
+    #[test]
+    fn resuming_matches_an_uninterrupted_run() {
+        let dir = temp_dir("resume");
+        let (prefix, _) = random_shard(&dir, "train", 4, 3);
+        let layout = FeatureLayout::HALFKP;
+        let data = Dataset::load_all(&[prefix], layout).unwrap();
+        let config = TrainConfig { epochs: 2, batch_size: 100, warmup_epochs: 1, ..TrainConfig::default() };
+        let net = Network::new(layout, 64, 8, 2, 9).unwrap();
+
+        let mut straight = Trainer::new(net.clone(), config.clone());
+        straight.train_epoch(&data);
+        let second = straight.train_epoch(&data);
+
+        let mut first = Trainer::new(net, config.clone());
+        first.train_epoch(&data);
+        first.best_val_loss = 1.5;
+        let checkpoint = dir.join("checkpoint.bin");
+        first.save(&checkpoint).unwrap();
+        let mut resumed = Trainer::load(&checkpoint, config).unwrap();
+        assert_eq!((resumed.epoch, resumed.step, resumed.best_val_loss), (1, first.step, 1.5));
+        assert_eq!(resumed.net.arch, first.net.arch);
+        let resumed_second = resumed.train_epoch(&data);
+
+        assert_eq!(resumed.net.params, straight.net.params);
+        assert_eq!(resumed_second.loss, second.loss);
+
+        std::fs::write(&checkpoint, b"XCTRAIN\n\x01\x00").unwrap();
+        assert!(Trainer::load(&checkpoint, TrainConfig::default()).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,200 @@
+//! nnue_trainer — train the dual-perspective NNUE on the CPU.
+//!
+//! Usage:
+//!   cargo run -p nnue_trainer --release -- --train data/train --val data/val --out runs/a
+//!   cargo run -p nnue_trainer --release -- --train data/train --out runs/a --resume runs/a/checkpoint.bin
+//!
+//! `--train` and `--val` take the prefixes given to `nnue_preprocess --dual
+//! --output`, `--train` any number of times.  Every epoch writes
+//! `checkpoint.bin` to the output directory, and `net.nnue` whenever the
+//! validation loss improves (every epoch without `--val`), loadable as an
+//! engine `EvalFile`.  A resumed run takes the network shape from the
+//! checkpoint and everything else from the options, which should match.
+
+use std::fs;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use clap::Parser;
+use chess_evaluation::net_file::NetFile;
+use nnue_features::{FeatureLayout, FeatureSet, KingBuckets};
+use nnue_trainer::{Dataset, Metrics, Network, TrainConfig, Trainer};
+
+#[derive(Parser)]
+#[command(about = "Train the dual-perspective NNUE on nnue_preprocess --dual shards")]
+struct Args {
+    /// Training shard prefix (repeatable)
+    #[arg(long, required = true)]
+    train: Vec<PathBuf>,
+
+    /// Validation shard prefix; keeps the net with the lowest validation loss
+    #[arg(long)]
+    val: Option<PathBuf>,
+
+    /// Output directory for checkpoint.bin and net.nnue
+    #[arg(long)]
+    out: PathBuf,
+
+    #[arg(long, default_value_t = 250)]
+    epochs: usize,
+
+    #[arg(long, default_value_t = 32768)]
+    batch_size: usize,
+
+    #[arg(long, default_value_t = 1e-3)]
+    lr: f32,
+
+    #[arg(long, default_value_t = 5)]
+    warmup_epochs: usize,
+
+    #[arg(long, default_value_t = 1e-4)]
+    weight_decay: f32,
+
+    /// Global gradient norm limit
+    #[arg(long, default_value_t = 1.0)]
+    grad_clip: f32,
+
+    #[arg(long, default_value_t = 0.3)]
+    cp_weight: f32,
+
+    #[arg(long, default_value_t = 0.7)]
+    wdl_weight: f32,
+
+    /// Clip of the cp regression target
+    #[arg(long, default_value_t = 800.0)]
+    max_cp_abs: f32,
+
+    /// Input features the shards were encoded with: legacy-768, halfkp or halfka
+    #[arg(long, default_value = "halfkp")]
+    features: FeatureSet,
+
+    /// King buckets the shards were encoded with: 8, 16 or 32
+    #[arg(long, default_value_t = 8)]
+    king_buckets: usize,
+
+    #[arg(long, default_value_t = 1024)]
+    hidden1: usize,
+
+    #[arg(long, default_value_t = 32)]
+    hidden2: usize,
+
+    #[arg(long, default_value_t = 3)]
+    output_buckets: usize,
+
+    /// Seed of the initial weights and of the shuffles
+    #[arg(long, default_value_t = 42)]
+    seed: u64,
+
+    /// Continue from a checkpoint written by an earlier run
+    #[arg(long, conflicts_with = "init")]
+    resume: Option<PathBuf>,
+
+    /// Start from the weights of a net file instead of a random init
+    #[arg(long)]
+    init: Option<PathBuf>,
+
+    /// KEY=VALUE recorded in the exported net's metadata (repeatable)
+    #[arg(long)]
+    meta: Vec<String>,
+}
+
+fn fail(msg: impl std::fmt::Display) -> ! {
+    eprintln!("error: {msg}");
+    std::process::exit(1);
+}
+
+fn summary(m: &Metrics) -> String {
+    format!(
+        "loss={:.4} cp_loss={:.1} wdl_loss={:.4} cp_mae={:.2} wdl_acc={:.3}",
+        m.loss, m.cp_loss, m.wdl_loss, m.cp_mae, m.wdl_acc
+    )
+}
+
+fn main() {
+    let args = Args::parse();
+    let config = TrainConfig {
+        epochs: args.epochs,
+        batch_size: args.batch_size,
+        lr: args.lr,
+        warmup_epochs: args.warmup_epochs,
+        weight_decay: args.weight_decay,
+        grad_clip: args.grad_clip,
+        cp_weight: args.cp_weight,
+        wdl_weight: args.wdl_weight,
+        max_cp_abs: args.max_cp_abs,
+        seed: args.seed,
+    };
+    let meta: Vec<(String, String)> = args
+        .meta
+        .iter()
+        .map(|kv| kv.split_once('=').map(|(k, v)| (k.to_string(), v.to_string())))
+        .collect::<Option<_>>()
+        .unwrap_or_else(|| fail("--meta takes KEY=VALUE"));
+
+    let mut trainer = match (&args.resume, &args.init) {
+        (Some(path), _) => Trainer::load(path, config).unwrap_or_else(|e| fail(e)),
+        (None, Some(path)) => {
+            let bytes = fs::read(path).unwrap_or_else(|e| fail(format!("cannot read {}: {e}", path.display())));
+            let net = NetFile::from_bytes(&bytes).and_then(|n| Network::from_net_file(&n));
+            Trainer::new(net.unwrap_or_else(|e| fail(format!("{}: {e}", path.display()))), config)
+        }
+        (None, None) => {
+            let kings = KingBuckets::from_count(args.king_buckets).unwrap_or_else(|| fail("--king-buckets must be 8, 16 or 32"));
+            let layout = FeatureLayout::new(args.features, kings);
+            Trainer::new(Network::new(layout, args.hidden1, args.hidden2, args.output_buckets, args.seed).unwrap_or_else(|e| fail(e)), config)
+        }
+    };
+    let layout = trainer.net.arch.features;
+    println!("architecture  {}", trainer.net.arch);
+
+    let t0 = Instant::now();
+    let train = Dataset::load_all(&args.train, layout).unwrap_or_else(|e| fail(e));
+    let val = args.val.as_ref().map(|p| Dataset::load(p, layout).unwrap_or_else(|e| fail(e)));
+    println!(
+        "loaded {} training and {} validation positions in {:.1}s",
+        train.len(),
+        val.as_ref().map_or(0, Dataset::len),
+        t0.elapsed().as_secs_f64()
+    );
+    if train.is_empty() {
+        fail("no training positions");
+    }
+
+    fs::create_dir_all(&args.out).unwrap_or_else(|e| fail(format!("cannot create {}: {e}", args.out.display())));
+    let checkpoint = args.out.join("checkpoint.bin");
+    let net_path = args.out.join("net.nnue");
+
+    while trainer.epoch < trainer.config.epochs {
+        let t0 = Instant::now();
+        let lr = trainer.lr();
+        let tr = trainer.train_epoch(&train);
+        let va = val.as_ref().map(|v| trainer.evaluate(v));
+        let secs = t0.elapsed().as_secs_f64();
+        println!(
+            "epoch={} lr={lr:.2e} train[{}]{} ({secs:.1}s, {:.0} pos/s)",
+            trainer.epoch,
+            summary(&tr),
+            va.as_ref().map_or(String::new(), |va| format!(" val[{}]", summary(va))),
+            train.len() as f64 / secs
+        );
+
+        let improved = va.is_none_or(|va| va.loss < trainer.best_val_loss);
+        if improved {
+            let mut metadata = vec![
+                ("trainer".to_string(), "nnue_trainer".to_string()),
+                ("epochs".to_string(), trainer.epoch.to_string()),
+                ("positions".to_string(), train.len().to_string()),
+            ];
+            if let Some(va) = va {
+                trainer.best_val_loss = va.loss;
+                metadata.push(("val_loss".to_string(), format!("{:.4}", va.loss)));
+            }
+            metadata.extend(meta.iter().cloned());
+            let net = trainer.net.to_net_file(metadata).unwrap_or_else(|e| fail(e));
+            fs::write(&net_path, net.to_bytes())
+                .unwrap_or_else(|e| fail(format!("cannot write {}: {e}", net_path.display())));
+            println!("saved net: {} ({})", net_path.display(), net.hash_hex());
+        }
+        trainer.save(&checkpoint).unwrap_or_else(|e| fail(e));
+    }
+}
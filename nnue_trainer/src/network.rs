@@ -0,0 +1,470 @@
+//! The dual-perspective network in f32: forward, backward and export.
+//!
+//! Same shape as `EvalNetDual` in `nn_training`: a shared feature
+//! transformer applied to the white and black perspectives, each SCReLU'd
+//! and concatenated white first, then a SCReLU hidden layer and a cp and a
+//! WDL head with one row per output bucket.
+
+use chess_evaluation::net_file::{Architecture, NetFile, TENSOR_NAMES};
+use nnue_features::{bucket_for_piece_count, FeatureLayout};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// Quantization scale of exported nets, the one the engine's integer path
+/// expects.
+pub const SCALE: f32 = 256.0;
+
+// Tensor positions, in `TENSOR_NAMES` order.
+const FT_W: usize = 0;
+const FT_B: usize = 1;
+const L2_W: usize = 2;
+const L2_B: usize = 3;
+const CP_W: usize = 4;
+const CP_B: usize = 5;
+const WDL_W: usize = 6;
+const WDL_B: usize = 7;
+
+/// SCReLU: clamp(x, 0, 1)².
+#[inline]
+fn screlu(x: f32) -> f32 {
+    let c = x.clamp(0.0, 1.0);
+    c * c
+}
+
+/// Derivative of SCReLU, passing through at the clamp bounds like torch.
+#[inline]
+fn screlu_grad(x: f32) -> f32 {
+    if (0.0..=1.0).contains(&x) { 2.0 * x } else { 0.0 }
+}
+
+/// Dot product in eight independent lanes, so it vectorizes.
+#[inline]
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    let (ca, cb) = (a.chunks_exact(8), b.chunks_exact(8));
+    let tail: f32 = ca.remainder().iter().zip(cb.remainder()).map(|(x, y)| x * y).sum();
+    let mut lanes = [0.0f32; 8];
+    for (x, y) in ca.zip(cb) {
+        for ((l, x), y) in lanes.iter_mut().zip(x).zip(y) {
+            *l += x * y;
+        }
+    }
+    lanes.iter().sum::<f32>() + tail
+}
+
+/// `y += a * x`.
+#[inline]
+fn axpy(y: &mut [f32], a: f32, x: &[f32]) {
+    for (y, x) in y.iter_mut().zip(x) {
+        *y += a * x;
+    }
+}
+
+/// A network with f32 parameters, all tensors in one vector in
+/// `TENSOR_NAMES` order.  The feature transformer is stored one row of
+/// `hidden1` weights per input feature (the transpose of the exported
+/// tensor), so a position only touches the rows of its active features.
+#[derive(Clone, Debug)]
+pub struct Network {
+    pub arch: Architecture,
+    pub params: Vec<f32>,
+    /// Start of each tensor in `params`, plus the end.
+    offsets: [usize; 9],
+}
+
+/// Activations of one forward pass, kept for the backward pass.
+pub struct Scratch {
+    /// Feature transformer outputs before SCReLU, white then black.
+    acc: Vec<f32>,
+    h1: Vec<f32>,
+    pre2: Vec<f32>,
+    h2: Vec<f32>,
+    dpre2: Vec<f32>,
+    dh1: Vec<f32>,
+}
+
+/// Gradients of a batch: dense for everything after the feature
+/// transformer weights, one row per touched feature for those.
+pub struct Gradients {
+    hidden1: usize,
+    pub(crate) dense: Vec<f32>,
+    /// Row of each feature in `rows`, `u32::MAX` if untouched.
+    slot: Vec<u32>,
+    pub(crate) features: Vec<u16>,
+    pub(crate) rows: Vec<f32>,
+}
+
+impl Gradients {
+    pub(crate) fn row_mut(&mut self, feature: u16) -> &mut [f32] {
+        let slot = &mut self.slot[feature as usize];
+        if *slot == u32::MAX {
+            *slot = self.features.len() as u32;
+            self.features.push(feature);
+            self.rows.resize(self.rows.len() + self.hidden1, 0.0);
+        }
+        &mut self.rows[*slot as usize * self.hidden1..][..self.hidden1]
+    }
+
+    /// Add `other` in, rows in `other`'s order so merging is deterministic.
+    pub(crate) fn merge(&mut self, other: &Gradients) {
+        axpy(&mut self.dense, 1.0, &other.dense);
+        for (i, &f) in other.features.iter().enumerate() {
+            let h = self.hidden1;
+            axpy(self.row_mut(f), 1.0, &other.rows[i * h..][..h]);
+        }
+    }
+
+    pub(crate) fn norm_sq(&self) -> f64 {
+        self.dense.iter().chain(&self.rows).map(|&g| g as f64 * g as f64).sum()
+    }
+
+    pub(crate) fn scale(&mut self, k: f32) {
+        for g in self.dense.iter_mut().chain(self.rows.iter_mut()) {
+            *g *= k;
+        }
+    }
+}
+
+impl Network {
+    /// A freshly initialized network, as `EvalNetDual` initializes it: the
+    /// feature transformer uniform in ±0.1 with zero bias, the other layers
+    /// uniform in ±1/√fan_in.  Fails for shapes `Architecture::tensor_lens`
+    /// rejects.
+    pub fn new(features: FeatureLayout, hidden1: usize, hidden2: usize, output_buckets: usize, seed: u64) -> Result<Self, String> {
+        let arch = Architecture { features, dual_perspective: true, hidden1, hidden2, output_buckets, scale: SCALE };
+        let mut net = Self::zeroed(arch)?;
+        let mut rng = StdRng::seed_from_u64(seed);
+        let fan_in = [0, 0, 2 * hidden1, 2 * hidden1, hidden2, hidden2, hidden2, hidden2];
+        for (k, fan_in) in fan_in.into_iter().enumerate() {
+            let bound = match k {
+                FT_W => 0.1,
+                FT_B => continue,
+                _ => 1.0 / (fan_in as f32).sqrt(),
+            };
+            for w in net.tensor_mut(k) {
+                *w = rng.gen_range(-bound..bound);
+            }
+        }
+        Ok(net)
+    }
+
+    /// A network of shape `arch` with all parameters zero.
+    pub(crate) fn zeroed(arch: Architecture) -> Result<Self, String> {
+        let mut offsets = [0; 9];
+        for (k, len) in arch.tensor_lens()?.into_iter().enumerate() {
+            offsets[k + 1] = offsets[k] + len;
+        }
+        Ok(Network { arch, params: vec![0.0; offsets[8]], offsets })
+    }
+
+    /// Dequantize a dual-perspective net file, e.g. to fine-tune it.
+    pub fn from_net_file(net: &NetFile) -> Result<Self, String> {
+        if !net.arch.dual_perspective {
+            return Err("only dual-perspective nets can be trained".into());
+        }
+        let mut out = Self::zeroed(net.arch.clone())?;
+        out.arch.scale = SCALE;
+        let (dim, h1) = (net.arch.features.input_dim(), net.arch.hidden1);
+        for (k, raw) in net.tensors.iter().enumerate() {
+            let dst = out.tensor_mut(k);
+            for (i, &v) in raw.iter().enumerate() {
+                // The file holds the feature transformer as [hidden1 × inputs].
+                let i = if k == FT_W { (i % dim) * h1 + i / dim } else { i };
+                dst[i] = v as f32 / net.arch.scale;
+            }
+        }
+        Ok(out)
+    }
+
+    /// Quantize to a net file the engine loads, failing if a weight leaves
+    /// the i16 range at `SCALE` rather than clipping it.
+    pub fn to_net_file(&self, metadata: Vec<(String, String)>) -> Result<NetFile, String> {
+        let (dim, h1) = (self.arch.features.input_dim(), self.arch.hidden1);
+        let mut tensors: [Vec<i16>; 8] = Default::default();
+        for (k, out) in tensors.iter_mut().enumerate() {
+            let src = self.tensor(k);
+            let mut overflow = 0;
+            *out = (0..src.len())
+                .map(|i| {
+                    let v = if k == FT_W { src[(i % dim) * h1 + i / dim] } else { src[i] };
+                    let q = (v * SCALE).round();
+                    if !(i16::MIN as f32..=i16::MAX as f32).contains(&q) {
+                        overflow += 1;
+                    }
+                    q as i16
+                })
+                .collect();
+            if overflow > 0 {
+                return Err(format!(
+                    "quantization overflow in {}: {overflow}/{} values out of i16 range at scale {SCALE}",
+                    TENSOR_NAMES[k],
+                    src.len()
+                ));
+            }
+        }
+        let mut arch = self.arch.clone();
+        arch.scale = SCALE;
+        Ok(NetFile { arch, metadata, tensors })
+    }
+
+    fn tensor(&self, k: usize) -> &[f32] {
+        &self.params[self.offsets[k]..self.offsets[k + 1]]
+    }
+
+    fn tensor_mut(&mut self, k: usize) -> &mut [f32] {
+        &mut self.params[self.offsets[k]..self.offsets[k + 1]]
+    }
+
+    /// Number of feature transformer weights, the part of `params` trained
+    /// sparsely.
+    pub(crate) fn sparse_len(&self) -> usize {
+        self.offsets[FT_B]
+    }
+
+    pub fn scratch(&self) -> Scratch {
+        let (h1, h2) = (self.arch.hidden1, self.arch.hidden2);
+        Scratch {
+            acc: vec![0.0; 2 * h1],
+            h1: vec![0.0; 2 * h1],
+            pre2: vec![0.0; h2],
+            h2: vec![0.0; h2],
+            dpre2: vec![0.0; h2],
+            dh1: vec![0.0; 2 * h1],
+        }
+    }
+
+    pub fn gradients(&self) -> Gradients {
+        Gradients {
+            hidden1: self.arch.hidden1,
+            dense: vec![0.0; self.params.len() - self.sparse_len()],
+            slot: vec![u32::MAX; self.arch.features.input_dim()],
+            features: Vec::new(),
+            rows: Vec::new(),
+        }
+    }
+
+    /// Output bucket of a position with `pieces` pieces.
+    pub fn bucket(&self, pieces: usize) -> usize {
+        bucket_for_piece_count(pieces, self.arch.output_buckets)
+    }
+
+    /// White-absolute centipawns and WDL logits (`[win, draw, loss]`) of a
+    /// position given its active features from both sides.
+    pub fn forward(&self, white: &[u16], black: &[u16], bucket: usize, s: &mut Scratch) -> (f32, [f32; 3]) {
+        let (h1, h2) = (self.arch.hidden1, self.arch.hidden2);
+        let ft_w = self.tensor(FT_W);
+        for (acc, features) in s.acc.chunks_exact_mut(h1).zip([white, black]) {
+            acc.copy_from_slice(self.tensor(FT_B));
+            for &f in features {
+                axpy(acc, 1.0, &ft_w[f as usize * h1..][..h1]);
+            }
+        }
+        for (h, &a) in s.h1.iter_mut().zip(&s.acc) {
+            *h = screlu(a);
+        }
+
+        let (l2_w, l2_b) = (self.tensor(L2_W), self.tensor(L2_B));
+        for j in 0..h2 {
+            s.pre2[j] = l2_b[j] + dot(&l2_w[j * 2 * h1..][..2 * h1], &s.h1);
+            s.h2[j] = screlu(s.pre2[j]);
+        }
+
+        let cp = self.tensor(CP_B)[bucket] + dot(&self.tensor(CP_W)[bucket * h2..][..h2], &s.h2);
+        let (wdl_w, wdl_b) = (self.tensor(WDL_W), self.tensor(WDL_B));
+        let logits = std::array::from_fn(|k| {
+            let row = bucket * 3 + k;
+            wdl_b[row] + dot(&wdl_w[row * h2..][..h2], &s.h2)
+        });
+        (cp, logits)
+    }
+
+    /// Accumulate the gradients of the last `forward` on `s`, given the loss
+    /// gradients with respect to its cp output and WDL logits.
+    #[allow(clippy::too_many_arguments)]
+    pub fn backward(
+        &self,
+        white: &[u16],
+        black: &[u16],
+        bucket: usize,
+        s: &mut Scratch,
+        d_cp: f32,
+        d_logits: [f32; 3],
+        grads: &mut Gradients,
+    ) {
+        let (h1, h2) = (self.arch.hidden1, self.arch.hidden2);
+        let base = self.sparse_len();
+        let range = |k: usize| self.offsets[k] - base..self.offsets[k + 1] - base;
+        let g = &mut grads.dense;
+
+        // Heads, and the gradient reaching the hidden layer.
+        let (cp_w, wdl_w) = (self.tensor(CP_W), self.tensor(WDL_W));
+        axpy(&mut g[range(CP_W)][bucket * h2..][..h2], d_cp, &s.h2);
+        g[range(CP_B)][bucket] += d_cp;
+        for (d, w) in s.dpre2.iter_mut().zip(&cp_w[bucket * h2..][..h2]) {
+            *d = d_cp * w;
+        }
+        for (k, &dl) in d_logits.iter().enumerate() {
+            let row = bucket * 3 + k;
+            axpy(&mut g[range(WDL_W)][row * h2..][..h2], dl, &s.h2);
+            g[range(WDL_B)][row] += dl;
+            axpy(&mut s.dpre2, dl, &wdl_w[row * h2..][..h2]);
+        }
+        for (d, &x) in s.dpre2.iter_mut().zip(&s.pre2) {
+            *d *= screlu_grad(x);
+        }
+
+        // Layer 2, and the gradient reaching the feature transformer.
+        let l2_w = self.tensor(L2_W);
+        s.dh1.fill(0.0);
+        for (j, &d) in s.dpre2.iter().enumerate() {
+            if d == 0.0 {
+                continue;
+            }
+            axpy(&mut g[range(L2_W)][j * 2 * h1..][..2 * h1], d, &s.h1);
+            g[range(L2_B)][j] += d;
+            axpy(&mut s.dh1, d, &l2_w[j * 2 * h1..][..2 * h1]);
+        }
+        for (d, &x) in s.dh1.iter_mut().zip(&s.acc) {
+            *d *= screlu_grad(x);
+        }
+
+        // Feature transformer: the shared bias, then each active row.
+        for d in s.dh1.chunks_exact(h1) {
+            axpy(&mut g[range(FT_B)], 1.0, d);
+        }
+        for (d, features) in s.dh1.chunks_exact(h1).zip([white, black]) {
+            for &f in features {
+                axpy(grads.row_mut(f), 1.0, d);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tiny_net(seed: u64) -> Network {
+        let mut net = Network::new(FeatureLayout::LEGACY, 16, 8, 2, seed).unwrap();
+        // Larger first-layer weights than the default init, so that some
+        // units saturate and every SCReLU branch is exercised.
+        let mut rng = StdRng::seed_from_u64(seed + 1);
+        for w in net.tensor_mut(FT_W) {
+            *w = rng.gen_range(-0.3..0.3);
+        }
+        net
+    }
+
+    type Position = (Vec<u16>, Vec<u16>, usize, f32);
+
+    fn random_positions(rng: &mut StdRng, n: usize) -> Vec<Position> {
+        (0..n)
+            .map(|_| {
+                let count = rng.gen_range(2..=12);
+                let mut feature = || rng.gen_range(0..768u16);
+                let white = (0..count).map(|_| feature()).collect();
+                let black = (0..count).map(|_| feature()).collect();
+                (white, black, rng.gen_range(0..2), rng.gen_range(-1.0..1.0))
+            })
+            .collect()
+    }
+
+    /// A smooth loss on both outputs: cp²/2 plus a weighted logit sum.
+    fn loss(net: &Network, positions: &[Position]) -> f64 {
+        let mut s = net.scratch();
+        positions
+            .iter()
+            .map(|(w, b, bucket, t)| {
+                let (cp, logits) = net.forward(w, b, *bucket, &mut s);
+                (0.5 * cp * cp + t * (logits[0] - 2.0 * logits[1] + 0.5 * logits[2])) as f64
+            })
+            .sum()
+    }
+
+    #[test]
+    fn gradients_match_finite_differences() {
+        let net = tiny_net(7);
+        let mut rng = StdRng::seed_from_u64(8);
+        let positions = random_positions(&mut rng, 6);
+
+        let mut grads = net.gradients();
+        let mut s = net.scratch();
+        for (w, b, bucket, t) in &positions {
+            let (cp, _) = net.forward(w, b, *bucket, &mut s);
+            net.backward(w, b, *bucket, &mut s, cp, [*t, -2.0 * t, 0.5 * t], &mut grads);
+        }
+        let mut analytic = vec![0.0f32; net.params.len()];
+        analytic[net.sparse_len()..].copy_from_slice(&grads.dense);
+        for (i, &f) in grads.features.iter().enumerate() {
+            let row = &mut analytic[f as usize * 16..][..16];
+            row.copy_from_slice(&grads.rows[i * 16..][..16]);
+        }
+
+        // Every non-sparse parameter, and the touched feature rows.
+        let mut checked = 0;
+        let touched = grads.features.iter().flat_map(|&f| f as usize * 16..f as usize * 16 + 16);
+        for i in touched.chain(net.sparse_len()..net.params.len()) {
+            // Small enough not to step over an SCReLU bound.
+            let eps = 1e-3;
+            let mut plus = net.clone();
+            plus.params[i] += eps;
+            let mut minus = net.clone();
+            minus.params[i] -= eps;
+            let numeric = (loss(&plus, &positions) - loss(&minus, &positions)) / (2.0 * eps as f64);
+            let a = analytic[i] as f64;
+            assert!(
+                (numeric - a).abs() <= 2e-2 + 2e-2 * a.abs(),
+                "parameter {i}: analytic {a}, numeric {numeric}"
+            );
+            checked += 1;
+        }
+        assert!(checked > 500);
+    }
+
+    #[test]
+    fn untouched_rows_get_no_gradient() {
+        let net = tiny_net(3);
+        let mut grads = net.gradients();
+        let mut s = net.scratch();
+        net.forward(&[1, 5], &[700], 0, &mut s);
+        net.backward(&[1, 5], &[700], 0, &mut s, 1.0, [0.5, 0.0, -0.5], &mut grads);
+        assert_eq!(grads.features, [1, 5, 700]);
+        assert_eq!(grads.rows.len(), 3 * 16);
+    }
+
+    #[test]
+    fn merge_adds_rows_by_feature() {
+        let net = tiny_net(4);
+        let (mut a, mut b) = (net.gradients(), net.gradients());
+        a.row_mut(3)[0] = 1.0;
+        b.row_mut(9)[1] = 2.0;
+        b.row_mut(3)[0] = 0.5;
+        b.dense[0] = 4.0;
+        a.merge(&b);
+        assert_eq!(a.features, [3, 9]);
+        assert_eq!((a.rows[0], a.rows[16 + 1], a.dense[0]), (1.5, 2.0, 4.0));
+        assert!((a.norm_sq() - (1.5f64.powi(2) + 4.0 + 16.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn net_file_round_trip() {
+        let net = Network::new(FeatureLayout::HALFKP, 32, 8, 4, 1).unwrap();
+        let file = net.to_net_file(vec![("epochs".into(), "0".into())]).unwrap();
+        assert_eq!(file.arch.tensor_lens().unwrap(), std::array::from_fn(|k| net.tensor(k).len()));
+        // The file holds the feature transformer transposed.
+        let (dim, h1) = (FeatureLayout::HALFKP.input_dim(), 32);
+        assert_eq!(file.tensors[FT_W][5 * dim + 100], (net.params[100 * h1 + 5] * SCALE).round() as i16);
+
+        let back = Network::from_net_file(&NetFile::from_bytes(&file.to_bytes()).unwrap()).unwrap();
+        assert_eq!(back.arch, net.arch);
+        for (a, b) in back.params.iter().zip(&net.params) {
+            assert!((a - b).abs() <= 0.5 / SCALE + 1e-6);
+        }
+    }
+
+    #[test]
+    fn export_refuses_to_clip() {
+        let mut net = Network::new(FeatureLayout::LEGACY, 16, 8, 1, 2).unwrap();
+        net.tensor_mut(CP_B)[0] = 200.0;
+        let err = net.to_net_file(Vec::new()).unwrap_err();
+        assert!(err.contains("cp_head_bias"), "{err}");
+    }
+}
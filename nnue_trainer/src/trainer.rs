@@ -0,0 +1,463 @@
+//! Mini-batch training with the loss, optimizer and schedule of the Python
+//! pipeline, and checkpoints to resume it.
+//!
+//! The loss is `cp_weight · Huber(δ = 100)` on the clipped score plus
+//! `wdl_weight ·` soft-target cross-entropy against `cp_to_wdl` of the raw
+//! score, both averaged over the batch.  AdamW runs on the global-norm
+//! clipped gradient, the learning rate warming up linearly and then
+//! following a cosine down to 1/100 of it, per epoch.  Feature transformer
+//! rows are updated only in the batches whose positions use them, which is
+//! what keeps an epoch cheap with 12,288 rows; the steps a row sat out are
+//! replayed with a zero gradient (decay, fading moments) when it is next
+//! used and at the end of the epoch, so epochs end where dense AdamW would.
+//!
+//! Batches are split into fixed chunks computed in parallel and merged in
+//! order, so results do not depend on the number of threads.
+
+use std::fs;
+use std::path::Path;
+
+use chess_evaluation::net_file::Architecture;
+use nnue_features::{FeatureLayout, FeatureSet, KingBuckets};
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use rayon::prelude::*;
+
+use crate::data::{cp_to_wdl, Dataset};
+use crate::network::{Gradients, Network, SCALE};
+
+/// Positions per parallel work item.
+const CHUNK: usize = 64;
+const HUBER_DELTA: f32 = 100.0;
+const BETA1: f32 = 0.9;
+const BETA2: f32 = 0.999;
+const ADAM_EPS: f32 = 1e-8;
+
+const CHECKPOINT_MAGIC: [u8; 8] = *b"XCTRAIN\n";
+const CHECKPOINT_VERSION: u32 = 1;
+
+/// Hyper-parameters, defaulting to `configs/halfkp_dual_all_37m_1024.yaml`.
+#[derive(Clone, Debug)]
+pub struct TrainConfig {
+    pub epochs: usize,
+    pub batch_size: usize,
+    pub lr: f32,
+    pub warmup_epochs: usize,
+    pub weight_decay: f32,
+    /// Global gradient norm limit.
+    pub grad_clip: f32,
+    pub cp_weight: f32,
+    pub wdl_weight: f32,
+    /// Clip of the cp regression target; WDL targets use the raw score.
+    pub max_cp_abs: f32,
+    /// Seed of the per-epoch shuffles.
+    pub seed: u64,
+}
+
+impl Default for TrainConfig {
+    fn default() -> Self {
+        TrainConfig {
+            epochs: 250,
+            batch_size: 32768,
+            lr: 1e-3,
+            warmup_epochs: 5,
+            weight_decay: 1e-4,
+            grad_clip: 1.0,
+            cp_weight: 0.3,
+            wdl_weight: 0.7,
+            max_cp_abs: 800.0,
+            seed: 42,
+        }
+    }
+}
+
+impl TrainConfig {
+    /// Learning rate of epoch `epoch` (from 0).
+    pub fn lr_at(&self, epoch: usize) -> f32 {
+        let warmup = self.warmup_epochs;
+        if epoch < warmup {
+            let start = 1.0 / warmup as f32;
+            return self.lr * (start + (1.0 - start) * epoch as f32 / warmup as f32);
+        }
+        let min_lr = self.lr / 100.0;
+        let span = self.epochs.saturating_sub(warmup).max(1) as f32;
+        let t = (epoch - warmup) as f32 / span;
+        min_lr + (self.lr - min_lr) * 0.5 * (1.0 + (std::f32::consts::PI * t).cos())
+    }
+}
+
+/// Averages over an epoch.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Metrics {
+    pub loss: f64,
+    pub cp_loss: f64,
+    pub wdl_loss: f64,
+    /// Mean absolute error against the clipped cp target.
+    pub cp_mae: f64,
+    /// Share of positions whose most likely WDL outcome is the target's.
+    pub wdl_acc: f64,
+    pub positions: usize,
+}
+
+impl Metrics {
+    fn add(&mut self, other: &Metrics) {
+        self.loss += other.loss;
+        self.cp_loss += other.cp_loss;
+        self.wdl_loss += other.wdl_loss;
+        self.cp_mae += other.cp_mae;
+        self.wdl_acc += other.wdl_acc;
+        self.positions += other.positions;
+    }
+
+    /// Turn sums into means.
+    fn average(mut self) -> Self {
+        let n = self.positions.max(1) as f64;
+        self.loss /= n;
+        self.cp_loss /= n;
+        self.wdl_loss /= n;
+        self.cp_mae /= n;
+        self.wdl_acc /= n;
+        self
+    }
+}
+
+fn argmax(v: &[f32; 3]) -> usize {
+    (1..3).fold(0, |best, k| if v[k] > v[best] { k } else { best })
+}
+
+/// Run `positions` through `net`, summing their metrics and, when `grads`
+/// is given, their loss gradients scaled by `1 / batch`.
+fn process(
+    net: &Network,
+    config: &TrainConfig,
+    data: &Dataset,
+    positions: &[usize],
+    batch: usize,
+    mut grads: Option<&mut Gradients>,
+) -> Metrics {
+    let mut s = net.scratch();
+    let mut m = Metrics { positions: positions.len(), ..Metrics::default() };
+    for &i in positions {
+        let (white, black) = data.features(i);
+        let bucket = net.bucket(data.piece_count(i));
+        let (cp, logits) = net.forward(white, black, bucket, &mut s);
+
+        let target = data.cp(i).clamp(-config.max_cp_abs, config.max_cp_abs);
+        let err = cp - target;
+        let cp_loss = if err.abs() <= HUBER_DELTA {
+            0.5 * err * err
+        } else {
+            HUBER_DELTA * (err.abs() - 0.5 * HUBER_DELTA)
+        };
+
+        let wdl = cp_to_wdl(data.cp(i));
+        let max = logits[0].max(logits[1]).max(logits[2]);
+        let log_sum = logits.iter().map(|l| (l - max).exp()).sum::<f32>().ln() + max;
+        let wdl_loss: f32 = wdl.iter().zip(&logits).map(|(t, l)| -t * (l - log_sum)).sum();
+
+        m.loss += (config.cp_weight * cp_loss + config.wdl_weight * wdl_loss) as f64;
+        m.cp_loss += cp_loss as f64;
+        m.wdl_loss += wdl_loss as f64;
+        m.cp_mae += err.abs() as f64;
+        m.wdl_acc += (argmax(&logits) == argmax(&wdl)) as u8 as f64;
+
+        if let Some(grads) = grads.as_deref_mut() {
+            let k = 1.0 / batch as f32;
+            let d_cp = config.cp_weight * err.clamp(-HUBER_DELTA, HUBER_DELTA) * k;
+            let d_logits = std::array::from_fn(|j| config.wdl_weight * ((logits[j] - log_sum).exp() - wdl[j]) * k);
+            net.backward(white, black, bucket, &mut s, d_cp, d_logits, grads);
+        }
+    }
+    m
+}
+
+/// The constants of AdamW step `t` (from 1).
+struct AdamStep {
+    decay: f32,
+    step_size: f32,
+    bias2_sqrt: f32,
+}
+
+impl AdamStep {
+    fn new(t: u64, lr: f32, weight_decay: f32) -> Self {
+        let bias1 = 1.0 - BETA1.powi(t as i32);
+        let bias2 = 1.0 - BETA2.powi(t as i32);
+        AdamStep { decay: 1.0 - lr * weight_decay, step_size: lr / bias1, bias2_sqrt: bias2.sqrt() }
+    }
+
+    #[inline]
+    fn update_one(&self, p: &mut f32, m: &mut f32, v: &mut f32, g: f32) {
+        *p *= self.decay;
+        *m = BETA1 * *m + (1.0 - BETA1) * g;
+        *v = BETA2 * *v + (1.0 - BETA2) * g * g;
+        *p -= self.step_size * *m / (v.sqrt() / self.bias2_sqrt + ADAM_EPS);
+    }
+
+    fn update(&self, p: &mut [f32], m: &mut [f32], v: &mut [f32], g: &[f32]) {
+        for (((p, m), v), &g) in p.iter_mut().zip(m).zip(v).zip(g) {
+            self.update_one(p, m, v, g);
+        }
+    }
+}
+
+/// A network with its optimizer state and progress.
+pub struct Trainer {
+    pub net: Network,
+    pub config: TrainConfig,
+    /// AdamW moments, one per parameter.
+    m: Vec<f32>,
+    v: Vec<f32>,
+    /// Step each feature transformer row has been brought up to.
+    row_step: Vec<u64>,
+    /// Epochs completed.
+    pub epoch: usize,
+    /// Optimizer steps taken.
+    pub step: u64,
+    /// Lowest validation loss so far, `INFINITY` if none.
+    pub best_val_loss: f64,
+}
+
+impl Trainer {
+    pub fn new(net: Network, config: TrainConfig) -> Self {
+        let n = net.params.len();
+        let row_step = vec![0; net.arch.features.input_dim()];
+        Trainer { net, config, m: vec![0.0; n], v: vec![0.0; n], row_step, epoch: 0, step: 0, best_val_loss: f64::INFINITY }
+    }
+
+    /// Learning rate of the next epoch.
+    pub fn lr(&self) -> f32 {
+        self.config.lr_at(self.epoch)
+    }
+
+    /// One pass over `data` in a shuffled order seeded by the epoch number.
+    pub fn train_epoch(&mut self, data: &Dataset) -> Metrics {
+        let mut order: Vec<usize> = (0..data.len()).collect();
+        let mut rng = StdRng::seed_from_u64(self.config.seed ^ (self.epoch as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        order.shuffle(&mut rng);
+
+        let lr = self.lr();
+        let mut total = Metrics::default();
+        for batch in order.chunks(self.config.batch_size.max(1)) {
+            let (net, config) = (&self.net, &self.config);
+            let parts: Vec<(Metrics, Gradients)> = batch
+                .par_chunks(CHUNK)
+                .map(|chunk| {
+                    let mut grads = net.gradients();
+                    let m = process(net, config, data, chunk, batch.len(), Some(&mut grads));
+                    (m, grads)
+                })
+                .collect();
+            let mut parts = parts.into_iter();
+            let (m, mut grads) = parts.next().expect("empty batch");
+            total.add(&m);
+            for (m, g) in parts {
+                total.add(&m);
+                grads.merge(&g);
+            }
+            self.apply(&mut grads, lr);
+        }
+        self.catch_up_all(lr);
+        self.epoch += 1;
+        total.average()
+    }
+
+    /// Metrics of the current network on `data`, without training.
+    pub fn evaluate(&self, data: &Dataset) -> Metrics {
+        let all: Vec<usize> = (0..data.len()).collect();
+        let parts: Vec<Metrics> = all
+            .par_chunks(CHUNK)
+            .map(|chunk| process(&self.net, &self.config, data, chunk, chunk.len(), None))
+            .collect();
+        let mut total = Metrics::default();
+        for m in &parts {
+            total.add(m);
+        }
+        total.average()
+    }
+
+    /// Clip the batch gradient and take an AdamW step.
+    fn apply(&mut self, grads: &mut Gradients, lr: f32) {
+        let norm = grads.norm_sq().sqrt() as f32;
+        if norm > self.config.grad_clip {
+            grads.scale(self.config.grad_clip / (norm + 1e-6));
+        }
+
+        self.step += 1;
+        let step = AdamStep::new(self.step, lr, self.config.weight_decay);
+        let dense = self.net.sparse_len();
+        step.update(&mut self.net.params[dense..], &mut self.m[dense..], &mut self.v[dense..], &grads.dense);
+        let h1 = self.net.arch.hidden1;
+        for (i, &f) in grads.features.iter().enumerate() {
+            self.catch_up(f as usize, self.step - 1, lr);
+            self.row_step[f as usize] = self.step;
+            let row = f as usize * h1..(f as usize + 1) * h1;
+            let (p, m, v) = (&mut self.net.params[row.clone()], &mut self.m[row.clone()], &mut self.v[row]);
+            step.update(p, m, v, &grads.rows[i * h1..][..h1]);
+        }
+    }
+
+    /// Bring every feature transformer row up to the current step.
+    fn catch_up_all(&mut self, lr: f32) {
+        for f in 0..self.row_step.len() {
+            self.catch_up(f, self.step, lr);
+        }
+    }
+
+    /// Take feature transformer row `f` through the steps up to `to` it has
+    /// not seen, in which its gradient was zero.  Steps never span epochs,
+    /// so `lr` is theirs.
+    fn catch_up(&mut self, f: usize, to: u64, lr: f32) {
+        let from = std::mem::replace(&mut self.row_step[f], to);
+        if from >= to {
+            return;
+        }
+        let steps: Vec<AdamStep> = (from + 1..=to).map(|t| AdamStep::new(t, lr, self.config.weight_decay)).collect();
+        let h1 = self.net.arch.hidden1;
+        let row = f * h1..(f + 1) * h1;
+        let (p, m, v) = (&mut self.net.params[row.clone()], &mut self.m[row.clone()], &mut self.v[row]);
+        for ((p, m), v) in p.iter_mut().zip(m).zip(v) {
+            if *m == 0.0 && *v == 0.0 {
+                // Never moved: the steps only decay it.
+                steps.iter().for_each(|step| *p *= step.decay);
+                continue;
+            }
+            for step in &steps {
+                step.update_one(p, m, v, 0.0);
+            }
+        }
+    }
+
+    // ── Checkpoints ───────────────────────────────────────────────────────
+
+    /// Save the network, optimizer state and progress.  The configuration
+    /// is not saved: resume with the same options.
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let arch = &self.net.arch;
+        let mut out = Vec::with_capacity(64 + 12 * self.net.params.len());
+        out.extend_from_slice(&CHECKPOINT_MAGIC);
+        out.extend_from_slice(&CHECKPOINT_VERSION.to_le_bytes());
+        let name = arch.features.set.name();
+        out.push(name.len() as u8);
+        out.extend_from_slice(name.as_bytes());
+        for v in [arch.features.kings.count(), arch.hidden1, arch.hidden2, arch.output_buckets, self.epoch] {
+            out.extend_from_slice(&(v as u32).to_le_bytes());
+        }
+        out.extend_from_slice(&self.step.to_le_bytes());
+        out.extend_from_slice(&self.best_val_loss.to_le_bytes());
+        out.extend_from_slice(&(self.net.params.len() as u64).to_le_bytes());
+        for values in [&self.net.params, &self.m, &self.v] {
+            for x in values.iter() {
+                out.extend_from_slice(&x.to_le_bytes());
+            }
+        }
+        // Write then rename, so an interrupted save keeps the last checkpoint.
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, &out).and_then(|()| fs::rename(&tmp, path)).map_err(|e| format!("cannot write {}: {e}", path.display()))
+    }
+
+    /// Resume from a checkpoint written by `save`.
+    pub fn load(path: &Path, config: TrainConfig) -> Result<Self, String> {
+        let bytes = fs::read(path).map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+        let bad = || format!("{}: truncated or corrupt checkpoint", path.display());
+        let mut pos = 0;
+        let mut take = |n: usize| -> Result<&[u8], String> {
+            let s = bytes.get(pos..pos + n).ok_or_else(bad)?;
+            pos += n;
+            Ok(s)
+        };
+        let u32_at = |s: &[u8]| u32::from_le_bytes(s.try_into().unwrap()) as usize;
+
+        if take(8)? != CHECKPOINT_MAGIC {
+            return Err(format!("{}: not a trainer checkpoint", path.display()));
+        }
+        let version = u32_at(take(4)?);
+        if version != CHECKPOINT_VERSION as usize {
+            return Err(format!("{}: checkpoint version {version}, expected {CHECKPOINT_VERSION}", path.display()));
+        }
+        let name_len = take(1)?[0] as usize;
+        let name = std::str::from_utf8(take(name_len)?).map_err(|_| bad())?;
+        let set: FeatureSet = name.parse()?;
+        let kings = KingBuckets::from_count(u32_at(take(4)?)).ok_or_else(bad)?;
+        let (hidden1, hidden2, buckets) = (u32_at(take(4)?), u32_at(take(4)?), u32_at(take(4)?));
+        let features = FeatureLayout::new(set, kings);
+        let arch = Architecture { features, dual_perspective: true, hidden1, hidden2, output_buckets: buckets, scale: SCALE };
+        let mut net = Network::zeroed(arch).map_err(|e| format!("{}: {e}", path.display()))?;
+        let epoch = u32_at(take(4)?);
+        let step = u64::from_le_bytes(take(8)?.try_into().unwrap());
+        let best_val_loss = f64::from_le_bytes(take(8)?.try_into().unwrap());
+        let n = u64::from_le_bytes(take(8)?.try_into().unwrap()) as usize;
+        if n != net.params.len() {
+            return Err(bad());
+        }
+        let mut floats = || -> Result<Vec<f32>, String> {
+            Ok(take(4 * n)?.chunks_exact(4).map(|b| f32::from_le_bytes(b.try_into().unwrap())).collect())
+        };
+        net.params = floats()?;
+        let (m, v) = (floats()?, floats()?);
+        // Checkpoints are taken between epochs, with every row up to date.
+        let row_step = vec![step; features.input_dim()];
+        Ok(Trainer { net, config, m, v, row_step, epoch, step, best_val_loss })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn schedule_warms_up_then_decays() {
+        let config = TrainConfig { epochs: 10, warmup_epochs: 2, lr: 1e-3, ..TrainConfig::default() };
+        assert!((config.lr_at(0) - 5e-4).abs() < 1e-9);
+        assert!((config.lr_at(1) - 7.5e-4).abs() < 1e-9);
+        assert!((config.lr_at(2) - 1e-3).abs() < 1e-9);
+        assert!((config.lr_at(6) - (1e-5 + (1e-3 - 1e-5) * 0.5)).abs() < 1e-9);
+        for e in 2..9 {
+            assert!(config.lr_at(e + 1) < config.lr_at(e));
+        }
+        let no_warmup = TrainConfig { epochs: 4, warmup_epochs: 0, ..TrainConfig::default() };
+        assert_eq!(no_warmup.lr_at(0), no_warmup.lr);
+    }
+
+    /// Rows caught up lazily end where dense AdamW puts them: the same
+    /// steps with every row in the gradient, the untouched ones at zero.
+    #[test]
+    fn sparse_rows_catch_up_to_dense_adamw() {
+        let net = Network::new(FeatureLayout::LEGACY, 8, 4, 1, 3).unwrap();
+        let config = TrainConfig { weight_decay: 0.5, ..TrainConfig::default() };
+        let mut sparse = Trainer::new(net.clone(), config.clone());
+        let mut dense = Trainer::new(net, config);
+        let mut rng = StdRng::seed_from_u64(4);
+        let lr = 1e-2;
+        for _ in 0..40 {
+            let seed = rng.gen();
+            let grads = |all_rows: bool| {
+                let mut rng = StdRng::seed_from_u64(seed);
+                let mut g = sparse.net.gradients();
+                g.dense.iter_mut().for_each(|x| *x = rng.gen_range(-0.1..0.1));
+                for _ in 0..4 {
+                    g.row_mut(rng.gen_range(0..768)).iter_mut().for_each(|x| *x = rng.gen_range(-0.1..0.1));
+                }
+                if all_rows {
+                    for f in 0..768 {
+                        g.row_mut(f);
+                    }
+                }
+                g
+            };
+            let (mut g_sparse, mut g_dense) = (grads(false), grads(true));
+            sparse.apply(&mut g_sparse, lr);
+            dense.apply(&mut g_dense, lr);
+        }
+        assert_ne!(sparse.net.params, dense.net.params);
+        sparse.catch_up_all(lr);
+        assert_eq!(sparse.net.params, dense.net.params);
+        assert_eq!((&sparse.m, &sparse.v), (&dense.m, &dense.v));
+    }
+
+    #[test]
+    fn argmax_picks_the_first_maximum() {
+        assert_eq!(argmax(&[0.1, 0.8, 0.1]), 1);
+        assert_eq!(argmax(&[0.5, 0.2, 0.5]), 0);
+        assert_eq!(argmax(&[-1.0, -2.0, 0.0]), 2);
+    }
+}
@@ -2,31 +2,22 @@
 //!
 //! Usage: self_play <engine1> <engine2> [--games N] [--movetime MS] [--no-ponder]
 //!        [--engine1-opt "Name=Value"] [--engine2-opt "Name=Value"]
+//!
+//! The final report includes engine1's Elo difference with a 95% interval,
+//! which is how the `Skill Level` table in `chess_evaluation::skill` is
+//! calibrated (engine1 weakened, engine2 at full strength).
+//!
+//! With ponder on, engine1 ponders the reply it predicted the standard UCI
+//! way: `go ponder` on the predicted position, then `ponderhit` when engine2
+//! plays it, or `stop` and a normal search when it does not.
 
 use std::io::{BufRead, BufReader, Write};
 use std::process::{Child, ChildStdin, Command, Stdio};
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{mpsc, Arc};
-use std::thread::{self, JoinHandle};
-use std::time::{Duration, Instant};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
 
 use chess_board::ChessBoard;
-use chess_evaluation::{evaluate_board, iterative_deepening_root, SearchResult};
-
-#[cfg(any(feature = "nn-full-forward", feature = "nn-incremental", feature = "runtime-switch"))]
-static NNUE_WEIGHTS: &[u8] = include_bytes!("../../chess_evaluation/src/eval.npz");
-
-#[cfg(any(feature = "nn-full-forward", feature = "nn-incremental", feature = "runtime-switch"))]
-fn init_nn() {
-    match chess_evaluation::init_neural_eval_from_bytes(NNUE_WEIGHTS) {
-        Ok(()) => {
-            #[cfg(feature = "runtime-switch")]
-            chess_evaluation::set_neural_eval_enabled(true);
-            eprintln!("Neural eval loaded ({} KB).", NNUE_WEIGHTS.len() / 1024);
-        }
-        Err(e) => eprintln!("warn: neural eval not loaded: {e}"),
-    }
-}
 use chess_foundation::{piece::PieceType, ChessMove};
 use move_generator::{
     move_generator::get_all_legal_moves_for_color, piece_conductor::PieceConductor,
@@ -199,154 +190,33 @@ impl Engine {
         }
     }
 
-}
-
-impl Drop for Engine {
-    fn drop(&mut self) {
-        let _ = writeln!(self.stdin, "quit");
-        let _ = self.child.wait();
+    /// Start pondering: `position_cmd` already ends with the predicted reply.
+    fn ponder(&mut self, position_cmd: &str, movetime_ms: u64) {
+        self.send(position_cmd);
+        self.send(&format!("go ponder movetime {}", movetime_ms));
     }
-}
-
-// ── Multi-ponder ─────────────────────────────────────────────────────────────
-
-/// How many opponent candidate moves to ponder in parallel.
-const PONDER_CANDIDATES: usize = 4;
-
-struct PonderThread {
-    /// UCI string of the opponent move (for matching).
-    opponent_uci: String,
-    stop: Arc<AtomicBool>,
-    handle: JoinHandle<SearchResult>,
-}
 
-struct MultiPonder {
-    threads: Vec<PonderThread>,
-}
-
-impl MultiPonder {
-    /// Start pondering the top N opponent candidate moves.
-    /// `board` is the position AFTER our move was played.
-    /// `is_white` is the color that will move next (our color, after opponent replies).
-    fn start(
-        board: &ChessBoard,
-        conductor: &PieceConductor,
-        is_white_next: bool,
-        n: usize,
-    ) -> Self {
-        let opponent_white = !is_white_next;
-        let mut board_for_gen = board.clone();
-        let mut legal = Vec::new();
-        get_all_legal_moves_for_color(&mut board_for_gen, conductor, opponent_white, &mut legal, &mut Vec::new());
-
-        if legal.is_empty() {
-            return MultiPonder { threads: Vec::new() };
+    /// The predicted reply was played: the ponder search turns into the real
+    /// one, with `movetime_ms` counted from now.
+    fn ponderhit(&mut self, movetime_ms: u64) -> (Option<String>, Option<String>) {
+        self.send("ponderhit");
+        match self.wait_for("bestmove", Duration::from_millis(movetime_ms + 10_000)) {
+            Some(line) => Self::parse_bestmove(&line),
+            None => (None, None),
         }
-
-        // Rank opponent moves: play each, evaluate from opponent's perspective,
-        // pick the best N for the opponent.
-        let mut scored: Vec<(ChessMove, i32)> = legal
-            .into_iter()
-            .map(|mut m| {
-                let mut b = board.clone();
-                b.make_move(&mut m);
-                let eval = evaluate_board(&b, conductor);
-                // Opponent wants low score if white_next, high if black_next
-                let opponent_score = if opponent_white { eval } else { -eval };
-                (m, opponent_score)
-            })
-            .collect();
-
-        scored.sort_by(|a, b| b.1.cmp(&a.1)); // best for opponent first
-        scored.truncate(n);
-
-        let threads: Vec<PonderThread> = scored
-            .into_iter()
-            .map(|(opp_mv, _score)| {
-                let uci = mv_to_uci(opp_mv);
-                let stop = Arc::new(AtomicBool::new(false));
-
-                // Set up board: apply opponent's move, then search for our reply
-                let mut board_c = board.clone();
-                let mut mv_c = opp_mv;
-                board_c.make_move(&mut mv_c);
-
-                let cond_c = conductor.clone();
-                let stop_c = Arc::clone(&stop);
-                let handle = thread::spawn(move || {
-                    iterative_deepening_root(
-                        &mut board_c,
-                        &cond_c,
-                        None, // no opening book for ponder
-                        64,
-                        is_white_next,
-                        None, // no deadline — stopped by flag
-                        Some(stop_c),
-                        0,
-                    )
-                });
-
-                PonderThread {
-                    opponent_uci: uci,
-                    stop,
-                    handle,
-                }
-            })
-            .collect();
-
-        MultiPonder { threads }
     }
 
-    /// Check if any ponder thread matches the opponent's actual move.
-    /// Stops all threads. Returns the SearchResult if there was a hit.
-    fn resolve(self, actual_move: &str, movetime_ms: u64) -> Option<SearchResult> {
-        // Find the matching thread (if any)
-        let mut hit_idx = None;
-        for (i, t) in self.threads.iter().enumerate() {
-            if t.opponent_uci == actual_move {
-                hit_idx = Some(i);
-            } else {
-                // Stop non-matching threads immediately
-                t.stop.store(true, Ordering::Release);
-            }
-        }
-
-        if let Some(idx) = hit_idx {
-            // Let the matching thread continue for movetime_ms more
-            let hit_stop = Arc::clone(&self.threads[idx].stop);
-            let deadline = Instant::now() + Duration::from_millis(movetime_ms);
-
-            // Wait for deadline or external conditions
-            while Instant::now() < deadline {
-                thread::sleep(Duration::from_millis(2));
-            }
-            hit_stop.store(true, Ordering::Release);
-        }
-
-        // Collect all results
-        let mut hit_result = None;
-        for (i, t) in self.threads.into_iter().enumerate() {
-            let result = t.handle.join().unwrap_or(SearchResult {
-                score: 0,
-                best_move: None,
-                ponder_move: None,
-                total_nodes: 0,
-            });
-            if hit_idx == Some(i) {
-                hit_result = Some(result);
-            }
-        }
-        hit_result
+    /// Abandon a ponder search, discarding its bestmove.
+    fn stop_ponder(&mut self) {
+        self.send("stop");
+        let _ = self.wait_for("bestmove", Duration::from_secs(10));
     }
+}
 
-    /// Stop all threads without waiting for results.
-    fn stop_all(self) {
-        for t in &self.threads {
-            t.stop.store(true, Ordering::Release);
-        }
-        for t in self.threads {
-            let _ = t.handle.join();
-        }
+impl Drop for Engine {
+    fn drop(&mut self) {
+        let _ = writeln!(self.stdin, "quit");
+        let _ = self.child.wait();
     }
 }
 
@@ -390,8 +260,8 @@ fn play_game(
     engine1.new_game();
     engine2.new_game();
 
-    // Multi-ponder state for engine1: pondering top N opponent candidate moves.
-    let mut active_ponder: Option<MultiPonder> = None;
+    // The reply engine1 is pondering on, if any.
+    let mut pondering: Option<String> = None;
     let mut ponder_hits: u32 = 0;
     let mut ponder_attempts: u32 = 0;
 
@@ -409,7 +279,7 @@ fn play_game(
         get_all_legal_moves_for_color(&mut board, conductor, is_white, &mut legal, &mut Vec::new());
 
         if legal.is_empty() {
-            if let Some(p) = active_ponder.take() { p.stop_all(); }
+            if pondering.take().is_some() { engine1.stop_ponder(); }
             if conductor.is_king_in_check(&board, is_white) {
                 if is_white {
                     game_return!(if engine1_is_white { GameResult::Engine2Wins } else { GameResult::Engine1Wins },
@@ -424,12 +294,12 @@ fn play_game(
         }
 
         if board.is_repetition(3) {
-            if let Some(p) = active_ponder.take() { p.stop_all(); }
+            if pondering.take().is_some() { engine1.stop_ponder(); }
             game_return!(GameResult::Draw, "repetition".to_string());
         }
 
         if no_progress >= FIFTY_MOVE_PLIES {
-            if let Some(p) = active_ponder.take() { p.stop_all(); }
+            if pondering.take().is_some() { engine1.stop_ponder(); }
             game_return!(GameResult::Draw, "50-move rule".to_string());
         }
 
@@ -448,44 +318,34 @@ fn play_game(
         // ── Ask the right engine ──
         let ask_engine1 = is_white == engine1_is_white;
 
-        // Check multi-ponder hit for engine1
-        let ponder_result = if ask_engine1 {
-            if let Some(mp) = active_ponder.take() {
-                ponder_attempts += 1;
-                let last_move = move_list.last().map(|s| s.as_str()).unwrap_or("");
-                let result = mp.resolve(last_move, movetime_ms);
-                if result.is_some() { ponder_hits += 1; }
-                result
-            } else {
-                None
+        // A ponder hit lets engine1's running search carry on; a miss
+        // stops it and engine1 searches the actual position from scratch.
+        let (uci_str, ponder_move) = if ask_engine1 {
+            match pondering.take() {
+                Some(predicted) if move_list.last() == Some(&predicted) => {
+                    ponder_attempts += 1;
+                    ponder_hits += 1;
+                    engine1.ponderhit(movetime_ms)
+                }
+                Some(_) => {
+                    ponder_attempts += 1;
+                    engine1.stop_ponder();
+                    engine1.best_move(&position_cmd, movetime_ms)
+                }
+                None => engine1.best_move(&position_cmd, movetime_ms),
             }
         } else {
-            None
-        };
-
-        let uci_str = if let Some(ref result) = ponder_result {
-            // Ponder hit! Use the result directly.
-            result.best_move.map(|m| mv_to_uci(m))
-        } else {
-            // No ponder hit — ask the engine via UCI as usual.
-            // If engine1 had a ponder running that missed, it was already
-            // stopped by resolve() above (which stops all threads).
-            let (mv, _) = if ask_engine1 {
-                engine1.best_move(&position_cmd, movetime_ms)
-            } else {
-                engine2.best_move(&position_cmd, movetime_ms)
-            };
-            mv
+            engine2.best_move(&position_cmd, movetime_ms)
         };
 
         let Some(uci_str) = uci_str else {
-            if let Some(p) = active_ponder.take() { p.stop_all(); }
+            if pondering.take().is_some() { engine1.stop_ponder(); }
             game_return!(GameResult::Draw, "engine returned no move".to_string());
         };
 
         // ── Apply move ──
         let Some(mut chess_move) = parse_uci_move(&uci_str, &legal) else {
-            if let Some(p) = active_ponder.take() { p.stop_all(); }
+            if pondering.take().is_some() { engine1.stop_ponder(); }
             game_return!(GameResult::Draw, format!("illegal move '{}'", uci_str));
         };
         let is_pawn = chess_move.chess_piece.map_or(false, |p| p.piece_type() == PieceType::Pawn);
@@ -498,29 +358,50 @@ fn play_game(
         board.make_move(&mut chess_move);
         move_list.push(mv_to_uci(chess_move));
 
-        // ── Start multi-ponder for engine1 after it moves ──
+        // ── Engine1 ponders on the reply it predicted ──
         if ponder && ask_engine1 {
-            // board is now the position after engine1's move.
-            // Ponder the opponent's top N candidate replies.
-            active_ponder = Some(MultiPonder::start(
-                &board,
-                conductor,
-                engine1_is_white, // engine1's color = the side that will move after opponent
-                PONDER_CANDIDATES,
-            ));
+            if let Some(predicted) = ponder_move {
+                let moves = format!("{} {}", move_list.join(" "), predicted);
+                engine1.ponder(&format!("{} moves {}", position_base, moves), movetime_ms);
+                pondering = Some(predicted);
+            }
         }
     }
 
-    if let Some(p) = active_ponder.take() { p.stop_all(); }
+    if pondering.take().is_some() { engine1.stop_ponder(); }
     (GameResult::Draw, "move limit".to_string(), ponder_hits, ponder_attempts)
 }
 
+// ── Elo estimate ──────────────────────────────────────────────────────────────
+
+fn elo_from_score(p: f64) -> f64 {
+    -400.0 * (1.0 / p - 1.0).log10()
+}
+
+/// Elo difference implied by a wins/draws/losses record, with the half-width
+/// of its 95% interval.  `None` when the score is 0% or 100%.
+fn elo_difference(wins: u32, draws: u32, losses: u32) -> Option<(f64, f64)> {
+    let n = (wins + draws + losses) as f64;
+    if n == 0.0 {
+        return None;
+    }
+    let p = (wins as f64 + draws as f64 * 0.5) / n;
+    if p <= 0.0 || p >= 1.0 {
+        return None;
+    }
+    let var = (wins as f64 * (1.0 - p).powi(2)
+        + draws as f64 * (0.5 - p).powi(2)
+        + losses as f64 * p.powi(2))
+        / n;
+    let se = (var / n).sqrt();
+    let lo = elo_from_score((p - 1.96 * se).max(1e-6));
+    let hi = elo_from_score((p + 1.96 * se).min(1.0 - 1e-6));
+    Some((elo_from_score(p), (hi - lo) / 2.0))
+}
+
 // ── CLI ───────────────────────────────────────────────────────────────────────
 
 fn main() {
-    #[cfg(any(feature = "nn-full-forward", feature = "nn-incremental", feature = "runtime-switch"))]
-    init_nn();
-
     let args: Vec<String> = std::env::args().collect();
 
     let mut engine1_path: Option<String> = None;
@@ -581,6 +462,10 @@ fn main() {
         }
     }
 
+    if ponder {
+        engine1_opts.push("Ponder=true".to_string());
+    }
+
     let e1 = engine1_path
         .expect("Usage: self_play <engine1> <engine2> [--games N] [--movetime MS]");
     let e2 = engine2_path
@@ -680,5 +565,9 @@ fn main() {
         e2_wins as f64 / total * 100.0
     );
     println!("  {} score: {:.1}%", e1_name, score);
+    match elo_difference(e1_wins, draws, e2_wins) {
+        Some((elo, margin)) => println!("  {} Elo difference: {:+.0} ± {:.0}", e1_name, elo, margin),
+        None => println!("  {} Elo difference: n/a (no decisive spread)", e1_name),
+    }
     println!("{}", "═".repeat(60));
 }